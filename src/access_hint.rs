@@ -0,0 +1,86 @@
+use std::fmt;
+
+use crate::error::Error;
+
+/// IAM permission a request denied with `AccessDenied` is missing, see [`access_hint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessHint {
+    /// S3 operation that was denied, e.g. `GetObject`.
+    pub operation: &'static str,
+    /// IAM action granting the operation, e.g. `s3:GetObject`.
+    pub action: &'static str,
+    /// ARN of the resource the action has to be granted on.
+    pub resource: String,
+    /// `s3:prefix` condition a listing of a prefix has to be allowed by.
+    pub prefix_condition: Option<String>,
+}
+
+impl fmt::Display for AccessHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requires {} on {}",
+            self.operation, self.action, self.resource
+        )?;
+        if let Some(prefix) = &self.prefix_condition {
+            write!(f, " with s3:prefix \"{}\"", prefix)?;
+        }
+        Ok(())
+    }
+}
+
+impl AccessHint {
+    /// Permission `err`, a failed request for `key` in `bucket`, needed, `None` for errors that
+    /// aren't requests of a known operation.
+    pub(crate) fn new(err: &Error, bucket: &str, key: &str) -> Option<Self> {
+        let (operation, action) = match err {
+            Error::S3Head(_) => ("HeadObject", "s3:GetObject"),
+            Error::S3GetObject(_) => ("GetObject", "s3:GetObject"),
+            Error::S3PutObject(_) => ("PutObject", "s3:PutObject"),
+            Error::S3CopyObject(_) => ("CopyObject", "s3:PutObject"),
+            Error::S3CreateMultipart(_) => ("CreateMultipartUpload", "s3:PutObject"),
+            Error::S3UploadPart(_) => ("UploadPart", "s3:PutObject"),
+            Error::S3UploadPartCopy(_) => ("UploadPartCopy", "s3:PutObject"),
+            Error::S3CompleteMultipart(_) => ("CompleteMultipartUpload", "s3:PutObject"),
+            Error::S3AbortMultipart(_) => ("AbortMultipartUpload", "s3:AbortMultipartUpload"),
+            Error::S3ListParts(_) => ("ListParts", "s3:ListMultipartUploadParts"),
+            Error::S3DeleteObject(_) => ("DeleteObject", "s3:DeleteObject"),
+            Error::S3DeleteObjects(_) => ("DeleteObjects", "s3:DeleteObject"),
+            Error::S3GetObjectTagging(_) => ("GetObjectTagging", "s3:GetObjectTagging"),
+            Error::S3PutObjectTagging(_) => ("PutObjectTagging", "s3:PutObjectTagging"),
+            Error::S3DeleteObjectTagging(_) => ("DeleteObjectTagging", "s3:DeleteObjectTagging"),
+            Error::S3ListObjects(_) => ("ListObjectsV2", "s3:ListBucket"),
+            Error::S3ListObjectVersions(_) => ("ListObjectVersions", "s3:ListBucketVersions"),
+            _ => return None,
+        };
+        // Listings are granted on the bucket, restricted to prefixes by condition
+        let listing = matches!(
+            err,
+            Error::S3ListObjects(_) | Error::S3ListObjectVersions(_)
+        );
+        Some(AccessHint {
+            operation,
+            action,
+            resource: match listing {
+                true => format!("arn:aws:s3:::{}", bucket),
+                false => format!("arn:aws:s3:::{}/{}", bucket, key),
+            },
+            prefix_condition: (listing && !key.is_empty()).then(|| key.to_owned()),
+        })
+    }
+}
+
+/// Missing permission of a request denied with `AccessDenied`.
+///
+/// A `HeadObject` or `GetObject` of a missing object is also denied without `s3:ListBucket`.
+pub fn access_hint(err: &object_store::Error) -> Option<&AccessHint> {
+    let source = match err {
+        object_store::Error::Generic { source, .. } => source,
+        object_store::Error::NotFound { source, .. } => source,
+        _ => return None,
+    };
+    match source.downcast_ref::<Error>() {
+        Some(Error::Request { hint, .. }) => hint.as_ref(),
+        _ => None,
+    }
+}
@@ -0,0 +1,72 @@
+use aws_sdk_s3::{
+    config::{
+        interceptors::BeforeSerializationInterceptorContextMut, ConfigBag, Interceptor,
+        RuntimeComponents,
+    },
+    error::BoxError,
+    operation::{
+        copy_object::CopyObjectInput, create_multipart_upload::CreateMultipartUploadInput,
+        put_object::PutObjectInput,
+    },
+    types::ObjectCannedAcl,
+};
+
+/// Canned ACL applied to written objects, sent as `x-amz-acl`.
+///
+/// Set per put with [`PutAttributes::acl`](crate::attributes::PutAttributes::acl) or for every
+/// put, multipart upload and copy with
+/// [`S3Builder::with_default_acl`](crate::builder::S3Builder::with_default_acl).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CannedAcl {
+    Private,
+    PublicRead,
+    PublicReadWrite,
+    AuthenticatedRead,
+    AwsExecRead,
+    BucketOwnerRead,
+    BucketOwnerFullControl,
+}
+
+impl CannedAcl {
+    pub(crate) fn sdk(self) -> ObjectCannedAcl {
+        match self {
+            CannedAcl::Private => ObjectCannedAcl::Private,
+            CannedAcl::PublicRead => ObjectCannedAcl::PublicRead,
+            CannedAcl::PublicReadWrite => ObjectCannedAcl::PublicReadWrite,
+            CannedAcl::AuthenticatedRead => ObjectCannedAcl::AuthenticatedRead,
+            CannedAcl::AwsExecRead => ObjectCannedAcl::AwsExecRead,
+            CannedAcl::BucketOwnerRead => ObjectCannedAcl::BucketOwnerRead,
+            CannedAcl::BucketOwnerFullControl => ObjectCannedAcl::BucketOwnerFullControl,
+        }
+    }
+}
+
+/// Sets the ACL of puts, multipart uploads and copies that don't have one.
+#[derive(Debug, Clone)]
+pub(crate) struct DefaultAcl(pub(crate) CannedAcl);
+
+impl Interceptor for DefaultAcl {
+    fn name(&self) -> &'static str {
+        "DefaultAcl"
+    }
+
+    fn modify_before_serialization(
+        &self,
+        context: &mut BeforeSerializationInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let input = context.input_mut();
+        let acl = if let Some(input) = input.downcast_mut::<PutObjectInput>() {
+            &mut input.acl
+        } else if let Some(input) = input.downcast_mut::<CreateMultipartUploadInput>() {
+            &mut input.acl
+        } else if let Some(input) = input.downcast_mut::<CopyObjectInput>() {
+            &mut input.acl
+        } else {
+            return Ok(());
+        };
+        acl.get_or_insert_with(|| self.0.sdk());
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use aws_sdk_s3::{
+    error::SdkError,
+    types::{BucketLocationConstraint, CreateBucketConfiguration},
+};
+
+use crate::S3;
+
+impl S3 {
+    /// Creates the store's bucket in the client's region, e.g. to provision LocalStack or MinIO
+    /// in tests. Succeeds if the caller already owns the bucket.
+    pub async fn create_bucket(&self) -> object_store::Result<()> {
+        let mut request = self.client.create_bucket().bucket(self.bucket.clone());
+        // `us-east-1` is the default location and rejected as an explicit constraint
+        let region = self.client.config().region().map(|region| region.as_ref());
+        if let Some(region) = region.filter(|region| *region != "us-east-1") {
+            request = request.create_bucket_configuration(
+                CreateBucketConfiguration::builder()
+                    .location_constraint(BucketLocationConstraint::from(region))
+                    .build(),
+            );
+        }
+        match request.send().await {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(err)) if err.err().is_bucket_already_owned_by_you() => {
+                Ok(())
+            }
+            Err(err) => Err(self.request_error("", err).into()),
+        }
+    }
+
+    /// Deletes the store's bucket, which has to be empty.
+    pub async fn delete_bucket(&self) -> object_store::Result<()> {
+        self.client
+            .delete_bucket()
+            .bucket(self.bucket.clone())
+            .send()
+            .await
+            .map_err(|err| self.request_error("", err))?;
+        Ok(())
+    }
+
+    /// Whether the store's bucket exists, buckets of other accounts count as existing.
+    pub async fn bucket_exists(&self) -> object_store::Result<bool> {
+        match self
+            .client
+            .head_bucket()
+            .bucket(self.bucket.clone())
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(err)) if err.err().is_not_found() => Ok(false),
+            Err(SdkError::ServiceError(err)) if err.raw().status().as_u16() == 403 => Ok(true),
+            Err(err) => Err(self.request_error("", err).into()),
+        }
+    }
+}
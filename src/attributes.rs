@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+
+use aws_sdk_s3::operation::{
+    create_multipart_upload::builders::CreateMultipartUploadFluentBuilder,
+    get_object::GetObjectOutput, head_object::HeadObjectOutput,
+    put_object::builders::PutObjectFluentBuilder,
+};
+use bytes::Bytes;
+use object_store::{
+    path::Path, GetOptions, GetResult, MultipartId, ObjectMeta, PutOptions, PutResult,
+};
+use tokio::io::AsyncWrite;
+
+use crate::{acl::CannedAcl, storage_class::StorageClass, S3};
+
+/// Content headers, user metadata and ACL of an object.
+///
+/// Headers left `None` are not sent, `cache_control` then falls back to the store's cache policy,
+/// `acl` to the store's default ACL and `storage_class` to the store's storage class. Attributes
+/// read from an object never have an ACL or storage class, see
+/// [`ObjectStatus::storage_class`](crate::status::ObjectStatus::storage_class).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PutAttributes {
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_encoding: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_language: Option<String>,
+    /// `x-amz-meta-*` headers, keys without the prefix.
+    pub metadata: BTreeMap<String, String>,
+    pub acl: Option<CannedAcl>,
+    pub storage_class: Option<StorageClass>,
+}
+
+macro_rules! apply_attributes {
+    ($builder:expr, $attributes:expr) => {{
+        let attributes = $attributes;
+        let builder = $builder
+            .set_content_encoding(attributes.content_encoding.clone())
+            .set_content_disposition(attributes.content_disposition.clone())
+            .set_content_language(attributes.content_language.clone())
+            .set_acl(attributes.acl.map(CannedAcl::sdk))
+            .set_storage_class(attributes.storage_class.as_ref().map(StorageClass::sdk));
+        // Unset headers keep the inferred content type and the cache policy's headers
+        let builder = match &attributes.content_type {
+            Some(content_type) => builder.content_type(content_type),
+            None => builder,
+        };
+        let builder = match &attributes.cache_control {
+            Some(cache_control) => builder.cache_control(cache_control),
+            None => builder,
+        };
+        attributes
+            .metadata
+            .iter()
+            .fold(builder, |builder, (key, value)| {
+                builder.metadata(key, value)
+            })
+    }};
+}
+
+macro_rules! read_attributes {
+    ($output:expr) => {{
+        let output = $output;
+        PutAttributes {
+            content_type: output.content_type().map(ToOwned::to_owned),
+            cache_control: output.cache_control().map(ToOwned::to_owned),
+            content_encoding: output.content_encoding().map(ToOwned::to_owned),
+            content_disposition: output.content_disposition().map(ToOwned::to_owned),
+            content_language: output.content_language().map(ToOwned::to_owned),
+            metadata: output
+                .metadata()
+                .map(|metadata| metadata.clone().into_iter().collect())
+                .unwrap_or_default(),
+            acl: None,
+            storage_class: None,
+        }
+    }};
+}
+
+impl PutAttributes {
+    pub(crate) fn put(&self, builder: PutObjectFluentBuilder) -> PutObjectFluentBuilder {
+        apply_attributes!(builder, self)
+    }
+
+    pub(crate) fn create_multipart(
+        &self,
+        builder: CreateMultipartUploadFluentBuilder,
+    ) -> CreateMultipartUploadFluentBuilder {
+        apply_attributes!(builder, self)
+    }
+}
+
+impl From<&GetObjectOutput> for PutAttributes {
+    fn from(value: &GetObjectOutput) -> Self {
+        read_attributes!(value)
+    }
+}
+
+impl From<&HeadObjectOutput> for PutAttributes {
+    fn from(value: &HeadObjectOutput) -> Self {
+        read_attributes!(value)
+    }
+}
+
+impl S3 {
+    /// Like [`ObjectStore::put_opts`](object_store::ObjectStore::put_opts) but also sets the
+    /// content headers and user metadata of `attributes`.
+    pub async fn put_with_attributes(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+        attributes: &PutAttributes,
+    ) -> object_store::Result<PutResult> {
+        self.put_object(location, bytes, opts, attributes).await
+    }
+
+    /// Like [`ObjectStore::put_multipart`](object_store::ObjectStore::put_multipart) but also
+    /// sets the content headers and user metadata of `attributes`.
+    pub async fn put_multipart_with_attributes(
+        &self,
+        location: &Path,
+        attributes: &PutAttributes,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.multipart_writer(location, attributes).await
+    }
+
+    /// Like [`ObjectStore::head`](object_store::ObjectStore::head) but additionally returns the
+    /// content headers and user metadata.
+    pub async fn head_with_attributes(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(ObjectMeta, PutAttributes)> {
+        let (meta, _, attributes) = self.head_object(location, None).await?;
+        Ok((meta, attributes))
+    }
+
+    /// Like [`ObjectStore::get_opts`](object_store::ObjectStore::get_opts) but additionally
+    /// returns the content headers and user metadata.
+    pub async fn get_with_attributes(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<(GetResult, PutAttributes)> {
+        let (result, _, attributes) = self.get_object(location, options).await?;
+        Ok((result, attributes))
+    }
+}
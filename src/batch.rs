@@ -0,0 +1,189 @@
+use aws_sdk_s3control::types::{
+    JobManifest, JobManifestFieldName, JobManifestFormat, JobManifestLocation, JobManifestSpec,
+    JobOperation, JobReport, JobReportFormat, JobReportScope, S3CopyObjectOperation,
+    S3GlacierJobTier, S3InitiateRestoreObjectOperation, S3SetObjectTaggingOperation, S3Tag,
+};
+use object_store::{path::Path, ObjectStore};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha2::{Digest, Sha256};
+
+use crate::{checksum::hex, error::Error, partition::Partition, S3};
+
+/// Characters left unescaped in keys of a batch operations CSV manifest.
+const MANIFEST_KEY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Idempotency token of the job of the manifest at `manifest_path` with `e_tag`, the hex
+/// SHA-256 of both. S3 Control accepts at most 64 characters, which manifest paths alone exceed.
+pub fn client_request_token(manifest_path: &Path, e_tag: &str) -> String {
+    let token = format!("{}-{}", manifest_path, e_tag.trim_matches('"'));
+    hex(&Sha256::digest(token.as_bytes()))[..64].to_owned()
+}
+
+/// Operation an S3 Batch Operations job applies to every object of its manifest.
+#[derive(Debug, Clone)]
+pub enum BatchOperation {
+    /// Copy the objects into `target_bucket`, optionally below `target_prefix`.
+    Copy {
+        target_bucket: String,
+        target_prefix: Option<String>,
+    },
+    /// Replace the tag set of the objects.
+    Tag(Vec<(String, String)>),
+    /// Restore archived objects for `days` days.
+    Restore { days: i32, tier: RestoreTier },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RestoreTier {
+    Bulk,
+    Standard,
+}
+
+/// Settings of an S3 Batch Operations job, see [`S3::submit_batch_job`].
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    /// Account owning the job.
+    pub account_id: String,
+    /// IAM role the job assumes to access the objects.
+    pub role_arn: String,
+    pub priority: i32,
+    pub description: Option<String>,
+    /// Prefix of the generated manifest and the completion report in the store's bucket.
+    pub prefix: String,
+    /// Whether the job waits for confirmation in the console before it runs.
+    pub confirmation_required: bool,
+}
+
+impl BatchJob {
+    pub fn new(account_id: impl Into<String>, role_arn: impl Into<String>) -> Self {
+        BatchJob {
+            account_id: account_id.into(),
+            role_arn: role_arn.into(),
+            priority: 10,
+            description: None,
+            prefix: "batch-operations".to_owned(),
+            confirmation_required: false,
+        }
+    }
+}
+
+impl S3 {
+    /// Creates an S3 Batch Operations job applying `operation` to `keys`.
+    ///
+    /// The keys are written as a CSV manifest below [`BatchJob::prefix`] in the store's bucket,
+    /// the job reports failed tasks below the same prefix. Returns the id of the created job.
+    pub async fn submit_batch_job(
+        &self,
+        job: &BatchJob,
+        operation: BatchOperation,
+        keys: impl IntoIterator<Item = Path>,
+    ) -> object_store::Result<String> {
+        let manifest = keys
+            .into_iter()
+            .map(|key| {
+                format!(
+                    "{},{}\n",
+                    self.bucket,
+                    utf8_percent_encode(key.as_ref(), MANIFEST_KEY)
+                )
+            })
+            .collect::<String>();
+        let manifest_path = Path::from(format!(
+            "{}/manifest-{}.csv",
+            job.prefix,
+            self.now().timestamp_millis()
+        ));
+        let e_tag = self
+            .put(&manifest_path, manifest.into())
+            .await?
+            .e_tag
+            .ok_or(Error::Unknown)?;
+
+        let response = self
+            .control
+            .create_job()
+            .account_id(&job.account_id)
+            .role_arn(&job.role_arn)
+            .priority(job.priority)
+            .set_description(job.description.clone())
+            .confirmation_required(job.confirmation_required)
+            .client_request_token(client_request_token(&manifest_path, &e_tag))
+            .operation(job_operation(operation, self.partition))
+            .manifest(
+                JobManifest::builder()
+                    .spec(
+                        JobManifestSpec::builder()
+                            .format(JobManifestFormat::S3BatchOperationsCsv20180820)
+                            .fields(JobManifestFieldName::Bucket)
+                            .fields(JobManifestFieldName::Key)
+                            .build(),
+                    )
+                    .location(
+                        JobManifestLocation::builder()
+                            .object_arn(
+                                self.partition
+                                    .s3_arn(&self.bucket, Some(manifest_path.as_ref())),
+                            )
+                            .e_tag(e_tag.trim_matches('"'))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .report(
+                JobReport::builder()
+                    .enabled(true)
+                    .bucket(self.partition.s3_arn(&self.bucket, None))
+                    .prefix(&job.prefix)
+                    .format(JobReportFormat::ReportCsv20180820)
+                    .report_scope(JobReportScope::FailedTasksOnly)
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(Error::from)?;
+        Ok(response.job_id.ok_or(Error::Unknown)?)
+    }
+}
+
+fn job_operation(operation: BatchOperation, partition: Partition) -> JobOperation {
+    match operation {
+        BatchOperation::Copy {
+            target_bucket,
+            target_prefix,
+        } => JobOperation::builder()
+            .s3_put_object_copy(
+                S3CopyObjectOperation::builder()
+                    .target_resource(partition.s3_arn(&target_bucket, None))
+                    .set_target_key_prefix(target_prefix)
+                    .build(),
+            )
+            .build(),
+        BatchOperation::Tag(tags) => JobOperation::builder()
+            .s3_put_object_tagging(
+                S3SetObjectTaggingOperation::builder()
+                    .set_tag_set(Some(
+                        tags.into_iter()
+                            .map(|(key, value)| S3Tag::builder().key(key).value(value).build())
+                            .collect(),
+                    ))
+                    .build(),
+            )
+            .build(),
+        BatchOperation::Restore { days, tier } => JobOperation::builder()
+            .s3_initiate_restore_object(
+                S3InitiateRestoreObjectOperation::builder()
+                    .expiration_in_days(days)
+                    .glacier_job_tier(match tier {
+                        RestoreTier::Bulk => S3GlacierJobTier::Bulk,
+                        RestoreTier::Standard => S3GlacierJobTier::Standard,
+                    })
+                    .build(),
+            )
+            .build(),
+    }
+}
@@ -0,0 +1,120 @@
+use std::io;
+
+use bytes::{Bytes, BytesMut};
+use futures::{stream, Stream, StreamExt};
+use js_sys::{Reflect, Uint8Array};
+use object_store::{path::Path, ObjectStore, PutResult};
+use tokio::io::AsyncWriteExt;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, ReadableStream, ReadableStreamDefaultReader};
+
+use crate::{attributes::PutAttributes, error::Error, resumable::read_slice, S3};
+
+impl S3 {
+    /// Uploads `blob`, e.g. a `File` picked with `<input type="file">`, to `location`.
+    ///
+    /// Blobs larger than a part are sliced into parts that are only read once the multipart
+    /// upload has room for them, so the blob is never held in memory as a whole.
+    pub async fn put_blob(&self, location: &Path, blob: &Blob) -> object_store::Result<PutResult> {
+        let size = blob.size() as usize;
+        let part_size = self.multipart_part_size;
+        let parts = stream::unfold(0, move |offset| async move {
+            (offset < size).then_some(())?;
+            let end = (offset + part_size).min(size);
+            let part = read_slice(blob, offset..end).await.map_err(Into::into);
+            Some((part, end))
+        });
+        self.put_chunks(location, parts).await
+    }
+
+    /// Uploads the `Uint8Array` chunks of `stream` to `location`, like [`S3::put_blob`] without
+    /// holding the stream in memory. The stream is locked while it's read.
+    pub async fn put_readable_stream(
+        &self,
+        location: &Path,
+        stream: &ReadableStream,
+    ) -> object_store::Result<PutResult> {
+        let reader: ReadableStreamDefaultReader = stream.get_reader().unchecked_into();
+        let chunks = stream::unfold(Some(reader), |reader| async move {
+            let reader = reader?;
+            match read_chunk(&reader).await {
+                Ok(Some(chunk)) => Some((Ok(chunk), Some(reader))),
+                Ok(None) => {
+                    reader.release_lock();
+                    None
+                }
+                Err(err) => Some((Err(err.into()), None)),
+            }
+        });
+        self.put_chunks(location, chunks).await
+    }
+
+    /// Uploads `chunks` with a single put if they fit into a part, with a multipart upload
+    /// otherwise. The multipart upload is aborted if reading a chunk or uploading a part fails.
+    async fn put_chunks(
+        &self,
+        location: &Path,
+        chunks: impl Stream<Item = object_store::Result<Bytes>>,
+    ) -> object_store::Result<PutResult> {
+        let mut chunks = std::pin::pin!(chunks);
+        let mut head = BytesMut::new();
+        while head.len() <= self.multipart_part_size {
+            match chunks.next().await.transpose()? {
+                Some(chunk) => head.extend_from_slice(&chunk),
+                None => return self.put(location, head.freeze()).await,
+            }
+        }
+
+        let (upload_id, mut writer) = self
+            .put_multipart_with_report(location, &PutAttributes::default())
+            .await?;
+        let written = async {
+            writer.write_all(&head).await.map_err(from_io)?;
+            drop(head);
+            while let Some(chunk) = chunks.next().await {
+                writer.write_all(&chunk?).await.map_err(from_io)?;
+            }
+            writer.shutdown().await.map_err(from_io)
+        }
+        .await;
+        match (written, writer.report()) {
+            (Ok(()), Some(report)) => Ok(PutResult {
+                e_tag: report.e_tag,
+                version: report.version,
+            }),
+            (result, _) => {
+                let _ = self.abort_multipart(location, &upload_id).await;
+                Err(result.err().unwrap_or_else(|| Error::Unknown.into()))
+            }
+        }
+    }
+}
+
+/// Next chunk of `reader`, `None` once the stream is done.
+pub(crate) async fn read_chunk(
+    reader: &ReadableStreamDefaultReader,
+) -> Result<Option<Bytes>, Error> {
+    let result = JsFuture::from(reader.read()).await?;
+    if Reflect::get(&result, &"done".into())?.is_truthy() {
+        return Ok(None);
+    }
+    let value: Uint8Array = Reflect::get(&result, &"value".into())?.dyn_into()?;
+    Ok(Some(Bytes::from(value.to_vec())))
+}
+
+/// Recovers the store error a part upload failed with from the writer's I/O error.
+fn from_io(err: io::Error) -> object_store::Error {
+    if !err
+        .get_ref()
+        .is_some_and(|inner| inner.is::<object_store::Error>())
+    {
+        return object_store::Error::Generic {
+            store: "S3",
+            source: Box::new(err),
+        };
+    }
+    *err.into_inner()
+        .and_then(|inner| inner.downcast().ok())
+        .expect("checked to be a store error")
+}
@@ -0,0 +1,100 @@
+use std::net::Ipv4Addr;
+
+use crate::error::Error;
+
+/// Rules bucket names are checked against when the store is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BucketNaming {
+    /// Naming rules of S3 general purpose buckets: 3 to 63 lowercase letters, digits, dots and
+    /// hyphens, starting and ending with a letter or digit.
+    #[default]
+    Strict,
+    /// Legacy rules of buckets created in `us-east-1` before 2018 and of some S3-compatible
+    /// services: up to 255 letters, digits, dots, hyphens and underscores.
+    Legacy,
+    /// Bucket names aren't checked at all.
+    Unchecked,
+}
+
+const RESERVED_PREFIXES: [&str; 3] = ["xn--", "sthree-", "amzn-s3-demo-"];
+const RESERVED_SUFFIXES: [&str; 5] = ["-s3alias", "--ol-s3", ".mrap", "--x-s3", "--table-s3"];
+
+/// Checks `bucket` against `naming` and, unless unchecked, whether it can be part of the host
+/// name of virtual-hosted style requests.
+pub(crate) fn validate_bucket(
+    bucket: &str,
+    naming: BucketNaming,
+    path_style: bool,
+) -> Result<(), Error> {
+    let reason = match naming {
+        BucketNaming::Strict => strict_issue(bucket),
+        BucketNaming::Legacy => legacy_issue(bucket),
+        BucketNaming::Unchecked => return Ok(()),
+    };
+    if let Some(reason) = reason {
+        return Err(Error::InvalidBucketName {
+            bucket: bucket.to_owned(),
+            reason,
+        });
+    }
+    if !path_style {
+        if let Some(reason) = virtual_host_issue(bucket) {
+            return Err(Error::BucketNotVirtualHostable {
+                bucket: bucket.to_owned(),
+                reason,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn strict_issue(bucket: &str) -> Option<&'static str> {
+    if !(3..=63).contains(&bucket.len()) {
+        Some("must be 3 to 63 characters long")
+    } else if !bucket
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'.' || b == b'-')
+    {
+        Some("may only contain lowercase letters, digits, dots and hyphens")
+    } else if !bucket.starts_with(|c: char| c.is_ascii_alphanumeric())
+        || !bucket.ends_with(|c: char| c.is_ascii_alphanumeric())
+    {
+        Some("must start and end with a letter or digit")
+    } else if bucket.contains("..") {
+        Some("must not contain adjacent dots")
+    } else if bucket.parse::<Ipv4Addr>().is_ok() {
+        Some("must not be formatted as an IP address")
+    } else if RESERVED_PREFIXES.iter().any(|p| bucket.starts_with(p))
+        || RESERVED_SUFFIXES.iter().any(|s| bucket.ends_with(s))
+    {
+        Some("uses a prefix or suffix reserved by S3")
+    } else {
+        None
+    }
+}
+
+fn legacy_issue(bucket: &str) -> Option<&'static str> {
+    if !(3..=255).contains(&bucket.len()) {
+        Some("must be 3 to 255 characters long")
+    } else if !bucket
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_'))
+    {
+        Some("may only contain letters, digits, dots, hyphens and underscores")
+    } else {
+        None
+    }
+}
+
+fn virtual_host_issue(bucket: &str) -> Option<&'static str> {
+    if bucket.contains('.') {
+        // The wildcard certificate of the endpoint only covers a single label
+        Some("contains dots, which break TLS certificate validation")
+    } else if bucket.bytes().any(|b| b.is_ascii_uppercase() || b == b'_') {
+        Some("contains uppercase letters or underscores, which aren't valid in host names")
+    } else if bucket.len() > 63 {
+        Some("is longer than the 63 characters of a host name label")
+    } else {
+        None
+    }
+}
@@ -1,5 +1,10 @@
 use std::panic;
-use std::{ops::Deref, sync::Arc, time::SystemTime};
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use std::pin::pin;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use async_trait::async_trait;
 use aws_credential_types::{
@@ -12,45 +17,404 @@ use aws_sdk_s3::{
 };
 use aws_smithy_async::time::{SharedTimeSource, TimeSource};
 use aws_smithy_http::result::ConnectorError;
-use wasm_bindgen::{JsCast, JsValue};
-use wasm_timer::UNIX_EPOCH;
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use futures::future::{self, Either};
+use http::{HeaderName, HeaderValue};
+use tokio::sync::OwnedSemaphorePermit;
 
-use crate::{error::Error, S3};
+#[cfg(feature = "tolerant")]
+use crate::tolerant::{Leniency, TolerantParser};
+use crate::{
+    acl::{CannedAcl, DefaultAcl},
+    bucket_name::{validate_bucket, BucketNaming},
+    cache_control::CachePolicy,
+    checksum::ChecksumAlgorithm,
+    circuit::{Admission, CircuitBreaker},
+    coalesce::{DEFAULT_COALESCE_GAP, DEFAULT_MAX_COALESCED_SIZE},
+    conditional::{ConditionalDelete, ConditionalPut, CopyIfNotExists},
+    content_type::ContentTypes,
+    copy::MAX_COPY_OBJECT_SIZE,
+    delete::MAX_DELETE_BATCH,
+    delta::MANIFEST_PREFIX,
+    encryption::SseConfig,
+    environment::{default_timer, Clock, Random, SystemClock, SystemRandom, Timer},
+    error::Error,
+    events::TransferEvents,
+    instrument::StreamObserver,
+    journal::{Journal, JournalSink},
+    list_encoding::ListEncoding,
+    metrics::{counted, MetricsRecorder},
+    multipart::{DEFAULT_CONCURRENCY, DEFAULT_PART_SIZE},
+    observe::{Observer, ObserverHook},
+    parallel_get::ParallelDownloads,
+    partition::Partition,
+    persistent_cache::{EvictionPolicy, PersistentCache},
+    profile::{CompatProfile, Unsupported},
+    progress::ProgressListener,
+    read_only::ReadOnly,
+    remote_signing::{RemoteSigner, Signer, SigningKey, PLACEHOLDER_SECRET},
+    requester_pays::RequesterPays,
+    response_log::{CapturedResponse, ResponseLog},
+    retry::RetryConfig,
+    signed_headers::SignedHeaders,
+    signing::{SigningOverrides, UnsignedPayload},
+    storage_class::{DefaultStorageClass, StorageClass},
+    throttle::Throttle,
+    timeout::{FetchControl, Timeouts},
+    validate::{MAX_PART_SIZE, MAX_PUT_SIZE, MIN_PART_SIZE},
+    web_identity::{WebIdentityProvider, WebIdentityToken},
+    S3,
+};
 
 #[derive(Default)]
 pub struct S3Builder {
     pub(crate) bucket: Option<String>,
+    /// Key prefix of [`S3Builder::from_url`].
+    pub(crate) prefix: Option<object_store::path::Path>,
+    pub(crate) bucket_naming: BucketNaming,
     pub(crate) region: Option<String>,
     pub(crate) access_key_id: Option<String>,
     pub(crate) secret_access_key: Option<String>,
     pub(crate) session_token: Option<String>,
     pub(crate) endpoint: Option<String>,
+    pub(crate) force_path_style: Option<bool>,
+    pub(crate) allow_http: Option<bool>,
+    pub(crate) profile: Option<Arc<dyn CompatProfile>>,
+    pub(crate) profile_name: Option<String>,
+    pub(crate) unsupported: Unsupported,
+    pub(crate) response_capture: Option<usize>,
+    pub(crate) cache_policy: Option<Arc<dyn CachePolicy>>,
+    pub(crate) content_types: Option<ContentTypes>,
+    pub(crate) upload_quota: Option<u64>,
+    pub(crate) max_get_size: Option<usize>,
+    pub(crate) journal_capacity: Option<usize>,
+    pub(crate) journal_sinks: Vec<Arc<dyn JournalSink>>,
+    pub(crate) circuit_breaker: Option<(usize, Duration)>,
+    pub(crate) timer: Option<Arc<dyn Timer>>,
+    pub(crate) random: Option<Arc<dyn Random>>,
+    pub(crate) clock: Option<Arc<dyn Clock>>,
+    pub(crate) stream_observer: Option<Arc<dyn StreamObserver>>,
+    pub(crate) progress: Option<Arc<dyn ProgressListener>>,
+    pub(crate) observer_hook: Option<Arc<dyn ObserverHook>>,
+    pub(crate) signed_headers: Vec<(String, String)>,
+    pub(crate) signing_region: Option<String>,
+    pub(crate) signing_name: Option<String>,
+    pub(crate) unsigned_payload: bool,
+    pub(crate) request_payer: bool,
+    pub(crate) remote_signer: Option<Arc<dyn RemoteSigner>>,
+    pub(crate) default_acl: Option<CannedAcl>,
+    pub(crate) storage_class: Option<StorageClass>,
+    pub(crate) sts_endpoint: Option<String>,
+    pub(crate) sts_regional_endpoint: bool,
+    pub(crate) copy_if_not_exists: CopyIfNotExists,
+    pub(crate) conditional_put: ConditionalPut,
+    pub(crate) conditional_delete: ConditionalDelete,
+    pub(crate) delete_batch_size: Option<usize>,
+    pub(crate) sse: Option<SseConfig>,
+    pub(crate) checksum: Option<ChecksumAlgorithm>,
+    pub(crate) verify_checksums: bool,
+    pub(crate) list_encoding: ListEncoding,
+    pub(crate) multipart_part_size: Option<usize>,
+    pub(crate) multipart_concurrency: Option<usize>,
+    pub(crate) multipart_copy_threshold: Option<usize>,
+    pub(crate) multipart_put_threshold: Option<usize>,
+    pub(crate) idempotency_tokens: bool,
+    pub(crate) range_coalescing: Option<(usize, usize)>,
+    pub(crate) persistent_cache: Option<PersistentCache>,
+    pub(crate) adaptive_multipart_concurrency: Option<(usize, usize)>,
+    pub(crate) parallel_downloads: Option<ParallelDownloads>,
+    pub(crate) max_chunk_size: Option<usize>,
+    pub(crate) delta_manifest_prefix: Option<object_store::path::Path>,
+    pub(crate) retry: Option<RetryConfig>,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) request_timeout: Option<Duration>,
+    pub(crate) max_concurrent_requests: Option<usize>,
+    pub(crate) rate_limit: Option<(u32, u32)>,
+    pub(crate) anonymous: bool,
+    pub(crate) web_identity: Option<(String, String, Arc<dyn WebIdentityToken>)>,
+    #[cfg(feature = "tolerant")]
+    pub(crate) leniency: Option<Leniency>,
+    #[cfg(feature = "shared_session")]
+    pub(crate) shared_session: bool,
 }
 
 impl S3Builder {
     pub fn build(self) -> Result<S3, Error> {
         panic::set_hook(Box::new(console_error_panic_hook::hook));
-        let access_key_id = self.access_key_id.ok_or(Error::Unknown)?;
-        let secret_access_key = self.secret_access_key.ok_or(Error::Unknown)?;
-        let session_token = self.session_token;
-        let credentials = Credentials::from_keys(
-            access_key_id.deref(),
-            secret_access_key.deref(),
-            session_token,
+        // Building without the prefix would write outside of it
+        if let Some(prefix) = self
+            .prefix
+            .as_ref()
+            .filter(|prefix| prefix.parts().next().is_some())
+        {
+            return Err(Error::UnappliedPrefix(prefix.to_string()));
+        }
+        self.apply_profile().build_store()
+    }
+    fn build_store(self) -> Result<S3, Error> {
+        if let Some(endpoint) = &self.endpoint {
+            let scheme = endpoint.split_once("://").map(|(scheme, _)| scheme);
+            match scheme {
+                Some("https") => (),
+                Some("http") if self.allow_http.unwrap_or(true) => (),
+                _ => return Err(Error::InvalidEndpoint(endpoint.clone())),
+            }
+        }
+        let diagnostics = Arc::new(self.diagnostics());
+        let partition = self.partition();
+        let sts_endpoint = self.sts_endpoint();
+        let bucket = self.bucket.ok_or(Error::Unknown)?;
+        let path_style = self.force_path_style.unwrap_or(true);
+        validate_bucket(&bucket, self.bucket_naming, path_style)?;
+        let response_log = self.response_capture.map(ResponseLog::new);
+        let metrics = MetricsRecorder::default();
+        let signed_headers = SignedHeaders::new(
+            self.signed_headers
+                .iter()
+                .map(|(name, value)| {
+                    Ok((
+                        HeaderName::try_from(name.as_str())
+                            .map_err(|_| Error::InvalidHeader(name.clone()))?,
+                        HeaderValue::try_from(value.as_str())
+                            .map_err(|_| Error::InvalidHeader(name.clone()))?,
+                    ))
+                })
+                .collect::<Result<_, Error>>()?,
         );
+        let timer = self.timer.unwrap_or_else(default_timer);
+        let random = self.random.unwrap_or_else(|| Arc::new(SystemRandom));
+        let clock = self.clock.unwrap_or_else(|| Arc::new(SystemClock));
+        let journal =
+            (self.journal_capacity.is_some() || !self.journal_sinks.is_empty()).then(|| {
+                Journal::new(
+                    self.journal_capacity.unwrap_or(0),
+                    self.journal_sinks,
+                    clock.clone(),
+                )
+            });
+        let circuit_breaker = self
+            .circuit_breaker
+            .map(|(failure_threshold, probe_interval)| {
+                CircuitBreaker::new(failure_threshold, probe_interval, timer.clone())
+            });
+        let signing_region = self.signing_region.clone().or_else(|| self.region.clone());
+        // Static keys and remote signers sign in the adapter, which caches the derived keys
+        let signing_key = match (&self.remote_signer, &self.web_identity, &signing_region) {
+            (Some(remote), _, _) => Some(SigningKey::Remote(remote.clone())),
+            (None, None, Some(_)) if !self.anonymous => Some(SigningKey::secret(
+                self.secret_access_key.clone().ok_or(Error::Unknown)?,
+            )),
+            _ => None,
+        };
+        let signer = match signing_key {
+            Some(key) => Some(Arc::new(Signer::new(
+                self.access_key_id.clone().ok_or(Error::Unknown)?,
+                self.session_token.clone(),
+                signing_region.ok_or(Error::Unknown)?,
+                self.signing_name.clone().unwrap_or_else(|| "s3".to_owned()),
+                self.unsigned_payload,
+                key,
+                clock.clone(),
+            ))),
+            None => None,
+        };
+        let adapter = Adapter::new(
+            self.access_key_id.as_deref() == Some("access_key"),
+            response_log.clone(),
+            metrics.clone(),
+            circuit_breaker.clone(),
+            timer.clone(),
+            random.clone(),
+            self.retry.unwrap_or_else(RetryConfig::disabled),
+            Timeouts {
+                connect: self.connect_timeout,
+                total: self.request_timeout,
+            },
+            Throttle::new(self.max_concurrent_requests, self.rate_limit, timer.clone()),
+        )
+        .with_signer(signer.clone());
+        let static_credentials = || {
+            Ok::<_, Error>(Credentials::from_keys(
+                self.access_key_id.as_deref().ok_or(Error::Unknown)?,
+                self.secret_access_key.as_deref().ok_or(Error::Unknown)?,
+                self.session_token.clone(),
+            ))
+        };
+        // Presigned URLs are signed by the SDK, which needs the keys the adapter signs with
+        let presign_credentials = match (&signer, &self.remote_signer) {
+            (Some(signer), Some(_)) => Some(Credentials::from_keys(
+                signer.access_key_id(),
+                PLACEHOLDER_SECRET,
+                self.session_token.clone(),
+            )),
+            (Some(_), None) => Some(static_credentials()?),
+            (None, _) => None,
+        };
+        let credentials = match (self.anonymous, self.web_identity) {
+            // Requests are sent unsigned to the adapter, which signs them
+            _ if signer.is_some() => None,
+            (true, _) => None,
+            (false, Some((role_arn, session_name, token))) => {
+                let sts = aws_sdk_sts::Client::from_conf(
+                    aws_sdk_sts::Config::builder()
+                        .region(self.region.clone().map(Region::new))
+                        .endpoint_url(sts_endpoint)
+                        .sleep_impl(SharedAsyncSleep::new(TimerSleep(timer.clone())))
+                        .time_source(SharedTimeSource::new(ClockTime(clock.clone())))
+                        .http_connector(adapter.clone())
+                        .build(),
+                );
+                let provider =
+                    WebIdentityProvider::new(sts, role_arn, session_name, token, clock.clone());
+                #[cfg(feature = "shared_session")]
+                let provider = match self.shared_session {
+                    true => provider.shared(),
+                    false => provider,
+                };
+                Some(SharedCredentialsProvider::new(provider))
+            }
+            (false, None) => Some(SharedCredentialsProvider::new(static_credentials()?)),
+        };
+        #[cfg(feature = "batch")]
+        let control = {
+            let mut builder = aws_sdk_s3control::Config::builder()
+                .region(self.region.clone().map(Region::new))
+                .credentials_cache(CredentialsCache::no_caching())
+                .sleep_impl(SharedAsyncSleep::new(TimerSleep(timer.clone())))
+                .time_source(SharedTimeSource::new(ClockTime(clock.clone())))
+                .http_connector(adapter.clone())
+                .interceptor(signed_headers.clone());
+            builder.set_credentials_provider(credentials.clone());
+            if self.anonymous {
+                builder = builder.interceptor(ReadOnly);
+            }
+            #[cfg(feature = "tolerant")]
+            if let Some(leniency) = self.leniency {
+                builder = builder.interceptor(TolerantParser::new(leniency));
+            }
+            aws_sdk_s3control::Client::from_conf(builder.build())
+        };
         let mut builder = Config::builder()
-            .force_path_style(true)
-            .region(self.region.map(|x| Region::new(x)))
-            .credentials_provider(SharedCredentialsProvider::new(credentials))
+            .force_path_style(path_style)
+            .region(self.region.map(Region::new))
             .credentials_cache(CredentialsCache::no_caching())
-            .sleep_impl(SharedAsyncSleep::new(BrowserSleep))
-            .time_source(SharedTimeSource::new(BrowserNow))
-            .http_connector(Adapter::new(access_key_id == "access_key"));
+            .sleep_impl(SharedAsyncSleep::new(TimerSleep(timer.clone())))
+            .time_source(SharedTimeSource::new(ClockTime(clock.clone())))
+            .http_connector(adapter)
+            .interceptor(signed_headers);
+        // Without a credentials provider only operations allowing unsigned requests can be sent
+        builder.set_credentials_provider(credentials);
+        if self.anonymous {
+            builder = builder.interceptor(ReadOnly);
+        }
+        if self.signing_region.is_some() || self.signing_name.is_some() {
+            builder = builder.endpoint_resolver(SigningOverrides::new(
+                self.signing_region,
+                self.signing_name,
+            ));
+        }
+        if self.unsigned_payload {
+            builder = builder.interceptor(UnsignedPayload);
+        }
+        if self.request_payer {
+            builder = builder.interceptor(RequesterPays);
+        }
+        if let Some(acl) = self.default_acl {
+            builder = builder.interceptor(DefaultAcl(acl));
+        }
+        if let Some(storage_class) = self.storage_class {
+            builder = builder.interceptor(DefaultStorageClass(storage_class));
+        }
+        #[cfg(feature = "tolerant")]
+        if let Some(leniency) = self.leniency {
+            builder = builder.interceptor(TolerantParser::new(leniency));
+        }
+        if cfg!(feature = "tracing") || self.observer_hook.is_some() {
+            builder =
+                builder.interceptor(Observer::new(self.observer_hook, timer.clone(), path_style));
+        }
         builder.set_endpoint_url(self.endpoint);
         let sdk_config = builder.build();
+        let client = Arc::new(Client::from_conf(sdk_config.clone()));
+        let presign_client = match presign_credentials {
+            Some(credentials) => Arc::new(Client::from_conf(
+                sdk_config
+                    .to_builder()
+                    .credentials_provider(credentials)
+                    .build(),
+            )),
+            None => client.clone(),
+        };
         Ok(S3 {
-            client: Arc::new(Client::from_conf(sdk_config)),
-            bucket: self.bucket.ok_or(Error::Unknown)?,
+            client,
+            presign_client,
+            signer,
+            bucket,
+            response_log,
+            metrics,
+            cache_policy: self.cache_policy,
+            content_types: self.content_types.map(Arc::new),
+            upload_quota: self.upload_quota,
+            max_get_size: self.max_get_size,
+            journal,
+            head_denied: Arc::default(),
+            circuit_breaker,
+            timer,
+            random,
+            clock,
+            stream_observer: self.stream_observer,
+            progress: self.progress,
+            events: TransferEvents::default(),
+            partition,
+            copy_if_not_exists: self.copy_if_not_exists,
+            conditional_put: self.conditional_put,
+            conditional_delete: self.conditional_delete,
+            unsupported: self.unsupported,
+            capabilities: Arc::default(),
+            delete_batch_size: self
+                .delete_batch_size
+                .unwrap_or(MAX_DELETE_BATCH)
+                .clamp(1, MAX_DELETE_BATCH),
+            sse: self.sse,
+            checksum: self.checksum,
+            verify_checksums: self.verify_checksums,
+            list_encoding: self.list_encoding,
+            multipart_part_size: usize::try_from(
+                (self.multipart_part_size.unwrap_or(DEFAULT_PART_SIZE) as u64)
+                    .clamp(MIN_PART_SIZE as u64, MAX_PART_SIZE),
+            )
+            .unwrap_or(usize::MAX),
+            multipart_concurrency: self
+                .multipart_concurrency
+                .unwrap_or(DEFAULT_CONCURRENCY)
+                .max(1),
+            multipart_copy_threshold: self
+                .multipart_copy_threshold
+                .map_or(MAX_COPY_OBJECT_SIZE, |bytes| bytes as u64)
+                .min(MAX_COPY_OBJECT_SIZE),
+            multipart_put_threshold: self
+                .multipart_put_threshold
+                .map_or(MAX_PUT_SIZE, |bytes| bytes as u64)
+                .min(MAX_PUT_SIZE),
+            idempotency_tokens: self.idempotency_tokens,
+            coalesce_gap: self
+                .range_coalescing
+                .map_or(DEFAULT_COALESCE_GAP, |(gap, _)| gap),
+            max_coalesced_size: self
+                .range_coalescing
+                .map_or(DEFAULT_MAX_COALESCED_SIZE, |(_, max_size)| max_size),
+            persistent_cache: self.persistent_cache,
+            adaptive_multipart_concurrency: self.adaptive_multipart_concurrency,
+            parallel_downloads: self.parallel_downloads,
+            max_chunk_size: self.max_chunk_size,
+            delta_manifest_prefix: self
+                .delta_manifest_prefix
+                .unwrap_or_else(|| MANIFEST_PREFIX.into()),
+            diagnostics,
+            #[cfg(feature = "batch")]
+            control: Arc::new(control),
+            #[cfg(feature = "shared_session")]
+            shared_session: self.shared_session,
         })
     }
     pub fn bucket(mut self, value: impl Into<String>) -> Self {
@@ -78,56 +442,479 @@ impl S3Builder {
         self.endpoint = Some(value.into());
         self
     }
+    /// Send requests to an S3-compatible service like MinIO, Cloudflare R2 or LocalStack at `url`
+    /// instead of AWS, same as [`S3Builder::endpoint`].
+    pub fn with_endpoint(self, url: impl Into<String>) -> Self {
+        self.endpoint(url)
+    }
+    /// Address the bucket in the path (`<endpoint>/<bucket>/<key>`) instead of the host name
+    /// (`<bucket>.<endpoint>/<key>`), defaults to `true` which every S3-compatible service
+    /// supports.
+    pub fn with_force_path_style(mut self, force_path_style: bool) -> Self {
+        self.force_path_style = Some(force_path_style);
+        self
+    }
+    /// Rules the bucket name is checked against by [`S3Builder::build`], defaults to
+    /// [`BucketNaming::Strict`]. Names that can't be part of a host name are rejected with
+    /// [`Error::BucketNotVirtualHostable`] unless path-style addressing is used.
+    pub fn with_bucket_naming(mut self, naming: BucketNaming) -> Self {
+        self.bucket_naming = naming;
+        self
+    }
+    /// Adjust the store to the quirks of an S3-compatible service, e.g.
+    /// [`R2`](crate::profile::R2) or [`Minio`](crate::profile::Minio). Settings of the builder take
+    /// precedence over the profile's endpoint, region and addressing style.
+    pub fn with_profile(mut self, profile: impl CompatProfile + 'static) -> Self {
+        self.profile = Some(Arc::new(profile));
+        self
+    }
+    /// Whether an `http://` endpoint is accepted, defaults to `true`. Disallowing it makes
+    /// [`S3Builder::build`] fail with [`Error::InvalidEndpoint`] for unencrypted endpoints.
+    pub fn with_allow_http(mut self, allow_http: bool) -> Self {
+        self.allow_http = Some(allow_http);
+        self
+    }
+    /// Send unsigned requests, e.g. to read public datasets without credentials.
+    ///
+    /// The access key and secret aren't required. Only `GET` and `HEAD` requests can be sent,
+    /// writes fail with [`Error::AnonymousWrite`].
+    pub fn with_anonymous(mut self) -> Self {
+        self.anonymous = true;
+        self
+    }
+    /// Exchange the OIDC token of `token` for temporary credentials of `role_arn` with STS
+    /// `AssumeRoleWithWebIdentity` instead of using an access key, see
+    /// [`S3Builder::sts_endpoint`]. The credentials are refreshed before they expire.
+    pub fn with_web_identity(
+        mut self,
+        role_arn: impl Into<String>,
+        session_name: impl Into<String>,
+        token: impl WebIdentityToken + 'static,
+    ) -> Self {
+        self.web_identity = Some((role_arn.into(), session_name.into(), Arc::new(token)));
+        self
+    }
+    /// Keep the raw response headers of the last `capacity` requests, see [`S3::captured_responses`].
+    pub fn with_response_header_capture(mut self, capacity: usize) -> Self {
+        self.response_capture = Some(capacity);
+        self
+    }
+    /// Apply the caching headers chosen by `policy` to every written object.
+    pub fn with_cache_policy(mut self, policy: impl CachePolicy + 'static) -> Self {
+        self.cache_policy = Some(Arc::new(policy));
+        self
+    }
+    /// Set the `Content-Type` of written objects without one from the extension of their path,
+    /// e.g. `image/png` for `.png`, so browsers can display them from presigned URLs.
+    pub fn with_content_type_inference(mut self, infer: bool) -> Self {
+        self.content_types = infer.then(|| self.content_types.unwrap_or_default());
+        self
+    }
+    /// Infer `content_type` for objects with `extension`, e.g. `geojson`, taking precedence over
+    /// the built-in types. Enables [content type inference](Self::with_content_type_inference).
+    pub fn with_content_type_for(
+        mut self,
+        extension: &str,
+        content_type: impl Into<String>,
+    ) -> Self {
+        let content_types = self.content_types.unwrap_or_default();
+        self.content_types = Some(content_types.with_override(extension, content_type.into()));
+        self
+    }
+    /// Largest upload [`S3::validate_put`] considers acceptable.
+    pub fn with_upload_quota(mut self, bytes: u64) -> Self {
+        self.upload_quota = Some(bytes);
+        self
+    }
+    /// Refuse unranged reads of objects larger than `bytes` with [`Error::BodyTooLarge`].
+    pub fn with_max_get_size(mut self, bytes: usize) -> Self {
+        self.max_get_size = Some(bytes);
+        self
+    }
+    /// Fail fast with [`Error::CircuitOpen`] after `failure_threshold` consecutive failed
+    /// requests to an endpoint, probing it again every `probe_interval`.
+    pub fn with_circuit_breaker(
+        mut self,
+        failure_threshold: usize,
+        probe_interval: Duration,
+    ) -> Self {
+        self.circuit_breaker = Some((failure_threshold, probe_interval));
+        self
+    }
+    /// Keep at most `requests` requests of the store in flight, further requests wait for one
+    /// to finish. Every attempt of a retried request counts.
+    pub fn with_max_concurrent_requests(mut self, requests: usize) -> Self {
+        self.max_concurrent_requests = Some(requests);
+        self
+    }
+    /// Send at most `requests_per_second` requests per second on average, with bursts of up to
+    /// `burst` requests.
+    pub fn with_rate_limit(mut self, requests_per_second: u32, burst: u32) -> Self {
+        self.rate_limit = Some((requests_per_second, burst));
+        self
+    }
+    /// Measure durations and sleep with `timer` instead of the browser clock.
+    pub fn with_timer(mut self, timer: impl Timer + 'static) -> Self {
+        self.timer = Some(Arc::new(timer));
+        self
+    }
+    /// Draw jitter and other random choices from `random` instead of the platform generator.
+    pub fn with_random(mut self, random: impl Random + 'static) -> Self {
+        self.random = Some(Arc::new(random));
+        self
+    }
+    /// Take signing times, credential expiry and other dates from `clock` instead of the
+    /// browser clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+    /// Report every S3 operation with its bucket, key, byte counts, duration and status to `hook`.
+    pub fn with_observer_hook(mut self, hook: impl ObserverHook + 'static) -> Self {
+        self.observer_hook = Some(Arc::new(hook));
+        self
+    }
+    /// Report the timing of every GET payload stream to `observer`, see [`StreamTiming`].
+    ///
+    /// [`StreamTiming`]: crate::instrument::StreamTiming
+    pub fn with_stream_observer(mut self, observer: impl StreamObserver + 'static) -> Self {
+        self.stream_observer = Some(Arc::new(observer));
+        self
+    }
+    /// Report the progress of every upload and download to `listener`, see
+    /// [`S3::with_progress_listener`] to follow single operations.
+    pub fn with_progress_listener(mut self, listener: impl ProgressListener + 'static) -> Self {
+        self.progress = Some(Arc::new(listener));
+        self
+    }
+    /// Add the header `name: value` to every request, covered by the request signature.
+    pub fn with_signed_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.signed_headers.push((name.into(), value.into()));
+        self
+    }
+    /// Sign requests for `region` instead of the configured region, e.g. `auto` for
+    /// Cloudflare R2.
+    pub fn with_signing_region(mut self, region: impl Into<String>) -> Self {
+        self.signing_region = Some(region.into());
+        self
+    }
+    /// Sign requests for the service `name` instead of `s3`, for gateways expecting another one.
+    pub fn with_signing_name(mut self, name: impl Into<String>) -> Self {
+        self.signing_name = Some(name.into());
+        self
+    }
+    /// Send `x-amz-content-sha256: UNSIGNED-PAYLOAD` instead of signing the body hash.
+    pub fn with_unsigned_payload(mut self, unsigned_payload: bool) -> Self {
+        self.unsigned_payload = unsigned_payload;
+        self
+    }
+    /// Have `signer` sign every request instead of holding a secret key, only the
+    /// [`access_key_id`](Self::access_key_id) and, for temporary credentials, the
+    /// [`session_token`](Self::session_token) are needed.
+    pub fn with_remote_signer(mut self, signer: impl RemoteSigner + 'static) -> Self {
+        self.remote_signer = Some(Arc::new(signer));
+        self
+    }
+    /// Send `x-amz-request-payer: requester` with every read, required to read from
+    /// requester-pays buckets of other accounts.
+    pub fn with_request_payer(mut self, request_payer: bool) -> Self {
+        self.request_payer = request_payer;
+        self
+    }
+    /// Apply `acl` to every put, multipart upload and copy that doesn't set its own with
+    /// [`PutAttributes::acl`](crate::attributes::PutAttributes::acl), e.g.
+    /// [`CannedAcl::BucketOwnerFullControl`] for writes into buckets of other accounts.
+    pub fn with_default_acl(mut self, acl: CannedAcl) -> Self {
+        self.default_acl = Some(acl);
+        self
+    }
+    /// Write every put, multipart upload and copy that doesn't set its own storage class with
+    /// [`PutAttributes::storage_class`](crate::attributes::PutAttributes::storage_class) to
+    /// `storage_class`, e.g. [`StorageClass::GlacierIr`] for a store of cold partitions.
+    pub fn with_storage_class(mut self, storage_class: StorageClass) -> Self {
+        self.storage_class = Some(storage_class);
+        self
+    }
+    /// Exchange credentials with the STS endpoint `url` instead of the derived one.
+    pub fn with_sts_endpoint(mut self, url: impl Into<String>) -> Self {
+        self.sts_endpoint = Some(url.into());
+        self
+    }
+    /// Exchange credentials with the STS endpoint of the configured region instead of the global
+    /// one.
+    pub fn with_sts_regional_endpoint(mut self, regional: bool) -> Self {
+        self.sts_regional_endpoint = regional;
+        self
+    }
+    /// STS endpoint used for credential exchanges, see [`S3Builder::with_sts_endpoint`] and
+    /// [`S3Builder::with_sts_regional_endpoint`].
+    ///
+    /// Partitions without a global endpoint always use the regional one.
+    pub fn sts_endpoint(&self) -> String {
+        let partition = self.partition();
+        match (&self.sts_endpoint, &self.region) {
+            (Some(endpoint), _) => endpoint.clone(),
+            (None, Some(region))
+                if self.sts_regional_endpoint || !partition.has_global_endpoints() =>
+            {
+                partition.regional_endpoint("sts", region)
+            }
+            _ => format!("https://sts.{}", partition.dns_suffix()),
+        }
+    }
+    /// Partition of the configured region.
+    pub fn partition(&self) -> Partition {
+        self.region
+            .as_deref()
+            .map(Partition::from_region)
+            .unwrap_or_default()
+    }
+    /// Strategy of [`ObjectStore::copy_if_not_exists`](object_store::ObjectStore::copy_if_not_exists),
+    /// defaults to [`CopyIfNotExists::HeadThenCopy`].
+    pub fn with_copy_if_not_exists(mut self, strategy: CopyIfNotExists) -> Self {
+        self.copy_if_not_exists = strategy;
+        self
+    }
+    /// How puts with [`PutMode::Create`](object_store::PutMode::Create) or
+    /// [`PutMode::Update`](object_store::PutMode::Update) are made conditional, defaults to
+    /// [`ConditionalPut::Conditional`].
+    pub fn with_conditional_put(mut self, strategy: ConditionalPut) -> Self {
+        self.conditional_put = strategy;
+        self
+    }
+    /// How [`S3::delete_if_match`] makes the delete conditional, defaults to
+    /// [`ConditionalDelete::Conditional`].
+    pub fn with_conditional_delete(mut self, strategy: ConditionalDelete) -> Self {
+        self.conditional_delete = strategy;
+        self
+    }
+    /// Keys deleted per `DeleteObjects` request by `delete_stream`, at most
+    /// [`MAX_DELETE_BATCH`] which is also the default.
+    pub fn with_delete_batch_size(mut self, keys: usize) -> Self {
+        self.delete_batch_size = Some(keys);
+        self
+    }
+    /// Encrypt written objects with `sse`. SSE-C keys are also sent with every read.
+    pub fn with_sse(mut self, sse: SseConfig) -> Self {
+        self.sse = Some(sse);
+        self
+    }
+    /// Send the `algorithm` checksum of the data with every upload. S3 rejects uploads that
+    /// don't match it, completed multipart uploads whose checksum doesn't match their parts are
+    /// counted in [`StoreMetrics::checksum_mismatches`](crate::metrics::StoreMetrics::checksum_mismatches).
+    pub fn with_checksum(mut self, algorithm: ChecksumAlgorithm) -> Self {
+        self.checksum = Some(algorithm);
+        self
+    }
+    /// Request the checksums of downloaded objects and fail reads of whole objects whose data
+    /// doesn't match with [`Error::ChecksumMismatch`]. Ranged reads and objects without a
+    /// checksum aren't verified.
+    pub fn with_checksum_verification(mut self, verify: bool) -> Self {
+        self.verify_checksums = verify;
+        self
+    }
+    /// Key encoding requested for listings, defaults to [`ListEncoding::Plain`].
+    pub fn with_list_encoding(mut self, encoding: ListEncoding) -> Self {
+        self.list_encoding = encoding;
+        self
+    }
+    /// Bytes buffered per part of `put_multipart` uploads, clamped to the part sizes S3 accepts.
+    /// Defaults to 10 MiB.
+    pub fn with_multipart_part_size(mut self, bytes: usize) -> Self {
+        self.multipart_part_size = Some(bytes);
+        self
+    }
+    /// Parts of a `put_multipart` upload in flight at once, defaults to 16.
+    pub fn with_multipart_concurrency(mut self, parts: usize) -> Self {
+        self.multipart_concurrency = Some(parts);
+        self
+    }
+    /// Merge ranges of `get_ranges` that are at most `gap` bytes apart into one request as long
+    /// as it's at most `max_request_size` bytes, defaults to [`DEFAULT_COALESCE_GAP`] and
+    /// [`DEFAULT_MAX_COALESCED_SIZE`].
+    pub fn with_range_coalescing(mut self, gap: usize, max_request_size: usize) -> Self {
+        self.range_coalescing = Some((gap, max_request_size));
+        self
+    }
+    /// Cache ranges read with `get_range` and `get_ranges` in IndexedDB, so they survive page
+    /// reloads. At most `max_bytes` are cached, entries are evicted according to `policy`.
+    ///
+    /// Every cached read sends a `HEAD` to check the object's ETag, cached ranges of other
+    /// versions are never served.
+    pub fn with_persistent_cache(mut self, max_bytes: usize, policy: EvictionPolicy) -> Self {
+        self.persistent_cache = Some(PersistentCache { max_bytes, policy });
+        self
+    }
+    /// Copy objects larger than `bytes` part by part with `UploadPartCopy` instead of a single
+    /// `CopyObject`, at most and by default [`MAX_COPY_OBJECT_SIZE`].
+    pub fn with_multipart_copy_threshold(mut self, bytes: usize) -> Self {
+        self.multipart_copy_threshold = Some(bytes);
+        self
+    }
+    /// Upload payloads of `put` and `put_opts` larger than `bytes` with a multipart upload of
+    /// slices of the payload instead of a single `PutObject`, at most and by default
+    /// [`MAX_PUT_SIZE`].
+    pub fn with_multipart_put_threshold(mut self, bytes: usize) -> Self {
+        self.multipart_put_threshold = Some(bytes);
+        self
+    }
+    /// Adjust the parts of a `put_multipart` upload in flight between `min` and `max` to the
+    /// measured throughput instead of keeping a fixed number, backing off when requests fail.
+    /// Overrides [`S3Builder::with_multipart_concurrency`].
+    pub fn with_adaptive_multipart_concurrency(mut self, min: usize, max: usize) -> Self {
+        self.adaptive_multipart_concurrency = Some((min, max));
+        self
+    }
+    /// Read objects larger than `threshold` bytes with ranged requests of `chunk_size` bytes,
+    /// up to `parallelism` of them in flight, instead of a single one. The chunks are streamed
+    /// in order.
+    ///
+    /// Whole-object `get`s start with a ranged request for the first chunk, which also tells
+    /// the size. Objects of at most `threshold` bytes are completed with a single request for
+    /// the rest.
+    pub fn with_parallel_downloads(
+        mut self,
+        threshold: usize,
+        chunk_size: usize,
+        parallelism: usize,
+    ) -> Self {
+        self.parallel_downloads = Some(ParallelDownloads {
+            threshold,
+            chunk_size: chunk_size.max(1),
+            parallelism: parallelism.max(1),
+        });
+        self
+    }
+    /// Split the payload streams of `get`s into chunks of at most `bytes` bytes. The chunks are
+    /// slices of the response body, nothing is copied.
+    ///
+    /// By default a response is streamed as a single chunk.
+    pub fn with_max_chunk_size(mut self, bytes: usize) -> Self {
+        self.max_chunk_size = Some(bytes.max(1));
+        self
+    }
+    /// Store the block manifests of [`S3::delta_put`] below `prefix` instead of
+    /// [`MANIFEST_PREFIX`]. Manifests are regular objects and show up in listings of the prefix.
+    pub fn with_delta_manifest_prefix(
+        mut self,
+        prefix: impl Into<object_store::path::Path>,
+    ) -> Self {
+        self.delta_manifest_prefix = Some(prefix.into());
+        self
+    }
+    /// Repair responses of S3-compatible endpoints the SDK parser would reject as described by
+    /// `leniency`.
+    #[cfg(feature = "tolerant")]
+    pub fn with_tolerant_parsing(mut self, leniency: Leniency) -> Self {
+        self.leniency = Some(leniency);
+        self
+    }
+    /// Coordinate with the stores of the other tabs and workers of the origin that opted in:
+    /// web identity tokens are exchanged by one tab at a time and the credentials are shared
+    /// through IndexedDB, and ranges missing from the persistent cache are downloaded by
+    /// one tab while the others wait for it behind a Web Lock and read them from the cache.
+    #[cfg(feature = "shared_session")]
+    pub fn with_shared_session(mut self) -> Self {
+        self.shared_session = true;
+        self
+    }
+    /// Abort attempts of a request that didn't receive the response headers within `timeout`.
+    ///
+    /// Aborted attempts are retried as configured with [`S3Builder::with_retry`], a request whose
+    /// attempts all timed out fails with [`Error::RequestTimeout`], see
+    /// [`is_timeout`](crate::timeout::is_timeout).
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+    /// Abort attempts of a request that didn't complete, including reading the response body,
+    /// within `timeout`. Retried like [`S3Builder::with_connect_timeout`].
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+    /// Retry failed requests according to `retry`, by default every request is sent once.
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+    /// Attach a random token to every put as
+    /// [`IDEMPOTENCY_TOKEN_KEY`](crate::idempotency::IDEMPOTENCY_TOKEN_KEY) metadata.
+    ///
+    /// When a retried conditional put is rejected because an earlier attempt of it was written in
+    /// the meantime, the put succeeds with the written object instead of failing.
+    pub fn with_idempotency_tokens(mut self) -> Self {
+        self.idempotency_tokens = true;
+        self
+    }
+    /// Record completed puts, copies and deletes in a journal retaining the last `capacity`
+    /// entries, see [`S3::journal_since`] and [`S3::subscribe_journal`].
+    pub fn with_journal(mut self, capacity: usize) -> Self {
+        self.journal_capacity = Some(capacity);
+        self
+    }
+    /// Forward every journal entry to `sink`, enables the journal.
+    pub fn with_journal_sink(mut self, sink: impl JournalSink + 'static) -> Self {
+        self.journal_sinks.push(Arc::new(sink));
+        self
+    }
 }
 
 #[derive(Debug)]
-struct BrowserNow;
-impl TimeSource for BrowserNow {
+struct ClockTime(Arc<dyn Clock>);
+impl TimeSource for ClockTime {
     fn now(&self) -> SystemTime {
-        let offset = wasm_timer::SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap();
-        std::time::UNIX_EPOCH + offset
+        self.0.now()
     }
 }
 
 #[derive(Debug, Clone)]
-struct BrowserSleep;
-impl AsyncSleep for BrowserSleep {
+struct TimerSleep(Arc<dyn Timer>);
+impl AsyncSleep for TimerSleep {
     fn sleep(&self, duration: std::time::Duration) -> Sleep {
-        Sleep::new(Box::pin(async move {
-            wasm_timer::Delay::new(duration).await.unwrap();
-        }))
+        Sleep::new(self.0.sleep(duration))
     }
 }
 
-#[async_trait(?Send)]
-trait MakeRequestBrowser {
+/// Sends a single attempt of a request, with `fetch` on wasm and hyper in native builds.
+#[cfg_attr(all(feature = "native", not(target_arch = "wasm32")), async_trait)]
+#[cfg_attr(
+    not(all(feature = "native", not(target_arch = "wasm32"))),
+    async_trait(?Send)
+)]
+pub(crate) trait MakeRequest {
     async fn send(
+        &self,
         parts: http::request::Parts,
         body: SdkBody,
-    ) -> Result<http::Response<SdkBody>, JsValue>;
+        control: &FetchControl,
+    ) -> Result<http::Response<SdkBody>, Error>;
 }
 
 pub struct BrowserHttpClient {}
 
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
 #[async_trait(?Send)]
-impl MakeRequestBrowser for BrowserHttpClient {
+impl MakeRequest for BrowserHttpClient {
     async fn send(
+        &self,
         parts: http::request::Parts,
         body: SdkBody,
-    ) -> Result<http::Response<SdkBody>, JsValue> {
-        use js_sys::{Array, ArrayBuffer, Reflect, Uint8Array};
+        control: &FetchControl,
+    ) -> Result<http::Response<SdkBody>, Error> {
+        use js_sys::{Array, Reflect, Uint8Array};
+        use wasm_bindgen::JsCast;
         use wasm_bindgen_futures::JsFuture;
 
-        let mut opts = web_sys::RequestInit::new();
-        opts.method(parts.method.as_str());
-        opts.mode(web_sys::RequestMode::Cors);
+        let opts = web_sys::RequestInit::new();
+        opts.set_method(parts.method.as_str());
+        opts.set_mode(web_sys::RequestMode::Cors);
+        opts.set_signal(control.signal().as_ref());
 
         let body_pinned = std::pin::Pin::new(body.bytes().unwrap());
-        if body_pinned.len() > 0 {
+        if !body_pinned.is_empty() {
             let uint_8_array = unsafe { Uint8Array::view(&body_pinned) };
-            opts.body(Some(&uint_8_array));
+            opts.set_body(&uint_8_array);
         }
 
         let request = web_sys::Request::new_with_str_and_init(&parts.uri.to_string(), &opts)?;
@@ -140,16 +927,12 @@ impl MakeRequestBrowser for BrowserHttpClient {
             request.headers().set(name, value)?;
         }
 
-        let window = web_sys::window().ok_or("could not get window")?;
+        let window =
+            web_sys::window().ok_or_else(|| Error::Js("could not get window".to_owned()))?;
         let promise = window.fetch_with_request(&request);
         let res_web = JsFuture::from(promise).await?;
         let res_web: web_sys::Response = res_web.dyn_into().unwrap();
-
-        let promise_array = res_web.array_buffer()?;
-        let array = JsFuture::from(promise_array).await?;
-        let buf: ArrayBuffer = array.dyn_into().unwrap();
-        let slice = Uint8Array::new(&buf);
-        let body = slice.to_vec();
+        control.connected();
 
         let mut builder = http::Response::builder().status(res_web.status());
         for i in js_sys::try_iter(&res_web.headers())?.unwrap() {
@@ -165,7 +948,20 @@ impl MakeRequestBrowser for BrowserHttpClient {
                 .unwrap();
             builder = builder.header(&key, &value);
         }
-        let res_body = SdkBody::from(body);
+        // Objects are streamed, other responses are buffered for the interceptors that read them
+        let streamed = parts.method == http::Method::GET
+            && res_web.ok()
+            && parts
+                .uri
+                .query()
+                .is_some_and(|query| query.contains("x-id=GetObject"));
+        let res_body = match res_web.body() {
+            Some(stream) if streamed => control.stream_body(stream),
+            _ => {
+                let buffer = JsFuture::from(res_web.array_buffer()?).await?;
+                SdkBody::from(Uint8Array::new(&buffer).to_vec())
+            }
+        };
         let res = builder.body(res_body).unwrap();
         Ok(res)
     }
@@ -173,12 +969,19 @@ impl MakeRequestBrowser for BrowserHttpClient {
 
 pub struct MockedHttpClient {}
 
-#[async_trait(?Send)]
-impl MakeRequestBrowser for MockedHttpClient {
+#[cfg_attr(all(feature = "native", not(target_arch = "wasm32")), async_trait)]
+#[cfg_attr(
+    not(all(feature = "native", not(target_arch = "wasm32"))),
+    async_trait(?Send)
+)]
+impl MakeRequest for MockedHttpClient {
     async fn send(
+        &self,
         _parts: http::request::Parts,
         _body: SdkBody,
-    ) -> Result<http::Response<SdkBody>, JsValue> {
+        control: &FetchControl,
+    ) -> Result<http::Response<SdkBody>, Error> {
+        control.connected();
         let body = "{
             \"Functions\": [
                 {
@@ -197,16 +1000,266 @@ impl MakeRequestBrowser for MockedHttpClient {
 }
 
 #[derive(Debug, Clone)]
-struct Adapter {
+pub(crate) struct Adapter {
     use_mock: bool,
+    response_log: Option<ResponseLog>,
+    metrics: MetricsRecorder,
+    circuit_breaker: Option<CircuitBreaker>,
+    timer: Arc<dyn Timer>,
+    random: Arc<dyn Random>,
+    retry: RetryConfig,
+    timeouts: Timeouts,
+    throttle: Throttle,
+    signer: Option<Arc<Signer>>,
+    #[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+    client: crate::native::NativeHttpClient,
+}
+
+impl Adapter {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        use_mock: bool,
+        response_log: Option<ResponseLog>,
+        metrics: MetricsRecorder,
+        circuit_breaker: Option<CircuitBreaker>,
+        timer: Arc<dyn Timer>,
+        random: Arc<dyn Random>,
+        retry: RetryConfig,
+        timeouts: Timeouts,
+        throttle: Throttle,
+    ) -> Self {
+        Self {
+            use_mock,
+            response_log,
+            metrics,
+            circuit_breaker,
+            timer,
+            random,
+            retry,
+            timeouts,
+            throttle,
+            signer: None,
+            #[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+            client: Default::default(),
+        }
+    }
+
+    /// Signs every request with `signer` before sending it.
+    pub(crate) fn with_signer(mut self, signer: Option<Arc<Signer>>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Sends the request, retrying network errors and retryable statuses as configured.
+    pub(crate) async fn send(
+        &self,
+        parts: http::request::Parts,
+        body: SdkBody,
+    ) -> Result<http::Response<SdkBody>, ConnectorError> {
+        let (mut parts, mut body) = (parts, body);
+        if let Some(signer) = &self.signer {
+            signer
+                .sign(&mut parts, &body)
+                .await
+                .map_err(|err| ConnectorError::other(Box::new(err), None))?;
+        }
+        let mut retry = 0;
+        loop {
+            let replay = if retry + 1 < self.retry.max_attempts {
+                body.try_clone().map(|body| (clone_parts(&parts), body))
+            } else {
+                None
+            };
+            let result = self.send_once(parts, body).await;
+            let retryable = match &result {
+                Ok(response) => self.retry.is_retryable(response.status()),
+                Err(err) => err.is_io() || err.is_timeout(),
+            };
+            match replay {
+                Some(replay) if retryable => {
+                    let backoff = self.retry.backoff(retry, self.random.as_ref());
+                    self.timer.sleep(backoff).await;
+                    (parts, body) = replay;
+                    retry += 1;
+                }
+                _ => return result,
+            }
+        }
+    }
+
+    async fn send_once(
+        &self,
+        parts: http::request::Parts,
+        body: SdkBody,
+    ) -> Result<http::Response<SdkBody>, ConnectorError> {
+        let request_line = (parts.method.clone(), parts.uri.clone());
+        let bytes_sent = body.bytes().map_or(0, |bytes| bytes.len() as u64);
+        let start = self.timer.now();
+        let endpoint = format!(
+            "{}://{}",
+            parts.uri.scheme_str().unwrap_or("https"),
+            parts
+                .uri
+                .authority()
+                .map_or("", |authority| authority.as_str())
+        );
+        let admission = self
+            .circuit_breaker
+            .as_ref()
+            .map_or(Admission::Allow, |breaker| breaker.admit(&endpoint));
+        if admission == Admission::Reject {
+            return Err(ConnectorError::other(
+                Box::new(Error::CircuitOpen { endpoint }),
+                None,
+            ));
+        }
+
+        let permit = self.throttle.admit().await;
+        let result = self
+            .fetch(parts, body, admission, endpoint.clone(), permit)
+            .await;
+        let response = match result {
+            Ok(response) => response,
+            // Rejected by the circuit breaker, nothing was sent
+            Err(err) if err.is_other() => return Err(err),
+            Err(err) => {
+                self.metrics
+                    .record(self.timer.now().saturating_sub(start), bytes_sent, 0, false);
+                if let Some(breaker) = &self.circuit_breaker {
+                    breaker.record(&endpoint, false);
+                }
+                return Err(err);
+            }
+        };
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.record(&endpoint, !response.status().is_server_error());
+        }
+        self.metrics.record(
+            self.timer.now().saturating_sub(start),
+            bytes_sent,
+            response
+                .body()
+                .bytes()
+                .map_or(0, |bytes| bytes.len() as u64),
+            response.status().is_success() || response.status().is_redirection(),
+        );
+        // Streamed bodies are counted as they arrive
+        let response = match response.body().bytes() {
+            Some(_) => response,
+            None => response.map(|body| counted(body, self.metrics.clone())),
+        };
+        if let Some(response_log) = &self.response_log {
+            let (method, uri) = request_line;
+            response_log.record(CapturedResponse {
+                method,
+                uri,
+                status: response.status(),
+                headers: response.headers().clone(),
+            });
+        }
+        Ok(response)
+    }
 }
 
 impl Adapter {
-    fn new(use_mock: bool) -> Self {
-        Self { use_mock }
+    /// Fetches in a task of its own, JS futures can't be held by the `Send` futures of the SDK.
+    #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+    async fn fetch(
+        &self,
+        parts: http::request::Parts,
+        body: SdkBody,
+        admission: Admission,
+        endpoint: String,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> Result<http::Response<SdkBody>, ConnectorError> {
+        let (mut tx, rx) = tokio::sync::oneshot::channel();
+        let use_mock = self.use_mock;
+        let timer = self.timer.clone();
+        let timeouts = self.timeouts;
+        wasm_bindgen_futures::spawn_local(async move {
+            let (mock, browser) = (MockedHttpClient {}, BrowserHttpClient {});
+            let client: &dyn MakeRequest = if use_mock { &mock } else { &browser };
+            if admission == Admission::Probe && !probe(client, &endpoint).await {
+                let _ = tx.send(Err(ConnectorError::other(
+                    Box::new(Error::CircuitOpen { endpoint }),
+                    None,
+                )));
+                return;
+            }
+            let control = FetchControl::new().with_deadline(&timeouts, timer.clone());
+            let fetched =
+                timeouts.run(client.send(parts, body, &control), &control, timer.as_ref());
+            let result = match future::select(pin!(fetched), pin!(tx.closed())).await {
+                Either::Left((result, _)) => result,
+                // The request future was dropped, nobody is waiting for the response anymore
+                Either::Right(_) => {
+                    control.abort();
+                    return;
+                }
+            };
+            let _ = tx.send(result);
+            // The request is in flight until its body is read, even if its future is dropped
+            control.body_done().await;
+            drop(permit);
+        });
+
+        rx.await
+            .unwrap_or_else(|err| Err(ConnectorError::user(Box::new(err))))
+    }
+
+    /// Fetches in place, dropping the request future drops the connection.
+    #[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+    async fn fetch(
+        &self,
+        parts: http::request::Parts,
+        body: SdkBody,
+        admission: Admission,
+        endpoint: String,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> Result<http::Response<SdkBody>, ConnectorError> {
+        let mock = MockedHttpClient {};
+        let client: &(dyn MakeRequest + Sync) = if self.use_mock { &mock } else { &self.client };
+        if admission == Admission::Probe && !probe(client, &endpoint).await {
+            return Err(ConnectorError::other(
+                Box::new(Error::CircuitOpen { endpoint }),
+                None,
+            ));
+        }
+        let control = FetchControl::new();
+        let result = self
+            .timeouts
+            .run(
+                client.send(parts, body, &control),
+                &control,
+                self.timer.as_ref(),
+            )
+            .await;
+        drop(permit);
+        result
     }
 }
 
+/// Copy of the request line and headers of a request, extensions aren't carried over.
+fn clone_parts(parts: &http::request::Parts) -> http::request::Parts {
+    let (mut clone, _) = http::Request::new(()).into_parts();
+    clone.method = parts.method.clone();
+    clone.uri = parts.uri.clone();
+    clone.version = parts.version;
+    clone.headers = parts.headers.clone();
+    clone
+}
+
+/// Sends an unsigned `HEAD` to the root of `endpoint`, any response below 500 counts as healthy.
+async fn probe(client: &(impl MakeRequest + ?Sized), endpoint: &str) -> bool {
+    let Ok(request) = http::Request::head(format!("{}/", endpoint)).body(()) else {
+        return false;
+    };
+    let (parts, _) = request.into_parts();
+    let control = FetchControl::default();
+    let response = client.send(parts, SdkBody::empty(), &control).await;
+    response.is_ok_and(|response| !response.status().is_server_error())
+}
+
 impl tower::Service<http::Request<SdkBody>> for Adapter {
     type Response = http::Response<SdkBody>;
 
@@ -225,26 +1278,10 @@ impl tower::Service<http::Request<SdkBody>> for Adapter {
     }
 
     fn call(&mut self, req: http::Request<SdkBody>) -> Self::Future {
-        let (parts, body) = req.into_parts();
-        let uri = parts.uri.to_string();
-
-        let (tx, rx) = tokio::sync::oneshot::channel();
-        let use_mock = self.use_mock;
-        wasm_bindgen_futures::spawn_local(async move {
-            let fut = if use_mock {
-                MockedHttpClient::send(parts, body)
-            } else {
-                BrowserHttpClient::send(parts, body)
-            };
-            let _ = tx.send(
-                fut.await
-                    .unwrap_or_else(|_| panic!("failure while making request to: {}", uri)),
-            );
-        });
-
+        let adapter = self.clone();
         Box::pin(async move {
-            let response = rx.await.map_err(|e| ConnectorError::user(Box::new(e)))?;
-            Ok(response)
+            let (parts, body) = req.into_parts();
+            adapter.send(parts, body).await
         })
     }
 }
@@ -0,0 +1,249 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    ops::Range,
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use object_store::{
+    path::Path, GetOptions, GetRange, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore,
+    PutOptions, PutResult,
+};
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+
+/// Byte ranges of one object, fetched from the same version.
+#[derive(Debug, Default)]
+struct CachedObject {
+    e_tag: Option<String>,
+    /// Range, data and last use of every cached range.
+    ranges: Vec<(Range<usize>, Bytes, u64)>,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    objects: HashMap<Path, CachedObject>,
+    /// Cached ranges by last use, oldest first.
+    order: BTreeMap<u64, (Path, Range<usize>)>,
+    size: usize,
+    tick: u64,
+}
+
+impl Lru {
+    fn get(&mut self, location: &Path, range: &Range<usize>) -> Option<Bytes> {
+        let object = self.objects.get_mut(location)?;
+        let (cached, data, used) = object.ranges.iter_mut().find(|(cached, data, _)| {
+            cached.start <= range.start
+                && range.end <= cached.end
+                && range.end - cached.start <= data.len()
+        })?;
+        let data = data.slice(range.start - cached.start..range.end - cached.start);
+        self.tick += 1;
+        let key = self.order.remove(used);
+        *used = self.tick;
+        if let Some(key) = key {
+            self.order.insert(self.tick, key);
+        }
+        Some(data)
+    }
+
+    fn insert(
+        &mut self,
+        location: &Path,
+        e_tag: Option<String>,
+        range: Range<usize>,
+        data: Bytes,
+        capacity: usize,
+    ) {
+        if data.len() > capacity {
+            return;
+        }
+        self.validate(location, e_tag.as_deref());
+        self.tick += 1;
+        self.size += data.len();
+        self.order
+            .insert(self.tick, (location.clone(), range.clone()));
+        let object = self.objects.entry(location.clone()).or_default();
+        object.e_tag = e_tag;
+        object.ranges.push((range, data, self.tick));
+        while self.size > capacity {
+            let Some((_, (location, range))) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(object) = self.objects.get_mut(&location) {
+                if let Some(idx) = object
+                    .ranges
+                    .iter()
+                    .position(|(cached, _, _)| *cached == range)
+                {
+                    let (_, data, _) = object.ranges.swap_remove(idx);
+                    self.size -= data.len();
+                }
+                if object.ranges.is_empty() {
+                    self.objects.remove(&location);
+                }
+            }
+        }
+    }
+
+    /// Drops the ranges of `location` if they were fetched from a version other than `e_tag`.
+    fn validate(&mut self, location: &Path, e_tag: Option<&str>) {
+        let stale = self
+            .objects
+            .get(location)
+            .is_some_and(|object| e_tag.is_none() || object.e_tag.as_deref() != e_tag);
+        if stale {
+            self.evict(location);
+        }
+    }
+
+    fn evict(&mut self, location: &Path) {
+        if let Some(object) = self.objects.remove(location) {
+            for (_, data, used) in object.ranges {
+                self.order.remove(&used);
+                self.size -= data.len();
+            }
+        }
+    }
+}
+
+/// Wrapper store caching byte ranges read with `get_range` and `get_ranges` in memory, e.g. the
+/// footers and metadata of Parquet files that are read over and over.
+///
+/// Up to `capacity` bytes are cached, the least recently used ranges are dropped first. Range
+/// hits are served without asking `inner` and never revalidated on their own. Only when the
+/// object is read with `get_opts` or inspected with `head` through the wrapper are its ranges
+/// checked against the ETag and dropped once it changed. Writes through the wrapper drop the
+/// ranges of the written object, changes by other writers go unnoticed until then.
+#[derive(Debug)]
+pub struct CachingStore<T: ObjectStore> {
+    inner: T,
+    capacity: usize,
+    cache: Mutex<Lru>,
+}
+
+impl<T: ObjectStore> CachingStore<T> {
+    pub fn new(inner: T, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            cache: Mutex::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Bytes currently cached.
+    pub fn cached_bytes(&self) -> usize {
+        self.cache.lock().size
+    }
+
+    /// Drops every cached range.
+    pub fn clear(&self) {
+        *self.cache.lock() = Lru::default();
+    }
+
+    fn evict(&self, location: &Path) {
+        self.cache.lock().evict(location);
+    }
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for CachingStore<T> {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        let result = self.inner.put_opts(location, bytes, opts).await?;
+        self.evict(location);
+        Ok(result)
+    }
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.evict(location);
+        self.inner.put_multipart(location).await
+    }
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let result = self.inner.get_opts(location, options).await?;
+        self.cache
+            .lock()
+            .validate(location, result.meta.e_tag.as_deref());
+        Ok(result)
+    }
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> object_store::Result<Bytes> {
+        if let Some(data) = self.cache.lock().get(location, &range) {
+            return Ok(data);
+        }
+        let options = GetOptions {
+            range: Some(GetRange::Bounded(range.clone())),
+            ..Default::default()
+        };
+        let result = self.inner.get_opts(location, options).await?;
+        let e_tag = result.meta.e_tag.clone();
+        // Reads past the end of the object return fewer bytes than requested
+        let start = result.range.start;
+        let data = result.bytes().await?;
+        let served = start..start + data.len();
+        self.cache
+            .lock()
+            .insert(location, e_tag, served, data.clone(), self.capacity);
+        Ok(data)
+    }
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        let meta = self.inner.head(location).await?;
+        self.cache.lock().validate(location, meta.e_tag.as_deref());
+        Ok(meta)
+    }
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await?;
+        self.evict(location);
+        Ok(())
+    }
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        self.inner.list(prefix)
+    }
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy(from, to).await?;
+        self.evict(to);
+        Ok(())
+    }
+    async fn rename(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.rename(from, to).await?;
+        self.evict(from);
+        self.evict(to);
+        Ok(())
+    }
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await?;
+        self.evict(to);
+        Ok(())
+    }
+}
+
+impl<T: ObjectStore> Display for CachingStore<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Caching({})", self.inner)
+    }
+}
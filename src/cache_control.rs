@@ -0,0 +1,96 @@
+use std::fmt::Debug;
+
+use chrono::{DateTime, Duration, Utc};
+use object_store::path::Path;
+
+/// Caching headers written with an object.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheHeaders {
+    pub cache_control: Option<String>,
+    pub expires: Option<DateTime<Utc>>,
+}
+
+/// Decides which caching headers an object gets when it is written.
+///
+/// Consulted by `put` and `put_multipart` of stores built with
+/// [`S3Builder::with_cache_policy`](crate::builder::S3Builder::with_cache_policy).
+pub trait CachePolicy: Debug + Send + Sync {
+    /// Headers for an object written to `location` at `now`, `None` to leave them unset.
+    fn headers(&self, location: &Path, now: DateTime<Utc>) -> Option<CacheHeaders>;
+}
+
+/// [`CachePolicy`] mapping glob patterns to caching headers, the first matching rule wins.
+///
+/// In patterns `*` matches within a path segment and `**` matches across segments, e.g.
+/// `assets/**/*.js` or `*.html`.
+#[derive(Debug, Clone, Default)]
+pub struct PathCachePolicy {
+    rules: Vec<CacheRule>,
+}
+
+#[derive(Debug, Clone)]
+struct CacheRule {
+    pattern: String,
+    cache_control: Option<String>,
+    expires_in: Option<Duration>,
+}
+
+impl PathCachePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets `Cache-Control` for objects matching `pattern`.
+    pub fn cache_control(mut self, pattern: impl Into<String>, value: impl Into<String>) -> Self {
+        self.rules.push(CacheRule {
+            pattern: pattern.into(),
+            cache_control: Some(value.into()),
+            expires_in: None,
+        });
+        self
+    }
+
+    /// Sets `Cache-Control` and an `Expires` header `expires_in` after the write for objects
+    /// matching `pattern`.
+    pub fn cache_control_and_expires(
+        mut self,
+        pattern: impl Into<String>,
+        value: impl Into<String>,
+        expires_in: Duration,
+    ) -> Self {
+        self.rules.push(CacheRule {
+            pattern: pattern.into(),
+            cache_control: Some(value.into()),
+            expires_in: Some(expires_in),
+        });
+        self
+    }
+}
+
+impl CachePolicy for PathCachePolicy {
+    fn headers(&self, location: &Path, now: DateTime<Utc>) -> Option<CacheHeaders> {
+        let rule = self
+            .rules
+            .iter()
+            .find(|rule| glob_match(rule.pattern.as_bytes(), location.as_ref().as_bytes()))?;
+        Some(CacheHeaders {
+            cache_control: rule.cache_control.clone(),
+            expires: rule.expires_in.map(|expires_in| now + expires_in),
+        })
+    }
+}
+
+pub(crate) fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*'] => true,
+        [b'*', b'*', b'/', rest @ ..] => (0..=path.len())
+            .filter(|i| *i == 0 || path[i - 1] == b'/')
+            .any(|i| glob_match(rest, &path[i..])),
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| glob_match(rest, &path[i..])),
+        [b'*', rest @ ..] => (0..=path.len())
+            .take_while(|i| *i == 0 || path[i - 1] != b'/')
+            .any(|i| glob_match(rest, &path[i..])),
+        [c, rest @ ..] => path.first() == Some(c) && glob_match(rest, &path[1..]),
+    }
+}
@@ -0,0 +1,192 @@
+use aws_sdk_s3::types::{BucketVersioningStatus, ChecksumAlgorithm};
+use bytes::Bytes;
+use http::HeaderValue;
+use object_store::{path::Path, ObjectStore};
+
+use crate::{
+    conditional::{ConditionalDelete, ConditionalPut, CopyIfNotExists},
+    error::{status_code, Error},
+    validate::{MAX_PARTS, MIN_PART_SIZE},
+    S3,
+};
+
+/// Prefix below which [`S3::capabilities`] writes its probe objects.
+pub const PROBE_PREFIX: &str = ".capabilities";
+
+/// Optional S3 features supported by the endpoint and bucket of a store.
+///
+/// S3-compatible servers implement different subsets of the S3 API, see
+/// [`S3::capabilities`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `If-None-Match: *` on `PutObject` rejects overwrites.
+    pub conditional_put: bool,
+    /// Object tags can be read back.
+    pub tagging: bool,
+    /// Additional checksums like `x-amz-checksum-crc32` are validated and returned.
+    pub checksums: bool,
+    /// `ListObjectsV2` honors the delimiter and returns common prefixes.
+    pub delimiter: bool,
+    /// Versioning is enabled on the bucket.
+    pub versioning: bool,
+}
+
+/// Features engines layering on top of a store can plan with, see [`S3::store_capabilities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoreCapabilities {
+    /// Puts with [`PutMode::Create`](object_store::PutMode::Create) and
+    /// [`PutMode::Update`](object_store::PutMode::Update) are atomic rather than checked with a
+    /// `HeadObject` first.
+    pub supports_conditional_put: bool,
+    /// [`GetRange::Suffix`](object_store::GetRange::Suffix) reads are a single request.
+    pub supports_suffix_range: bool,
+    /// Objects can be tagged.
+    pub supports_tagging: bool,
+    /// Most parts of a multipart upload.
+    pub max_part_count: u64,
+    /// Smallest part of a multipart upload except the last one.
+    pub min_part_size: usize,
+}
+
+impl S3 {
+    /// Features of the store as far as they are known from its
+    /// [`CompatProfile`](crate::profile::CompatProfile), its configuration and a previous
+    /// [`S3::capabilities`] probe. Features neither ruled out by the profile nor by a probe are
+    /// assumed to be supported.
+    pub fn store_capabilities(&self) -> StoreCapabilities {
+        let probed = self.cached_capabilities();
+        StoreCapabilities {
+            supports_conditional_put: self.conditional_put_strategy()
+                == ConditionalPut::Conditional,
+            supports_suffix_range: !self.unsupported.suffix_range,
+            supports_tagging: !self.unsupported.tagging
+                && probed.is_none_or(|probed| probed.tagging),
+            max_part_count: MAX_PARTS,
+            min_part_size: MIN_PART_SIZE,
+        }
+    }
+
+    /// Probes the optional features of the endpoint, cached after the first successful probe.
+    ///
+    /// Writes and deletes a small object below [`PROBE_PREFIX`]. Once probed, conditional puts and
+    /// copies fall back to their head based strategy if the endpoint doesn't support them.
+    pub async fn capabilities(&self) -> object_store::Result<Capabilities> {
+        if let Some(capabilities) = *self.capabilities.lock() {
+            return Ok(capabilities);
+        }
+        let probe = Path::from(format!(
+            "{}/{:016x}/probe",
+            PROBE_PREFIX,
+            self.random.next_u64()
+        ));
+        let result = self.probe(&probe).await;
+        let _ = self.delete(&probe).await;
+        let capabilities = result?;
+        *self.capabilities.lock() = Some(capabilities);
+        Ok(capabilities)
+    }
+
+    /// Capabilities of a previous [`S3::capabilities`] call.
+    pub fn cached_capabilities(&self) -> Option<Capabilities> {
+        *self.capabilities.lock()
+    }
+
+    async fn probe(&self, probe: &Path) -> object_store::Result<Capabilities> {
+        // Requests of stores with a checksum algorithm already carry a checksum
+        let request = self.put_request(probe, Bytes::from_static(b"probe"));
+        let created = match self.checksum {
+            Some(_) => request,
+            None => request.checksum_algorithm(ChecksumAlgorithm::Crc32),
+        }
+        .tagging("probe=true")
+        .send()
+        .await
+        .map_err(Error::from)?;
+        let checksums = created.checksum_crc32().is_some()
+            || created.checksum_crc32_c().is_some()
+            || created.checksum_sha256().is_some();
+
+        // The probe exists, so a server honoring the condition rejects the overwrite
+        let overwrite = self
+            .put_request(probe, Bytes::from_static(b"probe"))
+            .customize()
+            .await
+            .map_err(|_| Error::Unknown)?
+            .mutate_request(|request| {
+                request
+                    .headers_mut()
+                    .insert(http::header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+            })
+            .send()
+            .await;
+        let conditional_put = matches!(
+            overwrite.as_ref().map_err(status_code),
+            Err(Some(409 | 412))
+        );
+
+        let tagging = self
+            .client
+            .get_object_tagging()
+            .bucket(self.bucket.clone())
+            .key(probe.to_string())
+            .send()
+            .await
+            .is_ok_and(|output| !output.tag_set().unwrap_or_default().is_empty());
+
+        let listing = self
+            .client
+            .list_objects_v2()
+            .bucket(self.bucket.clone())
+            .prefix(format!("{}/", PROBE_PREFIX))
+            .delimiter("/")
+            .send()
+            .await
+            .map_err(Error::from)?;
+        let delimiter = listing.contents().unwrap_or_default().is_empty()
+            && !listing.common_prefixes().unwrap_or_default().is_empty();
+
+        let versioning = self
+            .client
+            .get_bucket_versioning()
+            .bucket(self.bucket.clone())
+            .send()
+            .await
+            .is_ok_and(|output| output.status() == Some(&BucketVersioningStatus::Enabled));
+
+        Ok(Capabilities {
+            conditional_put,
+            tagging,
+            checksums,
+            delimiter,
+            versioning,
+        })
+    }
+
+    /// Conditional put strategy, falling back to head-then-put on endpoints without support.
+    pub(crate) fn conditional_put_strategy(&self) -> ConditionalPut {
+        match self.cached_capabilities() {
+            Some(capabilities) if !capabilities.conditional_put => ConditionalPut::HeadThenPut,
+            _ => self.conditional_put,
+        }
+    }
+
+    /// Copy-if-not-exists strategy, falling back to head-then-copy on endpoints without
+    /// conditional requests.
+    pub(crate) fn copy_if_not_exists_strategy(&self) -> CopyIfNotExists {
+        match self.cached_capabilities() {
+            Some(capabilities) if !capabilities.conditional_put => CopyIfNotExists::HeadThenCopy,
+            _ => self.copy_if_not_exists,
+        }
+    }
+
+    /// Conditional delete strategy, falling back to head-then-delete on endpoints without
+    /// conditional requests.
+    pub(crate) fn conditional_delete_strategy(&self) -> ConditionalDelete {
+        match self.cached_capabilities() {
+            Some(capabilities) if !capabilities.conditional_put => {
+                ConditionalDelete::HeadThenDelete
+            }
+            _ => self.conditional_delete,
+        }
+    }
+}
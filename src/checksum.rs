@@ -0,0 +1,353 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use aws_sdk_s3::{
+    operation::{
+        complete_multipart_upload::CompleteMultipartUploadOutput, get_object::GetObjectOutput,
+        put_object::builders::PutObjectFluentBuilder,
+        upload_part::builders::UploadPartFluentBuilder, upload_part::UploadPartOutput,
+    },
+    types::{builders::CompletedPartBuilder, CompletedPart, CopyPartResult},
+};
+use aws_smithy_types::base64;
+use bytes::Bytes;
+use futures::{stream::BoxStream, Stream};
+use md5::{Digest, Md5};
+use sha2::Sha256;
+
+use crate::{
+    encryption::SseConfig, error::Error, metrics::MetricsRecorder, parts::UploadedPart, S3,
+};
+
+/// Additional checksum sent with every upload, see
+/// [`S3Builder::with_checksum`](crate::builder::S3Builder::with_checksum).
+///
+/// S3 rejects uploads whose data doesn't match the checksum and returns it on downloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Crc32c,
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    /// Base64 encoded checksum of `data`, as sent in the `x-amz-checksum-*` header.
+    pub fn checksum(self, data: &[u8]) -> String {
+        let mut hasher = Hasher::new(self);
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    /// Checksum S3 reports for a multipart upload of parts with the base64 encoded
+    /// `part_checksums`: the checksum of the concatenated part digests and the number of parts.
+    /// `None` if a part checksum isn't valid base64.
+    pub fn composite<'a>(
+        self,
+        part_checksums: impl IntoIterator<Item = &'a str>,
+    ) -> Option<String> {
+        let mut digests = Vec::new();
+        let mut parts = 0;
+        for checksum in part_checksums {
+            digests.extend(base64::decode(checksum).ok()?);
+            parts += 1;
+        }
+        Some(format!("{}-{}", self.checksum(&digests), parts))
+    }
+
+    pub(crate) fn sdk(self) -> aws_sdk_s3::types::ChecksumAlgorithm {
+        match self {
+            ChecksumAlgorithm::Crc32c => aws_sdk_s3::types::ChecksumAlgorithm::Crc32C,
+            ChecksumAlgorithm::Sha256 => aws_sdk_s3::types::ChecksumAlgorithm::Sha256,
+        }
+    }
+}
+
+enum Hasher {
+    Crc32c(u32),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Crc32c => Hasher::Crc32c(0),
+            ChecksumAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Hasher::Crc32c(crc) => base64::encode(crc.to_be_bytes()),
+            Hasher::Sha256(hasher) => base64::encode(hasher.finalize()),
+        }
+    }
+}
+
+/// Requests carrying the checksum of their data.
+pub(crate) trait WithChecksum: Sized {
+    fn with_checksum(self, algorithm: ChecksumAlgorithm, checksum: String) -> Self;
+}
+
+/// Responses reporting the checksum S3 computed.
+pub(crate) trait ReportedChecksum {
+    fn reported_checksum(&self, algorithm: ChecksumAlgorithm) -> Option<&str>;
+}
+
+macro_rules! checksum_impls {
+    ($($request:ty),*; $($response:ty),*) => {
+        $(impl WithChecksum for $request {
+            fn with_checksum(self, algorithm: ChecksumAlgorithm, checksum: String) -> Self {
+                match algorithm {
+                    ChecksumAlgorithm::Crc32c => self.checksum_crc32_c(checksum),
+                    ChecksumAlgorithm::Sha256 => self.checksum_sha256(checksum),
+                }
+            }
+        })*
+        $(impl ReportedChecksum for $response {
+            fn reported_checksum(&self, algorithm: ChecksumAlgorithm) -> Option<&str> {
+                match algorithm {
+                    ChecksumAlgorithm::Crc32c => self.checksum_crc32_c(),
+                    ChecksumAlgorithm::Sha256 => self.checksum_sha256(),
+                }
+            }
+        })*
+    };
+}
+
+checksum_impls!(
+    PutObjectFluentBuilder, UploadPartFluentBuilder, CompletedPartBuilder;
+    UploadPartOutput, CopyPartResult, CompleteMultipartUploadOutput, GetObjectOutput, CompletedPart
+);
+
+impl ReportedChecksum for UploadedPart {
+    fn reported_checksum(&self, _: ChecksumAlgorithm) -> Option<&str> {
+        self.checksum.as_deref()
+    }
+}
+
+/// Checksum of the first supported algorithm `response` reports, unless it's the composite
+/// checksum of a multipart upload that can't be checked against the body.
+pub(crate) fn full_object_checksum(
+    response: &GetObjectOutput,
+) -> Option<(ChecksumAlgorithm, String)> {
+    [ChecksumAlgorithm::Crc32c, ChecksumAlgorithm::Sha256]
+        .into_iter()
+        .find_map(|algorithm| Some((algorithm, response.reported_checksum(algorithm)?)))
+        .filter(|(_, checksum)| !checksum.contains('-'))
+        .map(|(algorithm, checksum)| (algorithm, checksum.to_owned()))
+}
+
+/// Body stream failing with [`Error::ChecksumMismatch`] at its end unless its data matches the
+/// checksum of the response.
+pub(crate) struct VerifiedStream {
+    inner: BoxStream<'static, object_store::Result<Bytes>>,
+    key: String,
+    expected: String,
+    hasher: Option<Hasher>,
+}
+
+impl VerifiedStream {
+    /// Verifies `body` against `checksum`, if any.
+    pub(crate) fn wrap(
+        body: BoxStream<'static, object_store::Result<Bytes>>,
+        checksum: Option<(ChecksumAlgorithm, String)>,
+        key: String,
+    ) -> BoxStream<'static, object_store::Result<Bytes>> {
+        match checksum {
+            Some((algorithm, expected)) => Box::pin(VerifiedStream {
+                inner: body,
+                key,
+                expected,
+                hasher: Some(Hasher::new(algorithm)),
+            }),
+            None => body,
+        }
+    }
+}
+
+impl Stream for VerifiedStream {
+    type Item = object_store::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        match this.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                if let Some(hasher) = &mut this.hasher {
+                    hasher.update(&chunk);
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(None) => match this.hasher.take().map(Hasher::finish) {
+                Some(actual) if actual != this.expected => {
+                    Poll::Ready(Some(Err(Error::ChecksumMismatch {
+                        key: this.key.clone(),
+                        expected: this.expected.clone(),
+                        actual,
+                    }
+                    .into())))
+                }
+                _ => Poll::Ready(None),
+            },
+            poll => poll,
+        }
+    }
+}
+
+/// Adds the `algorithm` checksum of `data` to an upload request.
+pub(crate) fn with_checksum<B: WithChecksum>(
+    builder: B,
+    algorithm: Option<ChecksumAlgorithm>,
+    data: &[u8],
+) -> B {
+    match algorithm {
+        Some(algorithm) => builder.with_checksum(algorithm, algorithm.checksum(data)),
+        None => builder,
+    }
+}
+
+/// Part to complete a multipart upload with, uploads started with a checksum algorithm list
+/// the checksum of every part.
+pub(crate) fn completed_part(
+    part_number: i32,
+    e_tag: Option<&str>,
+    algorithm: Option<ChecksumAlgorithm>,
+    response: &impl ReportedChecksum,
+) -> Result<CompletedPart, Error> {
+    let builder = CompletedPart::builder()
+        .part_number(part_number)
+        .e_tag(e_tag.ok_or(Error::Unknown)?);
+    let checksum = algorithm
+        .and_then(|algorithm| Some((algorithm, response.reported_checksum(algorithm)?.to_owned())));
+    Ok(match checksum {
+        Some((algorithm, checksum)) => builder.with_checksum(algorithm, checksum),
+        None => builder,
+    }
+    .build())
+}
+
+/// Checks the checksum and ETag of a completed multipart upload against the ones derived from
+/// its `parts`, which are in ascending part order, if the upload was sent with a checksum
+/// `algorithm`.
+///
+/// The object is committed at this point, so a mismatch is recorded in
+/// [`StoreMetrics::checksum_mismatches`](crate::metrics::StoreMetrics::checksum_mismatches)
+/// instead of failing the upload. Content derived ETags are only compared if `content_etags`
+/// holds, i.e. without SSE-KMS or SSE-C.
+pub(crate) fn verify_completion(
+    key: &str,
+    parts: &[CompletedPart],
+    algorithm: Option<ChecksumAlgorithm>,
+    content_etags: bool,
+    response: &CompleteMultipartUploadOutput,
+    metrics: &MetricsRecorder,
+) {
+    let Some(algorithm) = algorithm else {
+        return;
+    };
+    if let Some((expected, actual)) = completion_mismatch(parts, algorithm, content_etags, response)
+    {
+        metrics.record_checksum_mismatch();
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            key,
+            expected,
+            actual,
+            "completed multipart upload doesn't match its parts"
+        );
+        #[cfg(not(feature = "tracing"))]
+        let _ = (key, expected, actual);
+    }
+}
+
+/// Expected and reported checksum or ETag of a completed upload that don't match.
+fn completion_mismatch(
+    parts: &[CompletedPart],
+    algorithm: ChecksumAlgorithm,
+    content_etags: bool,
+    response: &CompleteMultipartUploadOutput,
+) -> Option<(String, String)> {
+    let checksums = parts
+        .iter()
+        .map(|part| part.reported_checksum(algorithm))
+        .collect::<Option<Vec<_>>>();
+    let expected = checksums.and_then(|checksums| algorithm.composite(checksums));
+    if let (Some(expected), Some(actual)) = (expected, response.reported_checksum(algorithm)) {
+        // Some services omit the part count of composite checksums
+        if expected.split('-').next() != actual.split('-').next() {
+            return Some((expected, actual.to_owned()));
+        }
+    }
+    if content_etags {
+        let digests = parts
+            .iter()
+            .map(|part| hex_digest(part.e_tag()?.trim_matches('"')))
+            .collect::<Option<Vec<_>>>();
+        let expected = digests
+            .map(|digests| format!("{}-{}", hex(&Md5::digest(digests.concat())), parts.len()));
+        if let (Some(expected), Some(actual)) = (expected, response.e_tag()) {
+            if expected != actual.trim_matches('"') {
+                return Some((expected, actual.to_owned()));
+            }
+        }
+    }
+    None
+}
+
+/// Lowercase hex encoding of `bytes`, as used by ETags and SigV4.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Bytes of a hex encoded MD5 digest.
+fn hex_digest(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() != 32 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+impl S3 {
+    /// Adds the checksum of `data` in the store's algorithm to an upload request.
+    pub(crate) fn checksummed<B: WithChecksum>(&self, builder: B, data: &[u8]) -> B {
+        with_checksum(builder, self.checksum, data)
+    }
+
+    /// See [`completed_part`].
+    pub(crate) fn completed_part(
+        &self,
+        part_number: i32,
+        e_tag: Option<&str>,
+        response: &impl ReportedChecksum,
+    ) -> Result<CompletedPart, Error> {
+        completed_part(part_number, e_tag, self.checksum, response)
+    }
+
+    /// See [`verify_completion`].
+    pub(crate) fn verify_completion(
+        &self,
+        key: &str,
+        parts: &[CompletedPart],
+        response: &CompleteMultipartUploadOutput,
+    ) {
+        let content_etags = matches!(self.sse, None | Some(SseConfig::S3));
+        verify_completion(
+            key,
+            parts,
+            self.checksum,
+            content_etags,
+            response,
+            &self.metrics,
+        )
+    }
+}
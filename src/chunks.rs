@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+
+/// Splits the chunks of `inner` into slices of at most `max_chunk_size` bytes. The slices are
+/// views of the chunk they're cut from, nothing is copied.
+pub(crate) fn bounded(
+    inner: BoxStream<'static, object_store::Result<Bytes>>,
+    max_chunk_size: Option<usize>,
+) -> BoxStream<'static, object_store::Result<Bytes>> {
+    let Some(max_chunk_size) = max_chunk_size else {
+        return inner;
+    };
+    inner
+        .map_ok(move |mut chunk| {
+            stream::iter(std::iter::from_fn(move || {
+                (!chunk.is_empty()).then(|| Ok(chunk.split_to(max_chunk_size.min(chunk.len()))))
+            }))
+        })
+        .try_flatten()
+        .boxed()
+}
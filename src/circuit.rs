@@ -0,0 +1,112 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+
+use crate::{environment::Timer, error::Error, S3};
+
+/// Decision of the circuit breaker for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Admission {
+    Allow,
+    /// The circuit is open but the probe interval elapsed, probe the endpoint before sending.
+    Probe,
+    Reject,
+}
+
+#[derive(Debug, Default)]
+struct EndpointState {
+    consecutive_failures: usize,
+    opened_at: Option<Duration>,
+}
+
+/// Per endpoint circuit breaker shared by all requests of a store.
+///
+/// After `failure_threshold` consecutive failed requests to an endpoint its circuit opens and
+/// requests fail fast with [`Error::CircuitOpen`]. Every `probe_interval` one request probes the
+/// endpoint with an unsigned `HEAD`; any response below 500 closes the circuit again.
+#[derive(Debug, Clone)]
+pub(crate) struct CircuitBreaker {
+    failure_threshold: usize,
+    probe_interval: Duration,
+    timer: Arc<dyn Timer>,
+    endpoints: Arc<Mutex<HashMap<String, EndpointState>>>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(
+        failure_threshold: usize,
+        probe_interval: Duration,
+        timer: Arc<dyn Timer>,
+    ) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            probe_interval,
+            timer,
+            endpoints: Arc::default(),
+        }
+    }
+
+    pub(crate) fn admit(&self, endpoint: &str) -> Admission {
+        let now = self.timer.now();
+        let mut endpoints = self.endpoints.lock();
+        let Some(opened_at) = endpoints
+            .get_mut(endpoint)
+            .and_then(|state| state.opened_at.as_mut())
+        else {
+            return Admission::Allow;
+        };
+        if now.saturating_sub(*opened_at) < self.probe_interval {
+            return Admission::Reject;
+        }
+        // Only a single request probes per interval, the others keep failing fast
+        *opened_at = now;
+        Admission::Probe
+    }
+
+    pub(crate) fn record(&self, endpoint: &str, success: bool) {
+        let mut endpoints = self.endpoints.lock();
+        let state = endpoints.entry(endpoint.to_owned()).or_default();
+        if success {
+            *state = EndpointState::default();
+            return;
+        }
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold && state.opened_at.is_none() {
+            state.opened_at = Some(self.timer.now());
+        }
+    }
+
+    fn open_endpoints(&self) -> Vec<String> {
+        self.endpoints
+            .lock()
+            .iter()
+            .filter(|(_, state)| state.opened_at.is_some())
+            .map(|(endpoint, _)| endpoint.clone())
+            .collect()
+    }
+}
+
+/// Whether `err` was caused by a request rejected because the circuit of its endpoint is open.
+pub fn is_circuit_open(err: &object_store::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err);
+    while let Some(err) = source {
+        if matches!(err.downcast_ref::<Error>(), Some(Error::CircuitOpen { .. })) {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+impl S3 {
+    /// Endpoints whose circuit is currently open.
+    ///
+    /// Empty unless the store was built with
+    /// [`S3Builder::with_circuit_breaker`](crate::builder::S3Builder::with_circuit_breaker).
+    pub fn open_circuits(&self) -> Vec<String> {
+        self.circuit_breaker
+            .as_ref()
+            .map(CircuitBreaker::open_endpoints)
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,86 @@
+use std::ops::Range;
+
+use bytes::Bytes;
+use futures::{stream, StreamExt, TryStreamExt};
+use object_store::{path::Path, GetOptions, GetRange, ObjectStore};
+
+use crate::S3;
+
+/// Default largest gap between two ranges of [`ObjectStore::get_ranges`] that are fetched with
+/// a single request.
+pub const DEFAULT_COALESCE_GAP: usize = 1024 * 1024;
+
+/// Default size up to which ranges are merged into a single request.
+pub const DEFAULT_MAX_COALESCED_SIZE: usize = 16 * 1024 * 1024;
+
+/// Coalesced requests of a `get_ranges` call in flight at once.
+const COALESCE_CONCURRENCY: usize = 10;
+
+/// Ranges to request for `ranges`, sorted by offset.
+///
+/// Ranges that overlap or are at most `gap` bytes apart are merged as long as the merged range
+/// stays within `max_size` bytes. Ranges larger than `max_size` are requested as they are.
+pub(crate) fn coalesce_ranges(
+    ranges: &[Range<usize>],
+    gap: usize,
+    max_size: usize,
+) -> Vec<Range<usize>> {
+    let mut sorted = ranges
+        .iter()
+        .filter(|range| !range.is_empty())
+        .cloned()
+        .collect::<Vec<_>>();
+    sorted.sort_by_key(|range| range.start);
+    let mut coalesced: Vec<Range<usize>> = Vec::with_capacity(sorted.len());
+    for range in sorted {
+        match coalesced.last_mut() {
+            Some(last)
+                if range.start <= last.end.saturating_add(gap)
+                    && range.end.max(last.end) - last.start <= max_size =>
+            {
+                last.end = last.end.max(range.end);
+            }
+            // Overlapping ranges are always merged to not fetch bytes twice
+            Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+            _ => coalesced.push(range),
+        }
+    }
+    coalesced
+}
+
+impl S3 {
+    /// [`ObjectStore::get_ranges`] fetching the coalesced ranges and slicing the results.
+    pub(crate) async fn get_coalesced_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        let fetch = coalesce_ranges(ranges, self.coalesce_gap, self.max_coalesced_size);
+        let fetched = stream::iter(fetch.iter().cloned())
+            .map(|range| async move {
+                let options = GetOptions {
+                    range: Some(GetRange::Bounded(range)),
+                    ..Default::default()
+                };
+                self.get_opts(location, options).await?.bytes().await
+            })
+            .buffered(COALESCE_CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(ranges
+            .iter()
+            .map(|range| {
+                if range.is_empty() {
+                    return Bytes::new();
+                }
+                // Last fetched range starting at or before the requested one contains it
+                let idx = fetch.partition_point(|fetched| fetched.start <= range.start) - 1;
+                let offset = fetch[idx].start;
+                let data = &fetched[idx];
+                data.slice(
+                    (range.start - offset).min(data.len())..(range.end - offset).min(data.len()),
+                )
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,40 @@
+/// How [`ObjectStore::copy_if_not_exists`](object_store::ObjectStore::copy_if_not_exists)
+/// guarantees that it doesn't overwrite an existing object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyIfNotExists {
+    /// Checks for the target with `HeadObject` before copying.
+    ///
+    /// Works with every endpoint, but a concurrent writer creating the target between the check
+    /// and the copy is overwritten.
+    #[default]
+    HeadThenCopy,
+    /// Sends a single `CopyObject` with `If-None-Match: *`, which the endpoint rejects atomically
+    /// if the target exists. Endpoints ignoring the header silently overwrite the target.
+    Conditional,
+}
+
+/// How [`PutMode::Create`](object_store::PutMode::Create) and
+/// [`PutMode::Update`](object_store::PutMode::Update) puts are made conditional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConditionalPut {
+    /// Sends `If-None-Match: *` respectively `If-Match: <etag>` with the `PutObject`, which the
+    /// endpoint evaluates atomically.
+    #[default]
+    Conditional,
+    /// Checks the existing object with `HeadObject` before an unconditional put, for endpoints
+    /// that ignore conditional headers on writes. Concurrent writers can race the check.
+    HeadThenPut,
+}
+
+/// How [`S3::delete_if_match`](crate::S3::delete_if_match) makes sure it only deletes the
+/// expected version of an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConditionalDelete {
+    /// Sends `If-Match: <etag>` with the `DeleteObject`, which the endpoint evaluates atomically.
+    /// Endpoints ignoring the header delete unconditionally.
+    #[default]
+    Conditional,
+    /// Checks the ETag with `HeadObject` before an unconditional delete. A concurrent writer
+    /// replacing the object between the check and the delete loses its write.
+    HeadThenDelete,
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use object_store::path::Path;
+
+/// Content types of common extensions, sorted by extension.
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("avif", "image/avif"),
+    ("avro", "application/avro"),
+    ("bin", "application/octet-stream"),
+    ("bmp", "image/bmp"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("ico", "image/vnd.microsoft.icon"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("js", "text/javascript"),
+    ("json", "application/json"),
+    ("jsonl", "application/jsonl"),
+    ("m4a", "audio/mp4"),
+    ("md", "text/markdown"),
+    ("mjs", "text/javascript"),
+    ("mov", "video/quicktime"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("ndjson", "application/x-ndjson"),
+    ("oga", "audio/ogg"),
+    ("ogg", "audio/ogg"),
+    ("ogv", "video/ogg"),
+    ("parquet", "application/vnd.apache.parquet"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("ttf", "font/ttf"),
+    ("txt", "text/plain"),
+    ("wasm", "application/wasm"),
+    ("wav", "audio/wav"),
+    ("weba", "audio/webm"),
+    ("webm", "video/webm"),
+    ("webp", "image/webp"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("xml", "application/xml"),
+    ("zip", "application/zip"),
+    ("zst", "application/zstd"),
+];
+
+/// Infers the `Content-Type` of written objects from the extension of their path, see
+/// [`S3Builder::with_content_type_inference`](crate::builder::S3Builder::with_content_type_inference).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ContentTypes {
+    /// Content types by lowercase extension, checked before the built-in ones.
+    overrides: HashMap<String, String>,
+}
+
+impl ContentTypes {
+    pub(crate) fn with_override(mut self, extension: &str, content_type: String) -> Self {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        self.overrides.insert(extension, content_type);
+        self
+    }
+
+    /// Content type of objects at `location`, `None` for unknown extensions.
+    pub(crate) fn infer(&self, location: &Path) -> Option<String> {
+        let extension = location.extension()?.to_ascii_lowercase();
+        if let Some(content_type) = self.overrides.get(&extension) {
+            return Some(content_type.clone());
+        }
+        CONTENT_TYPES
+            .binary_search_by_key(&extension.as_str(), |(extension, _)| extension)
+            .ok()
+            .map(|idx| CONTENT_TYPES[idx].1.to_owned())
+    }
+}
@@ -0,0 +1,168 @@
+use aws_sdk_s3::types::CompletedMultipartUpload;
+use futures::{stream, StreamExt, TryStreamExt};
+use http::HeaderValue;
+use object_store::{path::Path, ObjectMeta};
+
+use crate::{
+    attributes::PutAttributes,
+    encryption::SseConfig,
+    error::{status_code, Error},
+    journal::Mutation,
+    key_encoding::copy_source,
+    multipart::AbortOnDrop,
+    S3,
+};
+
+/// Largest object a single `CopyObject` request can copy, objects above are copied part by part.
+pub const MAX_COPY_OBJECT_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Most parts of a multipart upload.
+const MAX_PARTS: usize = 10_000;
+
+impl S3 {
+    /// Copies `from` to `to` with a single `CopyObject`, or with `UploadPartCopy` if the source is
+    /// larger than the multipart copy threshold.
+    ///
+    /// With `if_not_exists` the request creating `to` is sent with `If-None-Match: *` and an
+    /// existing target fails with [`object_store::Error::AlreadyExists`].
+    pub(crate) async fn copy_object(
+        &self,
+        from: &Path,
+        to: &Path,
+        if_not_exists: bool,
+    ) -> object_store::Result<()> {
+        let (meta, _, attributes) = self.head_object(from, None).await?;
+        let result = if meta.size as u64 > self.multipart_copy_threshold {
+            self.multipart_copy(&meta, to, &attributes, if_not_exists)
+                .await
+        } else {
+            self.single_copy(from, to, if_not_exists).await
+        };
+        match result {
+            Ok(()) => {
+                self.record(|| Mutation::Copy {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+                Ok(())
+            }
+            Err(object_store::Error::Precondition { path, source }) if if_not_exists => {
+                Err(object_store::Error::AlreadyExists { path, source })
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn single_copy(
+        &self,
+        from: &Path,
+        to: &Path,
+        if_not_exists: bool,
+    ) -> object_store::Result<()> {
+        let request = self.copy_request(from, to);
+        let result = if if_not_exists {
+            request
+                .customize()
+                .await
+                .map_err(|_| Error::Unknown)?
+                .mutate_request(|request| {
+                    request
+                        .headers_mut()
+                        .insert(http::header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+                })
+                .send()
+                .await
+        } else {
+            request.send().await
+        };
+        match result {
+            Ok(_) => Ok(()),
+            Err(err) if status_code(&err) == Some(412) => Err(self.request_error(to, err).into()),
+            Err(err) => Err(self.request_error(from, err).into()),
+        }
+    }
+
+    /// Copies `source` to `to` in parts of at least the multipart part size, keeping its
+    /// attributes. The parts are only copied from the version of `source` that was inspected.
+    async fn multipart_copy(
+        &self,
+        source: &ObjectMeta,
+        to: &Path,
+        attributes: &PutAttributes,
+        if_not_exists: bool,
+    ) -> object_store::Result<()> {
+        let part_size = self
+            .multipart_part_size
+            .max(source.size.div_ceil(MAX_PARTS));
+        let upload_id = self.create_multipart(to, attributes).await?;
+        let guard = AbortOnDrop::new(self, to, &upload_id);
+        let copy_source = copy_source(&self.bucket, source.location.as_ref());
+        let upload = async {
+            let parts = stream::iter((0..source.size).step_by(part_size).enumerate())
+                .map(|(index, start)| {
+                    let end = (start + part_size).min(source.size);
+                    let request = self
+                        .client
+                        .upload_part_copy()
+                        .bucket(self.bucket.clone())
+                        .key(to.to_string())
+                        .upload_id(&upload_id)
+                        .part_number(index as i32 + 1)
+                        .copy_source(&copy_source)
+                        .copy_source_range(format!("bytes={}-{}", start, end - 1))
+                        .set_copy_source_if_match(source.e_tag.clone());
+                    async move {
+                        let result = self
+                            .encrypted(request, SseConfig::upload_part_copy)
+                            .send()
+                            .await
+                            .map_err(|err| self.request_error(&source.location, err))?
+                            .copy_part_result
+                            .ok_or(Error::Unknown)?;
+                        Ok::<_, object_store::Error>(self.completed_part(
+                            index as i32 + 1,
+                            result.e_tag(),
+                            &result,
+                        )?)
+                    }
+                })
+                .buffered(self.multipart_concurrency)
+                .try_collect::<Vec<_>>()
+                .await?;
+            let request = self
+                .client
+                .complete_multipart_upload()
+                .bucket(self.bucket.clone())
+                .key(to.to_string())
+                .upload_id(&upload_id)
+                .multipart_upload(
+                    CompletedMultipartUpload::builder()
+                        .set_parts(Some(parts))
+                        .build(),
+                );
+            let result = if if_not_exists {
+                request
+                    .customize()
+                    .await
+                    .map_err(|_| Error::Unknown)?
+                    .mutate_request(|request| {
+                        request
+                            .headers_mut()
+                            .insert(http::header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+                    })
+                    .send()
+                    .await
+            } else {
+                request.send().await
+            };
+            result.map_err(|err| self.request_error(to, err))?;
+            Ok::<_, object_store::Error>(())
+        }
+        .await;
+        match upload {
+            Ok(()) => guard.disarm(),
+            Err(_) => guard.abort().await,
+        }
+        upload
+    }
+}
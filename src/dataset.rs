@@ -0,0 +1,121 @@
+use bytes::Bytes;
+use js_sys::Uint8Array;
+use object_store::{path::Path, ObjectMeta, ObjectStore};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::File;
+
+use crate::{error::Error, journal::Mutation, listing::ListingEntry, S3};
+
+/// Prefix below which [`S3::register_dataset`] creates datasets.
+pub const DATASET_PREFIX: &str = "datasets";
+
+/// Name of the manifest written next to the files of a dataset.
+pub const MANIFEST: &str = "manifest.json";
+
+/// File of a registered dataset.
+#[derive(Debug, Clone)]
+pub struct RegisteredFile {
+    /// `s3://bucket/key` URL of the uploaded object.
+    pub url: String,
+    pub content_type: Option<String>,
+    pub meta: ObjectMeta,
+}
+
+/// Outcome of [`S3::register_dataset`].
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub prefix: Path,
+    pub manifest: Path,
+    pub files: Vec<RegisteredFile>,
+}
+
+/// Content of the dataset manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetManifest {
+    pub created: chrono::DateTime<chrono::Utc>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Name of the file as selected by the user.
+    pub name: String,
+    pub content_type: Option<String>,
+    #[serde(flatten)]
+    pub entry: ListingEntry,
+}
+
+impl S3 {
+    /// Uploads user selected `files` below a newly generated dataset prefix.
+    ///
+    /// Every file keeps its name and content type, a [`MANIFEST`] describing all files is written
+    /// last, so a dataset with a manifest is complete.
+    pub async fn register_dataset(&self, files: &[File]) -> object_store::Result<Dataset> {
+        let created = self.now();
+        let prefix = Path::from(format!(
+            "{}/{}-{:08x}",
+            DATASET_PREFIX,
+            created.format("%Y%m%dT%H%M%S"),
+            self.random.next_u64() as u32
+        ));
+
+        let mut registered = Vec::with_capacity(files.len());
+        let mut manifest = Vec::with_capacity(files.len());
+        for file in files {
+            let buffer = JsFuture::from(file.array_buffer())
+                .await
+                .map_err(Error::from)?;
+            let bytes = Bytes::from(Uint8Array::new(&buffer).to_vec());
+            let size = bytes.len();
+            let location = prefix.child(file.name());
+            let content_type = Some(file.type_())
+                .filter(|content_type| !content_type.is_empty())
+                .or_else(|| self.inferred_content_type(&location));
+
+            let result = self
+                .put_request(&location, bytes)
+                .set_content_type(content_type.clone())
+                .send()
+                .await
+                .map_err(Error::from)?;
+            self.record(|| Mutation::Put {
+                location: location.clone(),
+                e_tag: result.e_tag.clone(),
+                version: result.version_id.clone(),
+            });
+
+            let meta = ObjectMeta {
+                location: location.clone(),
+                last_modified: created,
+                size,
+                e_tag: result.e_tag,
+                version: result.version_id,
+            };
+            manifest.push(ManifestFile {
+                name: file.name(),
+                content_type: content_type.clone(),
+                entry: meta.clone().into(),
+            });
+            registered.push(RegisteredFile {
+                url: format!("s3://{}/{}", self.bucket, location),
+                content_type,
+                meta,
+            });
+        }
+
+        let manifest_path = prefix.child(MANIFEST);
+        let manifest = serde_json::to_vec(&DatasetManifest {
+            created,
+            files: manifest,
+        })
+        .map_err(Error::from)?;
+        self.put(&manifest_path, manifest.into()).await?;
+
+        Ok(Dataset {
+            prefix,
+            manifest: manifest_path,
+            files: registered,
+        })
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use http::HeaderValue;
+use object_store::{path::Path, ObjectStore};
+
+use crate::{conditional::ConditionalDelete, error::Error, journal::Mutation, S3};
+
+/// Most keys a single `DeleteObjects` request accepts.
+pub const MAX_DELETE_BATCH: usize = 1000;
+
+impl S3 {
+    /// Deletes the object at `location` only if its ETag is still `e_tag`, failing with
+    /// [`object_store::Error::Precondition`] if another writer replaced it in the meantime.
+    ///
+    /// Endpoints without conditional deletes check the ETag with a `HeadObject` first, see
+    /// [`ConditionalDelete`].
+    pub async fn delete_if_match(&self, location: &Path, e_tag: &str) -> object_store::Result<()> {
+        let request = self
+            .client
+            .delete_object()
+            .bucket(self.bucket.clone())
+            .key(location.to_string());
+        let result = match self.conditional_delete_strategy() {
+            ConditionalDelete::Conditional => {
+                let value = HeaderValue::try_from(e_tag)
+                    .map_err(|_| Error::InvalidHeader(e_tag.to_owned()))?;
+                request
+                    .customize()
+                    .await
+                    .map_err(|_| Error::Unknown)?
+                    .mutate_request(move |request| {
+                        request
+                            .headers_mut()
+                            .insert(http::header::IF_MATCH, value.clone());
+                    })
+                    .send()
+                    .await
+            }
+            ConditionalDelete::HeadThenDelete => {
+                let meta = self.head(location).await?;
+                if meta.e_tag.as_deref() != Some(e_tag) {
+                    return Err(object_store::Error::Precondition {
+                        path: location.to_string(),
+                        source: format!("{} does not match {:?}", e_tag, meta.e_tag).into(),
+                    });
+                }
+                request.send().await
+            }
+        };
+        result.map_err(|err| self.request_error(location, err))?;
+        self.record(|| Mutation::Delete {
+            location: location.clone(),
+        });
+        Ok(())
+    }
+
+    /// Deletes `locations` with one `DeleteObjects` request per batch of keys.
+    ///
+    /// Keys S3 refuses to delete are reported as [`Error::DeleteFailed`] in place of their path,
+    /// the remaining keys of the batch are still deleted.
+    pub(crate) fn delete_batched<'a>(
+        &'a self,
+        locations: BoxStream<'a, object_store::Result<Path>>,
+    ) -> BoxStream<'a, object_store::Result<Path>> {
+        locations
+            .chunks(self.delete_batch_size)
+            .then(move |batch| async move {
+                let (locations, errors): (Vec<_>, Vec<_>) =
+                    batch.into_iter().partition(Result::is_ok);
+                let locations = locations
+                    .into_iter()
+                    .map(Result::unwrap)
+                    .collect::<Vec<_>>();
+                let deleted = self.delete_objects(locations).await;
+                stream::iter(errors.into_iter().chain(deleted))
+            })
+            .flatten()
+            .boxed()
+    }
+
+    async fn delete_objects(&self, locations: Vec<Path>) -> Vec<object_store::Result<Path>> {
+        if locations.is_empty() {
+            return Vec::new();
+        }
+        let objects = locations
+            .iter()
+            .map(|location| ObjectIdentifier::builder().key(location.as_ref()).build())
+            .collect();
+        let response = match self
+            .client
+            .delete_objects()
+            .bucket(self.bucket.clone())
+            .delete(
+                Delete::builder()
+                    .set_objects(Some(objects))
+                    .quiet(true)
+                    .build(),
+            )
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => {
+                // The whole batch failed, report the error once and skip its keys
+                return vec![Err(Error::from(err).into())];
+            }
+        };
+        let mut failed = response
+            .errors()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|err| Some((err.key()?.to_owned(), err)))
+            .collect::<HashMap<_, _>>();
+        locations
+            .into_iter()
+            .map(|location| match failed.remove(location.as_ref()) {
+                Some(err) => Err(Error::DeleteFailed {
+                    key: location.to_string(),
+                    code: err.code().unwrap_or_default().to_owned(),
+                    message: err.message().unwrap_or_default().to_owned(),
+                }
+                .into()),
+                None => {
+                    self.record(|| Mutation::Delete {
+                        location: location.clone(),
+                    });
+                    Ok(location)
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use aws_sdk_s3::{primitives::ByteStream, types::CompletedMultipartUpload};
+use bytes::Bytes;
+use object_store::{path::Path, ObjectMeta, ObjectStore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    attributes::PutAttributes,
+    encryption::SseConfig,
+    error::{is_not_found, Error},
+    etag::etag,
+    journal::Mutation,
+    key_encoding::copy_source,
+    multipart::AbortOnDrop,
+    validate::MIN_PART_SIZE,
+    S3,
+};
+
+/// Prefix the [`BlockManifest`]s of objects are stored below by default, as `.blocks/<key>`.
+///
+/// Manifests are regular objects of the bucket, listings include them. Use
+/// [`S3Builder::with_delta_manifest_prefix`](crate::builder::S3Builder::with_delta_manifest_prefix)
+/// to keep them out of the keys the application lists.
+pub const MANIFEST_PREFIX: &str = ".blocks";
+
+/// Block signatures of an object, stored below the manifest prefix of the store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockManifest {
+    pub block_size: usize,
+    pub size: usize,
+    pub e_tag: Option<String>,
+    /// Hex encoded MD5 digest of every block.
+    pub digests: Vec<String>,
+}
+
+impl BlockManifest {
+    /// Computes the manifest of local `data`, `block_size` must be greater than 0.
+    pub fn new(data: &[u8], block_size: usize) -> Self {
+        BlockManifest {
+            block_size,
+            size: data.len(),
+            e_tag: None,
+            digests: data.chunks(block_size).map(etag).collect(),
+        }
+    }
+
+    fn block_len(&self, index: usize) -> usize {
+        (self.size - index * self.block_size).min(self.block_size)
+    }
+}
+
+/// Outcome of a [`S3::delta_put`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeltaReport {
+    /// Parts copied server side from the previous version of the object.
+    pub copied_parts: usize,
+    /// Parts uploaded from the local data.
+    pub uploaded_parts: usize,
+    pub uploaded_bytes: usize,
+}
+
+impl S3 {
+    fn manifest_path(&self, location: &Path) -> Path {
+        self.delta_manifest_prefix
+            .parts()
+            .chain(location.parts())
+            .collect()
+    }
+
+    /// Whether `location` is the [`BlockManifest`] of an object, below the manifest prefix.
+    pub fn is_block_manifest(&self, location: &Path) -> bool {
+        location.prefix_matches(&self.delta_manifest_prefix)
+    }
+
+    /// Replaces the object at `location` with `data`, uploading only blocks that changed.
+    ///
+    /// `data` is split into `block_size` blocks (at least [`MIN_PART_SIZE`]). Blocks whose digest
+    /// matches a block of the existing object are copied server side with `UploadPartCopy`, the
+    /// others are uploaded. Block digests of the existing object are read from its manifest below
+    /// the manifest prefix, or derived with ranged reads if there is none. A new manifest is
+    /// written after the upload. A `block_size` of 0 is rejected, manifests themselves are put
+    /// as a whole without a manifest of their own.
+    pub async fn delta_put(
+        &self,
+        location: &Path,
+        data: Bytes,
+        block_size: usize,
+    ) -> object_store::Result<DeltaReport> {
+        if block_size == 0 {
+            return Err(Error::ZeroBlockSize.into());
+        }
+        let block_size = block_size.max(MIN_PART_SIZE);
+        let local = BlockManifest::new(&data, block_size);
+        if local.digests.is_empty() || self.is_block_manifest(location) {
+            self.put(location, data).await?;
+            return Ok(DeltaReport::default());
+        }
+        let remote = match self.head(location).await {
+            Ok(meta) => Some(self.block_manifest(&meta, block_size).await?),
+            Err(err) if is_not_found(&err) => None,
+            Err(err) => return Err(err),
+        };
+        let remote_blocks: HashMap<(&str, usize), usize> = remote
+            .iter()
+            .flat_map(|remote| {
+                remote
+                    .digests
+                    .iter()
+                    .enumerate()
+                    .map(|(index, digest)| ((digest.as_str(), remote.block_len(index)), index))
+            })
+            .collect();
+
+        let upload_id = self
+            .create_multipart(location, &PutAttributes::default())
+            .await?;
+        let guard = AbortOnDrop::new(self, location, &upload_id);
+
+        let mut report = DeltaReport::default();
+        let mut parts = Vec::with_capacity(local.digests.len());
+        let upload = async {
+            for (index, digest) in local.digests.iter().enumerate() {
+                let part_number = index as i32 + 1;
+                let start = index * block_size;
+                let len = local.block_len(index);
+                let source = remote_blocks.get(&(digest.as_str(), len));
+                let part = match (source, &remote) {
+                    (Some(source), Some(remote)) => {
+                        let source_start = source * block_size;
+                        report.copied_parts += 1;
+                        let request = self
+                            .client
+                            .upload_part_copy()
+                            .bucket(self.bucket.clone())
+                            .key(location.to_string())
+                            .upload_id(&upload_id)
+                            .part_number(part_number)
+                            .copy_source(copy_source(&self.bucket, location.as_ref()))
+                            .copy_source_range(format!(
+                                "bytes={}-{}",
+                                source_start,
+                                source_start + len - 1
+                            ))
+                            .set_copy_source_if_match(remote.e_tag.clone());
+                        let result = self
+                            .encrypted(request, SseConfig::upload_part_copy)
+                            .send()
+                            .await
+                            .map_err(|err| self.request_error(location, err))?
+                            .copy_part_result
+                            .ok_or(Error::Unknown)?;
+                        self.completed_part(part_number, result.e_tag(), &result)?
+                    }
+                    _ => {
+                        report.uploaded_parts += 1;
+                        report.uploaded_bytes += len;
+                        let block = data.slice(start..start + len);
+                        let request = self
+                            .checksummed(self.client.upload_part(), &block)
+                            .bucket(self.bucket.clone())
+                            .key(location.to_string())
+                            .upload_id(&upload_id)
+                            .part_number(part_number)
+                            .body(ByteStream::from(block));
+                        let response = self
+                            .encrypted(request, SseConfig::upload_part)
+                            .send()
+                            .await
+                            .map_err(|err| self.request_error(location, err))?;
+                        self.completed_part(part_number, response.e_tag(), &response)?
+                    }
+                };
+                parts.push(part);
+            }
+            self.client
+                .complete_multipart_upload()
+                .bucket(self.bucket.clone())
+                .key(location.to_string())
+                .upload_id(&upload_id)
+                .multipart_upload(
+                    CompletedMultipartUpload::builder()
+                        .set_parts(Some(parts))
+                        .build(),
+                )
+                .send()
+                .await
+                .map_err(|err| self.request_error(location, err))
+        }
+        .await;
+
+        let result = match upload {
+            Ok(result) => {
+                guard.disarm();
+                result
+            }
+            Err(err) => {
+                guard.abort().await;
+                return Err(err.into());
+            }
+        };
+
+        self.record(|| Mutation::Put {
+            location: location.clone(),
+            e_tag: result.e_tag.clone(),
+            version: result.version_id.clone(),
+        });
+        let manifest = BlockManifest {
+            e_tag: result.e_tag,
+            ..local
+        };
+        let manifest = serde_json::to_vec(&manifest).map_err(Error::from)?;
+        self.put(&self.manifest_path(location), manifest.into())
+            .await?;
+        Ok(report)
+    }
+
+    /// Block signatures of an existing object, read from its manifest or derived by ranged reads.
+    pub async fn block_manifest(
+        &self,
+        meta: &ObjectMeta,
+        block_size: usize,
+    ) -> object_store::Result<BlockManifest> {
+        if block_size == 0 {
+            return Err(Error::ZeroBlockSize.into());
+        }
+        if let Ok(manifest) = self.get(&self.manifest_path(&meta.location)).await {
+            let manifest: BlockManifest =
+                serde_json::from_slice(&manifest.bytes().await?).map_err(Error::from)?;
+            if manifest.block_size == block_size
+                && manifest.size == meta.size
+                && manifest.e_tag == meta.e_tag
+            {
+                return Ok(manifest);
+            }
+        }
+        let mut digests = Vec::new();
+        for start in (0..meta.size).step_by(block_size) {
+            let block = self
+                .get_range(&meta.location, start..(start + block_size).min(meta.size))
+                .await?;
+            digests.push(etag(&block));
+        }
+        Ok(BlockManifest {
+            block_size,
+            size: meta.size,
+            e_tag: meta.e_tag.clone(),
+            digests,
+        })
+    }
+}
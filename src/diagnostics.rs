@@ -0,0 +1,165 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{builder::S3Builder, S3};
+
+/// Release line of `object_store` whose `ObjectStore` trait the crate implements.
+const OBJECT_STORE_VERSION: &str = "0.9";
+
+/// Redacted summary of a store's configuration for bug reports, see [`S3::diagnostics`].
+///
+/// Secrets are never included: credentials are reduced to their kind and the first characters
+/// of the access key id, user info and query are stripped from the endpoint and SSE-C keys are
+/// omitted. Displays as pretty-printed JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostics {
+    pub crate_version: String,
+    pub object_store_version: String,
+    /// Enabled cargo features of the crate.
+    pub features: Vec<String>,
+    /// Architecture the crate was compiled for, e.g. `wasm32`.
+    pub target: String,
+    pub bucket: String,
+    pub region: Option<String>,
+    pub endpoint: Option<String>,
+    /// `path` or `virtual-hosted`.
+    pub addressing_style: String,
+    /// Name of the [`CompatProfile`](crate::profile::CompatProfile), if one was applied.
+    pub profile: Option<String>,
+    /// Kind of credentials, e.g. `static (AKIA…)`.
+    pub credentials: String,
+    pub retry: Option<String>,
+    pub connect_timeout_ms: Option<u64>,
+    pub request_timeout_ms: Option<u64>,
+    pub max_concurrent_requests: Option<usize>,
+    pub rate_limit: Option<(u32, u32)>,
+    pub multipart_part_size: usize,
+    pub multipart_concurrency: usize,
+    pub checksum: Option<String>,
+    pub verify_checksums: bool,
+    pub sse: Option<String>,
+    /// Storage class of writes without their own.
+    pub storage_class: Option<String>,
+    pub list_encoding: String,
+    pub conditional_put: String,
+    pub conditional_delete: String,
+    pub copy_if_not_exists: String,
+    pub unsupported: String,
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let json = serde_json::to_string_pretty(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+fn enabled_features() -> Vec<String> {
+    [
+        ("admin", cfg!(feature = "admin")),
+        ("batch", cfg!(feature = "batch")),
+        ("js", cfg!(feature = "js")),
+        ("native", cfg!(feature = "native")),
+        ("object_store_next", cfg!(feature = "object_store_next")),
+        ("otlp", cfg!(feature = "otlp")),
+        ("shared_session", cfg!(feature = "shared_session")),
+        ("tolerant", cfg!(feature = "tolerant")),
+        ("tracing", cfg!(feature = "tracing")),
+        ("wasm", cfg!(feature = "wasm")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(feature, _)| feature.to_owned())
+    .collect()
+}
+
+/// `endpoint` without user info, query and fragment.
+fn redact_endpoint(endpoint: &str) -> String {
+    let endpoint = endpoint.split(['?', '#']).next().unwrap_or_default();
+    match endpoint.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            let host = authority.rsplit('@').next().unwrap_or_default();
+            format!("{}://{}{}", scheme, host, path)
+        }
+        None => endpoint.to_owned(),
+    }
+}
+
+impl S3Builder {
+    /// The parts of [`Diagnostics`] only the builder knows, taken after the profile is applied.
+    pub(crate) fn diagnostics(&self) -> Diagnostics {
+        let access_key = |kind: &str| match &self.access_key_id {
+            Some(id) => format!("{} ({}…)", kind, id.chars().take(4).collect::<String>()),
+            None => kind.to_owned(),
+        };
+        let credentials = if self.anonymous {
+            "anonymous".to_owned()
+        } else if self.web_identity.is_some() {
+            "web identity".to_owned()
+        } else if self.remote_signer.is_some() {
+            access_key("remote signer")
+        } else {
+            access_key("static")
+        };
+        Diagnostics {
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+            object_store_version: OBJECT_STORE_VERSION.to_owned(),
+            features: enabled_features(),
+            target: std::env::consts::ARCH.to_owned(),
+            bucket: self.bucket.clone().unwrap_or_default(),
+            region: self.region.clone(),
+            endpoint: self.endpoint.as_deref().map(redact_endpoint),
+            addressing_style: match self.force_path_style.unwrap_or(true) {
+                true => "path".to_owned(),
+                false => "virtual-hosted".to_owned(),
+            },
+            profile: self.profile_name.clone(),
+            credentials,
+            retry: self.retry.as_ref().map(|retry| format!("{:?}", retry)),
+            connect_timeout_ms: self
+                .connect_timeout
+                .map(|timeout| timeout.as_millis() as u64),
+            request_timeout_ms: self
+                .request_timeout
+                .map(|timeout| timeout.as_millis() as u64),
+            max_concurrent_requests: self.max_concurrent_requests,
+            rate_limit: self.rate_limit,
+            multipart_part_size: 0,
+            multipart_concurrency: 0,
+            checksum: None,
+            verify_checksums: false,
+            sse: None,
+            storage_class: self
+                .storage_class
+                .as_ref()
+                .map(|storage_class| format!("{:?}", storage_class)),
+            list_encoding: String::new(),
+            conditional_put: String::new(),
+            conditional_delete: String::new(),
+            copy_if_not_exists: String::new(),
+            unsupported: String::new(),
+        }
+    }
+}
+
+impl S3 {
+    /// Redacted summary of the store's configuration to paste into bug reports.
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics {
+            bucket: self.bucket.clone(),
+            multipart_part_size: self.multipart_part_size,
+            multipart_concurrency: self.multipart_concurrency,
+            checksum: self.checksum.map(|checksum| format!("{:?}", checksum)),
+            verify_checksums: self.verify_checksums,
+            sse: self.sse.as_ref().map(|sse| format!("{:?}", sse)),
+            list_encoding: format!("{:?}", self.list_encoding),
+            conditional_put: format!("{:?}", self.conditional_put),
+            conditional_delete: format!("{:?}", self.conditional_delete),
+            copy_if_not_exists: format!("{:?}", self.copy_if_not_exists),
+            unsupported: format!("{:?}", self.unsupported),
+            ..(*self.diagnostics).clone()
+        }
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    fmt::Display,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use async_trait::async_trait;
+use aws_smithy_types::base64;
+use bytes::Bytes;
+use futures::{stream, stream::BoxStream, StreamExt, TryStreamExt};
+use object_store::{
+    path::Path, GetOptions, GetResult, GetResultPayload, ListResult, MultipartId, ObjectMeta,
+    ObjectStore, PutOptions, PutResult,
+};
+use tokio::io::AsyncWrite;
+
+use crate::{attributes::PutAttributes, builder::S3Builder, error::Error, resolve_range, S3};
+
+/// Plaintext bytes sealed together, every segment is followed by its 16 byte tag.
+pub const SEGMENT_SIZE: usize = 64 * 1024;
+
+const TAG_SIZE: usize = 16;
+const SEALED_SEGMENT: usize = SEGMENT_SIZE + TAG_SIZE;
+
+const ALGORITHM: &str = "AES256-GCM-64K";
+const META_ALGORITHM: &str = "cse-algorithm";
+const META_IV: &str = "cse-iv";
+const META_KEY_ID: &str = "cse-key-id";
+const META_WRAPPED_KEY: &str = "cse-wrapped-key";
+
+/// Key an object is encrypted with, along with what is needed to recover it later.
+#[derive(Clone)]
+pub struct DataKey {
+    /// AES-256 key encrypting the object.
+    pub key: [u8; 32],
+    /// Id of the key encrypting `key`, stored in the object's metadata.
+    pub key_id: String,
+    /// `key` encrypted under `key_id`, e.g. the `CiphertextBlob` of a KMS `GenerateDataKey`.
+    pub wrapped: Vec<u8>,
+}
+
+impl std::fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataKey")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Source of the data keys of an [`EncryptedStore`], e.g. a callback to KMS.
+#[async_trait]
+pub trait KeyProvider: std::fmt::Debug + Send + Sync {
+    /// Key for a new object.
+    async fn data_key(&self) -> object_store::Result<DataKey>;
+    /// Plaintext of the data key `wrapped` under `key_id`.
+    async fn unwrap_key(&self, key_id: &str, wrapped: &[u8]) -> object_store::Result<[u8; 32]>;
+}
+
+/// Encrypts every object with the same key.
+#[derive(Clone)]
+pub struct StaticKey {
+    id: String,
+    key: [u8; 32],
+}
+
+impl StaticKey {
+    /// `key` stored by its `id`, objects encrypted with another key can't be read.
+    pub fn new(id: impl Into<String>, key: [u8; 32]) -> Self {
+        Self { id: id.into(), key }
+    }
+}
+
+impl std::fmt::Debug for StaticKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StaticKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl KeyProvider for StaticKey {
+    async fn data_key(&self) -> object_store::Result<DataKey> {
+        Ok(DataKey {
+            key: self.key,
+            key_id: self.id.clone(),
+            wrapped: Vec::new(),
+        })
+    }
+    async fn unwrap_key(&self, key_id: &str, _wrapped: &[u8]) -> object_store::Result<[u8; 32]> {
+        match key_id == self.id {
+            true => Ok(self.key),
+            false => Err(Error::UnknownKey(key_id.to_owned()).into()),
+        }
+    }
+}
+
+/// AES-GCM over the segments of one object, the nonce of a segment is the object's IV with
+/// the segment index XORed into its last 8 bytes.
+#[derive(Clone)]
+struct Sealer {
+    cipher: Aes256Gcm,
+    iv: [u8; 12],
+}
+
+impl Sealer {
+    fn nonce(&self, index: u64) -> [u8; 12] {
+        let mut nonce = self.iv;
+        for (byte, index) in nonce[4..].iter_mut().zip(index.to_be_bytes()) {
+            *byte ^= index;
+        }
+        nonce
+    }
+
+    /// The last segment is authenticated as such, so truncated objects fail to decrypt.
+    fn seal(&self, index: u64, plain: &[u8], last: bool) -> Vec<u8> {
+        let payload = Payload {
+            msg: plain,
+            aad: &[u8::from(last)],
+        };
+        self.cipher
+            .encrypt(Nonce::from_slice(&self.nonce(index)), payload)
+            .expect("segments are far below the AES-GCM message limit")
+    }
+
+    fn open(&self, index: u64, sealed: &[u8], last: bool) -> Result<Vec<u8>, aes_gcm::Error> {
+        let payload = Payload {
+            msg: sealed,
+            aad: &[u8::from(last)],
+        };
+        self.cipher
+            .decrypt(Nonce::from_slice(&self.nonce(index)), payload)
+    }
+}
+
+/// Segments of an encrypted object of `size` bytes, there is at least the last one.
+fn segments(size: usize) -> usize {
+    size.div_ceil(SEALED_SEGMENT).max(1)
+}
+
+/// Size of the plaintext of an encrypted object of `size` bytes.
+fn plaintext_size(size: usize) -> usize {
+    size.saturating_sub(segments(size) * TAG_SIZE)
+}
+
+fn plaintext_meta(meta: ObjectMeta) -> ObjectMeta {
+    ObjectMeta {
+        size: plaintext_size(meta.size),
+        ..meta
+    }
+}
+
+/// Store encrypting objects client-side before they reach S3, with AES-256-GCM under the data
+/// keys of a [`KeyProvider`]. Built with [`S3Builder::build_encrypted`].
+///
+/// The IV and the id and wrapped form of the data key are stored in the `x-amz-meta-cse-*`
+/// metadata of every object. Objects are sealed in segments of [`SEGMENT_SIZE`] bytes, so
+/// multipart uploads and ranged reads don't need the whole object in memory. Sizes reported by
+/// heads and listings are plaintext sizes, every listed object is assumed to be encrypted.
+#[derive(Debug)]
+pub struct EncryptedStore {
+    inner: S3,
+    keys: Arc<dyn KeyProvider>,
+}
+
+impl S3Builder {
+    /// Builds an [`EncryptedStore`] encrypting with the data keys of `keys`.
+    pub fn build_encrypted(
+        self,
+        keys: impl KeyProvider + 'static,
+    ) -> Result<EncryptedStore, Error> {
+        Ok(EncryptedStore::new(self.build()?, keys))
+    }
+}
+
+impl EncryptedStore {
+    pub fn new(inner: S3, keys: impl KeyProvider + 'static) -> Self {
+        Self {
+            inner,
+            keys: Arc::new(keys),
+        }
+    }
+
+    pub fn inner(&self) -> &S3 {
+        &self.inner
+    }
+
+    /// Sealer for a new object, with the metadata to store along with it.
+    async fn new_sealer(&self) -> object_store::Result<(Sealer, PutAttributes)> {
+        let data_key = self.keys.data_key().await?;
+        let mut iv = [0; 12];
+        getrandom::getrandom(&mut iv).expect("Failed to get random bytes");
+        let mut attributes = PutAttributes::default();
+        attributes.metadata.extend([
+            (META_ALGORITHM.to_owned(), ALGORITHM.to_owned()),
+            (META_IV.to_owned(), base64::encode(iv)),
+            (META_KEY_ID.to_owned(), data_key.key_id),
+            (
+                META_WRAPPED_KEY.to_owned(),
+                base64::encode(data_key.wrapped),
+            ),
+        ]);
+        let sealer = Sealer {
+            cipher: Aes256Gcm::new(&data_key.key.into()),
+            iv,
+        };
+        Ok((sealer, attributes))
+    }
+
+    /// Sealer of the object at `location`, from the metadata it was stored with.
+    async fn sealer(
+        &self,
+        location: &Path,
+        attributes: &PutAttributes,
+    ) -> object_store::Result<Sealer> {
+        let failed = |reason: &str| Error::Decryption {
+            key: location.to_string(),
+            reason: reason.to_owned(),
+        };
+        let metadata = &attributes.metadata;
+        if metadata.get(META_ALGORITHM).map(String::as_str) != Some(ALGORITHM) {
+            return Err(failed("not encrypted client-side").into());
+        }
+        let iv = metadata
+            .get(META_IV)
+            .and_then(|iv| base64::decode(iv).ok())
+            .and_then(|iv| <[u8; 12]>::try_from(iv).ok())
+            .ok_or_else(|| failed("invalid IV"))?;
+        let key_id = metadata
+            .get(META_KEY_ID)
+            .ok_or_else(|| failed("missing key id"))?;
+        let wrapped = metadata
+            .get(META_WRAPPED_KEY)
+            .and_then(|wrapped| base64::decode(wrapped).ok())
+            .ok_or_else(|| failed("invalid wrapped key"))?;
+        let key = self.keys.unwrap_key(key_id, &wrapped).await?;
+        Ok(Sealer {
+            cipher: Aes256Gcm::new(&key.into()),
+            iv,
+        })
+    }
+
+    /// Decrypts the ciphertext `range` of an object of `size` bytes, it has to start at a
+    /// segment boundary.
+    fn open_range(
+        sealer: &Sealer,
+        location: &Path,
+        sealed: &[u8],
+        first_segment: usize,
+        size: usize,
+    ) -> object_store::Result<Vec<u8>> {
+        let last_segment = segments(size) - 1;
+        let mut plain = Vec::with_capacity(sealed.len());
+        for (offset, segment) in sealed.chunks(SEALED_SEGMENT).enumerate() {
+            let index = first_segment + offset;
+            let opened = sealer
+                .open(index as u64, segment, index == last_segment)
+                .map_err(|_| Error::Decryption {
+                    key: location.to_string(),
+                    reason: format!("segment {} failed authentication", index),
+                })?;
+            plain.extend(opened);
+        }
+        Ok(plain)
+    }
+}
+
+/// State of the decryption of a whole object's stream.
+struct Opening {
+    sealed: BoxStream<'static, object_store::Result<Bytes>>,
+    sealer: Sealer,
+    location: Path,
+    buffer: Vec<u8>,
+    index: u64,
+    ended: bool,
+}
+
+/// Decrypts `sealed` segment by segment, a segment is only known to be the last once the
+/// stream ends.
+fn open_stream(
+    sealed: BoxStream<'static, object_store::Result<Bytes>>,
+    sealer: Sealer,
+    location: Path,
+) -> BoxStream<'static, object_store::Result<Bytes>> {
+    let state = Opening {
+        sealed,
+        sealer,
+        location,
+        buffer: Vec::new(),
+        index: 0,
+        ended: false,
+    };
+    stream::try_unfold(Some(state), |state| async move {
+        let Some(mut state) = state else {
+            return Ok(None);
+        };
+        while state.buffer.len() <= SEALED_SEGMENT && !state.ended {
+            match state.sealed.try_next().await? {
+                Some(bytes) => state.buffer.extend_from_slice(&bytes),
+                None => state.ended = true,
+            }
+        }
+        let last = state.buffer.len() <= SEALED_SEGMENT;
+        let end = state.buffer.len().min(SEALED_SEGMENT);
+        let opened = state
+            .sealer
+            .open(state.index, &state.buffer[..end], last)
+            .map_err(|_| Error::Decryption {
+                key: state.location.to_string(),
+                reason: format!("segment {} failed authentication", state.index),
+            })?;
+        state.buffer.drain(..end);
+        state.index += 1;
+        Ok(Some((Bytes::from(opened), (!last).then_some(state))))
+    })
+    .boxed()
+}
+
+/// Seals what is written to it and writes the segments to the upload of `inner`.
+struct SealingWriter {
+    inner: Box<dyn AsyncWrite + Unpin + Send>,
+    sealer: Sealer,
+    index: u64,
+    plain: Vec<u8>,
+    sealed: Vec<u8>,
+    written: usize,
+    finished: bool,
+}
+
+impl SealingWriter {
+    fn seal(&mut self, last: bool) {
+        self.sealed = self.sealer.seal(self.index, &self.plain, last);
+        self.written = 0;
+        self.plain.clear();
+        self.index += 1;
+    }
+
+    /// Writes the sealed segment to `inner`.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.sealed.len() {
+            let written =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.sealed[self.written..]))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += written;
+        }
+        self.sealed.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for SealingWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_drain(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        // A full segment is only sealed once more data follows, the last one is sealed on shutdown
+        if self.plain.len() == SEGMENT_SIZE {
+            self.seal(false);
+            ready!(self.poll_drain(cx))?;
+        }
+        let length = buf.len().min(SEGMENT_SIZE - self.plain.len());
+        self.plain.extend_from_slice(&buf[..length]);
+        Poll::Ready(Ok(length))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_drain(cx))?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_drain(cx))?;
+        if !self.finished {
+            self.seal(true);
+            self.finished = true;
+            ready!(self.poll_drain(cx))?;
+        }
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for EncryptedStore {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        let (sealer, attributes) = self.new_sealer().await?;
+        let segments = match bytes.is_empty() {
+            true => vec![&bytes[..]],
+            false => bytes.chunks(SEGMENT_SIZE).collect(),
+        };
+        let last = segments.len() - 1;
+        let sealed = segments
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, segment)| sealer.seal(index as u64, segment, index == last))
+            .collect::<Vec<_>>();
+        self.inner
+            .put_with_attributes(location, sealed.into(), opts, &attributes)
+            .await
+    }
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        let (sealer, attributes) = self.new_sealer().await?;
+        let (id, inner) = self
+            .inner
+            .put_multipart_with_attributes(location, &attributes)
+            .await?;
+        let writer = SealingWriter {
+            inner,
+            sealer,
+            index: 0,
+            plain: Vec::with_capacity(SEGMENT_SIZE),
+            sealed: Vec::new(),
+            written: 0,
+            finished: false,
+        };
+        Ok((id, Box::new(writer)))
+    }
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        if options.range.is_none() && !options.head {
+            let (result, attributes) = self.inner.get_with_attributes(location, options).await?;
+            let sealer = self.sealer(location, &attributes).await?;
+            let meta = plaintext_meta(result.meta.clone());
+            let range = 0..meta.size;
+            let payload = open_stream(result.into_stream(), sealer, location.clone());
+            return Ok(GetResult {
+                payload: GetResultPayload::Stream(payload),
+                meta,
+                range,
+            });
+        }
+
+        // Ranges are resolved against the plaintext size, check the conditions with a head
+        let requested = options.range.clone();
+        let head = GetOptions {
+            range: None,
+            head: true,
+            ..options
+        };
+        let (result, attributes) = self.inner.get_with_attributes(location, head).await?;
+        let size = result.meta.size;
+        let meta = plaintext_meta(result.meta);
+        let Some(requested) = requested else {
+            return Ok(GetResult {
+                payload: GetResultPayload::Stream(stream::empty().boxed()),
+                range: 0..meta.size,
+                meta,
+            });
+        };
+        let range = resolve_range(&requested, meta.size);
+        if range.is_empty() {
+            return Ok(GetResult {
+                payload: GetResultPayload::Stream(stream::empty().boxed()),
+                meta,
+                range,
+            });
+        }
+        let sealer = self.sealer(location, &attributes).await?;
+        let first_segment = range.start / SEGMENT_SIZE;
+        let last_segment = (range.end - 1) / SEGMENT_SIZE;
+        let sealed_range =
+            first_segment * SEALED_SEGMENT..((last_segment + 1) * SEALED_SEGMENT).min(size);
+        let sealed = GetOptions {
+            range: Some(sealed_range.into()),
+            if_match: meta.e_tag.clone(),
+            version: meta.version.clone(),
+            ..Default::default()
+        };
+        let sealed = self.inner.get_opts(location, sealed).await?.bytes().await?;
+        let plain = Self::open_range(&sealer, location, &sealed, first_segment, size)?;
+        let offset = range.start - first_segment * SEGMENT_SIZE;
+        let bytes = Bytes::from(plain).slice(offset..offset + range.len());
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(stream::once(async { Ok(bytes) }).boxed()),
+            meta,
+            range,
+        })
+    }
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.inner.head(location).await.map(plaintext_meta)
+    }
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        self.inner.list(prefix).map_ok(plaintext_meta).boxed()
+    }
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let mut result = self.inner.list_with_delimiter(prefix).await?;
+        result.objects = result.objects.into_iter().map(plaintext_meta).collect();
+        Ok(result)
+    }
+    /// Copies keep the metadata of the source, including its key and IV.
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+impl Display for EncryptedStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Encrypted({})", self.inner)
+    }
+}
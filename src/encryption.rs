@@ -0,0 +1,161 @@
+use std::fmt::Debug;
+
+use aws_sdk_s3::{
+    operation::{
+        copy_object::builders::CopyObjectFluentBuilder,
+        create_multipart_upload::builders::CreateMultipartUploadFluentBuilder,
+        get_object::builders::GetObjectFluentBuilder,
+        head_object::builders::HeadObjectFluentBuilder,
+        put_object::builders::PutObjectFluentBuilder,
+        upload_part::builders::UploadPartFluentBuilder,
+        upload_part_copy::builders::UploadPartCopyFluentBuilder,
+    },
+    types::ServerSideEncryption,
+};
+use md5::{Digest, Md5};
+
+use crate::S3;
+
+/// Server side encryption applied to written objects.
+#[derive(Clone, PartialEq, Eq)]
+pub enum SseConfig {
+    /// SSE-S3, keys managed by S3.
+    S3,
+    /// SSE-KMS with `key_id`, or the bucket's default KMS key if `None`.
+    Kms { key_id: Option<String> },
+    /// SSE-C with a 256 bit customer provided key.
+    ///
+    /// S3 doesn't store the key, it is sent with every read and write of the object.
+    Customer { key: [u8; 32] },
+}
+
+impl Debug for SseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SseConfig::S3 => f.write_str("S3"),
+            SseConfig::Kms { key_id } => f.debug_struct("Kms").field("key_id", key_id).finish(),
+            SseConfig::Customer { .. } => f.debug_struct("Customer").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// `x-amz-server-side-encryption-customer-*` header values of an SSE-C key.
+struct CustomerKey {
+    algorithm: &'static str,
+    key: String,
+    key_md5: String,
+}
+
+impl CustomerKey {
+    fn new(key: &[u8; 32]) -> Self {
+        Self {
+            algorithm: "AES256",
+            key: aws_smithy_types::base64::encode(key),
+            key_md5: aws_smithy_types::base64::encode(Md5::digest(key)),
+        }
+    }
+}
+
+macro_rules! customer_key {
+    ($builder:expr, $key:expr) => {{
+        let key = CustomerKey::new($key);
+        $builder
+            .sse_customer_algorithm(key.algorithm)
+            .sse_customer_key(key.key)
+            .sse_customer_key_md5(key.key_md5)
+    }};
+}
+
+macro_rules! object_encryption {
+    ($builder:expr, $config:expr) => {
+        match $config {
+            SseConfig::S3 => $builder.server_side_encryption(ServerSideEncryption::Aes256),
+            SseConfig::Kms { key_id } => $builder
+                .server_side_encryption(ServerSideEncryption::AwsKms)
+                .set_ssekms_key_id(key_id.clone()),
+            SseConfig::Customer { key } => customer_key!($builder, key),
+        }
+    };
+}
+
+impl SseConfig {
+    fn customer_key(&self) -> Option<&[u8; 32]> {
+        match self {
+            SseConfig::Customer { key } => Some(key),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn put(&self, builder: PutObjectFluentBuilder) -> PutObjectFluentBuilder {
+        object_encryption!(builder, self)
+    }
+
+    pub(crate) fn create_multipart(
+        &self,
+        builder: CreateMultipartUploadFluentBuilder,
+    ) -> CreateMultipartUploadFluentBuilder {
+        object_encryption!(builder, self)
+    }
+
+    /// Encrypts the copy and, for SSE-C, decrypts the source with the same key.
+    pub(crate) fn copy(&self, builder: CopyObjectFluentBuilder) -> CopyObjectFluentBuilder {
+        let builder = match self.customer_key() {
+            Some(key) => {
+                let key = CustomerKey::new(key);
+                builder
+                    .copy_source_sse_customer_algorithm(key.algorithm)
+                    .copy_source_sse_customer_key(key.key)
+                    .copy_source_sse_customer_key_md5(key.key_md5)
+            }
+            None => builder,
+        };
+        object_encryption!(builder, self)
+    }
+
+    pub(crate) fn upload_part(&self, builder: UploadPartFluentBuilder) -> UploadPartFluentBuilder {
+        match self.customer_key() {
+            Some(key) => customer_key!(builder, key),
+            None => builder,
+        }
+    }
+
+    pub(crate) fn upload_part_copy(
+        &self,
+        builder: UploadPartCopyFluentBuilder,
+    ) -> UploadPartCopyFluentBuilder {
+        match self.customer_key() {
+            Some(key) => {
+                let source = CustomerKey::new(key);
+                customer_key!(builder, key)
+                    .copy_source_sse_customer_algorithm(source.algorithm)
+                    .copy_source_sse_customer_key(source.key)
+                    .copy_source_sse_customer_key_md5(source.key_md5)
+            }
+            None => builder,
+        }
+    }
+
+    pub(crate) fn get(&self, builder: GetObjectFluentBuilder) -> GetObjectFluentBuilder {
+        match self.customer_key() {
+            Some(key) => customer_key!(builder, key),
+            None => builder,
+        }
+    }
+
+    pub(crate) fn head(&self, builder: HeadObjectFluentBuilder) -> HeadObjectFluentBuilder {
+        match self.customer_key() {
+            Some(key) => customer_key!(builder, key),
+            None => builder,
+        }
+    }
+}
+
+impl S3 {
+    /// Applies the store's server side encryption to a request builder.
+    pub(crate) fn encrypted<B>(&self, builder: B, apply: impl FnOnce(&SseConfig, B) -> B) -> B {
+        match &self.sse {
+            Some(sse) => apply(sse, builder),
+            None => builder,
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Injectable sources of randomness, monotonic time and wall clock time.
+//!
+//! Everything in the crate that waits, measures durations or jitters goes through a [`Timer`]
+//! and a [`Random`], everything that needs the date, like signing and credential expiry, goes
+//! through a [`Clock`]. Tests can swap in [`ManualTimer`], [`SeededRandom`] and [`ManualClock`]
+//! and run the same way in the browser and natively.
+//!
+//! Builds for wasm use the JS clock, native builds with the `native` feature use tokio's.
+
+use std::{
+    fmt::Debug,
+    future::Future,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use futures::{channel::oneshot, future::BoxFuture, FutureExt};
+use parking_lot::Mutex;
+
+/// Source of random numbers, e.g. for backoff jitter.
+pub trait Random: Debug + Send + Sync {
+    fn next_u64(&self) -> u64;
+
+    /// Uniformly distributed duration in `0..=max`.
+    fn jitter(&self, max: Duration) -> Duration {
+        let nanos = max.as_nanos().min(u64::MAX as u128) as u64;
+        match nanos.checked_add(1) {
+            Some(bound) => Duration::from_nanos(self.next_u64() % bound),
+            None => Duration::from_nanos(self.next_u64()),
+        }
+    }
+}
+
+/// Monotonic clock that can also sleep.
+pub trait Timer: Debug + Send + Sync {
+    /// Time elapsed since an arbitrary but fixed origin.
+    fn now(&self) -> Duration;
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// Wall clock time, e.g. the signing time of requests.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// Clock of the platform, `Date.now()` on wasm.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        system_time()
+    }
+}
+
+/// Clock that only moves when set or advanced.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl ManualClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock()
+    }
+}
+
+/// Random numbers of the platform's cryptographic generator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRandom;
+
+impl Random for SystemRandom {
+    fn next_u64(&self) -> u64 {
+        let mut bytes = [0; 8];
+        getrandom::getrandom(&mut bytes).expect("Failed to get random bytes");
+        u64::from_le_bytes(bytes)
+    }
+}
+
+/// Reproducible xorshift generator.
+#[derive(Debug)]
+pub struct SeededRandom {
+    state: Mutex<u64>,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            // xorshift gets stuck at zero
+            state: Mutex::new(seed.max(1)),
+        }
+    }
+}
+
+impl Random for SeededRandom {
+    fn next_u64(&self) -> u64 {
+        let mut state = self.state.lock();
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+}
+
+/// Timer backed by `wasm_timer`, works in windows and workers.
+#[cfg(feature = "wasm")]
+#[derive(Debug, Clone)]
+pub struct BrowserTimer {
+    origin: wasm_timer::Instant,
+}
+
+#[cfg(feature = "wasm")]
+impl Default for BrowserTimer {
+    fn default() -> Self {
+        Self {
+            origin: wasm_timer::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl Timer for BrowserTimer {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        wasm_timer::Delay::new(duration).map(|_| ()).boxed()
+    }
+}
+
+/// Timer backed by tokio, sleeping requires a tokio runtime with the time driver enabled.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub struct TokioTimer {
+    origin: std::time::Instant,
+}
+
+#[cfg(feature = "native")]
+impl Default for TokioTimer {
+    fn default() -> Self {
+        Self {
+            origin: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl Timer for TokioTimer {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        tokio::time::sleep(duration).boxed()
+    }
+}
+
+/// Timer of the target the crate is built for.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+pub(crate) fn default_timer() -> Arc<dyn Timer> {
+    Arc::new(BrowserTimer::default())
+}
+
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+pub(crate) fn default_timer() -> Arc<dyn Timer> {
+    Arc::new(TokioTimer::default())
+}
+
+/// Wall clock time, `std::time::SystemTime::now` panics on wasm.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn system_time() -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_millis(js_sys::Date::now().max(0.0) as u64)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn system_time() -> SystemTime {
+    SystemTime::now()
+}
+
+/// Runs `future` in the background, on the JS event loop or the current tokio runtime.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+pub(crate) fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+pub(crate) fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+    tokio::spawn(future);
+}
+
+#[derive(Debug, Default)]
+struct ManualTimerState {
+    now: Duration,
+    sleepers: Vec<(Duration, oneshot::Sender<()>)>,
+}
+
+/// Timer that only moves when advanced, sleeps finish once their deadline is reached.
+#[derive(Debug, Clone, Default)]
+pub struct ManualTimer {
+    state: Arc<Mutex<ManualTimerState>>,
+}
+
+impl ManualTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the clock forward by `duration` and wakes all sleeps that are due.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock();
+        state.now += duration;
+        let now = state.now;
+        let (due, pending) = std::mem::take(&mut state.sleepers)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(deadline, _)| *deadline <= now);
+        state.sleepers = pending;
+        drop(state);
+        for (_, sleeper) in due {
+            let _ = sleeper.send(());
+        }
+    }
+}
+
+impl Timer for ManualTimer {
+    fn now(&self) -> Duration {
+        self.state.lock().now
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        if duration.is_zero() {
+            return futures::future::ready(()).boxed();
+        }
+        let (tx, rx) = oneshot::channel();
+        let mut state = self.state.lock();
+        let deadline = state.now + duration;
+        state.sleepers.push((deadline, tx));
+        rx.map(|_| ()).boxed()
+    }
+}
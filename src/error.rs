@@ -1,19 +1,26 @@
 use std::convert::Infallible;
 
 use aws_sdk_s3::{
-    error::SdkError,
+    error::{ProvideErrorMetadata, SdkError},
     operation::{
         abort_multipart_upload::AbortMultipartUploadError,
         complete_multipart_upload::CompleteMultipartUploadError, copy_object::CopyObjectError,
         create_multipart_upload::CreateMultipartUploadError, delete_object::DeleteObjectError,
-        get_object::GetObjectError, head_object::HeadObjectError,
-        list_objects_v2::ListObjectsV2Error, put_object::PutObjectError,
-        upload_part::UploadPartError,
+        delete_object_tagging::DeleteObjectTaggingError, delete_objects::DeleteObjectsError,
+        get_object::GetObjectError, get_object_tagging::GetObjectTaggingError,
+        head_object::HeadObjectError, list_object_versions::ListObjectVersionsError,
+        list_objects_v2::ListObjectsV2Error, list_parts::ListPartsError,
+        put_object::PutObjectError, put_object_tagging::PutObjectTaggingError,
+        restore_object::RestoreObjectError, upload_part::UploadPartError,
+        upload_part_copy::UploadPartCopyError, RequestId,
     },
     primitives::SdkBody,
 };
+use aws_smithy_types::error::ErrorMetadata;
 use thiserror::Error;
 
+use crate::{access_hint::AccessHint, storage_class::StorageClass, timeout::TimeoutPhase};
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("S3 infallble")]
@@ -22,6 +29,8 @@ pub enum Error {
     S3Head(#[from] SdkError<HeadObjectError, http::response::Response<SdkBody>>),
     #[error("S3 uploadpart object error")]
     S3UploadPart(#[from] SdkError<UploadPartError, http::response::Response<SdkBody>>),
+    #[error("S3 uploadpartcopy error")]
+    S3UploadPartCopy(#[from] SdkError<UploadPartCopyError, http::response::Response<SdkBody>>),
     #[error("S3 create multipart error")]
     S3CreateMultipart(
         #[from] SdkError<CreateMultipartUploadError, http::response::Response<SdkBody>>,
@@ -42,21 +51,416 @@ pub enum Error {
     S3CopyObject(#[from] SdkError<CopyObjectError, http::response::Response<SdkBody>>),
     #[error("S3 delete object error")]
     S3DeleteObject(#[from] SdkError<DeleteObjectError, http::response::Response<SdkBody>>),
+    #[error("S3 delete objects error")]
+    S3DeleteObjects(#[from] SdkError<DeleteObjectsError, http::response::Response<SdkBody>>),
     #[error("S3 list objects error")]
     S3ListObjects(#[from] SdkError<ListObjectsV2Error, http::response::Response<SdkBody>>),
+    #[error("S3 list object versions error")]
+    S3ListObjectVersions(
+        #[from] SdkError<ListObjectVersionsError, http::response::Response<SdkBody>>,
+    ),
+    #[error("STS assume role with web identity error")]
+    StsAssumeRole(
+        #[from]
+        SdkError<
+            aws_sdk_sts::operation::assume_role_with_web_identity::AssumeRoleWithWebIdentityError,
+            http::response::Response<SdkBody>,
+        >,
+    ),
+    #[error("S3 list parts error")]
+    S3ListParts(#[from] SdkError<ListPartsError, http::response::Response<SdkBody>>),
+    #[error("S3 get object tagging error")]
+    S3GetObjectTagging(#[from] SdkError<GetObjectTaggingError, http::response::Response<SdkBody>>),
+    #[error("S3 put object tagging error")]
+    S3PutObjectTagging(#[from] SdkError<PutObjectTaggingError, http::response::Response<SdkBody>>),
+    #[error("S3 delete object tagging error")]
+    S3DeleteObjectTagging(
+        #[from] SdkError<DeleteObjectTaggingError, http::response::Response<SdkBody>>,
+    ),
+    #[error("S3 restore object error")]
+    S3RestoreObject(#[from] SdkError<RestoreObjectError, http::response::Response<SdkBody>>),
+    #[cfg(feature = "admin")]
+    #[error("S3 create bucket error")]
+    S3CreateBucket(
+        #[from]
+        SdkError<
+            aws_sdk_s3::operation::create_bucket::CreateBucketError,
+            http::response::Response<SdkBody>,
+        >,
+    ),
+    #[cfg(feature = "admin")]
+    #[error("S3 delete bucket error")]
+    S3DeleteBucket(
+        #[from]
+        SdkError<
+            aws_sdk_s3::operation::delete_bucket::DeleteBucketError,
+            http::response::Response<SdkBody>,
+        >,
+    ),
+    #[cfg(feature = "admin")]
+    #[error("S3 head bucket error")]
+    S3HeadBucket(
+        #[from]
+        SdkError<
+            aws_sdk_s3::operation::head_bucket::HeadBucketError,
+            http::response::Response<SdkBody>,
+        >,
+    ),
+    #[cfg(feature = "batch")]
+    #[error("S3 control create job error")]
+    S3ControlCreateJob(
+        #[from]
+        SdkError<
+            aws_sdk_s3control::operation::create_job::CreateJobError,
+            http::response::Response<SdkBody>,
+        >,
+    ),
     #[error("S3 conversion error")]
     S3Conversion(#[from] aws_smithy_types::date_time::ConversionError),
     #[error("Parse int error")]
     ParseInt(#[from] std::num::ParseIntError),
+    #[error("JSON error")]
+    Json(#[from] serde_json::Error),
+    #[error("object of {size} bytes exceeds the read limit of {limit} bytes, use a ranged read")]
+    BodyTooLarge { size: usize, limit: usize },
+    #[error("offset {offset} is beyond the end of the object of {size} bytes")]
+    OffsetBeyondEnd { offset: usize, size: usize },
+    #[error("range {start}..{end} is empty")]
+    EmptyRange { start: usize, end: usize },
+    #[error("block size must be greater than 0")]
+    ZeroBlockSize,
+    #[error("circuit of endpoint {endpoint} is open")]
+    CircuitOpen { endpoint: String },
+    #[error("invalid presigning configuration: {0}")]
+    Presigning(String),
+    #[error("anonymous stores can only read, {0} requests need credentials")]
+    AnonymousWrite(String),
+    #[error("method {0} is not supported")]
+    UnsupportedMethod(String),
+    #[error("request timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("request aborted by its {phase} timeout of {after:?}")]
+    RequestTimeout {
+        phase: TimeoutPhase,
+        after: std::time::Duration,
+    },
+    #[error("conditional update requires an ETag")]
+    MissingETag,
+    #[error("failed to delete {key}: {code} {message}")]
+    DeleteFailed {
+        key: String,
+        code: String,
+        message: String,
+    },
+    #[error("checksum of {key} is {actual}, expected {expected}")]
+    ChecksumMismatch {
+        key: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("invalid manifest signature")]
+    InvalidSignature,
+    #[error("no digest of {key} in {digests}")]
+    MissingDigest { key: String, digests: String },
+    #[error("SHA-256 digest of {key} is {actual}, expected {expected}")]
+    DigestMismatch {
+        key: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("invalid endpoint {0}, expected an https:// or allowed http:// URL")]
+    InvalidEndpoint(String),
+    #[error("invalid bucket name {bucket}: {reason}")]
+    InvalidBucketName {
+        bucket: String,
+        reason: &'static str,
+    },
+    #[error(
+        "bucket name {bucket} {reason}, use path-style addressing with \
+         `S3Builder::with_force_path_style(true)`"
+    )]
+    BucketNotVirtualHostable {
+        bucket: String,
+        reason: &'static str,
+    },
+    #[error("invalid header {0}")]
+    InvalidHeader(String),
+    #[error("listed key {0} isn't valid URL encoded UTF-8")]
+    InvalidKey(String),
+    #[error("tags {0} aren't valid URL encoded UTF-8")]
+    InvalidTag(String),
+    #[error("upload of ticket {ticket} was rejected: {reason}")]
+    TicketRejected {
+        ticket: String,
+        reason: &'static str,
+    },
+    #[error("failed to write pack: {0}")]
+    PackFailed(String),
+    #[error(
+        "request for s3://{bucket}/{key} failed{}",
+        describe_response(*.status, .code, .message, .request_id, .hint)
+    )]
+    Request {
+        bucket: String,
+        key: String,
+        status: Option<u16>,
+        code: Option<String>,
+        message: Option<String>,
+        request_id: Option<String>,
+        /// Missing permission, for requests denied with `AccessDenied`.
+        hint: Option<AccessHint>,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("JavaScript error: {0}")]
+    Js(String),
+    #[error("HTTP request failed: {0}")]
+    Transport(String),
+    #[error("presigned request for {key} failed with HTTP {status}")]
+    PresignedRequest { key: String, status: u16 },
+    #[error("part {0} of the multipart upload wasn't uploaded")]
+    MissingPart(usize),
+    #[error("failed to decrypt {key}: {reason}")]
+    Decryption { key: String, reason: String },
+    #[error("no key {0} to decrypt with")]
+    UnknownKey(String),
+    #[error("OTLP export failed with HTTP {0}")]
+    OtlpExport(u16),
+    #[error("object {key} is archived, restore it with S3::restore_object before reading it")]
+    NotRestored {
+        key: String,
+        storage_class: Option<StorageClass>,
+        source: Box<Error>,
+    },
+    #[error("invalid s3://bucket/prefix URL {0}")]
+    InvalidUrl(String),
+    #[error("the store has the prefix {0}, build it with S3Builder::build_prefixed")]
+    UnappliedPrefix(String),
+    #[error("remote signing failed: {0}")]
+    RemoteSigning(String),
     #[error("unknown object store error")]
     Unknown,
 }
 
+impl Error {
+    /// Describes a failed request for `key` in `bucket` with the status, error code, message and
+    /// request id of its response, see [`Error::Request`].
+    pub(crate) fn request(self, bucket: &str, key: impl ToString) -> Error {
+        if let Some(timeout) = self.timeout() {
+            return Error::Request {
+                bucket: bucket.to_owned(),
+                key: key.to_string(),
+                status: None,
+                code: None,
+                message: None,
+                request_id: None,
+                hint: None,
+                source: Box::new(timeout),
+            };
+        }
+        if let Error::S3GetObject(SdkError::ServiceError(err)) = &self {
+            if let GetObjectError::InvalidObjectState(state) = err.err() {
+                return Error::NotRestored {
+                    key: key.to_string(),
+                    storage_class: state.storage_class().map(StorageClass::from),
+                    source: Box::new(self),
+                };
+            }
+        }
+        let (status, meta, request_id) = match &self {
+            Error::S3Infallible(err) => (status_code(err), None, err.request_id()),
+            Error::S3Head(err) => response_details(err),
+            Error::S3UploadPart(err) => response_details(err),
+            Error::S3UploadPartCopy(err) => response_details(err),
+            Error::S3CreateMultipart(err) => response_details(err),
+            Error::S3CompleteMultipart(err) => response_details(err),
+            Error::S3AbortMultipart(err) => response_details(err),
+            Error::S3GetObject(err) => response_details(err),
+            Error::S3PutObject(err) => response_details(err),
+            Error::S3CopyObject(err) => response_details(err),
+            Error::S3DeleteObject(err) => response_details(err),
+            Error::S3DeleteObjects(err) => response_details(err),
+            Error::S3ListObjects(err) => response_details(err),
+            Error::S3ListObjectVersions(err) => response_details(err),
+            Error::S3ListParts(err) => response_details(err),
+            Error::S3GetObjectTagging(err) => response_details(err),
+            Error::S3PutObjectTagging(err) => response_details(err),
+            Error::S3DeleteObjectTagging(err) => response_details(err),
+            Error::S3RestoreObject(err) => response_details(err),
+            #[cfg(feature = "admin")]
+            Error::S3CreateBucket(err) => response_details(err),
+            #[cfg(feature = "admin")]
+            Error::S3DeleteBucket(err) => response_details(err),
+            #[cfg(feature = "admin")]
+            Error::S3HeadBucket(err) => response_details(err),
+            _ => return self,
+        };
+        let (code, message) = meta
+            .map(|meta| (meta.code(), meta.message()))
+            .unwrap_or_default();
+        let key = key.to_string();
+        let hint = (code == Some("AccessDenied") || (status == Some(403) && code.is_none()))
+            .then(|| AccessHint::new(&self, bucket, &key))
+            .flatten();
+        Error::Request {
+            bucket: bucket.to_owned(),
+            key,
+            status,
+            code: code.map(ToOwned::to_owned),
+            message: message.map(ToOwned::to_owned),
+            request_id: request_id.map(ToOwned::to_owned),
+            hint,
+            source: Box::new(self),
+        }
+    }
+
+    /// The [`Error::RequestTimeout`] a request was aborted with, found by walking the sources of
+    /// the SDK error.
+    fn timeout(&self) -> Option<Error> {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(self);
+        while let Some(err) = source {
+            if let Some(Error::RequestTimeout { phase, after }) = err.downcast_ref::<Error>() {
+                return Some(Error::RequestTimeout {
+                    phase: *phase,
+                    after: *after,
+                });
+            }
+            source = err.source();
+        }
+        None
+    }
+}
+
 impl From<Error> for object_store::Error {
+    /// Failed requests map to the variant matching their response, everything else is
+    /// [`object_store::Error::Generic`].
     fn from(value: Error) -> Self {
-        object_store::Error::Generic {
-            store: "S3",
-            source: Box::new(value),
+        let Error::Request {
+            key, status, code, ..
+        } = &value
+        else {
+            return object_store::Error::Generic {
+                store: "S3",
+                source: Box::new(value),
+            };
+        };
+        let path = key.clone();
+        match (status, code.as_deref()) {
+            (Some(404), _) | (_, Some("NoSuchKey" | "NotFound")) => object_store::Error::NotFound {
+                path,
+                source: Box::new(value),
+            },
+            (Some(304), _) => object_store::Error::NotModified {
+                path,
+                source: Box::new(value),
+            },
+            (Some(412), _) => object_store::Error::Precondition {
+                path,
+                source: Box::new(value),
+            },
+            (Some(409), _) => object_store::Error::AlreadyExists {
+                path,
+                source: Box::new(value),
+            },
+            // `object_store` 0.9 has no variant for denied requests
+            _ => object_store::Error::Generic {
+                store: "S3",
+                source: Box::new(value),
+            },
+        }
+    }
+}
+
+fn response_details<E: ProvideErrorMetadata>(
+    err: &SdkError<E, http::response::Response<SdkBody>>,
+) -> (Option<u16>, Option<&ErrorMetadata>, Option<&str>) {
+    let meta = matches!(err, SdkError::ServiceError(_)).then(|| err.meta());
+    (status_code(err), meta, err.request_id())
+}
+
+fn describe_response(
+    status: Option<u16>,
+    code: &Option<String>,
+    message: &Option<String>,
+    request_id: &Option<String>,
+    hint: &Option<AccessHint>,
+) -> String {
+    let mut description = String::new();
+    if let Some(status) = status {
+        description.push_str(&format!(" with HTTP {status}"));
+    }
+    if let Some(code) = code {
+        description.push_str(&format!(" {code}"));
+    }
+    if let Some(message) = message {
+        description.push_str(&format!(": {message}"));
+    }
+    if let Some(request_id) = request_id {
+        description.push_str(&format!(" (request id {request_id})"));
+    }
+    if let Some(hint) = hint {
+        description.push_str(&format!(", {hint}"));
+    }
+    description
+}
+
+impl From<wasm_bindgen::JsValue> for Error {
+    fn from(value: wasm_bindgen::JsValue) -> Self {
+        Error::Js(format!("{:?}", value))
+    }
+}
+
+/// Whether `err` signals a missing object, either directly or through a wrapped SDK error.
+pub(crate) fn is_not_found(err: &object_store::Error) -> bool {
+    match err {
+        object_store::Error::NotFound { .. } => true,
+        object_store::Error::Generic { source, .. } => {
+            matches!(
+                source.downcast_ref::<Error>(),
+                Some(Error::S3Head(SdkError::ServiceError(err))) if err.err().is_not_found()
+            ) || matches!(
+                source.downcast_ref::<Error>(),
+                Some(Error::S3GetObject(SdkError::ServiceError(err))) if err.err().is_no_such_key()
+            )
         }
+        _ => false,
+    }
+}
+
+/// Whether `err` is a request rejected with `416 Range Not Satisfiable`.
+pub(crate) fn is_range_not_satisfiable(err: &object_store::Error) -> bool {
+    let object_store::Error::Generic { source, .. } = err else {
+        return false;
+    };
+    matches!(
+        source.downcast_ref::<Error>(),
+        Some(Error::Request {
+            status: Some(416),
+            ..
+        })
+    )
+}
+
+/// Whether `err` is an S3 `HeadObject` request rejected with `403 Forbidden`.
+pub(crate) fn is_head_denied(err: &object_store::Error) -> bool {
+    let object_store::Error::Generic { source, .. } = err else {
+        return false;
+    };
+    let source = match source.downcast_ref::<Error>() {
+        Some(Error::Request { source, .. }) => Some(source.as_ref()),
+        source => source,
+    };
+    matches!(
+        source,
+        Some(Error::S3Head(SdkError::ServiceError(err))) if err.raw().status().as_u16() == 403
+    )
+}
+
+/// HTTP status of the response an SDK error was created from, if there was one.
+pub(crate) fn status_code<E>(err: &SdkError<E, http::response::Response<SdkBody>>) -> Option<u16> {
+    match err {
+        SdkError::ServiceError(err) => Some(err.raw().status().as_u16()),
+        SdkError::ResponseError(err) => Some(err.raw().status().as_u16()),
+        _ => None,
     }
 }
@@ -0,0 +1,59 @@
+use md5::{Digest, Md5};
+use object_store::{path::Path, ObjectStore};
+
+use crate::{checksum::hex, encryption::SseConfig, S3};
+
+/// ETag S3 assigns to `data` written with a single `PutObject`.
+///
+/// Objects encrypted with SSE-KMS or SSE-C get ETags that aren't derived from their content.
+pub fn etag(data: &[u8]) -> String {
+    hex(&Md5::digest(data))
+}
+
+/// ETag S3 assigns to `data` uploaded with a multipart upload of `part_size` sized parts.
+pub fn multipart_etag(data: &[u8], part_size: usize) -> String {
+    let mut parts = data.chunks(part_size.max(1)).peekable();
+    let mut digests = Md5::new();
+    let mut count = 0;
+    if parts.peek().is_none() {
+        digests.update(Md5::digest(data));
+        count = 1;
+    }
+    for part in parts {
+        digests.update(Md5::digest(part));
+        count += 1;
+    }
+    format!("{}-{}", hex(&digests.finalize()), count)
+}
+
+impl S3 {
+    /// Checks whether the object at `location` has the content `data` without downloading it.
+    ///
+    /// Compares sizes and ETags; for multipart uploads the part size is taken from the size of
+    /// the first part. Returns `false` for objects whose ETag isn't content derived.
+    pub async fn matches_local(&self, location: &Path, data: &[u8]) -> object_store::Result<bool> {
+        let meta = self.head(location).await?;
+        if meta.size != data.len() {
+            return Ok(false);
+        }
+        let Some(remote) = meta.e_tag.as_deref().map(|e_tag| e_tag.trim_matches('"')) else {
+            return Ok(false);
+        };
+        if !remote.contains('-') {
+            return Ok(remote == etag(data));
+        }
+        let first_part = self
+            .client
+            .head_object()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .part_number(1);
+        let first_part = self
+            .encrypted(first_part, SseConfig::head)
+            .send()
+            .await
+            .map_err(|err| self.request_error(location, err))?;
+        let part_size = first_part.content_length() as usize;
+        Ok(remote == multipart_etag(data, part_size))
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::Debug,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::{stream::BoxStream, Stream, StreamExt};
+use object_store::path::Path;
+use parking_lot::Mutex;
+use serde::Serialize;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+use crate::S3;
+
+/// Direction of a transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Upload,
+    Download,
+}
+
+/// Progress of a put, multipart upload or GET stream, see [`S3::on_transfer`].
+///
+/// Serialized for JS handlers as an object with a `type` of `progress`, `completed` or `failed`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TransferEvent {
+    /// Another chunk or part was transferred, `bytes` counts all bytes transferred so far.
+    Progress {
+        location: String,
+        direction: Direction,
+        bytes: usize,
+    },
+    Completed {
+        location: String,
+        direction: Direction,
+        bytes: usize,
+    },
+    /// The transfer failed or was dropped after transferring `bytes`.
+    Failed {
+        location: String,
+        direction: Direction,
+        bytes: usize,
+    },
+}
+
+impl TransferEvent {
+    pub(crate) fn finished(location: &Path, direction: Direction, bytes: usize, ok: bool) -> Self {
+        let location = location.to_string();
+        if ok {
+            TransferEvent::Completed {
+                location,
+                direction,
+                bytes,
+            }
+        } else {
+            TransferEvent::Failed {
+                location,
+                direction,
+                bytes,
+            }
+        }
+    }
+}
+
+type Handler = dyn Fn(&TransferEvent) + Send + Sync;
+
+/// Handlers of the [`TransferEvent`]s of a store.
+///
+/// Handlers are only held weakly, the [`Subscription`] returned when registering one owns it. A
+/// handler is gone as soon as its subscription is dropped, so handlers of finished transfers
+/// can't pile up in long-lived stores.
+#[derive(Clone, Default)]
+pub(crate) struct TransferEvents {
+    handlers: Arc<Mutex<Vec<Weak<Handler>>>>,
+}
+
+impl Debug for TransferEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransferEvents")
+            .field("handlers", &self.handlers.lock().len())
+            .finish()
+    }
+}
+
+impl TransferEvents {
+    fn subscribe(&self, handler: Arc<Handler>) -> Subscription {
+        self.handlers.lock().push(Arc::downgrade(&handler));
+        Subscription { _handler: handler }
+    }
+
+    /// Passes the event to every live handler, `event` is only built if there is one.
+    pub(crate) fn emit(&self, event: impl FnOnce() -> TransferEvent) {
+        let handlers = {
+            let mut handlers = self.handlers.lock();
+            handlers.retain(|handler| handler.strong_count() > 0);
+            handlers
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>()
+        };
+        if handlers.is_empty() {
+            return;
+        }
+        // Called without holding the lock, handlers may subscribe or unsubscribe
+        let event = event();
+        for handler in handlers {
+            handler(&event);
+        }
+    }
+
+    /// Wraps a GET payload stream to report its progress.
+    pub(crate) fn track_download(
+        &self,
+        inner: BoxStream<'static, object_store::Result<Bytes>>,
+        location: Path,
+    ) -> BoxStream<'static, object_store::Result<Bytes>> {
+        Box::pin(TrackedStream {
+            inner,
+            location,
+            events: self.clone(),
+            bytes: 0,
+            finished: false,
+        })
+    }
+}
+
+/// Registration of a transfer event handler, dropping it unregisters the handler.
+#[must_use = "the handler is unregistered when the subscription is dropped"]
+pub struct Subscription {
+    _handler: Arc<Handler>,
+}
+
+impl Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription").finish_non_exhaustive()
+    }
+}
+
+impl Subscription {
+    /// Unregisters the handler, same as dropping the subscription.
+    pub fn unsubscribe(self) {}
+}
+
+thread_local! {
+    /// JS handlers by subscription id, JS functions can't be shared with the store's handlers.
+    static JS_HANDLERS: RefCell<HashMap<u64, js_sys::Function>> = RefCell::default();
+    static NEXT_JS_HANDLER: RefCell<u64> = const { RefCell::new(0) };
+}
+
+/// [`Subscription`] of a JS handler, to be handed to JS.
+///
+/// JS code has to call `unsubscribe()` or `free()` once it's done with the handler, after that
+/// the function isn't referenced from wasm anymore.
+#[wasm_bindgen]
+pub struct JsSubscription {
+    id: u64,
+    _subscription: Subscription,
+}
+
+#[wasm_bindgen]
+impl JsSubscription {
+    /// Unregisters the handler.
+    pub fn unsubscribe(self) {}
+}
+
+impl Drop for JsSubscription {
+    fn drop(&mut self) {
+        JS_HANDLERS.with(|handlers| handlers.borrow_mut().remove(&self.id));
+    }
+}
+
+/// Event as the JS object it serializes to.
+fn js_event(event: &TransferEvent) -> Result<JsValue, JsValue> {
+    let json = serde_json::to_string(event).map_err(|err| JsValue::from(err.to_string()))?;
+    js_sys::JSON::parse(&json)
+}
+
+/// GET payload stream reporting its progress as [`TransferEvent`]s.
+struct TrackedStream {
+    inner: BoxStream<'static, object_store::Result<Bytes>>,
+    location: Path,
+    events: TransferEvents,
+    bytes: usize,
+    finished: bool,
+}
+
+impl TrackedStream {
+    fn finish(&mut self, ok: bool) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.events
+            .emit(|| TransferEvent::finished(&self.location, Direction::Download, self.bytes, ok));
+    }
+}
+
+impl Stream for TrackedStream {
+    type Item = object_store::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = match self.inner.poll_next_unpin(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(item) => item,
+        };
+        match &item {
+            Some(Ok(chunk)) => {
+                self.bytes += chunk.len();
+                self.events.emit(|| TransferEvent::Progress {
+                    location: self.location.to_string(),
+                    direction: Direction::Download,
+                    bytes: self.bytes,
+                });
+            }
+            Some(Err(_)) => self.finish(false),
+            None => self.finish(true),
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Drop for TrackedStream {
+    fn drop(&mut self) {
+        self.finish(false);
+    }
+}
+
+impl S3 {
+    /// Calls `handler` with the progress of every put, multipart upload and GET stream of the
+    /// store until the returned subscription is dropped.
+    pub fn on_transfer(
+        &self,
+        handler: impl Fn(&TransferEvent) + Send + Sync + 'static,
+    ) -> Subscription {
+        self.events.subscribe(Arc::new(handler))
+    }
+
+    /// Like [`S3::on_transfer`] for a JS function, which is called with the event as an object.
+    pub fn on_transfer_js(&self, handler: js_sys::Function) -> JsSubscription {
+        let id = NEXT_JS_HANDLER.with(|next| {
+            let mut next = next.borrow_mut();
+            *next += 1;
+            *next
+        });
+        JS_HANDLERS.with(|handlers| handlers.borrow_mut().insert(id, handler));
+        let subscription = self.on_transfer(move |event| {
+            let Some(handler) = JS_HANDLERS.with(|handlers| handlers.borrow().get(&id).cloned())
+            else {
+                return;
+            };
+            if let Ok(event) = js_event(event) {
+                let _ = handler.call1(&JsValue::NULL, &event);
+            }
+        });
+        JsSubscription {
+            id,
+            _subscription: subscription,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::atomic::Ordering;
+
+use futures::{stream::BoxStream, StreamExt};
+use object_store::{path::Path, ObjectMeta, ObjectStore};
+
+use crate::{
+    error::{is_head_denied, is_not_found},
+    list_encoding::decode_key,
+    S3,
+};
+
+/// `HeadObject` requests [`S3::head_many`] keeps in flight.
+const HEAD_CONCURRENCY: usize = 16;
+
+impl S3 {
+    /// Heads every path of `locations`, with up to 16 requests in flight.
+    ///
+    /// Results are in the order of `locations`, missing objects fail with
+    /// [`object_store::Error::NotFound`] without ending the stream.
+    pub fn head_many<I>(&self, locations: I) -> BoxStream<'_, object_store::Result<ObjectMeta>>
+    where
+        I: IntoIterator<Item = Path>,
+        I::IntoIter: Send + 'static,
+    {
+        futures::stream::iter(locations)
+            .map(move |location| async move { self.head(&location).await })
+            .buffered(HEAD_CONCURRENCY)
+            .boxed()
+    }
+
+    /// Whether an object exists at `location`.
+    ///
+    /// Uses `HeadObject` until a request is rejected with `403 Forbidden`, e.g. because the policy
+    /// grants `s3:ListBucket` but not `s3:GetObject`. From then on the store answers with a
+    /// `ListObjectsV2` request limited to a single key.
+    pub async fn exists(&self, location: &Path) -> object_store::Result<bool> {
+        if !self.head_denied.load(Ordering::Relaxed) {
+            match self.head(location).await {
+                Ok(_) => return Ok(true),
+                Err(err) if is_not_found(&err) => return Ok(false),
+                Err(err) if is_head_denied(&err) => self.head_denied.store(true, Ordering::Relaxed),
+                Err(err) => return Err(err),
+            }
+        }
+        let key = location.to_string();
+        let response = self
+            .client
+            .list_objects_v2()
+            .bucket(self.bucket.clone())
+            .prefix(&key)
+            .max_keys(1)
+            .set_encoding_type(self.list_encoding.encoding_type())
+            .send()
+            .await
+            .map_err(|err| self.request_error(&key, err))?;
+        let first = response
+            .contents
+            .and_then(|contents| contents.into_iter().next())
+            .and_then(|object| object.key);
+        match first {
+            Some(first) => Ok(decode_key(first, response.encoding_type.as_ref())? == key),
+            None => Ok(false),
+        }
+    }
+}
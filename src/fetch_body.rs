@@ -0,0 +1,97 @@
+use std::{
+    pin::{pin, Pin},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use aws_sdk_s3::primitives::SdkBody;
+use aws_smithy_http::body::BoxBody;
+use bytes::Bytes;
+use futures::{
+    future::{self, BoxFuture, Either},
+    FutureExt,
+};
+use tokio::sync::{mpsc, oneshot};
+use wasm_bindgen::JsCast;
+use web_sys::{ReadableStream, ReadableStreamDefaultReader};
+
+use crate::{blob::read_chunk, error::Error, timeout::TimeoutPhase};
+
+/// Streams the `ReadableStream` body of a `fetch` response into an [`SdkBody`].
+///
+/// The stream is read in a local task, a chunk at a time as the body is polled. Dropping the
+/// body cancels the stream, which aborts the download. Once `deadline` elapses the stream is
+/// cancelled as well and the body fails with an [`Error::RequestTimeout`] of `after`. The
+/// returned receiver resolves once the stream is done, however it ended.
+pub(crate) fn stream_body(
+    stream: ReadableStream,
+    deadline: Option<(BoxFuture<'static, ()>, Duration)>,
+) -> (SdkBody, oneshot::Receiver<()>) {
+    let (tx, rx) = mpsc::channel(1);
+    let (done_tx, done_rx) = oneshot::channel();
+    let (expired, after) = match deadline {
+        Some((expired, after)) => (expired, after),
+        None => (future::pending().boxed(), Duration::ZERO),
+    };
+    wasm_bindgen_futures::spawn_local(async move {
+        let _done = done_tx;
+        let reader: ReadableStreamDefaultReader = stream.get_reader().unchecked_into();
+        let mut expired = expired;
+        loop {
+            let (read, closed) = (pin!(read_chunk(&reader)), pin!(tx.closed()));
+            let chunk = match future::select(future::select(read, closed), expired.as_mut()).await {
+                Either::Left((Either::Left((chunk, _)), _)) => chunk,
+                // The body was dropped, nobody reads the rest
+                Either::Left((Either::Right(_), _)) => {
+                    let _ = reader.cancel();
+                    return;
+                }
+                Either::Right(_) => {
+                    let _ = reader.cancel();
+                    let _ = tx
+                        .send(Err(Error::RequestTimeout {
+                            phase: TimeoutPhase::Total,
+                            after,
+                        }))
+                        .await;
+                    return;
+                }
+            };
+            let last = !matches!(chunk, Ok(Some(_)));
+            if let Some(chunk) = chunk.transpose() {
+                if tx.send(chunk).await.is_err() {
+                    let _ = reader.cancel();
+                    return;
+                }
+            }
+            if last {
+                return;
+            }
+        }
+    });
+    (SdkBody::from_dyn(BoxBody::new(ChannelBody(rx))), done_rx)
+}
+
+/// Body fed by the task reading a `ReadableStream`.
+struct ChannelBody(mpsc::Receiver<Result<Bytes, Error>>);
+
+impl http_body::Body for ChannelBody {
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.0
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map_err(Into::into)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+}
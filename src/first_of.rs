@@ -0,0 +1,212 @@
+use std::{fmt::Display, future::Future, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{
+    future::{self, Either},
+    stream::{self, BoxStream, FuturesUnordered},
+    FutureExt, StreamExt,
+};
+use object_store::{
+    path::Path, GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore,
+    PutOptions, PutResult,
+};
+use tokio::io::AsyncWrite;
+
+use crate::{
+    environment::{default_timer, Timer},
+    error::Error,
+};
+
+/// How [`FirstOf`] queries its stores.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FanOut {
+    /// Query all stores at once and use the first successful response.
+    #[default]
+    Concurrent,
+    /// Query the stores one after another, the next one only once the previous failed.
+    Sequential,
+}
+
+/// Read-only store serving every read from the first of several replicas that succeeds.
+///
+/// Useful when the same data is published to several locations, e.g. an S3 bucket and a CDN
+/// backed HTTP store. If all stores fail, the error of the last one is returned. Writes fail
+/// with [`object_store::Error::NotImplemented`].
+#[derive(Debug)]
+pub struct FirstOf {
+    stores: Vec<Arc<dyn ObjectStore>>,
+    fan_out: FanOut,
+    timeout: Option<Duration>,
+    timer: Arc<dyn Timer>,
+}
+
+impl FirstOf {
+    pub fn new(stores: impl IntoIterator<Item = Arc<dyn ObjectStore>>) -> Self {
+        Self {
+            stores: stores.into_iter().collect(),
+            fan_out: FanOut::default(),
+            timeout: None,
+            timer: default_timer(),
+        }
+    }
+
+    pub fn with_fan_out(mut self, fan_out: FanOut) -> Self {
+        self.fan_out = fan_out;
+        self
+    }
+
+    /// Give up on a store that didn't respond within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_timer(mut self, timer: impl Timer + 'static) -> Self {
+        self.timer = Arc::new(timer);
+        self
+    }
+
+    pub fn stores(&self) -> &[Arc<dyn ObjectStore>] {
+        &self.stores
+    }
+
+    async fn timed<T>(
+        &self,
+        request: impl Future<Output = object_store::Result<T>> + Send,
+    ) -> object_store::Result<T> {
+        let Some(timeout) = self.timeout else {
+            return request.await;
+        };
+        match future::select(request.boxed(), self.timer.sleep(timeout)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(Error::Timeout(timeout).into()),
+        }
+    }
+
+    async fn first<'a, T, F, Fut>(&'a self, request: F) -> object_store::Result<T>
+    where
+        F: Fn(&'a Arc<dyn ObjectStore>) -> Fut,
+        Fut: Future<Output = object_store::Result<T>> + Send + 'a,
+    {
+        let mut last_error = None;
+        match self.fan_out {
+            FanOut::Concurrent => {
+                let mut pending = self
+                    .stores
+                    .iter()
+                    .map(|store| self.timed(request(store)))
+                    .collect::<FuturesUnordered<_>>();
+                while let Some(result) = pending.next().await {
+                    match result {
+                        Ok(value) => return Ok(value),
+                        Err(err) => last_error = Some(err),
+                    }
+                }
+            }
+            FanOut::Sequential => {
+                for store in &self.stores {
+                    match self.timed(request(store)).await {
+                        Ok(value) => return Ok(value),
+                        Err(err) => last_error = Some(err),
+                    }
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::Unknown.into()))
+    }
+}
+
+fn read_only() -> object_store::Error {
+    object_store::Error::NotImplemented
+}
+
+#[async_trait]
+impl ObjectStore for FirstOf {
+    async fn put_opts(
+        &self,
+        _location: &Path,
+        _bytes: Bytes,
+        _opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        Err(read_only())
+    }
+    async fn put_multipart(
+        &self,
+        _location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        Err(read_only())
+    }
+    async fn abort_multipart(
+        &self,
+        _location: &Path,
+        _multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        Err(read_only())
+    }
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        self.first(|store| {
+            // `GetOptions` isn't `Clone`
+            let options = GetOptions {
+                if_match: options.if_match.clone(),
+                if_none_match: options.if_none_match.clone(),
+                if_modified_since: options.if_modified_since,
+                if_unmodified_since: options.if_unmodified_since,
+                range: options.range.clone(),
+                version: options.version.clone(),
+                head: options.head,
+            };
+            store.get_opts(location, options)
+        })
+        .await
+    }
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.first(|store| store.head(location)).await
+    }
+    async fn delete(&self, _location: &Path) -> object_store::Result<()> {
+        Err(read_only())
+    }
+    /// Lists the first store whose listing doesn't fail right away, stores aren't switched in the
+    /// middle of a listing.
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        let prefix = prefix.cloned();
+        stream::once(async move {
+            let mut last_error = None;
+            for store in &self.stores {
+                let mut listing = store.list(prefix.as_ref());
+                match listing.next().await {
+                    Some(Ok(first)) => return stream::iter([Ok(first)]).chain(listing).boxed(),
+                    Some(Err(err)) => last_error = Some(err),
+                    None => return stream::empty().boxed(),
+                }
+            }
+            stream::iter(last_error.map(Err)).boxed()
+        })
+        .flatten()
+        .boxed()
+    }
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.first(|store| store.list_with_delimiter(prefix)).await
+    }
+    async fn copy(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(read_only())
+    }
+    async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(read_only())
+    }
+}
+
+impl Display for FirstOf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stores = self
+            .stores
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        write!(f, "FirstOf({})", stores.join(", "))
+    }
+}
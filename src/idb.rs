@@ -0,0 +1,283 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures::{channel::oneshot, FutureExt};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{
+    Event, IdbDatabase, IdbFactory, IdbKeyRange, IdbObjectStore, IdbOpenDbRequest, IdbRequest,
+    IdbTransaction, IdbTransactionMode,
+};
+
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use crate::opfs::DOWNLOAD_STORE;
+#[cfg(feature = "shared_session")]
+use crate::shared_session::CREDENTIALS_STORE;
+use crate::{
+    error::Error,
+    index::INDEX_STORE,
+    metrics::METRICS_STORE,
+    persistent_cache::{DATA_STORE, ENTRY_STORE},
+    resumable::UPLOAD_STORE,
+};
+
+/// Name of the IndexedDB database shared by all persistent features of the crate.
+const DATABASE: &str = "object_store_s3_wasm";
+
+/// Object stores of [`DATABASE`], one per persistent feature.
+const STORES: &[&str] = &[
+    INDEX_STORE,
+    METRICS_STORE,
+    #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+    DOWNLOAD_STORE,
+    DATA_STORE,
+    ENTRY_STORE,
+    UPLOAD_STORE,
+    #[cfg(feature = "shared_session")]
+    CREDENTIALS_STORE,
+];
+
+/// Minimal promise-free wrapper around an IndexedDB connection.
+///
+/// Every feature keeps its data in its own object store of the shared [`DATABASE`]. Missing
+/// object stores are all created in a single upgrade, connections close themselves when another
+/// one upgrades the database.
+pub(crate) struct Database {
+    db: IdbDatabase,
+    _on_version_change: Closure<dyn FnMut(Event)>,
+}
+
+impl Database {
+    pub(crate) async fn open() -> Result<Self, Error> {
+        let factory = factory()?;
+        let db: IdbDatabase = wait(factory.open(DATABASE)?.as_ref()).await?.dyn_into()?;
+        let names = db.object_store_names();
+        if STORES.iter().all(|store| names.contains(store)) {
+            return Ok(Database::new(db));
+        }
+        let version = db.version() as u32 + 1;
+        db.close();
+
+        let request = factory.open_with_u32(DATABASE, version)?;
+        let upgrade = Closure::<dyn FnMut(Event)>::new(move |event: Event| {
+            let Some(db) = event
+                .target()
+                .and_then(|target| target.dyn_into::<IdbOpenDbRequest>().ok())
+                .and_then(|request| request.result().ok())
+                .and_then(|result| result.dyn_into::<IdbDatabase>().ok())
+            else {
+                return;
+            };
+            let names = db.object_store_names();
+            for store in STORES {
+                if !names.contains(store) {
+                    let _ = db.create_object_store(store);
+                }
+            }
+        });
+        let (blocked_tx, blocked_rx) = oneshot::channel::<()>();
+        let blocked_tx = RefCell::new(Some(blocked_tx));
+        let blocked = Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+            if let Some(tx) = blocked_tx.borrow_mut().take() {
+                let _ = tx.send(());
+            }
+        });
+        request.set_onupgradeneeded(Some(upgrade.as_ref().unchecked_ref()));
+        request.set_onblocked(Some(blocked.as_ref().unchecked_ref()));
+        let opened = {
+            let opened = wait(request.as_ref()).fuse();
+            let blocked = blocked_rx.fuse();
+            futures::pin_mut!(opened, blocked);
+            futures::select! {
+                opened = opened => Some(opened),
+                _ = blocked => None,
+            }
+        };
+        request.set_onupgradeneeded(None);
+        request.set_onblocked(None);
+        match opened {
+            Some(db) => Ok(Database::new(db?.dyn_into()?)),
+            None => {
+                // Connections of other pages that don't close on version changes keep the
+                // upgrade waiting, the connection it eventually opens is closed right away.
+                let late = Closure::once_into_js(move |event: Event| {
+                    if let Some(db) = event
+                        .target()
+                        .and_then(|target| target.dyn_into::<IdbOpenDbRequest>().ok())
+                        .and_then(|request| request.result().ok())
+                        .and_then(|result| result.dyn_into::<IdbDatabase>().ok())
+                    {
+                        db.close();
+                    }
+                });
+                request.set_onsuccess(Some(late.unchecked_ref()));
+                Err(Error::Js(
+                    "upgrade of the IndexedDB database is blocked by another connection".to_owned(),
+                ))
+            }
+        }
+    }
+
+    fn new(db: IdbDatabase) -> Self {
+        let on_version_change = {
+            let db = db.clone();
+            Closure::<dyn FnMut(Event)>::new(move |_: Event| db.close())
+        };
+        db.set_onversionchange(Some(on_version_change.as_ref().unchecked_ref()));
+        Database {
+            db,
+            _on_version_change: on_version_change,
+        }
+    }
+
+    pub(crate) async fn get(&self, store: &str, key: &str) -> Result<Option<JsValue>, Error> {
+        let request = self
+            .object_store(store, IdbTransactionMode::Readonly)?
+            .get(&JsValue::from_str(key))?;
+        let value = wait(&request).await?;
+        Ok((!value.is_undefined()).then_some(value))
+    }
+
+    pub(crate) async fn put(&self, store: &str, key: &str, value: &JsValue) -> Result<(), Error> {
+        let request = self
+            .object_store(store, IdbTransactionMode::Readwrite)?
+            .put_with_key(value, &JsValue::from_str(key))?;
+        wait(&request).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn delete(&self, store: &str, key: &str) -> Result<(), Error> {
+        let request = self
+            .object_store(store, IdbTransactionMode::Readwrite)?
+            .delete(&JsValue::from_str(key))?;
+        wait(&request).await?;
+        Ok(())
+    }
+
+    /// Values of all keys within `range`, in key order.
+    pub(crate) async fn get_all(
+        &self,
+        store: &str,
+        range: &IdbKeyRange,
+    ) -> Result<Vec<JsValue>, Error> {
+        let request = self
+            .object_store(store, IdbTransactionMode::Readonly)?
+            .get_all_with_key(range)?;
+        let values: js_sys::Array = wait(&request).await?.dyn_into()?;
+        Ok(values.iter().collect())
+    }
+
+    /// Writes and deletes entries of `store` in a single transaction.
+    pub(crate) async fn write_batch(
+        &self,
+        store: &str,
+        puts: impl IntoIterator<Item = (String, JsValue)>,
+        deletes: impl IntoIterator<Item = String>,
+    ) -> Result<(), Error> {
+        let object_store = self.object_store(store, IdbTransactionMode::Readwrite)?;
+        let mut last = None;
+        for (key, value) in puts {
+            last = Some(object_store.put_with_key(&value, &JsValue::from_str(&key))?);
+        }
+        for key in deletes {
+            last = Some(object_store.delete(&JsValue::from_str(&key))?);
+        }
+        // Requests of a transaction complete in order, the last one finishing means all did.
+        if let Some(request) = last {
+            wait(&request).await?;
+        }
+        Ok(())
+    }
+
+    /// Readwrite transaction spanning `stores`. Its requests are committed together, transactions
+    /// on the same stores wait until it finished.
+    pub(crate) fn write_transaction(&self, stores: &[&str]) -> Result<IdbTransaction, Error> {
+        let names = stores
+            .iter()
+            .map(|store| JsValue::from_str(store))
+            .collect::<js_sys::Array>();
+        Ok(self
+            .db
+            .transaction_with_str_sequence_and_mode(&names, IdbTransactionMode::Readwrite)?)
+    }
+
+    /// Deletes all keys within `range`.
+    pub(crate) async fn delete_range(&self, store: &str, range: &IdbKeyRange) -> Result<(), Error> {
+        let request = self
+            .object_store(store, IdbTransactionMode::Readwrite)?
+            .delete(range)?;
+        wait(&request).await?;
+        Ok(())
+    }
+
+    fn object_store(&self, store: &str, mode: IdbTransactionMode) -> Result<IdbObjectStore, Error> {
+        Ok(self
+            .db
+            .transaction_with_str_and_mode(store, mode)?
+            .object_store(store)?)
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        self.db.set_onversionchange(None);
+        self.db.close();
+    }
+}
+
+/// Returns the IndexedDB factory of the current global scope, works in windows and workers.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+fn factory() -> Result<IdbFactory, Error> {
+    let factory = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("indexedDB"))?;
+    if factory.is_undefined() || factory.is_null() {
+        return Err(Error::Js("IndexedDB is not available".to_owned()));
+    }
+    Ok(factory.dyn_into()?)
+}
+
+/// Native builds have no IndexedDB, every persistent feature fails to open its store.
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+fn factory() -> Result<IdbFactory, Error> {
+    Err(Error::Js("IndexedDB is not available".to_owned()))
+}
+
+/// Key range of all string keys starting with `prefix`.
+pub(crate) fn prefix_range(prefix: &str) -> Result<IdbKeyRange, Error> {
+    Ok(IdbKeyRange::bound(
+        &JsValue::from_str(prefix),
+        &JsValue::from_str(&format!("{}\u{ffff}", prefix)),
+    )?)
+}
+
+/// Waits for an IndexedDB request to finish and returns its result.
+pub(crate) async fn wait(request: &IdbRequest) -> Result<JsValue, Error> {
+    let (tx, rx) = oneshot::channel::<bool>();
+    let tx = Rc::new(RefCell::new(Some(tx)));
+    let on_success = {
+        let tx = tx.clone();
+        Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+            if let Some(tx) = tx.borrow_mut().take() {
+                let _ = tx.send(true);
+            }
+        })
+    };
+    let on_error = Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+        if let Some(tx) = tx.borrow_mut().take() {
+            let _ = tx.send(false);
+        }
+    });
+    request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+    request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    let succeeded = rx.await.unwrap_or(false);
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+    if succeeded {
+        Ok(request.result()?)
+    } else {
+        let message = request
+            .error()
+            .ok()
+            .flatten()
+            .map(|error| error.message())
+            .unwrap_or_else(|| "IndexedDB request failed".to_owned());
+        Err(Error::Js(message))
+    }
+}
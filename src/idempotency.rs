@@ -0,0 +1,36 @@
+use object_store::{path::Path, PutResult};
+
+use crate::S3;
+
+/// User metadata key, without the `x-amz-meta-` prefix, holding the idempotency token of a put.
+pub const IDEMPOTENCY_TOKEN_KEY: &str = "idempotency-token";
+
+impl S3 {
+    /// Fresh token identifying a put across its retries, if the store attaches tokens.
+    pub(crate) fn idempotency_token(&self) -> Option<String> {
+        self.idempotency_tokens.then(|| {
+            format!(
+                "{:016x}{:016x}",
+                self.random.next_u64(),
+                self.random.next_u64()
+            )
+        })
+    }
+
+    /// Result of the put with `token` at `location` if one of its attempts was written.
+    ///
+    /// A retry of a conditional put is rejected once a slow earlier attempt was written, the
+    /// object then carries the same token.
+    pub(crate) async fn landed_put(&self, location: &Path, token: &str) -> Option<PutResult> {
+        let (meta, _, attributes) = self.head_object(location, None).await.ok()?;
+        let landed = attributes
+            .metadata
+            .get(IDEMPOTENCY_TOKEN_KEY)
+            .map(String::as_str)
+            == Some(token);
+        landed.then_some(PutResult {
+            e_tag: meta.e_tag,
+            version: meta.version,
+        })
+    }
+}
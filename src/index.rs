@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use futures::TryStreamExt;
+use object_store::{path::Path, ObjectMeta, ObjectStore};
+use wasm_bindgen::JsValue;
+
+use crate::{
+    error::Error,
+    idb::{prefix_range, Database},
+    listing::ListingEntry,
+    S3,
+};
+
+/// IndexedDB object store holding the mirrored listings, keyed by `<bucket>/<location>`.
+pub(crate) const INDEX_STORE: &str = "listing_index";
+
+/// Search over the locally indexed listing, see [`S3::search_index`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexQuery {
+    /// Locations starting with the string, answered with a key range lookup.
+    Prefix(String),
+    /// Locations ending with the string.
+    Suffix(String),
+    /// Locations containing the string.
+    Contains(String),
+}
+
+impl IndexQuery {
+    fn matches(&self, location: &str) -> bool {
+        match self {
+            IndexQuery::Prefix(prefix) => location.starts_with(prefix.as_str()),
+            IndexQuery::Suffix(suffix) => location.ends_with(suffix.as_str()),
+            IndexQuery::Contains(needle) => location.contains(needle.as_str()),
+        }
+    }
+}
+
+/// Changes applied to the local index by [`S3::sync_index`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexReport {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl S3 {
+    fn index_key(&self, location: &str) -> String {
+        format!("{}/{}", self.bucket, location)
+    }
+
+    /// Mirrors the listing below `prefix` into IndexedDB.
+    ///
+    /// Only entries that are new or whose size, etag or modification time changed are written,
+    /// indexed entries no longer present in the bucket are removed.
+    pub async fn sync_index(&self, prefix: Option<&Path>) -> object_store::Result<IndexReport> {
+        let db = Database::open().await?;
+        let range = match prefix {
+            Some(prefix) => prefix_range(&self.index_key(&format!("{}/", prefix)))?,
+            None => prefix_range(&self.index_key(""))?,
+        };
+        let mut indexed = db
+            .get_all(INDEX_STORE, &range)
+            .await?
+            .into_iter()
+            .filter_map(|value| value.as_string())
+            .map(|value| {
+                let entry: ListingEntry = serde_json::from_str(&value)?;
+                Ok((entry.location.clone(), entry))
+            })
+            .collect::<Result<HashMap<_, _>, serde_json::Error>>()
+            .map_err(Error::from)?;
+
+        let mut report = IndexReport::default();
+        let mut puts = Vec::new();
+        let mut listing = self.list(prefix);
+        while let Some(meta) = listing.try_next().await? {
+            let entry = ListingEntry::from(meta);
+            match indexed.remove(&entry.location) {
+                Some(previous) if previous == entry => continue,
+                Some(_) => report.updated += 1,
+                None => report.added += 1,
+            }
+            let value = serde_json::to_string(&entry).map_err(Error::from)?;
+            puts.push((self.index_key(&entry.location), JsValue::from_str(&value)));
+        }
+        report.removed = indexed.len();
+        let deletes = indexed
+            .into_keys()
+            .map(|location| self.index_key(&location));
+        db.write_batch(INDEX_STORE, puts, deletes).await?;
+        Ok(report)
+    }
+
+    /// Objects of the local index matching `query`, ordered by location.
+    ///
+    /// Answers from IndexedDB only, the results are as fresh as the last [`S3::sync_index`].
+    pub async fn search_index(&self, query: &IndexQuery) -> object_store::Result<Vec<ObjectMeta>> {
+        let db = Database::open().await?;
+        let prefix = match query {
+            IndexQuery::Prefix(prefix) => prefix.as_str(),
+            _ => "",
+        };
+        let range = prefix_range(&self.index_key(prefix))?;
+        let mut results = Vec::new();
+        for value in db.get_all(INDEX_STORE, &range).await? {
+            let Some(value) = value.as_string() else {
+                continue;
+            };
+            let entry: ListingEntry = serde_json::from_str(&value).map_err(Error::from)?;
+            if query.matches(&entry.location) {
+                results.push(entry.into());
+            }
+        }
+        Ok(results)
+    }
+
+    /// Removes all indexed entries of the store's bucket.
+    pub async fn clear_index(&self) -> object_store::Result<()> {
+        let db = Database::open().await?;
+        db.delete_range(INDEX_STORE, &prefix_range(&self.index_key(""))?)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fmt::Debug,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::{stream::BoxStream, Stream, StreamExt};
+use object_store::path::Path;
+
+use crate::{environment::Timer, metrics::MetricsRecorder};
+
+/// Timing of a single GET payload stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamTiming {
+    /// Time from sending the request to receiving the first chunk, `None` if no chunk arrived.
+    pub time_to_first_byte: Option<Duration>,
+    pub chunks: usize,
+    pub bytes: usize,
+    /// Time spent waiting for chunks after the first one, i.e. server and network latency.
+    pub source_wait: Duration,
+    /// Longest wait for a single chunk after the first one.
+    pub max_gap: Duration,
+    /// Time between handing out a chunk and being polled for the next, i.e. consumer backpressure.
+    pub consumer_wait: Duration,
+    /// Whether the stream was read to its end, `false` if it failed or was dropped early.
+    pub completed: bool,
+}
+
+/// Receives the timing of every finished GET payload stream.
+pub trait StreamObserver: Debug + Send + Sync {
+    fn finished(&self, location: &Path, timing: &StreamTiming);
+}
+
+/// Payload stream recording [`StreamTiming`], reported once when it ends or is dropped.
+pub(crate) struct InstrumentedStream {
+    inner: BoxStream<'static, object_store::Result<Bytes>>,
+    location: Path,
+    timer: Arc<dyn Timer>,
+    metrics: MetricsRecorder,
+    observer: Option<Arc<dyn StreamObserver>>,
+    requested_at: Duration,
+    waiting_since: Option<Duration>,
+    yielded_at: Option<Duration>,
+    timing: StreamTiming,
+    reported: bool,
+}
+
+impl InstrumentedStream {
+    pub(crate) fn new(
+        inner: BoxStream<'static, object_store::Result<Bytes>>,
+        location: Path,
+        timer: Arc<dyn Timer>,
+        metrics: MetricsRecorder,
+        observer: Option<Arc<dyn StreamObserver>>,
+        requested_at: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            location,
+            timer,
+            metrics,
+            observer,
+            requested_at,
+            waiting_since: None,
+            yielded_at: None,
+            timing: StreamTiming::default(),
+            reported: false,
+        }
+    }
+
+    fn report(&mut self) {
+        if self.reported {
+            return;
+        }
+        self.reported = true;
+        self.metrics.record_stream(&self.timing);
+        if let Some(observer) = &self.observer {
+            observer.finished(&self.location, &self.timing);
+        }
+    }
+}
+
+impl Stream for InstrumentedStream {
+    type Item = object_store::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let now = self.timer.now();
+        if self.waiting_since.is_none() {
+            if let Some(yielded_at) = self.yielded_at.take() {
+                self.timing.consumer_wait += now.saturating_sub(yielded_at);
+            }
+            self.waiting_since = Some(now);
+        }
+        let item = match self.inner.poll_next_unpin(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(item) => item,
+        };
+        let now = self.timer.now();
+        match &item {
+            Some(Ok(chunk)) => {
+                let gap = now.saturating_sub(self.waiting_since.take().unwrap_or(now));
+                if self.timing.time_to_first_byte.is_none() {
+                    self.timing.time_to_first_byte = Some(now.saturating_sub(self.requested_at));
+                } else {
+                    self.timing.source_wait += gap;
+                    self.timing.max_gap = self.timing.max_gap.max(gap);
+                }
+                self.timing.chunks += 1;
+                self.timing.bytes += chunk.len();
+                self.yielded_at = Some(now);
+            }
+            Some(Err(_)) => self.report(),
+            None => {
+                self.timing.completed = true;
+                self.report();
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Drop for InstrumentedStream {
+    fn drop(&mut self) {
+        self.report();
+    }
+}
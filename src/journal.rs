@@ -0,0 +1,146 @@
+use std::{collections::VecDeque, fmt::Debug, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use futures::{
+    channel::mpsc::{self, UnboundedSender},
+    stream::BoxStream,
+    StreamExt,
+};
+use object_store::path::Path;
+use parking_lot::Mutex;
+
+use crate::{date_time, environment::Clock, S3};
+
+/// Mutating operation completed by a store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mutation {
+    Put {
+        location: Path,
+        e_tag: Option<String>,
+        version: Option<String>,
+    },
+    Delete {
+        location: Path,
+    },
+    Copy {
+        from: Path,
+        to: Path,
+    },
+}
+
+/// Entry of the operation journal, sequence numbers increase by one per entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub mutation: Mutation,
+}
+
+/// Receives every journal entry, e.g. to persist it.
+///
+/// Sinks are called after the entry was added to the journal, without holding its lock, so they
+/// may read the journal or mutate the store. Sinks of concurrent mutations may receive entries
+/// out of order, their sequence numbers order them.
+pub trait JournalSink: Debug + Send + Sync {
+    fn record(&self, entry: &JournalEntry);
+}
+
+#[derive(Debug, Default)]
+struct JournalState {
+    next_sequence: u64,
+    entries: VecDeque<JournalEntry>,
+    subscribers: Vec<UnboundedSender<JournalEntry>>,
+}
+
+/// Bounded, ordered log of the mutations completed by a store.
+#[derive(Debug, Clone)]
+pub(crate) struct Journal {
+    capacity: usize,
+    sinks: Vec<Arc<dyn JournalSink>>,
+    clock: Arc<dyn Clock>,
+    state: Arc<Mutex<JournalState>>,
+}
+
+impl Journal {
+    pub(crate) fn new(
+        capacity: usize,
+        sinks: Vec<Arc<dyn JournalSink>>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            capacity,
+            sinks,
+            clock,
+            state: Arc::default(),
+        }
+    }
+
+    pub(crate) fn record(&self, mutation: Mutation) {
+        let timestamp = date_time(self.clock.now());
+        let entry = {
+            let mut state = self.state.lock();
+            let entry = JournalEntry {
+                sequence: state.next_sequence,
+                timestamp,
+                mutation,
+            };
+            state.next_sequence += 1;
+            state
+                .subscribers
+                .retain(|subscriber| subscriber.unbounded_send(entry.clone()).is_ok());
+            if self.capacity > 0 {
+                if state.entries.len() == self.capacity {
+                    state.entries.pop_front();
+                }
+                state.entries.push_back(entry.clone());
+            }
+            entry
+        };
+        // Sinks may call back into the store, the lock isn't reentrant
+        for sink in &self.sinks {
+            sink.record(&entry);
+        }
+    }
+
+    fn entries_since(&self, sequence: u64) -> Vec<JournalEntry> {
+        self.state
+            .lock()
+            .entries
+            .iter()
+            .filter(|entry| entry.sequence >= sequence)
+            .cloned()
+            .collect()
+    }
+
+    fn subscribe(&self) -> BoxStream<'static, JournalEntry> {
+        let (tx, rx) = mpsc::unbounded();
+        self.state.lock().subscribers.push(tx);
+        rx.boxed()
+    }
+}
+
+impl S3 {
+    /// Retained journal entries with a sequence number of at least `sequence`, oldest first.
+    ///
+    /// Empty unless the store was built with [`S3Builder::with_journal`](crate::builder::S3Builder::with_journal).
+    pub fn journal_since(&self, sequence: u64) -> Vec<JournalEntry> {
+        self.journal
+            .as_ref()
+            .map(|journal| journal.entries_since(sequence))
+            .unwrap_or_default()
+    }
+
+    /// Stream of all journal entries recorded from now on.
+    pub fn subscribe_journal(&self) -> BoxStream<'static, JournalEntry> {
+        match &self.journal {
+            Some(journal) => journal.subscribe(),
+            None => futures::stream::empty().boxed(),
+        }
+    }
+
+    pub(crate) fn record(&self, mutation: impl FnOnce() -> Mutation) {
+        if let Some(journal) = &self.journal {
+            journal.record(mutation());
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::{cell::RefCell, rc::Rc};
+
+use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use js_sys::{Object, Promise, Reflect, Uint8Array};
+use object_store::{path::Path, ObjectStore};
+use serde::Deserialize;
+use wasm_bindgen::{closure::Closure, prelude::wasm_bindgen, JsError, JsValue};
+use wasm_bindgen_futures::future_to_promise;
+use web_sys::{ReadableStream, ReadableStreamDefaultController};
+
+use crate::{listing::ListingEntry, S3};
+
+/// Store configuration passed from JS, keys are camel case.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsConfig {
+    bucket: String,
+    region: Option<String>,
+    endpoint: Option<String>,
+    access_key_id: Option<String>,
+    secret_access_key: Option<String>,
+    session_token: Option<String>,
+    force_path_style: Option<bool>,
+    allow_http: Option<bool>,
+}
+
+/// S3 store for use from JS and TypeScript, exported as `S3Store`.
+///
+/// Every method returns a promise, errors reject it with an `Error`.
+#[wasm_bindgen(js_name = S3Store)]
+pub struct JsStore {
+    inner: S3,
+}
+
+fn js_error(err: impl std::fmt::Display) -> JsValue {
+    JsError::new(&err.to_string()).into()
+}
+
+/// Serializes `value` to the JS value of its JSON representation.
+fn to_js(value: &impl serde::Serialize) -> Result<JsValue, JsValue> {
+    js_sys::JSON::parse(&serde_json::to_string(value).map_err(js_error)?)
+}
+
+#[wasm_bindgen(js_class = S3Store)]
+impl JsStore {
+    /// Creates a store from an object like
+    /// `{ bucket, region, endpoint, accessKeyId, secretAccessKey, sessionToken, forcePathStyle,
+    /// allowHttp }`, only `bucket` is required.
+    #[wasm_bindgen(constructor)]
+    pub fn new(config: JsValue) -> Result<JsStore, JsValue> {
+        let config = js_sys::JSON::stringify(&config)?
+            .as_string()
+            .unwrap_or_default();
+        let config: JsConfig = serde_json::from_str(&config).map_err(js_error)?;
+        let mut builder = S3::builder().bucket(config.bucket);
+        if let Some(region) = config.region {
+            builder = builder.region(region);
+        }
+        if let Some(endpoint) = config.endpoint {
+            builder = builder.with_endpoint(endpoint);
+        }
+        if let Some(access_key_id) = config.access_key_id {
+            builder = builder.access_key_id(access_key_id);
+        }
+        if let Some(secret_access_key) = config.secret_access_key {
+            builder = builder.secret_access_key(secret_access_key);
+        }
+        if let Some(session_token) = config.session_token {
+            builder = builder.session_token(session_token);
+        }
+        if let Some(force_path_style) = config.force_path_style {
+            builder = builder.with_force_path_style(force_path_style);
+        }
+        if let Some(allow_http) = config.allow_http {
+            builder = builder.with_allow_http(allow_http);
+        }
+        Ok(JsStore {
+            inner: builder.build().map_err(js_error)?,
+        })
+    }
+
+    /// Resolves to the content of the object at `path` as a `Uint8Array`.
+    pub fn get(&self, path: String) -> Promise {
+        let s3 = self.inner.clone();
+        future_to_promise(async move {
+            let bytes = s3
+                .get(&Path::from(path))
+                .await
+                .map_err(js_error)?
+                .bytes()
+                .await
+                .map_err(js_error)?;
+            Ok(Uint8Array::from(bytes.as_ref()).into())
+        })
+    }
+
+    /// Resolves to a `ReadableStream` of `Uint8Array` chunks of the object at `path`.
+    #[wasm_bindgen(js_name = getStream)]
+    pub fn get_stream(&self, path: String) -> Promise {
+        let s3 = self.inner.clone();
+        future_to_promise(async move {
+            let stream = s3
+                .get(&Path::from(path))
+                .await
+                .map_err(js_error)?
+                .into_stream();
+            Ok(readable_stream(stream)?.into())
+        })
+    }
+
+    /// Writes `data` to `path`, resolves to `{ eTag, version }`.
+    pub fn put(&self, path: String, data: Uint8Array) -> Promise {
+        let s3 = self.inner.clone();
+        let data = Bytes::from(data.to_vec());
+        future_to_promise(async move {
+            let result = s3.put(&Path::from(path), data).await.map_err(js_error)?;
+            let object = Object::new();
+            Reflect::set(&object, &"eTag".into(), &result.e_tag.into())?;
+            Reflect::set(&object, &"version".into(), &result.version.into())?;
+            Ok(object.into())
+        })
+    }
+
+    /// Resolves to an array of
+    /// `{ location, last_modified, size, e_tag, version }` for every object below `prefix`.
+    pub fn list(&self, prefix: Option<String>) -> Promise {
+        let s3 = self.inner.clone();
+        future_to_promise(async move {
+            let prefix = prefix.map(Path::from);
+            let entries = s3
+                .list(prefix.as_ref())
+                .map_ok(ListingEntry::from)
+                .try_collect::<Vec<_>>()
+                .await
+                .map_err(js_error)?;
+            to_js(&entries)
+        })
+    }
+
+    /// Deletes the object at `path`.
+    pub fn delete(&self, path: String) -> Promise {
+        let s3 = self.inner.clone();
+        future_to_promise(async move {
+            s3.delete(&Path::from(path)).await.map_err(js_error)?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+}
+
+/// `ReadableStream` pulling its chunks from `stream`, cancelling it drops `stream`.
+fn readable_stream(
+    stream: BoxStream<'static, object_store::Result<Bytes>>,
+) -> Result<ReadableStream, JsValue> {
+    let stream = Rc::new(RefCell::new(Some(stream)));
+    let pull = {
+        let stream = stream.clone();
+        Closure::<dyn FnMut(ReadableStreamDefaultController) -> Promise>::new(
+            move |controller: ReadableStreamDefaultController| {
+                let stream = stream.clone();
+                future_to_promise(async move {
+                    // Taken while awaiting, the stream only pulls again once this resolved
+                    let Some(mut inner) = stream.borrow_mut().take() else {
+                        controller.close()?;
+                        return Ok(JsValue::UNDEFINED);
+                    };
+                    match inner.next().await {
+                        Some(Ok(chunk)) => {
+                            controller.enqueue_with_chunk(&Uint8Array::from(chunk.as_ref()))?;
+                            *stream.borrow_mut() = Some(inner);
+                        }
+                        Some(Err(err)) => controller.error_with_e(&js_error(err)),
+                        None => controller.close()?,
+                    }
+                    Ok(JsValue::UNDEFINED)
+                })
+            },
+        )
+    };
+    let cancel = Closure::<dyn FnMut(JsValue)>::new(move |_: JsValue| {
+        stream.borrow_mut().take();
+    });
+    let source = Object::new();
+    Reflect::set(&source, &"pull".into(), &pull.into_js_value())?;
+    Reflect::set(&source, &"cancel".into(), &cancel.into_js_value())?;
+    ReadableStream::new_with_underlying_source(&source)
+}
@@ -0,0 +1,38 @@
+use aws_sdk_s3::types::EncodingType;
+use object_store::path::Path;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::{error::Error, list_encoding::decode_key};
+
+/// Characters left unescaped in copy sources, the unreserved ones and the key delimiter.
+const COPY_SOURCE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'/');
+
+/// `x-amz-copy-source` of `key` in `bucket`. S3 expects the key URL-encoded, a verbatim `+`,
+/// `%` or space would name another object.
+pub fn copy_source(bucket: &str, key: &str) -> String {
+    format!("{}/{}", bucket, utf8_percent_encode(key, COPY_SOURCE))
+}
+
+/// [`Path`] of the S3 `key`, which addresses `key` again when written to or read from.
+///
+/// `Path::from` would escape characters like `%` or `{` a second time, so keys are taken
+/// verbatim where possible. Only keys no [`Path`] can hold, with empty or `.` segments or
+/// control characters, are escaped and normalized and won't round-trip.
+pub fn key_to_path(key: &str) -> Path {
+    Path::parse(key).unwrap_or_else(|_| Path::from(key))
+}
+
+/// S3 key addressed by `path`.
+pub fn path_to_key(path: &Path) -> String {
+    path.as_ref().to_owned()
+}
+
+/// [`Path`] of a key of a listing response sent with `encoding`.
+pub(crate) fn listed_path(key: String, encoding: Option<&EncodingType>) -> Result<Path, Error> {
+    decode_key(key, encoding).map(|key| key_to_path(&key))
+}
@@ -1,140 +1,478 @@
-use std::{fmt::Display, num::ParseIntError, ops::Range, sync::Arc};
+#![allow(clippy::result_large_err)]
+
+#[cfg(not(any(feature = "wasm", feature = "native")))]
+compile_error!("either the `wasm` or the `native` feature is required");
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+compile_error!("the `wasm` feature is required on wasm32");
+
+use std::{
+    fmt::Display,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc,
+    },
+    time::Duration,
+};
 
 use async_trait::async_trait;
-use aws_sdk_s3::Client;
+use attributes::PutAttributes;
+use aws_sdk_s3::{
+    operation::{
+        copy_object::builders::CopyObjectFluentBuilder,
+        head_object::builders::HeadObjectFluentBuilder,
+        put_object::builders::PutObjectFluentBuilder,
+    },
+    types::{EncodingType, Object},
+    Client,
+};
 use builder::S3Builder;
 use bytes::Bytes;
+use cache_control::{CacheHeaders, CachePolicy};
+use capabilities::Capabilities;
+use checksum::{full_object_checksum, ChecksumAlgorithm, VerifiedStream};
 use chrono::{DateTime, Utc};
-use error::Error;
+use circuit::CircuitBreaker;
+use conditional::{ConditionalDelete, ConditionalPut, CopyIfNotExists};
+use content_type::ContentTypes;
+use diagnostics::Diagnostics;
+use encryption::SseConfig;
+use environment::{Clock, Random, Timer};
+pub use error::Error;
+use error::{is_not_found, status_code};
+use events::{Direction, TransferEvent, TransferEvents};
 use futures::{
     stream::{self, BoxStream},
-    TryFutureExt, TryStreamExt,
+    StreamExt, TryStreamExt,
 };
-use multipart::MultiPartUpload;
+use http::HeaderValue;
+use idempotency::IDEMPOTENCY_TOKEN_KEY;
+use instrument::{InstrumentedStream, StreamObserver};
+use journal::{Journal, Mutation};
+use key_encoding::{copy_source, listed_path};
+use list_encoding::ListEncoding;
+use metrics::MetricsRecorder;
+use multipart::{ConcurrencyWindow, MultiPartUpload, PartWriter, UploadFailures};
 use object_store::{
-    multipart::WriteMultiPart, GetResultPayload, ListResult, ObjectMeta, ObjectStore, PutOptions,
-    PutResult,
+    GetResultPayload, ListResult, ObjectMeta, ObjectStore, PutMode, PutOptions, PutResult,
 };
+use parallel_get::ParallelDownloads;
+use parking_lot::Mutex;
+use partition::Partition;
+use persistent_cache::PersistentCache;
+use profile::Unsupported;
+use progress::{Operation, ProgressListener, ProgressStream};
+use remote_signing::Signer;
+use response_log::{CapturedResponse, ResponseLog};
+use status::ObjectStatus;
 use tokio::io::AsyncWrite;
+use upload_report::ReportRecorder;
 
+pub mod access_hint;
+pub mod acl;
+#[cfg(feature = "admin")]
+mod admin;
+pub mod attributes;
+#[cfg(feature = "batch")]
+pub mod batch;
+mod blob;
+pub mod bucket_name;
 pub mod builder;
+pub mod cache;
+pub mod cache_control;
+pub mod capabilities;
+pub mod checksum;
+mod chunks;
+pub mod circuit;
+pub mod coalesce;
+pub mod conditional;
+mod content_type;
+pub mod copy;
+pub mod dataset;
+pub mod delete;
+pub mod delta;
+pub mod diagnostics;
+pub mod encrypted;
+pub mod encryption;
+pub mod environment;
 mod error;
+pub mod etag;
+pub mod events;
+mod exists;
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+mod fetch_body;
+pub mod first_of;
+mod idb;
+pub mod idempotency;
+pub mod index;
+pub mod instrument;
+pub mod journal;
+#[cfg(feature = "js")]
+pub mod js;
+pub mod key_encoding;
+pub mod list_encoding;
+pub mod listing;
+pub mod listing_diff;
+pub mod metrics;
 mod multipart;
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+mod native;
+#[cfg(feature = "object_store_next")]
+mod next_store;
+pub mod observe;
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+pub mod opfs;
+pub mod optimistic;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+mod overwrite;
+pub mod pack;
+pub mod pages;
+mod parallel_get;
+pub mod partition;
+pub mod parts;
+pub mod persistent_cache;
+pub mod prefixed;
+pub mod presign;
+pub mod presigned_store;
+pub mod profile;
+pub mod progress;
+mod read_only;
+pub mod remote_signing;
+mod requester_pays;
+pub mod response_log;
+pub mod resumable;
+pub mod retry;
+pub mod shard;
+#[cfg(feature = "shared_session")]
+mod shared_session;
+mod signed_headers;
+mod signing;
+pub mod snapshot;
+pub mod staged;
+pub mod status;
+pub mod storage_class;
+pub mod streaming;
+pub mod tags;
+pub mod target;
+pub mod tee;
+mod throttle;
+pub mod tiered;
+pub mod timeout;
+#[cfg(feature = "tolerant")]
+pub mod tolerant;
+#[cfg(feature = "object_store_next")]
+pub mod upload_handle;
+pub mod upload_report;
+pub mod upload_ticket;
+pub mod validate;
+pub mod verify;
+pub mod versions;
+mod wait;
+pub mod web_identity;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct S3 {
     client: Arc<Client>,
+    /// Client with the credentials of the store, which `client` may leave to the http connector.
+    presign_client: Arc<Client>,
+    /// Signs the requests of `client` in the http connector, if the SDK doesn't.
+    signer: Option<Arc<Signer>>,
     bucket: String,
+    response_log: Option<ResponseLog>,
+    metrics: MetricsRecorder,
+    cache_policy: Option<Arc<dyn CachePolicy>>,
+    content_types: Option<Arc<ContentTypes>>,
+    upload_quota: Option<u64>,
+    max_get_size: Option<usize>,
+    journal: Option<Journal>,
+    head_denied: Arc<AtomicBool>,
+    circuit_breaker: Option<CircuitBreaker>,
+    timer: Arc<dyn Timer>,
+    random: Arc<dyn Random>,
+    clock: Arc<dyn Clock>,
+    stream_observer: Option<Arc<dyn StreamObserver>>,
+    progress: Option<Arc<dyn ProgressListener>>,
+    events: TransferEvents,
+    partition: Partition,
+    copy_if_not_exists: CopyIfNotExists,
+    conditional_put: ConditionalPut,
+    conditional_delete: ConditionalDelete,
+    unsupported: Unsupported,
+    capabilities: Arc<Mutex<Option<Capabilities>>>,
+    delete_batch_size: usize,
+    multipart_part_size: usize,
+    multipart_concurrency: usize,
+    multipart_copy_threshold: u64,
+    multipart_put_threshold: u64,
+    idempotency_tokens: bool,
+    coalesce_gap: usize,
+    max_coalesced_size: usize,
+    persistent_cache: Option<PersistentCache>,
+    adaptive_multipart_concurrency: Option<(usize, usize)>,
+    parallel_downloads: Option<ParallelDownloads>,
+    max_chunk_size: Option<usize>,
+    delta_manifest_prefix: object_store::path::Path,
+    diagnostics: Arc<Diagnostics>,
+    sse: Option<SseConfig>,
+    checksum: Option<ChecksumAlgorithm>,
+    verify_checksums: bool,
+    list_encoding: ListEncoding,
+    #[cfg(feature = "batch")]
+    control: Arc<aws_sdk_s3control::Client>,
+    #[cfg(feature = "shared_session")]
+    shared_session: bool,
 }
 
 impl S3 {
     pub fn builder() -> S3Builder {
         S3Builder::default()
     }
-}
 
-#[async_trait]
-impl ObjectStore for S3 {
-    async fn abort_multipart(
+    /// Partition of the store's region.
+    pub fn partition(&self) -> Partition {
+        self.partition
+    }
+
+    /// Store targeting `bucket` that shares the client, credentials and configuration of this one.
+    ///
+    /// Cheap enough to call per operation. Capabilities and denied `HeadObject` requests are
+    /// tracked per bucket.
+    pub fn with_bucket(&self, bucket: impl Into<String>) -> S3 {
+        S3 {
+            bucket: bucket.into(),
+            head_denied: Arc::default(),
+            capabilities: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// Describes a failed request for `key` in the store's bucket, see [`Error::Request`].
+    pub(crate) fn request_error(&self, key: impl ToString, err: impl Into<Error>) -> Error {
+        err.into().request(&self.bucket, key)
+    }
+
+    /// `PutObject` request with the cache headers of the store's policy and the inferred content
+    /// type applied.
+    pub(crate) fn put_request(
         &self,
         location: &object_store::path::Path,
-        multipart_id: &object_store::MultipartId,
-    ) -> object_store::Result<()> {
-        self.client
-            .abort_multipart_upload()
+        bytes: Bytes,
+    ) -> PutObjectFluentBuilder {
+        let cache_headers = self.cache_headers(location);
+        let request = self.checksummed(self.client.put_object(), &bytes);
+        let request = request
             .bucket(self.bucket.clone())
             .key(location.to_string())
-            .upload_id(multipart_id)
+            .body(bytes.into())
+            .set_content_type(self.inferred_content_type(location))
+            .set_cache_control(cache_headers.cache_control)
+            .set_expires(cache_headers.expires.map(smithy_date_time));
+        self.encrypted(request, SseConfig::put)
+    }
+
+    /// Starts a multipart upload with the cache headers of the store's policy and the inferred
+    /// content type, returns its id.
+    pub(crate) async fn create_multipart(
+        &self,
+        location: &object_store::path::Path,
+        attributes: &PutAttributes,
+    ) -> object_store::Result<String> {
+        self.create_tagged_multipart(location, attributes, None)
+            .await
+    }
+
+    pub(crate) async fn create_tagged_multipart(
+        &self,
+        location: &object_store::path::Path,
+        attributes: &PutAttributes,
+        tags: Option<String>,
+    ) -> object_store::Result<String> {
+        let cache_headers = self.cache_headers(location);
+        let request = self
+            .client
+            .create_multipart_upload()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .set_checksum_algorithm(self.checksum.map(ChecksumAlgorithm::sdk))
+            .set_tagging(tags)
+            .set_content_type(self.inferred_content_type(location))
+            .set_cache_control(cache_headers.cache_control)
+            .set_expires(cache_headers.expires.map(smithy_date_time));
+        let request = attributes.create_multipart(request);
+        let response = self
+            .encrypted(request, SseConfig::create_multipart)
             .send()
             .await
-            .map_err(Error::from)?;
-        Ok(())
+            .map_err(|err| self.request_error(location, err))?;
+        Ok(response.upload_id.ok_or(Error::Unknown)?)
     }
-    async fn copy(
+
+    pub(crate) fn copy_request(
         &self,
         from: &object_store::path::Path,
         to: &object_store::path::Path,
-    ) -> object_store::Result<()> {
-        let mut source_bucket_and_object: String = "".to_owned();
-        source_bucket_and_object.push_str(&self.bucket);
-        source_bucket_and_object.push('/');
-        source_bucket_and_object.push_str(from.as_ref());
-        self.client
+    ) -> CopyObjectFluentBuilder {
+        let request = self
+            .client
             .copy_object()
-            .copy_source(source_bucket_and_object)
+            .copy_source(copy_source(&self.bucket, from.as_ref()))
             .bucket(self.bucket.clone())
-            .key(to.to_string())
-            .send()
-            .await
-            .map_err(Error::from)?;
-        Ok(())
+            .key(to.to_string());
+        self.encrypted(request, SseConfig::copy)
     }
-    async fn copy_if_not_exists(
+
+    /// Monotonic clock the store measures durations and sleeps with.
+    pub fn timer(&self) -> &Arc<dyn Timer> {
+        &self.timer
+    }
+
+    /// Random source the store draws jitter from.
+    pub fn random(&self) -> &Arc<dyn Random> {
+        &self.random
+    }
+
+    /// Wall clock the store signs requests with.
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// Current time of the store's clock.
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        date_time(self.clock.now())
+    }
+
+    /// Raw responses of the most recent requests, oldest first.
+    ///
+    /// Empty unless the store was built with [`S3Builder::with_response_header_capture`].
+    pub fn captured_responses(&self) -> Vec<CapturedResponse> {
+        self.response_log
+            .as_ref()
+            .map(ResponseLog::entries)
+            .unwrap_or_default()
+    }
+
+    fn cache_headers(&self, location: &object_store::path::Path) -> CacheHeaders {
+        self.cache_policy
+            .as_ref()
+            .and_then(|policy| policy.headers(location, self.now()))
+            .unwrap_or_default()
+    }
+
+    /// Content type inferred from the extension of `location`, if inference is enabled.
+    pub(crate) fn inferred_content_type(
         &self,
-        _from: &object_store::path::Path,
-        _to: &object_store::path::Path,
-    ) -> object_store::Result<()> {
-        Err(object_store::Error::NotSupported {
-            source: Box::new(Error::Unknown),
-        })
+        location: &object_store::path::Path,
+    ) -> Option<String> {
+        self.content_types
+            .as_ref()
+            .and_then(|content_types| content_types.infer(location))
     }
-    async fn delete(&self, location: &object_store::path::Path) -> object_store::Result<()> {
-        self.client
-            .delete_object()
-            .bucket(self.bucket.clone())
-            .key(location.to_string())
-            .send()
-            .await
-            .map_err(Error::from)?;
-        Ok(())
+
+    /// Drops all captured responses.
+    pub fn clear_captured_responses(&self) {
+        if let Some(response_log) = &self.response_log {
+            response_log.clear();
+        }
     }
-    async fn get_opts(
+
+    /// Like [`ObjectStore::get_opts`] but additionally returns the replication and restore status
+    /// and the storage class.
+    pub async fn get_with_status(
         &self,
         location: &object_store::path::Path,
         options: object_store::GetOptions,
-    ) -> object_store::Result<object_store::GetResult> {
+    ) -> object_store::Result<(object_store::GetResult, ObjectStatus)> {
+        let (result, status, _) = self.get_object(location, options).await?;
+        Ok((result, status))
+    }
+
+    pub(crate) async fn get_object(
+        &self,
+        location: &object_store::path::Path,
+        options: object_store::GetOptions,
+    ) -> object_store::Result<(object_store::GetResult, ObjectStatus, PutAttributes)> {
+        if options.head {
+            return self.head_with_options(location, &options).await;
+        }
+        let requested_at = self.timer.now();
+        let mut options = options;
+        if let (Some(limit), None) = (self.max_get_size, &options.range) {
+            let (meta, _, _) = self
+                .head_object(location, options.version.as_deref())
+                .await?;
+            if meta.size > limit {
+                return Err(Error::BodyTooLarge {
+                    size: meta.size,
+                    limit,
+                }
+                .into());
+            }
+            // Make sure the object didn't grow between the size check and the read
+            options.if_match = options.if_match.or(meta.e_tag);
+        }
+        if let (true, Some(object_store::GetRange::Suffix(length))) =
+            (self.unsupported.suffix_range, &options.range)
+        {
+            let (meta, _, _) = self
+                .head_object(location, options.version.as_deref())
+                .await?;
+            // The suffix of an empty object is the whole object
+            options.range = (meta.size > 0).then(|| {
+                object_store::GetRange::Bounded(meta.size.saturating_sub(*length)..meta.size)
+            });
+            options.if_match = options.if_match.or(meta.e_tag);
+        }
+        if let (Some(parallel), None) = (self.parallel_downloads, &options.range) {
+            return self
+                .get_parallel(location, options, parallel, requested_at)
+                .await;
+        }
+        self.fetch_object(location, options, requested_at).await
+    }
+
+    /// Sends the `GetObject` request of `options`, [`S3::get_object`] without the extra
+    /// requests of size limits, suffix fallbacks and parallel downloads.
+    pub(crate) async fn fetch_object(
+        &self,
+        location: &object_store::path::Path,
+        options: object_store::GetOptions,
+        requested_at: Duration,
+    ) -> object_store::Result<(object_store::GetResult, ObjectStatus, PutAttributes)> {
         let request = self
             .client
             .get_object()
             .bucket(self.bucket.clone())
             .key(location.to_string());
-        let request = match options.if_match {
-            Some(if_match) => request.if_match(if_match),
-            None => request,
-        };
-        let request = match options.if_none_match {
-            Some(if_none_match) => request.if_none_match(if_none_match),
-            None => request,
-        };
-        let request = match options.if_modified_since {
-            Some(if_modified_since) => {
-                let date_time = aws_smithy_types::DateTime::from_millis(
-                    if_modified_since
-                        .signed_duration_since::<Utc>(DateTime::from_timestamp(0, 0).unwrap())
-                        .num_milliseconds(),
-                );
-                request.if_modified_since(date_time)
-            }
-            None => request,
-        };
-        let request = match options.if_unmodified_since {
-            Some(if_unmodified_since) => {
-                let date_time = aws_smithy_types::DateTime::from_millis(
-                    if_unmodified_since
-                        .signed_duration_since::<Utc>(DateTime::from_timestamp(0, 0).unwrap())
-                        .num_milliseconds(),
-                );
-                request.if_modified_since(date_time)
-            }
-            None => request,
-        };
-        let request = match options.range {
-            Some(object_store::GetRange::Bounded(range)) => request.range(
-                "bytes=".to_string() + &range.start.to_string() + "-" + &range.end.to_string(),
-            ),
-            _ => request,
-        };
-        let response = request.send().await.map_err(Error::from)?;
+        let request = self.encrypted(request, SseConfig::get);
+        let request = request
+            .set_if_match(options.if_match.clone())
+            .set_if_none_match(options.if_none_match.clone())
+            .set_if_modified_since(options.if_modified_since.map(smithy_date_time))
+            .set_if_unmodified_since(options.if_unmodified_since.map(smithy_date_time));
+        let request = request
+            .set_range(options.range.as_ref().map(range_header).transpose()?)
+            .set_version_id(options.version.clone());
+        let response = if self.verify_checksums {
+            request
+                .customize()
+                .await
+                .map_err(|_| Error::Unknown)?
+                .mutate_request(|request| {
+                    request
+                        .headers_mut()
+                        .insert("x-amz-checksum-mode", HeaderValue::from_static("ENABLED"));
+                })
+                .send()
+                .await
+        } else {
+            request.send().await
+        }
+        .map_err(|err| self.request_error(location, err))?;
         let last_modified = DateTime::from_timestamp_millis(
             response
                 .last_modified()
@@ -143,48 +481,136 @@ impl ObjectStore for S3 {
                 .map_err(Error::from)?,
         )
         .unwrap();
-        let size = response.content_length() as usize;
-        let range = response
-            .content_range
-            .ok_or(Error::Unknown)?
-            .trim_start_matches("bytes=")
-            .split("-")
-            .map(|x| x.parse::<usize>())
-            .collect::<Result<Vec<_>, ParseIntError>>()
-            .map_err(Error::from)?;
-
-        Ok(object_store::GetResult {
-            payload: GetResultPayload::Stream(Box::pin(response.body.map_err(|err| {
-                object_store::Error::Generic {
-                    store: "aws_smithy",
-                    source: Box::new(err),
+        let status = ObjectStatus::from(&response);
+        let attributes = PutAttributes::from(&response);
+        let content_length = response.content_length() as usize;
+        let (range, size) = match (response.content_range(), &options.range) {
+            (Some(content_range), _) => parse_content_range(content_range)?,
+            (None, None) => (0..content_length, content_length),
+            // The endpoint didn't report what it served, derive it from the object size
+            (None, Some(requested)) => {
+                let (meta, _, _) = self
+                    .head_object(location, options.version.as_deref())
+                    .await?;
+                let size = meta.size;
+                let range = resolve_range(requested, size);
+                if range.len() != content_length {
+                    return Err(Error::Unknown.into());
                 }
-            }))),
+                (range, size)
+            }
+        };
+        // Checksums cover the whole object, parts of it can't be verified
+        let checksum = (self.verify_checksums && range.len() == size)
+            .then(|| full_object_checksum(&response))
+            .flatten();
+        let body = VerifiedStream::wrap(
+            chunks::bounded(
+                response
+                    .body
+                    .map_err(|err| object_store::Error::Generic {
+                        store: "aws_smithy",
+                        source: Box::new(err),
+                    })
+                    .boxed(),
+                self.max_chunk_size,
+            ),
+            checksum,
+            location.to_string(),
+        );
+
+        let result = object_store::GetResult {
+            payload: GetResultPayload::Stream(self.events.track_download(
+                ProgressStream::wrap(
+                    Box::pin(InstrumentedStream::new(
+                        body,
+                        location.clone(),
+                        self.timer.clone(),
+                        self.metrics.clone(),
+                        self.stream_observer.clone(),
+                        requested_at,
+                    )),
+                    location.clone(),
+                    self.progress.clone(),
+                    range.len(),
+                ),
+                location.clone(),
+            )),
             meta: ObjectMeta {
-                location: location.to_string().into(),
+                location: location.clone(),
                 last_modified,
                 size,
                 e_tag: response.e_tag,
-                version: None,
-            },
-            range: Range {
-                start: range[0],
-                end: range[1],
+                version: response.version_id,
             },
-        })
+            range,
+        };
+        Ok((result, status, attributes))
     }
-    async fn head(
+    /// Like [`ObjectStore::head`] but additionally returns the replication, restore and archive
+    /// status and the storage class.
+    pub async fn head_with_status(
         &self,
         location: &object_store::path::Path,
-    ) -> object_store::Result<object_store::ObjectMeta> {
-        let output = self
+    ) -> object_store::Result<(ObjectMeta, ObjectStatus)> {
+        let (meta, status, _) = self.head_object(location, None).await?;
+        Ok((meta, status))
+    }
+
+    pub(crate) async fn head_object(
+        &self,
+        location: &object_store::path::Path,
+        version: Option<&str>,
+    ) -> object_store::Result<(ObjectMeta, ObjectStatus, PutAttributes)> {
+        let request = self
             .client
             .head_object()
             .set_bucket(Some(self.bucket.clone()))
             .set_key(Some(location.to_string()))
+            .set_version_id(version.map(ToOwned::to_owned));
+        self.send_head(location, request).await
+    }
+
+    /// [`GetOptions::head`](object_store::GetOptions::head) request, `HeadObject` evaluates the
+    /// preconditions so the body is never downloaded.
+    async fn head_with_options(
+        &self,
+        location: &object_store::path::Path,
+        options: &object_store::GetOptions,
+    ) -> object_store::Result<(object_store::GetResult, ObjectStatus, PutAttributes)> {
+        let request = self
+            .client
+            .head_object()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .set_if_match(options.if_match.clone())
+            .set_if_none_match(options.if_none_match.clone())
+            .set_if_modified_since(options.if_modified_since.map(smithy_date_time))
+            .set_if_unmodified_since(options.if_unmodified_since.map(smithy_date_time))
+            .set_version_id(options.version.clone());
+        let (meta, status, attributes) = self.send_head(location, request).await?;
+        let range = options
+            .range
+            .as_ref()
+            .map_or(0..meta.size, |range| resolve_range(range, meta.size));
+        let result = object_store::GetResult {
+            payload: GetResultPayload::Stream(stream::empty().boxed()),
+            meta,
+            range,
+        };
+        Ok((result, status, attributes))
+    }
+
+    async fn send_head(
+        &self,
+        location: &object_store::path::Path,
+        request: HeadObjectFluentBuilder,
+    ) -> object_store::Result<(ObjectMeta, ObjectStatus, PutAttributes)> {
+        let output = self
+            .encrypted(request, SseConfig::head)
             .send()
             .await
-            .map_err(Error::from)?;
+            .map_err(|err| self.request_error(location, err))?;
         let last_modified = DateTime::from_timestamp_millis(
             output
                 .last_modified()
@@ -198,108 +624,407 @@ impl ObjectStore for S3 {
             last_modified,
             size: output.content_length() as usize,
             e_tag: output.e_tag().map(|x| x.to_string()),
-            version: None,
+            version: output.version_id().map(|x| x.to_string()),
         };
-        Ok(meta)
+        Ok((
+            meta,
+            ObjectStatus::from(&output),
+            PutAttributes::from(&output),
+        ))
     }
-    fn list(
+
+    pub(crate) async fn put_object(
         &self,
-        prefix: Option<&object_store::path::Path>,
-    ) -> BoxStream<'_, object_store::Result<object_store::ObjectMeta>> {
-        let request = self.client.list_objects_v2().bucket(self.bucket.clone());
-        let request = match prefix {
-            Some(prefix) => request.prefix(prefix.to_string()),
-            None => request,
+        location: &object_store::path::Path,
+        bytes: Bytes,
+        opts: PutOptions,
+        attributes: &PutAttributes,
+    ) -> object_store::Result<PutResult> {
+        let size = bytes.len();
+        let result = if size as u64 > self.multipart_put_threshold {
+            self.put_split(location, bytes, opts, attributes).await
+        } else {
+            let result = self.send_put(location, bytes, opts, attributes).await;
+            if result.is_ok() {
+                self.report_progress(Operation::Put, location, size, Some(size));
+            }
+            result
         };
-        Box::pin(
-            request
-                .send()
-                .map_err(|_| object_store::Error::from(Error::Unknown))
-                .and_then(|response| async {
-                    match response.contents {
-                        Some(contents) => {
-                            Ok(Box::pin(stream::iter(contents.into_iter().map(|object| {
-                                let last_modified = DateTime::from_timestamp_millis(
-                                    object
-                                        .last_modified()
-                                        .ok_or(Error::Unknown)?
-                                        .to_millis()
-                                        .map_err(Error::from)?,
-                                )
-                                .unwrap();
-                                Ok(ObjectMeta {
-                                    location: object
-                                        .key
-                                        .ok_or(object_store::Error::Generic {
-                                            store: "aws",
-                                            source: Box::new(Error::Unknown),
-                                        })?
-                                        .into(),
-                                    last_modified,
-                                    size: object.size as usize,
-                                    e_tag: object.e_tag,
-                                    version: None,
-                                })
-                            }))) as BoxStream<_>)
+        self.events
+            .emit(|| TransferEvent::finished(location, Direction::Upload, size, result.is_ok()));
+        result
+    }
+
+    /// Header making a put of `mode` conditional, `None` if there's no condition or it was
+    /// already checked with a `HeadObject` because the store can't evaluate it.
+    pub(crate) async fn put_condition(
+        &self,
+        location: &object_store::path::Path,
+        mode: &PutMode,
+    ) -> object_store::Result<Option<(http::HeaderName, HeaderValue)>> {
+        Ok(match (mode, self.conditional_put_strategy()) {
+            (PutMode::Overwrite, _) => None,
+            (PutMode::Create, ConditionalPut::Conditional) => {
+                Some((http::header::IF_NONE_MATCH, HeaderValue::from_static("*")))
+            }
+            (PutMode::Create, ConditionalPut::HeadThenPut) => match self.head(location).await {
+                Ok(_) => {
+                    return Err(object_store::Error::AlreadyExists {
+                        path: location.to_string(),
+                        source: "object exists".into(),
+                    })
+                }
+                Err(err) if is_not_found(&err) => None,
+                Err(err) => return Err(err),
+            },
+            (PutMode::Update(version), strategy) => {
+                let e_tag = version.e_tag.clone().ok_or(Error::MissingETag)?;
+                if strategy == ConditionalPut::HeadThenPut {
+                    let meta = self.head(location).await.map_err(|err| match err {
+                        object_store::Error::NotFound { path, source } => {
+                            object_store::Error::Precondition { path, source }
                         }
-                        None => Ok(Box::pin(stream::empty()) as BoxStream<_>),
+                        err => err,
+                    })?;
+                    if meta.e_tag.as_ref() != Some(&e_tag) {
+                        return Err(object_store::Error::Precondition {
+                            path: location.to_string(),
+                            source: format!("{} does not match {:?}", e_tag, meta.e_tag).into(),
+                        });
                     }
-                })
-                .try_flatten_stream()
-                .into_stream(),
-        )
+                    None
+                } else {
+                    Some((
+                        http::header::IF_MATCH,
+                        HeaderValue::try_from(e_tag.as_str())
+                            .map_err(|_| Error::InvalidHeader(e_tag))?,
+                    ))
+                }
+            }
+        })
     }
 
-    async fn list_with_delimiter(
+    async fn send_put(
         &self,
-        prefix: Option<&object_store::path::Path>,
-    ) -> object_store::Result<object_store::ListResult> {
-        let request = self.client.list_objects_v2().bucket(self.bucket.clone());
-        let request = match prefix {
-            Some(prefix) => request.prefix(prefix.to_string()),
+        location: &object_store::path::Path,
+        bytes: Bytes,
+        opts: PutOptions,
+        attributes: &PutAttributes,
+    ) -> object_store::Result<PutResult> {
+        let request = attributes
+            .put(self.put_request(location, bytes))
+            .tagging(opts.tags.encoded());
+        let token = self.idempotency_token();
+        let request = match &token {
+            Some(token) => request.metadata(IDEMPOTENCY_TOKEN_KEY, token),
             None => request,
         };
-        let response = request.send().await.map_err(Error::from)?;
-        let objects = match response.contents {
-            Some(contents) => contents
-                .into_iter()
-                .map(|object| {
-                    let last_modified = DateTime::from_timestamp_millis(
-                        object
-                            .last_modified()
-                            .ok_or(Error::Unknown)?
-                            .to_millis()
-                            .map_err(Error::from)?,
-                    )
-                    .unwrap();
-                    Ok(ObjectMeta {
-                        location: object
-                            .key
-                            .ok_or(object_store::Error::Generic {
-                                store: "aws",
-                                source: Box::new(Error::Unknown),
-                            })?
-                            .into(),
-                        last_modified,
-                        size: object.size as usize,
-                        e_tag: object.e_tag,
-                        version: None,
+        let condition = self.put_condition(location, &opts.mode).await?;
+        let result = match condition {
+            Some((name, value)) => {
+                request
+                    .customize()
+                    .await
+                    .map_err(|_| Error::Unknown)?
+                    .mutate_request(move |request| {
+                        request.headers_mut().insert(name.clone(), value.clone());
                     })
-                })
-                .collect::<Result<Vec<_>, object_store::Error>>()?,
-            None => Vec::new(),
+                    .send()
+                    .await
+            }
+            None => request.send().await,
         };
+        let result = match result {
+            Ok(result) => PutResult {
+                e_tag: result.e_tag,
+                version: result.version_id,
+            },
+            // Only conditional puts fail their condition, a 409 of an overwrite is a conflict
+            // like `OperationAborted`
+            Err(err)
+                if matches!(status_code(&err), Some(409 | 412))
+                    && !matches!(opts.mode, PutMode::Overwrite) =>
+            {
+                let landed = match &token {
+                    Some(token) => self.landed_put(location, token).await,
+                    None => None,
+                };
+                let Some(landed) = landed else {
+                    let path = location.to_string();
+                    let source = Box::new(self.request_error(location, err));
+                    return Err(match opts.mode {
+                        PutMode::Create => object_store::Error::AlreadyExists { path, source },
+                        _ => object_store::Error::Precondition { path, source },
+                    });
+                };
+                landed
+            }
+            // S3 answers `If-Match` on a missing object with 404, the expected version is gone
+            Err(err)
+                if status_code(&err) == Some(404) && matches!(opts.mode, PutMode::Update(_)) =>
+            {
+                return Err(object_store::Error::Precondition {
+                    path: location.to_string(),
+                    source: Box::new(self.request_error(location, err)),
+                });
+            }
+            Err(err) => return Err(self.request_error(location, err).into()),
+        };
+        self.record(|| Mutation::Put {
+            location: location.clone(),
+            e_tag: result.e_tag.clone(),
+            version: result.version.clone(),
+        });
+        Ok(result)
+    }
+
+    pub(crate) async fn multipart_writer(
+        &self,
+        location: &object_store::path::Path,
+        attributes: &PutAttributes,
+    ) -> object_store::Result<(
+        object_store::MultipartId,
+        Box<dyn AsyncWrite + Unpin + Send>,
+    )> {
+        let (upload_id, writer) = self.part_writer(location, attributes, None).await?;
+        Ok((upload_id, Box::new(writer)))
+    }
+
+    pub(crate) async fn part_writer(
+        &self,
+        location: &object_store::path::Path,
+        attributes: &PutAttributes,
+        report: Option<ReportRecorder>,
+    ) -> object_store::Result<(object_store::MultipartId, PartWriter<MultiPartUpload>)> {
+        let upload_id = self.create_multipart(location, attributes).await?;
+        let upload = self.multipart_upload(location, &upload_id, report);
+        let window = match self.adaptive_multipart_concurrency {
+            Some((min, max)) => {
+                ConcurrencyWindow::adaptive(min, max, self.timer.clone(), upload.failures.clone())
+            }
+            None => ConcurrencyWindow::fixed(self.multipart_concurrency),
+        };
+
+        let multipart_upload = PartWriter::new(upload, self.multipart_part_size, window);
+
+        Ok((upload_id, multipart_upload))
+    }
+
+    /// Uploads the parts of the multipart upload `upload_id` to `location`.
+    pub(crate) fn multipart_upload(
+        &self,
+        location: &object_store::path::Path,
+        upload_id: &str,
+        report: Option<ReportRecorder>,
+    ) -> MultiPartUpload {
+        MultiPartUpload {
+            bucket: self.bucket.clone(),
+            location: location.to_string(),
+            upload_id: upload_id.to_owned(),
+            client: self.client.clone(),
+            journal: self.journal.clone(),
+            sse: self.sse.clone(),
+            checksum: self.checksum,
+            metrics: self.metrics.clone(),
+            parts: Mutex::default(),
+            report,
+            failures: UploadFailures::default(),
+            events: self.events.clone(),
+            progress: self.progress.clone(),
+            uploaded: AtomicUsize::new(0),
+            completed: AtomicBool::new(false),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3 {
+    async fn abort_multipart(
+        &self,
+        location: &object_store::path::Path,
+        multipart_id: &object_store::MultipartId,
+    ) -> object_store::Result<()> {
+        self.client
+            .abort_multipart_upload()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .upload_id(multipart_id)
+            .send()
+            .await
+            .map_err(|err| self.request_error(location, err))?;
+        Ok(())
+    }
+    async fn copy(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        self.copy_object(from, to, false).await
+    }
+    async fn copy_if_not_exists(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        match self.copy_if_not_exists_strategy() {
+            CopyIfNotExists::HeadThenCopy => {
+                match self.head(to).await {
+                    Ok(_) => {
+                        return Err(object_store::Error::AlreadyExists {
+                            path: to.to_string(),
+                            source: "target object exists".into(),
+                        })
+                    }
+                    Err(err) if is_not_found(&err) => (),
+                    Err(err) => return Err(err),
+                }
+                self.copy_object(from, to, false).await
+            }
+            CopyIfNotExists::Conditional => self.copy_object(from, to, true).await,
+        }
+    }
+    async fn rename(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        self.copy(from, to).await?;
+        self.delete(from).await
+    }
+    async fn rename_if_not_exists(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        self.copy_if_not_exists(from, to).await?;
+        self.delete(from).await
+    }
+    async fn delete(&self, location: &object_store::path::Path) -> object_store::Result<()> {
+        self.client
+            .delete_object()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .send()
+            .await
+            .map_err(|err| self.request_error(location, err))?;
+        self.record(|| Mutation::Delete {
+            location: location.clone(),
+        });
+        Ok(())
+    }
+    fn delete_stream<'a>(
+        &'a self,
+        locations: BoxStream<'a, object_store::Result<object_store::path::Path>>,
+    ) -> BoxStream<'a, object_store::Result<object_store::path::Path>> {
+        self.delete_batched(locations)
+    }
+    async fn get_opts(
+        &self,
+        location: &object_store::path::Path,
+        options: object_store::GetOptions,
+    ) -> object_store::Result<object_store::GetResult> {
+        self.get_with_status(location, options)
+            .await
+            .map(|(result, _)| result)
+    }
+    async fn get_ranges(
+        &self,
+        location: &object_store::path::Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        match &self.persistent_cache {
+            Some(cache) => self.get_persisted_ranges(cache, location, ranges).await,
+            None => self.get_coalesced_ranges(location, ranges).await,
+        }
+    }
+    async fn get_range(
+        &self,
+        location: &object_store::path::Path,
+        range: Range<usize>,
+    ) -> object_store::Result<Bytes> {
+        match &self.persistent_cache {
+            Some(cache) => Ok(self
+                .get_persisted_ranges(cache, location, &[range])
+                .await?
+                .swap_remove(0)),
+            None => {
+                let options = object_store::GetOptions {
+                    range: Some(range.into()),
+                    ..Default::default()
+                };
+                self.get_opts(location, options).await?.bytes().await
+            }
+        }
+    }
+    async fn head(
+        &self,
+        location: &object_store::path::Path,
+    ) -> object_store::Result<object_store::ObjectMeta> {
+        self.head_with_status(location).await.map(|(meta, _)| meta)
+    }
+    fn list(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+    ) -> BoxStream<'_, object_store::Result<object_store::ObjectMeta>> {
+        self.list_objects(prefix, None)
+            .map_ok(|(object, encoding)| stream::iter([object_meta(object, encoding.as_ref())]))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Lists after `offset` with `start-after`, instead of skipping the keys up to it.
+    fn list_with_offset(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+        offset: &object_store::path::Path,
+    ) -> BoxStream<'_, object_store::Result<object_store::ObjectMeta>> {
+        self.list_objects(prefix, Some(offset))
+            .map_ok(|(object, encoding)| stream::iter([object_meta(object, encoding.as_ref())]))
+            .try_flatten()
+            .boxed()
+    }
+
+    async fn list_with_delimiter(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+    ) -> object_store::Result<object_store::ListResult> {
+        // Only children of the prefix, `a/b` must not match `a/bc`
+        let key_prefix = prefix
+            .filter(|prefix| !prefix.as_ref().is_empty())
+            .map(|prefix| format!("{}{}", prefix, object_store::path::DELIMITER));
+        let mut objects = Vec::new();
+        let mut common_prefixes = Vec::new();
+        let mut token = None;
+        loop {
+            let response = self
+                .client
+                .list_objects_v2()
+                .bucket(self.bucket.clone())
+                .set_prefix(key_prefix.clone())
+                .delimiter(object_store::path::DELIMITER)
+                .set_continuation_token(token)
+                .set_encoding_type(self.list_encoding.encoding_type())
+                .send()
+                .await
+                .map_err(|err| {
+                    self.request_error(prefix.map(ToString::to_string).unwrap_or_default(), err)
+                })?;
+            let encoding = response.encoding_type.as_ref();
+            for object in response.contents.unwrap_or_default() {
+                objects.push(object_meta(object, encoding)?);
+            }
+            for prefix in response.common_prefixes.unwrap_or_default() {
+                let prefix = listed_path(prefix.prefix.ok_or(Error::Unknown)?, encoding)?;
+                common_prefixes.push(prefix);
+            }
+            token = response.next_continuation_token;
+            if !response.is_truncated || token.is_none() {
+                break;
+            }
+        }
         Ok(ListResult {
             objects,
-            common_prefixes: response
-                .common_prefixes
-                .and_then(|prefixes| {
-                    prefixes
-                        .into_iter()
-                        .map(|x| x.prefix.map(|y| y.into()))
-                        .collect::<Option<Vec<_>>>()
-                })
-                .unwrap_or(Vec::new()),
+            common_prefixes,
         })
     }
     async fn put_opts(
@@ -308,20 +1033,8 @@ impl ObjectStore for S3 {
         bytes: Bytes,
         opts: PutOptions,
     ) -> object_store::Result<PutResult> {
-        let result = self
-            .client
-            .put_object()
-            .bucket(self.bucket.clone())
-            .key(location.to_string())
-            .body(bytes.into())
-            .tagging(opts.tags.encoded())
-            .send()
+        self.put_object(location, bytes, opts, &PutAttributes::default())
             .await
-            .map_err(Error::from)?;
-        Ok(PutResult {
-            e_tag: result.e_tag,
-            version: result.version_id,
-        })
     }
     async fn put_multipart(
         &self,
@@ -330,29 +1043,158 @@ impl ObjectStore for S3 {
         object_store::MultipartId,
         Box<dyn AsyncWrite + Unpin + Send>,
     )> {
-        let response = self
-            .client
-            .create_multipart_upload()
-            .bucket(self.bucket.clone())
-            .key(location.to_string())
-            .send()
+        self.multipart_writer(location, &PutAttributes::default())
             .await
-            .map_err(Error::from)?;
-
-        let multipart_upload = Box::new(WriteMultiPart::new(
-            MultiPartUpload {
-                bucket: self.bucket.clone(),
-                location: location.to_string(),
-                upload_id: response.upload_id.clone().ok_or(Error::Unknown)?,
-                client: self.client.clone(),
-            },
-            16,
-        ));
+    }
+}
+
+impl S3 {
+    /// Objects of all `ListObjectsV2` pages below `prefix` with the encoding of their keys,
+    /// starting after the key `offset`.
+    pub(crate) fn list_objects(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+        offset: Option<&object_store::path::Path>,
+    ) -> BoxStream<'_, object_store::Result<(Object, Option<EncodingType>)>> {
+        let location = prefix.map(ToString::to_string).unwrap_or_default();
+        // Only children of the prefix, `a/b` must not match `a/bc`
+        let prefix = prefix
+            .filter(|prefix| !prefix.as_ref().is_empty())
+            .map(|prefix| format!("{}{}", prefix, object_store::path::DELIMITER));
+        let offset = offset.map(|offset| offset.to_string());
+        // `None` once the last page was fetched, `Some(None)` before the first page
+        stream::try_unfold(Some(None::<String>), move |token| {
+            let (prefix, location) = (prefix.clone(), location.clone());
+            // `start-after` is ignored once a continuation token is sent
+            let offset = offset
+                .clone()
+                .filter(|_| token.as_ref().is_some_and(Option::is_none));
+            async move {
+                let Some(token) = token else {
+                    return Ok(None);
+                };
+                let response = self
+                    .client
+                    .list_objects_v2()
+                    .bucket(self.bucket.clone())
+                    .set_prefix(prefix)
+                    .set_start_after(offset)
+                    .set_continuation_token(token)
+                    .set_encoding_type(self.list_encoding.encoding_type())
+                    .send()
+                    .await
+                    .map_err(|err| self.request_error(location, err))?;
+                let next = response
+                    .is_truncated()
+                    .then_some(response.next_continuation_token)
+                    .flatten()
+                    .map(Some);
+                let encoding = response.encoding_type;
+                let objects = response
+                    .contents
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |object| Ok((object, encoding.clone())));
+                Ok::<_, object_store::Error>(Some((stream::iter(objects), next)))
+            }
+        })
+        .try_flatten()
+        .boxed()
+    }
+}
 
-        Ok((response.upload_id.ok_or(Error::Unknown)?, multipart_upload))
+/// `Range` header of a [`GetRange`](object_store::GetRange), HTTP ranges are inclusive.
+///
+/// Empty bounded ranges have no inclusive form and are rejected, as `object_store` does.
+pub(crate) fn range_header(range: &object_store::GetRange) -> Result<String, Error> {
+    Ok(match range {
+        object_store::GetRange::Bounded(range) if range.end <= range.start => {
+            return Err(Error::EmptyRange {
+                start: range.start,
+                end: range.end,
+            })
+        }
+        object_store::GetRange::Bounded(range) => {
+            format!("bytes={}-{}", range.start, range.end - 1)
+        }
+        object_store::GetRange::Offset(offset) => format!("bytes={}-", offset),
+        object_store::GetRange::Suffix(length) => format!("bytes=-{}", length),
+    })
+}
+
+/// Byte range a [`GetRange`](object_store::GetRange) selects of an object of `size` bytes.
+pub(crate) fn resolve_range(range: &object_store::GetRange, size: usize) -> Range<usize> {
+    match range {
+        object_store::GetRange::Bounded(range) => range.start.min(size)..range.end.min(size),
+        object_store::GetRange::Offset(offset) => (*offset).min(size)..size,
+        object_store::GetRange::Suffix(length) => size.saturating_sub(*length)..size,
     }
 }
 
+/// Whether `options` read the latest version without conditions, so a cached copy may serve it.
+pub(crate) fn is_unconditional(options: &object_store::GetOptions) -> bool {
+    options.if_match.is_none()
+        && options.if_none_match.is_none()
+        && options.if_modified_since.is_none()
+        && options.if_unmodified_since.is_none()
+        && options.version.is_none()
+}
+
+/// Parses a `Content-Range: bytes <first>-<last>/<size>` header into the served range and the
+/// object size.
+pub(crate) fn parse_content_range(content_range: &str) -> Result<(Range<usize>, usize), Error> {
+    let (range, size) = content_range
+        .trim_start_matches("bytes")
+        .trim()
+        .split_once('/')
+        .ok_or(Error::Unknown)?;
+    let (first, last) = range.split_once('-').ok_or(Error::Unknown)?;
+    let (first, last): (usize, usize) = (first.parse()?, last.parse()?);
+    Ok((first..last + 1, size.parse()?))
+}
+
+/// Current time, taken from the JS clock on wasm.
+pub(crate) fn now() -> DateTime<Utc> {
+    date_time(environment::system_time())
+}
+
+pub(crate) fn date_time(time: std::time::SystemTime) -> DateTime<Utc> {
+    let since_epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    DateTime::from_timestamp_millis(since_epoch.as_millis() as i64).unwrap_or_default()
+}
+
+pub(crate) fn smithy_date_time(date_time: DateTime<Utc>) -> aws_smithy_types::DateTime {
+    aws_smithy_types::DateTime::from_millis(date_time.timestamp_millis())
+}
+
+/// Metadata of a listed object, decoding its key according to the listing's `encoding`.
+pub(crate) fn object_meta(
+    object: Object,
+    encoding: Option<&EncodingType>,
+) -> object_store::Result<ObjectMeta> {
+    let last_modified = DateTime::from_timestamp_millis(
+        object
+            .last_modified()
+            .ok_or(Error::Unknown)?
+            .to_millis()
+            .map_err(Error::from)?,
+    )
+    .unwrap();
+    let key = object.key.ok_or(object_store::Error::Generic {
+        store: "aws",
+        source: Box::new(Error::Unknown),
+    })?;
+    Ok(ObjectMeta {
+        location: listed_path(key, encoding)?,
+        last_modified,
+        size: object.size as usize,
+        e_tag: object.e_tag,
+        version: None,
+    })
+}
+
 impl Display for S3 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self.client.config())
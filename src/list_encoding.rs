@@ -0,0 +1,37 @@
+use aws_sdk_s3::types::EncodingType;
+use percent_encoding::percent_decode_str;
+
+use crate::error::Error;
+
+/// How keys are encoded in listing responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListEncoding {
+    /// Keys are returned verbatim in the XML response.
+    #[default]
+    Plain,
+    /// Requests `encoding-type=url` and decodes the returned keys and prefixes, for keys with
+    /// characters that aren't valid in XML or that some endpoints fail to escape.
+    Url,
+}
+
+impl ListEncoding {
+    pub(crate) fn encoding_type(self) -> Option<EncodingType> {
+        match self {
+            ListEncoding::Plain => None,
+            ListEncoding::Url => Some(EncodingType::Url),
+        }
+    }
+}
+
+/// Decodes a key of a listing response sent with `encoding`.
+///
+/// Endpoints ignoring the requested encoding type don't echo it, so their keys are kept as is.
+pub(crate) fn decode_key(key: String, encoding: Option<&EncodingType>) -> Result<String, Error> {
+    match encoding {
+        Some(EncodingType::Url) => Ok(percent_decode_str(&key.replace('+', " "))
+            .decode_utf8()
+            .map_err(|_| Error::InvalidKey(key.clone()))?
+            .into_owned()),
+        _ => Ok(key),
+    }
+}
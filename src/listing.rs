@@ -0,0 +1,285 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+
+use futures::{
+    future::BoxFuture,
+    stream::{self, BoxStream, FuturesUnordered},
+    Sink, SinkExt, StreamExt, TryStreamExt,
+};
+use object_store::{path::Path, ObjectMeta, ObjectStore};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{
+    error::Error,
+    key_encoding::{key_to_path, listed_path},
+    object_meta, S3,
+};
+
+/// Serializable form of an [`ObjectMeta`], one line of the NDJSON listing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListingEntry {
+    pub location: String,
+    pub last_modified: DateTime<Utc>,
+    pub size: usize,
+    pub e_tag: Option<String>,
+    pub version: Option<String>,
+}
+
+impl From<ObjectMeta> for ListingEntry {
+    fn from(value: ObjectMeta) -> Self {
+        ListingEntry {
+            location: value.location.to_string(),
+            last_modified: value.last_modified,
+            size: value.size,
+            e_tag: value.e_tag,
+            version: value.version,
+        }
+    }
+}
+
+impl From<ListingEntry> for ObjectMeta {
+    fn from(value: ListingEntry) -> Self {
+        ObjectMeta {
+            location: key_to_path(&value.location),
+            last_modified: value.last_modified,
+            size: value.size,
+            e_tag: value.e_tag,
+            version: value.version,
+        }
+    }
+}
+
+/// One page of a directory style listing, see [`S3::list_dir_page`].
+#[derive(Debug, Clone)]
+pub struct DirPage {
+    /// Common prefixes directly below the listed prefix.
+    pub folders: Vec<Path>,
+    /// Objects directly below the listed prefix.
+    pub files: Vec<ObjectMeta>,
+    /// Token to pass to the next [`S3::list_dir_page`] call, `None` on the last page.
+    pub next_token: Option<String>,
+}
+
+/// Entry yielded by [`S3::walk`].
+#[derive(Debug, Clone)]
+pub enum WalkEntry {
+    Folder { path: Path, depth: usize },
+    File { meta: ObjectMeta, depth: usize },
+}
+
+/// Folders, files and depth of a listed directory.
+type ListedDir = (Vec<Path>, Vec<ObjectMeta>, usize);
+
+struct Walk<'a> {
+    pending: VecDeque<(Option<Path>, usize)>,
+    in_flight: FuturesUnordered<BoxFuture<'a, object_store::Result<ListedDir>>>,
+    ready: VecDeque<object_store::Result<WalkEntry>>,
+}
+
+impl ListingEntry {
+    fn to_line(&self) -> Result<Bytes, Error> {
+        let mut line = serde_json::to_vec(self)?;
+        line.push(b'\n');
+        Ok(line.into())
+    }
+}
+
+impl S3 {
+    /// Streams the listing of `prefix` as newline delimited JSON [`ListingEntry`]s into `sink`.
+    ///
+    /// Every object is written as soon as it is listed, so memory use doesn't grow with the
+    /// number of objects. Returns the number of written lines.
+    pub async fn list_ndjson<S>(
+        &self,
+        prefix: Option<&Path>,
+        mut sink: S,
+    ) -> object_store::Result<usize>
+    where
+        S: Sink<Bytes> + Unpin,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let mut objects = self.list(prefix);
+        let mut lines = 0;
+        while let Some(object) = objects.try_next().await? {
+            let line = ListingEntry::from(object).to_line()?;
+            sink.send(line)
+                .await
+                .map_err(|err| object_store::Error::Generic {
+                    store: "S3",
+                    source: Box::new(err),
+                })?;
+            lines += 1;
+        }
+        sink.flush()
+            .await
+            .map_err(|err| object_store::Error::Generic {
+                store: "S3",
+                source: Box::new(err),
+            })?;
+        Ok(lines)
+    }
+
+    /// Like [`S3::list_ndjson`] but writes into a JS `WritableStream`, respecting its backpressure.
+    ///
+    /// The stream is not closed, its writer lock is released once the listing is written.
+    pub async fn list_ndjson_to_writable_stream(
+        &self,
+        prefix: Option<&Path>,
+        stream: &web_sys::WritableStream,
+    ) -> object_store::Result<usize> {
+        let writer = stream.get_writer().map_err(Error::from)?;
+        let mut objects = self.list(prefix);
+        let mut lines = 0;
+        let result = async {
+            while let Some(object) = objects.try_next().await? {
+                let line = ListingEntry::from(object).to_line()?;
+                JsFuture::from(writer.ready()).await.map_err(Error::from)?;
+                let chunk = js_sys::Uint8Array::from(line.as_ref());
+                JsFuture::from(writer.write_with_chunk(&chunk))
+                    .await
+                    .map_err(Error::from)?;
+                lines += 1;
+            }
+            Ok(lines)
+        }
+        .await;
+        writer.release_lock();
+        result
+    }
+
+    /// Lists only the keys below `prefix`, after the key `offset` if given.
+    ///
+    /// Owners are never requested and nothing but the keys is decoded and kept, which keeps
+    /// very large listings small. S3 still returns sizes, ETags and storage classes.
+    pub fn list_keys(
+        &self,
+        prefix: Option<&Path>,
+        offset: Option<&Path>,
+    ) -> BoxStream<'_, object_store::Result<Path>> {
+        self.list_objects(prefix, offset)
+            .and_then(|(object, encoding)| async move {
+                let key = object.key.ok_or(Error::Unknown)?;
+                Ok(listed_path(key, encoding.as_ref())?)
+            })
+            .boxed()
+    }
+
+    /// Lists one page of the "directory" `prefix`, returning folders and files separately.
+    ///
+    /// Start with `token = None` and pass the returned [`DirPage::next_token`] to fetch the
+    /// following page. `page_size` bounds the number of folders plus files per page.
+    pub async fn list_dir_page(
+        &self,
+        prefix: Option<&Path>,
+        token: Option<String>,
+        page_size: usize,
+    ) -> object_store::Result<DirPage> {
+        let response = self
+            .client
+            .list_objects_v2()
+            .bucket(self.bucket.clone())
+            .delimiter("/")
+            .set_prefix(
+                prefix
+                    .filter(|prefix| !prefix.as_ref().is_empty())
+                    .map(|prefix| format!("{}/", prefix)),
+            )
+            .set_continuation_token(token)
+            .set_encoding_type(self.list_encoding.encoding_type())
+            .max_keys(page_size.min(i32::MAX as usize) as i32)
+            .send()
+            .await
+            .map_err(|err| {
+                self.request_error(prefix.map(ToString::to_string).unwrap_or_default(), err)
+            })?;
+        let encoding = response.encoding_type.as_ref();
+        let files = response
+            .contents
+            .unwrap_or_default()
+            .into_iter()
+            .map(|object| object_meta(object, encoding))
+            .collect::<object_store::Result<Vec<_>>>()?;
+        let folders = response
+            .common_prefixes
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|prefix| prefix.prefix)
+            .map(|prefix| listed_path(prefix, encoding))
+            .collect::<Result<_, _>>()?;
+        Ok(DirPage {
+            folders,
+            files,
+            next_token: response.next_continuation_token,
+        })
+    }
+
+    /// Recursively walks the "directories" below `prefix`.
+    ///
+    /// Entries directly below `prefix` have depth `0`, folders are descended into as long as
+    /// their depth is smaller than `max_depth`. Up to `concurrency` directories are listed in
+    /// parallel, so entries of different directories may interleave.
+    pub fn walk(
+        &self,
+        prefix: Option<&Path>,
+        max_depth: usize,
+        concurrency: usize,
+    ) -> BoxStream<'_, object_store::Result<WalkEntry>> {
+        let walk = Walk {
+            pending: VecDeque::from([(prefix.cloned(), 0)]),
+            in_flight: FuturesUnordered::new(),
+            ready: VecDeque::new(),
+        };
+        Box::pin(stream::unfold(walk, move |mut walk| async move {
+            loop {
+                if let Some(entry) = walk.ready.pop_front() {
+                    return Some((entry, walk));
+                }
+                while walk.in_flight.len() < concurrency.max(1) {
+                    let Some((prefix, depth)) = walk.pending.pop_front() else {
+                        break;
+                    };
+                    walk.in_flight.push(Box::pin(async move {
+                        let (folders, files) = self.list_dir(prefix.as_ref()).await?;
+                        Ok((folders, files, depth))
+                    }));
+                }
+                match walk.in_flight.next().await? {
+                    Ok((folders, files, depth)) => {
+                        for path in folders {
+                            if depth < max_depth {
+                                walk.pending.push_back((Some(path.clone()), depth + 1));
+                            }
+                            walk.ready.push_back(Ok(WalkEntry::Folder { path, depth }));
+                        }
+                        walk.ready.extend(
+                            files
+                                .into_iter()
+                                .map(|meta| Ok(WalkEntry::File { meta, depth })),
+                        );
+                    }
+                    Err(err) => walk.ready.push_back(Err(err)),
+                }
+            }
+        }))
+    }
+
+    async fn list_dir(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<(Vec<Path>, Vec<ObjectMeta>)> {
+        let mut folders = Vec::new();
+        let mut files = Vec::new();
+        let mut token = None;
+        loop {
+            let page = self.list_dir_page(prefix, token, 1000).await?;
+            folders.extend(page.folders);
+            files.extend(page.files);
+            token = page.next_token;
+            if token.is_none() {
+                return Ok((folders, files));
+            }
+        }
+    }
+}
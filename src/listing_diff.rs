@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use object_store::{path::Path, ObjectStore};
+use serde::{Deserialize, Serialize};
+
+use crate::{listing::ListingEntry, S3};
+
+/// Serializable listing of the objects below a prefix, see [`S3::listing_snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListingSnapshot {
+    pub prefix: Option<String>,
+    pub captured: DateTime<Utc>,
+    /// Entries sorted by location.
+    pub entries: Vec<ListingEntry>,
+}
+
+/// Changes of a listing since a [`ListingSnapshot`], see [`S3::diff_listing`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingDiff {
+    /// Listed now but not in the snapshot.
+    pub added: Vec<ListingEntry>,
+    /// In the snapshot but not listed anymore, the snapshot's entry.
+    pub removed: Vec<ListingEntry>,
+    /// Listed with a different size, ETag, version or modification time, the current entry.
+    pub modified: Vec<ListingEntry>,
+    /// Current listing, to diff against on the next refresh.
+    pub snapshot: ListingSnapshot,
+}
+
+impl ListingDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl ListingSnapshot {
+    /// Entries added, removed and modified in `current` relative to `self`.
+    pub fn diff(&self, current: ListingSnapshot) -> ListingDiff {
+        let mut previous = self
+            .entries
+            .iter()
+            .map(|entry| (entry.location.as_str(), entry))
+            .collect::<BTreeMap<_, _>>();
+        let mut added = Vec::new();
+        let mut modified = Vec::new();
+        for entry in &current.entries {
+            match previous.remove(entry.location.as_str()) {
+                None => added.push(entry.clone()),
+                Some(previous) if previous != entry => modified.push(entry.clone()),
+                Some(_) => (),
+            }
+        }
+        ListingDiff {
+            added,
+            removed: previous.into_values().cloned().collect(),
+            modified,
+            snapshot: current,
+        }
+    }
+}
+
+impl S3 {
+    /// Lists the objects below `prefix` into a snapshot.
+    pub async fn listing_snapshot(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<ListingSnapshot> {
+        let captured = self.now();
+        let mut entries = self
+            .list(prefix)
+            .map_ok(ListingEntry::from)
+            .try_collect::<Vec<_>>()
+            .await?;
+        entries.sort_by(|a, b| a.location.cmp(&b.location));
+        Ok(ListingSnapshot {
+            prefix: prefix.map(ToString::to_string),
+            captured,
+            entries,
+        })
+    }
+
+    /// Lists the objects below `prefix` and compares them with `previous`, a snapshot of the same
+    /// prefix.
+    pub async fn diff_listing(
+        &self,
+        prefix: Option<&Path>,
+        previous: &ListingSnapshot,
+    ) -> object_store::Result<ListingDiff> {
+        Ok(previous.diff(self.listing_snapshot(prefix).await?))
+    }
+}
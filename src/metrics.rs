@@ -0,0 +1,247 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use aws_sdk_s3::primitives::SdkBody;
+use aws_smithy_http::body::BoxBody;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+use crate::{error::Error, idb::Database, instrument::StreamTiming, tiered::Tier, S3};
+
+/// IndexedDB object store holding persisted [`StoreMetrics`].
+pub(crate) const METRICS_STORE: &str = "metrics";
+
+/// Weight of a new sample in the exponentially weighted network estimates.
+const SMOOTHING: f64 = 0.2;
+
+/// Responses smaller than this are dominated by latency and not used to estimate throughput.
+const MIN_THROUGHPUT_SAMPLE: u64 = 64 * 1024;
+
+/// Request counters and network estimates collected by a store.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StoreMetrics {
+    pub requests: u64,
+    /// Requests that failed on the network level or returned a non 2xx/3xx status.
+    pub failed_requests: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub network: NetworkProfile,
+    #[serde(default)]
+    pub streams: StreamProfile,
+    #[serde(default)]
+    pub tiers: TierCounts,
+    /// Completed multipart uploads whose checksum or ETag didn't match their parts.
+    #[serde(default)]
+    pub checksum_mismatches: u64,
+}
+
+/// Reads served by each tier of a [`TieredStore`](crate::tiered::TieredStore).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TierCounts {
+    pub cache: u64,
+    pub cdn: u64,
+    pub origin: u64,
+}
+
+/// Smoothed estimates of the connection to the endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    /// Estimated request latency in milliseconds.
+    pub latency_ms: Option<f64>,
+    /// Estimated download throughput in bytes per second.
+    pub throughput_bytes_per_sec: Option<f64>,
+    /// Number of samples the estimates are based on.
+    pub samples: u64,
+}
+
+impl NetworkProfile {
+    fn update(&mut self, elapsed: Duration, bytes_received: u64) {
+        let millis = elapsed.as_secs_f64() * 1000.0;
+        self.latency_ms = Some(smooth(self.latency_ms, millis));
+        if bytes_received >= MIN_THROUGHPUT_SAMPLE && elapsed > Duration::ZERO {
+            let throughput = bytes_received as f64 / elapsed.as_secs_f64();
+            self.throughput_bytes_per_sec = Some(smooth(self.throughput_bytes_per_sec, throughput));
+        }
+        self.samples += 1;
+    }
+}
+
+/// Aggregated timing of GET payload streams, see [`StreamTiming`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamProfile {
+    pub streams: u64,
+    /// Estimated time to first byte in milliseconds.
+    pub time_to_first_byte_ms: Option<f64>,
+    /// Total time spent waiting for chunks after the first one, in milliseconds.
+    pub source_wait_ms: f64,
+    /// Total time chunks waited for the consumer, in milliseconds.
+    pub consumer_wait_ms: f64,
+}
+
+impl StreamProfile {
+    fn update(&mut self, timing: &StreamTiming) {
+        if let Some(time_to_first_byte) = timing.time_to_first_byte {
+            self.time_to_first_byte_ms = Some(smooth(
+                self.time_to_first_byte_ms,
+                time_to_first_byte.as_secs_f64() * 1000.0,
+            ));
+        }
+        self.source_wait_ms += timing.source_wait.as_secs_f64() * 1000.0;
+        self.consumer_wait_ms += timing.consumer_wait.as_secs_f64() * 1000.0;
+        self.streams += 1;
+    }
+}
+
+fn smooth(estimate: Option<f64>, sample: f64) -> f64 {
+    match estimate {
+        Some(estimate) => estimate + SMOOTHING * (sample - estimate),
+        None => sample,
+    }
+}
+
+/// Shared, cheaply clonable handle the http connector records into.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MetricsRecorder {
+    metrics: Arc<Mutex<StoreMetrics>>,
+}
+
+impl MetricsRecorder {
+    pub(crate) fn record(
+        &self,
+        elapsed: Duration,
+        bytes_sent: u64,
+        bytes_received: u64,
+        succeeded: bool,
+    ) {
+        let mut metrics = self.metrics.lock();
+        metrics.requests += 1;
+        if !succeeded {
+            metrics.failed_requests += 1;
+        }
+        metrics.bytes_sent += bytes_sent;
+        metrics.bytes_received += bytes_received;
+        metrics.network.update(elapsed, bytes_received);
+    }
+
+    /// Adds bytes of a streamed response body, which arrive after the request was recorded.
+    pub(crate) fn record_received(&self, bytes: u64) {
+        self.metrics.lock().bytes_received += bytes;
+    }
+
+    pub(crate) fn record_stream(&self, timing: &StreamTiming) {
+        self.metrics.lock().streams.update(timing);
+    }
+
+    pub(crate) fn record_checksum_mismatch(&self) {
+        self.metrics.lock().checksum_mismatches += 1;
+    }
+
+    pub(crate) fn record_tier(&self, tier: Tier) {
+        let mut metrics = self.metrics.lock();
+        match tier {
+            Tier::Cache => metrics.tiers.cache += 1,
+            Tier::Cdn => metrics.tiers.cdn += 1,
+            Tier::Origin => metrics.tiers.origin += 1,
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> StoreMetrics {
+        self.metrics.lock().clone()
+    }
+
+    pub(crate) fn restore(&self, metrics: StoreMetrics) {
+        *self.metrics.lock() = metrics;
+    }
+}
+
+impl S3 {
+    /// Snapshot of the metrics collected since the store was built or last restored.
+    pub fn metrics(&self) -> StoreMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// Replaces the collected metrics, e.g. with a snapshot of a previous session.
+    pub fn restore_metrics(&self, metrics: StoreMetrics) {
+        self.metrics.restore(metrics)
+    }
+
+    /// Saves the current metrics in IndexedDB under `key`.
+    pub async fn persist_metrics(&self, key: &str) -> object_store::Result<()> {
+        let value = serde_json::to_string(&self.metrics()).map_err(Error::from)?;
+        let db = Database::open().await?;
+        db.put(METRICS_STORE, key, &JsValue::from_str(&value))
+            .await?;
+        Ok(())
+    }
+
+    /// Restores metrics previously saved with [`S3::persist_metrics`].
+    ///
+    /// Returns `false` and keeps the current metrics if nothing was saved under `key`.
+    pub async fn restore_persisted_metrics(&self, key: &str) -> object_store::Result<bool> {
+        let db = Database::open().await?;
+        let Some(value) = db
+            .get(METRICS_STORE, key)
+            .await?
+            .and_then(|x| x.as_string())
+        else {
+            return Ok(false);
+        };
+        let metrics = serde_json::from_str(&value).map_err(Error::from)?;
+        self.restore_metrics(metrics);
+        Ok(true)
+    }
+
+    /// Removes metrics previously saved with [`S3::persist_metrics`].
+    pub async fn clear_persisted_metrics(&self, key: &str) -> object_store::Result<()> {
+        let db = Database::open().await?;
+        db.delete(METRICS_STORE, key).await?;
+        Ok(())
+    }
+}
+
+/// Counts the bytes of a streamed body into the store's metrics as they arrive.
+pub(crate) fn counted(body: SdkBody, metrics: MetricsRecorder) -> SdkBody {
+    SdkBody::from_dyn(BoxBody::new(CountedBody { body, metrics }))
+}
+
+struct CountedBody {
+    body: SdkBody,
+    metrics: MetricsRecorder,
+}
+
+impl http_body::Body for CountedBody {
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let poll = Pin::new(&mut self.body).poll_data(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &poll {
+            self.metrics.record_received(chunk.len() as u64);
+        }
+        poll
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.body).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.body.size_hint()
+    }
+}
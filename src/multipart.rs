@@ -1,67 +1,568 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use aws_sdk_s3::{
+    config::{
+        interceptors::FinalizerInterceptorContextRef, ConfigBag, Interceptor, RuntimeComponents,
+    },
+    error::{BoxError, SdkError},
     primitives::ByteStream,
     types::{CompletedMultipartUpload, CompletedPart},
     Client,
 };
-use object_store::multipart::{PartId, PutPart};
+use futures::{future::BoxFuture, stream::FuturesUnordered, StreamExt};
+use object_store::{
+    multipart::{PartId, PutPart},
+    path::Path,
+    ObjectStore,
+};
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+
+use crate::{
+    checksum::{completed_part, verify_completion, with_checksum, ChecksumAlgorithm},
+    encryption::SseConfig,
+    environment::{spawn, Timer},
+    error::Error,
+    etag::etag,
+    events::{Direction, TransferEvent, TransferEvents},
+    journal::{Journal, Mutation},
+    key_encoding::key_to_path,
+    metrics::MetricsRecorder,
+    progress::{Operation, Progress, ProgressListener},
+    upload_report::ReportRecorder,
+    S3,
+};
+
+/// Default part size of multipart uploads.
+pub(crate) const DEFAULT_PART_SIZE: usize = 10 * 1024 * 1024;
+
+/// Default number of parts uploaded concurrently by `put_multipart`.
+pub(crate) const DEFAULT_CONCURRENCY: usize = 16;
 
-use crate::error::Error;
+/// Relative throughput gain of a round that lets an adaptive window grow by another part.
+const RAMP_UP_GAIN: f64 = 0.1;
+
+/// Relative throughput loss of a round that shrinks an adaptive window by a part.
+const BACK_OFF_LOSS: f64 = 0.25;
+
+/// Aborts a multipart upload if the future driving it is dropped before the upload completed or
+/// failed, so cancelled uploads don't leave their parts behind. The abort is sent from a task of
+/// its own since the dropped future can't await it.
+pub(crate) struct AbortOnDrop {
+    store: Option<S3>,
+    location: Path,
+    upload_id: String,
+}
+
+impl AbortOnDrop {
+    pub(crate) fn new(store: &S3, location: &Path, upload_id: &str) -> Self {
+        Self {
+            store: Some(store.clone()),
+            location: location.clone(),
+            upload_id: upload_id.to_owned(),
+        }
+    }
+
+    /// The upload completed, there's nothing to abort.
+    pub(crate) fn disarm(mut self) {
+        self.store = None;
+    }
+
+    /// Aborts the failed upload, failures to abort are ignored.
+    pub(crate) async fn abort(mut self) {
+        if let Some(store) = self.store.take() {
+            let _ = store.abort_multipart(&self.location, &self.upload_id).await;
+        }
+    }
+}
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        let Some(store) = self.store.take() else {
+            return;
+        };
+        let location = std::mem::take(&mut self.location);
+        let upload_id = std::mem::take(&mut self.upload_id);
+        spawn(async move {
+            let _ = store.abort_multipart(&location, &upload_id).await;
+        });
+    }
+}
+
+/// Counts the failed attempts of the requests of a single multipart upload.
+///
+/// Added as an interceptor to the requests uploading and completing the parts, so that retried
+/// attempts count as well while requests of other operations of the store don't.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UploadFailures(Arc<FailureCounts>);
+
+#[derive(Debug, Default)]
+struct FailureCounts {
+    failed: AtomicU64,
+    /// Attempts the server throttled or failed with a 5xx status, including `503 SlowDown`.
+    overloaded: AtomicU64,
+}
+
+impl UploadFailures {
+    /// Failed attempts so far.
+    pub(crate) fn failed(&self) -> u64 {
+        self.0.failed.load(Ordering::Relaxed)
+    }
+
+    /// Throttled attempts and server errors so far.
+    pub(crate) fn overloaded(&self) -> u64 {
+        self.0.overloaded.load(Ordering::Relaxed)
+    }
+}
+
+impl Interceptor for UploadFailures {
+    fn name(&self) -> &'static str {
+        "UploadFailures"
+    }
+
+    fn read_after_attempt(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let status = context.response().map(|response| response.status());
+        if status.is_some_and(|status| status.is_success()) {
+            return Ok(());
+        }
+        self.0.failed.fetch_add(1, Ordering::Relaxed);
+        if status.is_some_and(|status| status.as_u16() == 429 || status.is_server_error()) {
+            self.0.overloaded.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
 
 pub(crate) struct MultiPartUpload {
     pub(crate) bucket: String,
     pub(crate) location: String,
     pub(crate) upload_id: String,
     pub(crate) client: Arc<Client>,
+    pub(crate) journal: Option<Journal>,
+    pub(crate) sse: Option<SseConfig>,
+    pub(crate) checksum: Option<ChecksumAlgorithm>,
+    pub(crate) metrics: MetricsRecorder,
+    /// Parts to complete the upload with by part index, with their checksums.
+    pub(crate) parts: Mutex<HashMap<usize, CompletedPart>>,
+    pub(crate) report: Option<ReportRecorder>,
+    pub(crate) failures: UploadFailures,
+    pub(crate) events: TransferEvents,
+    pub(crate) progress: Option<Arc<dyn ProgressListener>>,
+    /// Bytes of the parts uploaded so far.
+    pub(crate) uploaded: AtomicUsize,
+    /// Whether `CompleteMultipartUpload` succeeded, uploads dropped before are aborted.
+    pub(crate) completed: AtomicBool,
+}
+
+impl MultiPartUpload {
+    fn finished(&self, ok: bool) {
+        self.events.emit(|| {
+            TransferEvent::finished(
+                &key_to_path(&self.location),
+                Direction::Upload,
+                self.uploaded.load(Ordering::Relaxed),
+                ok,
+            )
+        });
+    }
+}
+
+impl Drop for MultiPartUpload {
+    fn drop(&mut self) {
+        if self.completed.load(Ordering::Relaxed) {
+            return;
+        }
+        // The writer was dropped without completing the upload, or completing it failed
+        let request = self
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.location)
+            .upload_id(&self.upload_id);
+        spawn(async move {
+            let _ = request.send().await;
+        });
+    }
 }
 
 #[async_trait]
 impl PutPart for MultiPartUpload {
     async fn put_part(&self, buf: Vec<u8>, part_idx: usize) -> Result<PartId, object_store::Error> {
         let part = part_idx + 1;
+        let size = buf.len();
+        let md5 = self.report.as_ref().map(|_| etag(&buf));
 
-        let response = self
-            .client
-            .upload_part()
+        let request = with_checksum(self.client.upload_part(), self.checksum, &buf)
             .bucket(&self.bucket)
             .key(&self.location)
             .upload_id(&self.upload_id)
             .part_number(part as i32)
-            .body(ByteStream::from(buf))
-            .send()
-            .await
-            .map_err(Error::from)?;
+            .body(ByteStream::from(buf));
+        let request = match &self.sse {
+            Some(sse) => sse.upload_part(request),
+            None => request,
+        };
+        let response = match request.customize().await {
+            Ok(request) => request.interceptor(self.failures.clone()).send().await,
+            Err(err) => Err(SdkError::construction_failure(err)),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => {
+                self.finished(false);
+                return Err(Error::from(err)
+                    .request(&self.bucket, &self.location)
+                    .into());
+            }
+        };
+        let e_tag = response.e_tag().ok_or(Error::Unknown)?;
+        let completed = completed_part(part as i32, Some(e_tag), self.checksum, &response)?;
+        self.parts.lock().insert(part_idx, completed);
+        let uploaded = self.uploaded.fetch_add(size, Ordering::Relaxed) + size;
+        self.events.emit(|| TransferEvent::Progress {
+            location: self.location.clone(),
+            direction: Direction::Upload,
+            bytes: uploaded,
+        });
+        if let Some(listener) = &self.progress {
+            listener.progress(&Progress {
+                operation: Operation::MultipartUpload,
+                location: &key_to_path(&self.location),
+                bytes: uploaded,
+                total: None,
+            });
+        }
+        if let (Some(report), Some(md5)) = (&self.report, md5) {
+            report.part(part, size, md5, e_tag);
+        }
 
         Ok(PartId {
-            content_id: response
-                .e_tag()
-                .map(|x| x.to_string())
-                .ok_or(Error::Unknown)?,
+            content_id: e_tag.to_string(),
         })
     }
 
     async fn complete(&self, completed_parts: Vec<PartId>) -> Result<(), object_store::Error> {
-        let upload = CompletedMultipartUpload::builder().set_parts(Some(
+        self.complete_parts(completed_parts).await.map(|_| ())
+    }
+}
+
+impl MultiPartUpload {
+    /// Completes the upload with `completed_parts`, returning the ETag and version of the object.
+    pub(crate) async fn complete_parts(
+        &self,
+        completed_parts: Vec<PartId>,
+    ) -> Result<(Option<String>, Option<String>), object_store::Error> {
+        // Parts are passed in upload order, their content id is the ETag
+        let parts = {
+            let mut uploaded = self.parts.lock();
             completed_parts
                 .into_iter()
-                .map(|x| {
-                    Ok(CompletedPart::builder()
-                        .part_number(x.content_id.parse()?)
-                        .build())
+                .enumerate()
+                .map(|(idx, part)| {
+                    uploaded.remove(&idx).unwrap_or_else(|| {
+                        CompletedPart::builder()
+                            .part_number(idx as i32 + 1)
+                            .e_tag(part.content_id)
+                            .build()
+                    })
                 })
-                .collect::<Result<Vec<_>, Error>>()?,
-        ));
-        self.client
+                .collect::<Vec<_>>()
+        };
+        let upload = CompletedMultipartUpload::builder().set_parts(Some(parts.clone()));
+        let request = self
+            .client
             .complete_multipart_upload()
             .bucket(&self.bucket)
             .key(&self.location)
             .upload_id(&self.upload_id)
-            .multipart_upload(upload.build())
-            .send()
-            .await
-            .map_err(Error::from)?;
+            .multipart_upload(upload.build());
+        let response = match request.customize().await {
+            Ok(request) => request.interceptor(self.failures.clone()).send().await,
+            Err(err) => Err(SdkError::construction_failure(err)),
+        };
+        self.finished(response.is_ok());
+        self.completed.store(response.is_ok(), Ordering::Relaxed);
+        let response =
+            response.map_err(|err| Error::from(err).request(&self.bucket, &self.location))?;
+        let content_etags = matches!(self.sse, None | Some(SseConfig::S3));
+        verify_completion(
+            &self.location,
+            &parts,
+            self.checksum,
+            content_etags,
+            &response,
+            &self.metrics,
+        );
+        if let Some(report) = &self.report {
+            report.complete(
+                response.e_tag.clone(),
+                response.version_id.clone(),
+                self.failures.failed(),
+            );
+        }
+        if let Some(journal) = &self.journal {
+            journal.record(Mutation::Put {
+                location: key_to_path(&self.location),
+                e_tag: response.e_tag.clone(),
+                version: response.version_id.clone(),
+            });
+        }
+        Ok((response.e_tag, response.version_id))
+    }
+}
+
+/// Number of parts a [`PartWriter`] keeps in flight.
+///
+/// A fixed window never changes. An adaptive window starts at `min` parts and is adjusted after
+/// every round of `limit` completed parts: it grows by one part while the aggregate throughput of
+/// a round improves by at least [`RAMP_UP_GAIN`] on the previous one, holds while it stays level
+/// and shrinks by one part once it drops by [`BACK_OFF_LOSS`]. Any request of the upload being
+/// throttled or failing with a server error in the meantime, including attempts that were retried
+/// successfully, halves the window.
+pub(crate) struct ConcurrencyWindow {
+    min: usize,
+    max: usize,
+    limit: usize,
+    adaptive: Option<Adaptive>,
+}
+
+struct Adaptive {
+    timer: Arc<dyn Timer>,
+    failures: UploadFailures,
+    overloaded: u64,
+    round_started: Duration,
+    round_bytes: u64,
+    round_parts: usize,
+    throughput: Option<f64>,
+}
+
+impl ConcurrencyWindow {
+    pub(crate) fn fixed(parts: usize) -> Self {
+        let parts = parts.max(1);
+        Self {
+            min: parts,
+            max: parts,
+            limit: parts,
+            adaptive: None,
+        }
+    }
+
+    pub(crate) fn adaptive(
+        min: usize,
+        max: usize,
+        timer: Arc<dyn Timer>,
+        failures: UploadFailures,
+    ) -> Self {
+        let min = min.max(1);
+        let overloaded = failures.overloaded();
+        Self {
+            min,
+            max: max.max(min),
+            limit: min,
+            adaptive: Some(Adaptive {
+                round_started: timer.now(),
+                timer,
+                failures,
+                overloaded,
+                round_bytes: 0,
+                round_parts: 0,
+                throughput: None,
+            }),
+        }
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Records a completed part of `bytes` bytes and adjusts the window.
+    fn record(&mut self, bytes: usize) {
+        let Some(adaptive) = &mut self.adaptive else {
+            return;
+        };
+        let now = adaptive.timer.now();
+        let overloaded = adaptive.failures.overloaded();
+        if overloaded > adaptive.overloaded {
+            adaptive.overloaded = overloaded;
+            self.limit = (self.limit / 2).max(self.min);
+            adaptive.restart_round(now);
+            // Throughput measured at the old window isn't comparable anymore
+            adaptive.throughput = None;
+            return;
+        }
+        adaptive.round_bytes += bytes as u64;
+        adaptive.round_parts += 1;
+        if adaptive.round_parts < self.limit {
+            return;
+        }
+        let elapsed = now.saturating_sub(adaptive.round_started).as_secs_f64();
+        if elapsed > 0.0 {
+            let throughput = adaptive.round_bytes as f64 / elapsed;
+            match adaptive.throughput {
+                Some(previous) if throughput < previous * (1.0 - BACK_OFF_LOSS) => {
+                    self.limit = (self.limit - 1).max(self.min);
+                }
+                Some(previous) if throughput < previous * (1.0 + RAMP_UP_GAIN) => (),
+                _ => self.limit = (self.limit + 1).min(self.max),
+            }
+            adaptive.throughput = Some(throughput);
+        }
+        adaptive.restart_round(now);
+    }
+}
+
+impl Adaptive {
+    fn restart_round(&mut self, now: Duration) {
+        self.round_started = now;
+        self.round_bytes = 0;
+        self.round_parts = 0;
+    }
+}
+
+type PartTask = BoxFuture<'static, Result<(usize, usize, PartId), io::Error>>;
+
+/// Like [`WriteMultiPart`](object_store::multipart::WriteMultiPart) with a configurable part size.
+///
+/// Buffers `part_size` bytes per part and uploads as many parts at once as the
+/// [`ConcurrencyWindow`] allows, so at most `part_size * (limit + 1)` bytes are held in memory.
+pub(crate) struct PartWriter<T: PutPart> {
+    inner: Arc<T>,
+    part_size: usize,
+    window: ConcurrencyWindow,
+    buffer: Vec<u8>,
+    next_part: usize,
+    tasks: FuturesUnordered<PartTask>,
+    completed: Vec<Option<PartId>>,
+    completion: Option<BoxFuture<'static, Result<(), io::Error>>>,
+}
+
+impl<T: PutPart + 'static> PartWriter<T> {
+    pub(crate) fn new(inner: T, part_size: usize, window: ConcurrencyWindow) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            part_size,
+            window,
+            buffer: Vec::new(),
+            next_part: 0,
+            tasks: FuturesUnordered::new(),
+            completed: Vec::new(),
+            completion: None,
+        }
+    }
+
+    fn upload_buffer(&mut self) {
+        let buffer = std::mem::take(&mut self.buffer);
+        let inner = Arc::clone(&self.inner);
+        let part_idx = self.next_part;
+        self.tasks.push(Box::pin(async move {
+            let size = buffer.len();
+            let part = inner.put_part(buffer, part_idx).await?;
+            Ok((part_idx, size, part))
+        }));
+        self.next_part += 1;
+    }
+
+    fn poll_tasks(&mut self, cx: &mut Context<'_>) -> Result<(), io::Error> {
+        while let Poll::Ready(Some(result)) = self.tasks.poll_next_unpin(cx) {
+            let (part_idx, size, part) = result?;
+            self.window.record(size);
+            if self.completed.len() <= part_idx {
+                self.completed.resize(part_idx + 1, None);
+            }
+            self.completed[part_idx] = Some(part);
+        }
         Ok(())
     }
 }
+
+impl<T: PutPart + 'static> AsyncWrite for PartWriter<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.get_mut();
+        this.poll_tasks(cx)?;
+        let mut written = 0;
+        loop {
+            let to_copy = (this.part_size - this.buffer.len()).min(buf.len() - written);
+            this.buffer
+                .extend_from_slice(&buf[written..written + to_copy]);
+            written += to_copy;
+            if this.buffer.len() < this.part_size || this.tasks.len() >= this.window.limit() {
+                break;
+            }
+            this.upload_buffer();
+            // Register the waker of the new task
+            this.poll_tasks(cx)?;
+        }
+        if written == 0 && !buf.is_empty() {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(written))
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        this.poll_tasks(cx)?;
+        if !this.buffer.is_empty() && this.tasks.len() < this.window.limit() {
+            this.upload_buffer();
+            this.poll_tasks(cx)?;
+        }
+        if this.tasks.is_empty() && this.buffer.is_empty() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        if this.completion.is_none() {
+            this.poll_tasks(cx)?;
+            if !this.buffer.is_empty() && this.tasks.len() < this.window.limit() {
+                this.upload_buffer();
+                this.poll_tasks(cx)?;
+            }
+            if !this.tasks.is_empty() || !this.buffer.is_empty() {
+                return Poll::Pending;
+            }
+            let parts = std::mem::take(&mut this.completed)
+                .into_iter()
+                .enumerate()
+                .map(|(idx, part)| {
+                    part.ok_or_else(|| {
+                        io::Error::other(format!("Missing information for upload part {idx}"))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let inner = Arc::clone(&this.inner);
+            this.completion = Some(Box::pin(async move {
+                inner.complete(parts).await?;
+                Ok(())
+            }));
+        }
+        this.completion
+            .as_mut()
+            .expect("completion task is set")
+            .as_mut()
+            .poll(cx)
+    }
+}
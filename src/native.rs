@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::{ByteStream, SdkBody};
+use aws_smithy_client::{conns::Https, hyper_ext};
+use tower::ServiceExt;
+
+use crate::{builder::MakeRequest, error::Error, timeout::FetchControl};
+
+/// Sends requests with the hyper connector of the smithy client, over rustls for `https`
+/// endpoints. Connections are pooled across clones.
+#[derive(Debug, Clone)]
+pub(crate) struct NativeHttpClient {
+    adapter: hyper_ext::Adapter<Https>,
+}
+
+impl Default for NativeHttpClient {
+    fn default() -> Self {
+        Self {
+            adapter: hyper_ext::Adapter::builder().build(aws_smithy_client::conns::https()),
+        }
+    }
+}
+
+#[async_trait]
+impl MakeRequest for NativeHttpClient {
+    async fn send(
+        &self,
+        parts: http::request::Parts,
+        body: SdkBody,
+        control: &FetchControl,
+    ) -> Result<http::Response<SdkBody>, Error> {
+        let response = self
+            .adapter
+            .clone()
+            .oneshot(http::Request::from_parts(parts, body))
+            .await
+            .map_err(|err| Error::Transport(err.to_string()))?;
+        control.connected();
+        // Responses are buffered, dropping the request future aborts native requests
+        let (parts, body) = response.into_parts();
+        let body = ByteStream::new(body)
+            .collect()
+            .await
+            .map_err(|err| Error::Transport(err.to_string()))?
+            .into_bytes();
+        Ok(http::Response::from_parts(parts, SdkBody::from(body)))
+    }
+}
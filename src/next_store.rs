@@ -0,0 +1,327 @@
+use std::ops::Range;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use object_store::ObjectStore;
+use object_store_next::{
+    path::Path, Attribute, Attributes, GetOptions, GetRange, GetResult, GetResultPayload,
+    ListResult, MultipartUpload, ObjectMeta, PutMode, PutMultipartOpts, PutOptions, PutPayload,
+    PutResult, TagSet,
+};
+use percent_encoding::percent_decode_str;
+
+use crate::{attributes::PutAttributes, S3};
+
+/// `object_store` 0.10 error of one of the `object_store` version the crate implements, keeping
+/// the variants callers match on.
+pub(crate) fn next_error(err: object_store::Error) -> object_store_next::Error {
+    match err {
+        object_store::Error::NotFound { path, source } => {
+            object_store_next::Error::NotFound { path, source }
+        }
+        object_store::Error::AlreadyExists { path, source } => {
+            object_store_next::Error::AlreadyExists { path, source }
+        }
+        object_store::Error::Precondition { path, source } => {
+            object_store_next::Error::Precondition { path, source }
+        }
+        object_store::Error::NotModified { path, source } => {
+            object_store_next::Error::NotModified { path, source }
+        }
+        object_store::Error::NotSupported { source } => {
+            object_store_next::Error::NotSupported { source }
+        }
+        object_store::Error::NotImplemented => object_store_next::Error::NotImplemented,
+        err => object_store_next::Error::Generic {
+            store: "S3",
+            source: Box::new(err),
+        },
+    }
+}
+
+/// `object_store` 0.9 path of `location`, both versions share the same encoding.
+pub(crate) fn path(location: &Path) -> object_store_next::Result<object_store::path::Path> {
+    object_store::path::Path::parse(location.as_ref()).map_err(|err| next_error(err.into()))
+}
+
+fn next_path(location: &object_store::path::Path) -> object_store_next::Result<Path> {
+    Path::parse(location.as_ref())
+        .map_err(|source| object_store_next::Error::InvalidPath { source })
+}
+
+fn next_meta(meta: object_store::ObjectMeta) -> object_store_next::Result<ObjectMeta> {
+    Ok(ObjectMeta {
+        location: next_path(&meta.location)?,
+        last_modified: meta.last_modified,
+        size: meta.size,
+        e_tag: meta.e_tag,
+        version: meta.version,
+    })
+}
+
+/// Content headers and user metadata of `attributes`, attributes S3 has no header for are
+/// rejected as the `object_store` documentation requires.
+pub(crate) fn put_attributes(attributes: &Attributes) -> object_store_next::Result<PutAttributes> {
+    let mut put_attributes = PutAttributes::default();
+    for (attribute, value) in attributes {
+        let value = value.as_ref().to_owned();
+        match attribute {
+            Attribute::ContentDisposition => put_attributes.content_disposition = Some(value),
+            Attribute::ContentEncoding => put_attributes.content_encoding = Some(value),
+            Attribute::ContentLanguage => put_attributes.content_language = Some(value),
+            Attribute::ContentType => put_attributes.content_type = Some(value),
+            Attribute::CacheControl => put_attributes.cache_control = Some(value),
+            Attribute::Metadata(key) => {
+                put_attributes.metadata.insert(key.to_string(), value);
+            }
+            _ => return Err(object_store_next::Error::NotImplemented),
+        }
+    }
+    Ok(put_attributes)
+}
+
+fn next_attributes(attributes: PutAttributes) -> Attributes {
+    let headers = [
+        (
+            Attribute::ContentDisposition,
+            attributes.content_disposition,
+        ),
+        (Attribute::ContentEncoding, attributes.content_encoding),
+        (Attribute::ContentLanguage, attributes.content_language),
+        (Attribute::ContentType, attributes.content_type),
+        (Attribute::CacheControl, attributes.cache_control),
+    ];
+    headers
+        .into_iter()
+        .filter_map(|(attribute, value)| Some((attribute, value?)))
+        .chain(
+            attributes
+                .metadata
+                .into_iter()
+                .map(|(key, value)| (Attribute::Metadata(key.into()), value)),
+        )
+        .collect()
+}
+
+/// Tags of `tags`, decoded from the form encoding both versions use.
+fn tags(tags: &TagSet) -> object_store::TagSet {
+    let decode = |value: &str| {
+        percent_decode_str(&value.replace('+', " "))
+            .decode_utf8_lossy()
+            .into_owned()
+    };
+    let mut decoded = object_store::TagSet::default();
+    for pair in tags.encoded().split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        decoded.push(&decode(key), &decode(value));
+    }
+    decoded
+}
+
+fn put_mode(mode: PutMode) -> object_store::PutMode {
+    match mode {
+        PutMode::Overwrite => object_store::PutMode::Overwrite,
+        PutMode::Create => object_store::PutMode::Create,
+        PutMode::Update(version) => object_store::PutMode::Update(object_store::UpdateVersion {
+            e_tag: version.e_tag,
+            version: version.version,
+        }),
+    }
+}
+
+fn get_options(options: GetOptions) -> object_store::GetOptions {
+    object_store::GetOptions {
+        if_match: options.if_match,
+        if_none_match: options.if_none_match,
+        if_modified_since: options.if_modified_since,
+        if_unmodified_since: options.if_unmodified_since,
+        range: options.range.map(|range| match range {
+            GetRange::Bounded(range) => object_store::GetRange::Bounded(range),
+            GetRange::Offset(offset) => object_store::GetRange::Offset(offset),
+            GetRange::Suffix(suffix) => object_store::GetRange::Suffix(suffix),
+        }),
+        version: options.version,
+        head: options.head,
+    }
+}
+
+/// The `ObjectStore` trait of `object_store` 0.10, for callers like current DataFusion and
+/// delta-rs releases. Every operation behaves like its `object_store` 0.9 counterpart.
+#[async_trait]
+impl object_store_next::ObjectStore for S3 {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        payload: PutPayload,
+        opts: PutOptions,
+    ) -> object_store_next::Result<PutResult> {
+        let attributes = put_attributes(&opts.attributes)?;
+        let opts = object_store::PutOptions {
+            mode: put_mode(opts.mode),
+            tags: tags(&opts.tags),
+        };
+        let result = self
+            .put_with_attributes(&path(location)?, Bytes::from(payload), opts, &attributes)
+            .await
+            .map_err(next_error)?;
+        Ok(PutResult {
+            e_tag: result.e_tag,
+            version: result.version,
+        })
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &Path,
+        opts: PutMultipartOpts,
+    ) -> object_store_next::Result<Box<dyn MultipartUpload>> {
+        S3::put_multipart_opts(self, location, opts).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store_next::Result<GetResult> {
+        let (result, attributes) = self
+            .get_with_attributes(&path(location)?, get_options(options))
+            .await
+            .map_err(next_error)?;
+        let meta = next_meta(result.meta.clone())?;
+        let range = result.range.clone();
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(result.into_stream().map_err(next_error).boxed()),
+            meta,
+            range,
+            attributes: next_attributes(attributes),
+        })
+    }
+
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store_next::Result<Vec<Bytes>> {
+        ObjectStore::get_ranges(self, &path(location)?, ranges)
+            .await
+            .map_err(next_error)
+    }
+
+    async fn head(&self, location: &Path) -> object_store_next::Result<ObjectMeta> {
+        next_meta(
+            ObjectStore::head(self, &path(location)?)
+                .await
+                .map_err(next_error)?,
+        )
+    }
+
+    async fn delete(&self, location: &Path) -> object_store_next::Result<()> {
+        ObjectStore::delete(self, &path(location)?)
+            .await
+            .map_err(next_error)
+    }
+
+    fn delete_stream<'a>(
+        &'a self,
+        locations: BoxStream<'a, object_store_next::Result<Path>>,
+    ) -> BoxStream<'a, object_store_next::Result<Path>> {
+        // Errors of the input are passed through unchanged, in order with the deleted paths
+        let locations = locations
+            .map(
+                |location| match location.and_then(|location| path(&location)) {
+                    Ok(location) => Ok(location),
+                    Err(err) => Err(object_store::Error::Generic {
+                        store: "S3",
+                        source: Box::new(err),
+                    }),
+                },
+            )
+            .boxed();
+        ObjectStore::delete_stream(self, locations)
+            .map(|location| match location {
+                Ok(location) => next_path(&location),
+                Err(object_store::Error::Generic { source, .. })
+                    if source.is::<object_store_next::Error>() =>
+                {
+                    Err(*source.downcast::<object_store_next::Error>().unwrap())
+                }
+                Err(err) => Err(next_error(err)),
+            })
+            .boxed()
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store_next::Result<ObjectMeta>> {
+        let prefix = match prefix.map(path).transpose() {
+            Ok(prefix) => prefix,
+            Err(err) => return futures::stream::once(async { Err(err) }).boxed(),
+        };
+        ObjectStore::list(self, prefix.as_ref())
+            .map(|meta| next_meta(meta.map_err(next_error)?))
+            .boxed()
+    }
+
+    fn list_with_offset(
+        &self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> BoxStream<'_, object_store_next::Result<ObjectMeta>> {
+        let paths = prefix
+            .map(path)
+            .transpose()
+            .and_then(|prefix| Ok((prefix, path(offset)?)));
+        let (prefix, offset) = match paths {
+            Ok(paths) => paths,
+            Err(err) => return futures::stream::once(async { Err(err) }).boxed(),
+        };
+        ObjectStore::list_with_offset(self, prefix.as_ref(), &offset)
+            .map(|meta| next_meta(meta.map_err(next_error)?))
+            .boxed()
+    }
+
+    async fn list_with_delimiter(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store_next::Result<ListResult> {
+        let prefix = prefix.map(path).transpose()?;
+        let listed = ObjectStore::list_with_delimiter(self, prefix.as_ref())
+            .await
+            .map_err(next_error)?;
+        Ok(ListResult {
+            common_prefixes: listed
+                .common_prefixes
+                .iter()
+                .map(next_path)
+                .collect::<Result<_, _>>()?,
+            objects: listed
+                .objects
+                .into_iter()
+                .map(next_meta)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store_next::Result<()> {
+        ObjectStore::copy(self, &path(from)?, &path(to)?)
+            .await
+            .map_err(next_error)
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> object_store_next::Result<()> {
+        ObjectStore::rename(self, &path(from)?, &path(to)?)
+            .await
+            .map_err(next_error)
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store_next::Result<()> {
+        ObjectStore::copy_if_not_exists(self, &path(from)?, &path(to)?)
+            .await
+            .map_err(next_error)
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> object_store_next::Result<()> {
+        ObjectStore::rename_if_not_exists(self, &path(from)?, &path(to)?)
+            .await
+            .map_err(next_error)
+    }
+}
@@ -0,0 +1,183 @@
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use aws_sdk_s3::{
+    config::{
+        interceptors::{BeforeSerializationInterceptorContextRef, FinalizerInterceptorContextRef},
+        ConfigBag, Interceptor, RuntimeComponents,
+    },
+    error::BoxError,
+};
+use aws_smithy_http::operation::Metadata;
+use aws_smithy_types::config_bag::{Storable, StoreReplace};
+use percent_encoding::percent_decode_str;
+
+use crate::environment::Timer;
+
+/// Summary of a finished S3 operation, including all of its attempts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationEvent {
+    /// Name of the S3 API operation, e.g. `GetObject`.
+    pub operation: String,
+    pub bucket: Option<String>,
+    pub key: Option<String>,
+    pub bytes_sent: u64,
+    /// Size of the response body, taken from `Content-Length` for streamed bodies.
+    pub bytes_received: u64,
+    pub duration: Duration,
+    /// Status of the last response, `None` if no response arrived.
+    pub status: Option<u16>,
+    pub success: bool,
+}
+
+/// Receives an [`OperationEvent`] for every S3 operation, e.g. to forward it to an app's
+/// telemetry.
+pub trait ObserverHook: Debug + Send + Sync {
+    fn finished(&self, event: &OperationEvent);
+}
+
+/// Start of the running operation.
+#[derive(Debug, Clone)]
+struct Started {
+    at: Duration,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl Storable for Started {
+    type Storer = StoreReplace<Self>;
+}
+
+/// Reports every operation to the [`ObserverHook`] and, with the `tracing` feature, records it in
+/// an `s3` span.
+#[derive(Debug, Clone)]
+pub(crate) struct Observer {
+    hook: Option<Arc<dyn ObserverHook>>,
+    timer: Arc<dyn Timer>,
+    path_style: bool,
+}
+
+impl Observer {
+    pub(crate) fn new(
+        hook: Option<Arc<dyn ObserverHook>>,
+        timer: Arc<dyn Timer>,
+        path_style: bool,
+    ) -> Self {
+        Self {
+            hook,
+            timer,
+            path_style,
+        }
+    }
+
+    /// Bucket and key addressed by `uri`.
+    fn location(&self, uri: &http::Uri) -> (Option<String>, Option<String>) {
+        let path = uri.path().trim_start_matches('/');
+        let (bucket, key) = if self.path_style {
+            match path.split_once('/') {
+                Some((bucket, key)) => (Some(bucket), Some(key)),
+                None => (Some(path), None),
+            }
+        } else {
+            let bucket = uri.host().and_then(|host| host.split_once('.'));
+            (bucket.map(|(bucket, _)| bucket), Some(path))
+        };
+        let decode = |part: &str| {
+            (!part.is_empty()).then(|| percent_decode_str(part).decode_utf8_lossy().into_owned())
+        };
+        (bucket.and_then(decode), key.and_then(decode))
+    }
+}
+
+impl Interceptor for Observer {
+    fn name(&self) -> &'static str {
+        "Observer"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "s3",
+            operation = cfg.load::<Metadata>().map(Metadata::name),
+            bucket = tracing::field::Empty,
+            key = tracing::field::Empty,
+            bytes_sent = tracing::field::Empty,
+            bytes_received = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+            status = tracing::field::Empty,
+            success = tracing::field::Empty,
+        );
+        cfg.interceptor_state().store_put(Started {
+            at: self.timer.now(),
+            #[cfg(feature = "tracing")]
+            span,
+        });
+        Ok(())
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let Some(started) = cfg.load::<Started>().cloned() else {
+            return Ok(());
+        };
+        let (bucket, key) = context
+            .request()
+            .map_or((None, None), |request| self.location(request.uri()));
+        let bytes_received = context.response().map_or(0, |response| {
+            response
+                .headers()
+                .get(http::header::CONTENT_LENGTH)
+                .and_then(|length| length.to_str().ok()?.parse().ok())
+                .or_else(|| response.body().bytes().map(|body| body.len() as u64))
+                .unwrap_or(0)
+        });
+        let event = OperationEvent {
+            operation: cfg
+                .load::<Metadata>()
+                .map_or_else(String::new, |metadata| metadata.name().to_owned()),
+            bucket,
+            key,
+            bytes_sent: context
+                .request()
+                .and_then(|request| request.body().bytes())
+                .map_or(0, |body| body.len() as u64),
+            bytes_received,
+            duration: self.timer.now().saturating_sub(started.at),
+            status: context
+                .response()
+                .map(|response| response.status().as_u16()),
+            success: context
+                .output_or_error()
+                .is_some_and(|result| result.is_ok()),
+        };
+        #[cfg(feature = "tracing")]
+        {
+            let span = &started.span;
+            span.record("bucket", event.bucket.as_deref());
+            span.record("key", event.key.as_deref());
+            span.record("bytes_sent", event.bytes_sent);
+            span.record("bytes_received", event.bytes_received);
+            span.record("duration_ms", event.duration.as_millis() as u64);
+            span.record("status", event.status);
+            span.record("success", event.success);
+            span.in_scope(|| {
+                if event.success {
+                    tracing::debug!("S3 operation finished");
+                } else {
+                    tracing::warn!("S3 operation failed");
+                }
+            });
+        }
+        if let Some(hook) = &self.hook {
+            hook.finished(&event);
+        }
+        Ok(())
+    }
+}
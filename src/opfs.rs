@@ -0,0 +1,142 @@
+use futures::TryStreamExt;
+use object_store::{path::Path, GetOptions, GetRange, ObjectMeta, ObjectStore};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    File, FileSystemCreateWritableOptions, FileSystemFileHandle, FileSystemWritableFileStream,
+};
+
+use crate::{error::Error, idb::Database, S3};
+
+pub(crate) const DOWNLOAD_STORE: &str = "opfs_downloads";
+
+/// Bytes written between two flushes of a [`S3::download_to_opfs`].
+pub const FLUSH_INTERVAL: usize = 8 * 1024 * 1024;
+
+/// Progress of a [`S3::download_to_opfs`], reported after every chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// Bytes present in the file, including the ones of an earlier interrupted download.
+    pub downloaded: usize,
+    pub total: usize,
+    /// Offset the download resumed from, `0` for a fresh download.
+    pub resumed_from: usize,
+}
+
+/// Flushed state of an unfinished download, kept in IndexedDB.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DownloadCheckpoint {
+    e_tag: String,
+    flushed: usize,
+}
+
+impl S3 {
+    fn download_key(&self, location: &Path, file: &FileSystemFileHandle) -> String {
+        format!("{}/{}:{}", self.bucket, location, file.name())
+    }
+
+    /// Streams the object at `location` into the OPFS file `file`.
+    ///
+    /// The file is flushed every [`FLUSH_INTERVAL`] bytes and the flushed length is checkpointed
+    /// in IndexedDB together with the object's ETag. Calling this again after an interruption
+    /// resumes from the last checkpoint if the object still has the same ETag, otherwise the file
+    /// is downloaded from the start. `progress` is called after every written chunk.
+    pub async fn download_to_opfs(
+        &self,
+        location: &Path,
+        file: &FileSystemFileHandle,
+        mut progress: impl FnMut(DownloadProgress),
+    ) -> object_store::Result<ObjectMeta> {
+        let meta = self.head(location).await?;
+        let e_tag = meta.e_tag.clone().ok_or(Error::MissingETag)?;
+        let db = Database::open().await?;
+        let key = self.download_key(location, file);
+        let checkpoint = db
+            .get(DOWNLOAD_STORE, &key)
+            .await?
+            .and_then(|value| value.as_string())
+            .and_then(|value| serde_json::from_str::<DownloadCheckpoint>(&value).ok());
+        let existing = JsFuture::from(file.get_file())
+            .await
+            .map_err(Error::from)?
+            .dyn_into::<File>()
+            .map_err(Error::from)?
+            .size() as usize;
+        let resumed_from = match checkpoint {
+            Some(checkpoint) if checkpoint.e_tag == e_tag => checkpoint.flushed.min(existing),
+            _ => 0,
+        };
+
+        let mut writable = open_writable(file, resumed_from).await?;
+        // A download interrupted after flushing its last chunk already holds the whole object,
+        // requesting the range after its end would fail with 416
+        if resumed_from < meta.size {
+            let options = GetOptions {
+                if_match: Some(e_tag.clone()),
+                range: (resumed_from > 0).then_some(GetRange::Offset(resumed_from)),
+                ..Default::default()
+            };
+            let mut stream = self.get_opts(location, options).await?.into_stream();
+            let mut downloaded = resumed_from;
+            let mut flushed = resumed_from;
+            while let Some(chunk) = stream.try_next().await? {
+                write(&writable, &chunk).await?;
+                downloaded += chunk.len();
+                if downloaded - flushed >= FLUSH_INTERVAL {
+                    close(&writable).await?;
+                    flushed = downloaded;
+                    let checkpoint = serde_json::to_string(&DownloadCheckpoint {
+                        e_tag: e_tag.clone(),
+                        flushed,
+                    })
+                    .map_err(Error::from)?;
+                    db.put(DOWNLOAD_STORE, &key, &JsValue::from_str(&checkpoint))
+                        .await?;
+                    writable = open_writable(file, flushed).await?;
+                }
+                progress(DownloadProgress {
+                    downloaded,
+                    total: meta.size,
+                    resumed_from,
+                });
+            }
+        } else {
+            progress(DownloadProgress {
+                downloaded: resumed_from,
+                total: meta.size,
+                resumed_from,
+            });
+        }
+        close(&writable).await?;
+        db.delete(DOWNLOAD_STORE, &key).await?;
+        Ok(meta)
+    }
+}
+
+/// Opens `file` for writing, keeping its first `offset` bytes and positioned after them.
+async fn open_writable(
+    file: &FileSystemFileHandle,
+    offset: usize,
+) -> Result<FileSystemWritableFileStream, Error> {
+    let options = FileSystemCreateWritableOptions::new();
+    options.set_keep_existing_data(offset > 0);
+    let writable: FileSystemWritableFileStream =
+        JsFuture::from(file.create_writable_with_options(&options))
+            .await?
+            .dyn_into()?;
+    JsFuture::from(writable.truncate_with_f64(offset as f64)?).await?;
+    JsFuture::from(writable.seek_with_f64(offset as f64)?).await?;
+    Ok(writable)
+}
+
+async fn write(writable: &FileSystemWritableFileStream, chunk: &[u8]) -> Result<(), Error> {
+    JsFuture::from(writable.write_with_u8_array(chunk)?).await?;
+    Ok(())
+}
+
+/// Closes `writable`, which commits the written data to the file.
+async fn close(writable: &FileSystemWritableFileStream) -> Result<(), Error> {
+    JsFuture::from(writable.close()).await?;
+    Ok(())
+}
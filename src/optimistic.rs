@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use object_store::{path::Path, ObjectStore, PutMode, PutOptions, UpdateVersion};
+
+use crate::{
+    error::{is_not_found, Error},
+    S3,
+};
+
+/// Attempts of [`OptimisticCell::update`] before the conflict is returned.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 5;
+
+const BASE_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Value of an [`OptimisticCell`] together with the generation it was read at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellValue {
+    pub value: Bytes,
+    /// ETag of the object, every successful write produces a new one.
+    pub e_tag: String,
+    pub version: Option<String>,
+}
+
+/// Small shared state stored in a single key and updated with compare-and-swap.
+///
+/// Writes are conditional on the ETag of the read they are based on, a write based on a stale
+/// read fails with [`object_store::Error::Precondition`], creating an existing value with
+/// [`object_store::Error::AlreadyExists`].
+#[derive(Debug)]
+pub struct OptimisticCell<'a> {
+    store: &'a S3,
+    location: Path,
+    max_attempts: usize,
+}
+
+fn is_conflict(err: &object_store::Error) -> bool {
+    matches!(
+        err,
+        object_store::Error::Precondition { .. } | object_store::Error::AlreadyExists { .. }
+    )
+}
+
+impl OptimisticCell<'_> {
+    /// Attempts of [`OptimisticCell::update`] before the conflict is returned, at least one.
+    pub fn with_max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn location(&self) -> &Path {
+        &self.location
+    }
+
+    /// Current value, `None` if the key doesn't exist yet.
+    pub async fn read(&self) -> object_store::Result<Option<CellValue>> {
+        let result = match self.store.get(&self.location).await {
+            Ok(result) => result,
+            Err(err) if is_not_found(&err) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let e_tag = result.meta.e_tag.clone().ok_or(Error::MissingETag)?;
+        let version = result.meta.version.clone();
+        let value = result.bytes().await?;
+        Ok(Some(CellValue {
+            value,
+            e_tag,
+            version,
+        }))
+    }
+
+    /// Writes `value` if the cell is still at `previous`, creates it if `previous` is `None`.
+    pub async fn write(
+        &self,
+        value: Bytes,
+        previous: Option<&CellValue>,
+    ) -> object_store::Result<CellValue> {
+        let mode = match previous {
+            Some(previous) => PutMode::Update(UpdateVersion {
+                e_tag: Some(previous.e_tag.clone()),
+                version: previous.version.clone(),
+            }),
+            None => PutMode::Create,
+        };
+        let result = self
+            .store
+            .put_opts(&self.location, value.clone(), PutOptions::from(mode))
+            .await?;
+        Ok(CellValue {
+            value,
+            e_tag: result.e_tag.unwrap_or_default(),
+            version: result.version,
+        })
+    }
+
+    /// Replaces the value with `merge` of the current one, `None` if the cell doesn't exist.
+    ///
+    /// On a conflicting write the cell is read again and `merge` applied to the new value, after
+    /// a jittered exponential backoff, until the write succeeds or the attempts are exhausted.
+    pub async fn update(
+        &self,
+        mut merge: impl FnMut(Option<&Bytes>) -> Bytes,
+    ) -> object_store::Result<CellValue> {
+        let mut attempt = 0;
+        loop {
+            let current = self.read().await?;
+            let value = merge(current.as_ref().map(|current| &current.value));
+            match self.write(value, current.as_ref()).await {
+                Err(err) if is_conflict(&err) && attempt + 1 < self.max_attempts => {
+                    let backoff = BASE_BACKOFF * 2u32.saturating_pow(attempt as u32);
+                    self.store
+                        .timer()
+                        .sleep(self.store.random().jitter(backoff))
+                        .await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl S3 {
+    /// Optimistically updated cell stored at `location`.
+    pub fn optimistic_cell(&self, location: Path) -> OptimisticCell<'_> {
+        OptimisticCell {
+            store: self,
+            location,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+}
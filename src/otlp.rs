@@ -0,0 +1,343 @@
+use std::{sync::Arc, time::Duration, time::UNIX_EPOCH};
+
+use aws_sdk_s3::primitives::SdkBody;
+use http::{header, HeaderName, HeaderValue, Method};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+
+use crate::{
+    builder::Adapter,
+    environment::{default_timer, spawn, system_time, Random, SystemRandom, Timer},
+    error::Error,
+    metrics::{MetricsRecorder, StoreMetrics},
+    observe::{ObserverHook, OperationEvent},
+    retry::RetryConfig,
+    throttle::Throttle,
+    timeout::Timeouts,
+};
+
+/// Spans sent in one export, unless set with [`OtlpExporter::with_batch_size`].
+pub const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// Spans kept while exports are held back or failing, the oldest are dropped beyond it.
+const MAX_QUEUED_SPANS: usize = 2048;
+
+#[derive(Debug, Default)]
+struct Queue {
+    spans: Vec<Value>,
+    exported_at: Option<Duration>,
+    /// Whether a background export is waiting for the export interval to pass.
+    scheduled: bool,
+}
+
+/// Ships [`OperationEvent`]s as spans and [`StoreMetrics`] as metrics to an OpenTelemetry
+/// collector with OTLP/HTTP JSON.
+///
+/// Install it with [`S3Builder::with_observer_hook`](crate::builder::S3Builder::with_observer_hook)
+/// and keep a clone to [`flush`](Self::flush) and [`export_metrics`](Self::export_metrics).
+/// Spans are exported in the background once a batch is full, at most once per export interval,
+/// and once the export interval passed otherwise.
+#[derive(Debug, Clone)]
+pub struct OtlpExporter {
+    endpoint: String,
+    service_name: String,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    batch_size: usize,
+    interval: Duration,
+    adapter: Adapter,
+    timer: Arc<dyn Timer>,
+    random: Arc<dyn Random>,
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl OtlpExporter {
+    /// Exporter posting to `endpoint`, the base URL of the collector's OTLP/HTTP receiver,
+    /// e.g. `https://collector.example.com:4318`.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        let timer = default_timer();
+        let random: Arc<dyn Random> = Arc::new(SystemRandom);
+        let adapter = adapter(timer.clone(), random.clone());
+        Self {
+            endpoint: endpoint.into().trim_end_matches('/').to_owned(),
+            service_name: env!("CARGO_PKG_NAME").to_owned(),
+            headers: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            interval: Duration::from_secs(5),
+            adapter,
+            timer,
+            random,
+            queue: Arc::default(),
+        }
+    }
+    /// Report as `service.name`, defaults to the crate name.
+    pub fn with_service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_name = name.into();
+        self
+    }
+    /// Send `name: value` with every export, e.g. the collector's API key.
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidHeader(name.to_owned());
+        self.headers.push((
+            HeaderName::try_from(name).map_err(|_| invalid())?,
+            HeaderValue::try_from(value).map_err(|_| invalid())?,
+        ));
+        Ok(self)
+    }
+    /// Export once `spans` spans are queued, defaults to [`DEFAULT_BATCH_SIZE`].
+    pub fn with_batch_size(mut self, spans: usize) -> Self {
+        self.batch_size = spans.max(1);
+        self
+    }
+    /// Export in the background at most once per `interval`, defaults to 5 seconds.
+    pub fn with_export_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    /// Measure the export interval and time out exports with `timer` instead of the browser
+    /// clock.
+    pub fn with_timer(mut self, timer: Arc<dyn Timer>) -> Self {
+        self.adapter = adapter(timer.clone(), self.random.clone());
+        self.timer = timer;
+        self
+    }
+
+    /// Spans waiting for an export.
+    pub fn pending(&self) -> usize {
+        self.queue.lock().spans.len()
+    }
+
+    /// Exports all queued spans now, regardless of the export interval.
+    pub async fn flush(&self) -> Result<(), Error> {
+        loop {
+            let batch = {
+                let mut queue = self.queue.lock();
+                if queue.spans.is_empty() {
+                    return Ok(());
+                }
+                queue.exported_at = Some(self.timer.now());
+                let end = self.batch_size.min(queue.spans.len());
+                queue.spans.drain(..end).collect::<Vec<_>>()
+            };
+            self.export_spans(batch).await?;
+        }
+    }
+
+    /// Exports the counters of `metrics` as cumulative sums and its network estimates as gauges.
+    pub async fn export_metrics(&self, metrics: &StoreMetrics) -> Result<(), Error> {
+        let now = unix_nanos(Duration::ZERO);
+        let sum = |name: &str, unit: &str, value: u64| {
+            json!({
+                "name": name,
+                "unit": unit,
+                "sum": {
+                    "aggregationTemporality": 2,
+                    "isMonotonic": true,
+                    "dataPoints": [{ "timeUnixNano": now, "asInt": value.to_string() }],
+                },
+            })
+        };
+        let gauge = |name: &str, unit: &str, value: Option<f64>| {
+            value.map(|value| {
+                json!({
+                    "name": name,
+                    "unit": unit,
+                    "gauge": { "dataPoints": [{ "timeUnixNano": now, "asDouble": value }] },
+                })
+            })
+        };
+        let mut exported = vec![
+            sum("s3.requests", "{request}", metrics.requests),
+            sum("s3.failed_requests", "{request}", metrics.failed_requests),
+            sum("s3.bytes_sent", "By", metrics.bytes_sent),
+            sum("s3.bytes_received", "By", metrics.bytes_received),
+        ];
+        exported.extend(gauge("s3.latency", "ms", metrics.network.latency_ms));
+        exported.extend(gauge(
+            "s3.throughput",
+            "By/s",
+            metrics.network.throughput_bytes_per_sec,
+        ));
+        let body = json!({
+            "resourceMetrics": [{
+                "resource": self.resource(),
+                "scopeMetrics": [{ "scope": scope(), "metrics": exported }],
+            }],
+        });
+        self.post("/v1/metrics", body).await
+    }
+
+    async fn export_spans(&self, spans: Vec<Value>) -> Result<(), Error> {
+        let body = json!({
+            "resourceSpans": [{
+                "resource": self.resource(),
+                "scopeSpans": [{ "scope": scope(), "spans": spans.clone() }],
+            }],
+        });
+        let result = self.post("/v1/traces", body).await;
+        if result.is_err() {
+            // Queue the spans again for the next export, ahead of newer ones
+            let mut queue = self.queue.lock();
+            let newer = std::mem::replace(&mut queue.spans, spans);
+            queue.spans.extend(newer);
+            let excess = queue.spans.len().saturating_sub(MAX_QUEUED_SPANS);
+            queue.spans.drain(..excess);
+        }
+        result
+    }
+
+    async fn post(&self, path: &str, body: Value) -> Result<(), Error> {
+        let mut request = http::Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}{}", self.endpoint, path))
+            .header(header::CONTENT_TYPE, "application/json");
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let (parts, _) = request
+            .body(())
+            .map_err(|err| Error::Transport(err.to_string()))?
+            .into_parts();
+        let response = self
+            .adapter
+            .send(parts, SdkBody::from(body.to_string()))
+            .await
+            .map_err(|err| Error::Transport(err.to_string()))?;
+        match response.status().is_success() {
+            true => Ok(()),
+            false => Err(Error::OtlpExport(response.status().as_u16())),
+        }
+    }
+
+    fn resource(&self) -> Value {
+        json!({ "attributes": [attribute("service.name", json!({ "stringValue": self.service_name }))] })
+    }
+
+    fn span(&self, event: &OperationEvent) -> Value {
+        let id = |words: usize| {
+            (0..words)
+                .map(|_| format!("{:016x}", self.random.next_u64()))
+                .collect::<String>()
+        };
+        let mut attributes = vec![
+            attribute("rpc.system", json!({ "stringValue": "aws-api" })),
+            attribute("rpc.service", json!({ "stringValue": "S3" })),
+            attribute("rpc.method", json!({ "stringValue": event.operation })),
+            attribute(
+                "s3.bytes_sent",
+                json!({ "intValue": event.bytes_sent.to_string() }),
+            ),
+            attribute(
+                "s3.bytes_received",
+                json!({ "intValue": event.bytes_received.to_string() }),
+            ),
+        ];
+        if let Some(bucket) = &event.bucket {
+            attributes.push(attribute("aws.s3.bucket", json!({ "stringValue": bucket })));
+        }
+        if let Some(key) = &event.key {
+            attributes.push(attribute("aws.s3.key", json!({ "stringValue": key })));
+        }
+        if let Some(status) = event.status {
+            attributes.push(attribute(
+                "http.response.status_code",
+                json!({ "intValue": status.to_string() }),
+            ));
+        }
+        json!({
+            "traceId": id(2),
+            "spanId": id(1),
+            "name": event.operation,
+            // SPAN_KIND_CLIENT
+            "kind": 3,
+            "startTimeUnixNano": unix_nanos(event.duration),
+            "endTimeUnixNano": unix_nanos(Duration::ZERO),
+            "attributes": attributes,
+            // STATUS_CODE_OK or STATUS_CODE_ERROR
+            "status": { "code": if event.success { 1 } else { 2 } },
+        })
+    }
+}
+
+impl ObserverHook for OtlpExporter {
+    fn finished(&self, event: &OperationEvent) {
+        let span = self.span(event);
+        let now = self.timer.now();
+        let batch = {
+            let mut queue = self.queue.lock();
+            queue.spans.push(span);
+            let excess = queue.spans.len().saturating_sub(MAX_QUEUED_SPANS);
+            queue.spans.drain(..excess);
+            let due = queue
+                .exported_at
+                .is_none_or(|at| now.saturating_sub(at) >= self.interval);
+            if queue.spans.len() < self.batch_size || !due {
+                if !queue.scheduled {
+                    queue.scheduled = true;
+                    let exporter = self.clone();
+                    spawn(async move { exporter.export_on_interval().await });
+                }
+                return;
+            }
+            queue.exported_at = Some(now);
+            queue.spans.drain(..self.batch_size).collect::<Vec<_>>()
+        };
+        let exporter = self.clone();
+        spawn(async move {
+            // Telemetry is best effort, failed spans are queued for the next export
+            let _ = exporter.export_spans(batch).await;
+        });
+    }
+}
+
+impl OtlpExporter {
+    /// Exports a batch every export interval until no spans are left, so spans of an idle store
+    /// don't wait for a full batch.
+    async fn export_on_interval(&self) {
+        loop {
+            self.timer.sleep(self.interval).await;
+            let batch = {
+                let mut queue = self.queue.lock();
+                if queue.spans.is_empty() {
+                    queue.scheduled = false;
+                    return;
+                }
+                queue.exported_at = Some(self.timer.now());
+                let end = self.batch_size.min(queue.spans.len());
+                queue.spans.drain(..end).collect::<Vec<_>>()
+            };
+            let _ = self.export_spans(batch).await;
+        }
+    }
+}
+
+/// Adapter sending exports one at a time, without retries.
+fn adapter(timer: Arc<dyn Timer>, random: Arc<dyn Random>) -> Adapter {
+    Adapter::new(
+        false,
+        None,
+        MetricsRecorder::default(),
+        None,
+        timer.clone(),
+        random,
+        RetryConfig::disabled(),
+        Timeouts::default(),
+        Throttle::new(Some(1), None, timer),
+    )
+}
+
+/// Wall clock time `ago` before now, in nanoseconds since the epoch as OTLP JSON expects.
+fn unix_nanos(ago: Duration) -> String {
+    let since_epoch = system_time()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .saturating_sub(ago);
+    since_epoch.as_nanos().to_string()
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+fn scope() -> Value {
+    json!({ "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") })
+}
@@ -0,0 +1,185 @@
+use std::ops::Range;
+
+use aws_sdk_s3::{primitives::ByteStream, types::CompletedMultipartUpload};
+use bytes::{Bytes, BytesMut};
+use object_store::{path::Path, GetOptions, GetRange, ObjectMeta, ObjectStore, PutResult};
+
+use crate::{
+    encryption::SseConfig,
+    error::Error,
+    journal::Mutation,
+    key_encoding::copy_source,
+    multipart::AbortOnDrop,
+    validate::{MAX_PART_SIZE, MIN_PART_SIZE},
+    S3,
+};
+
+/// Part of the multipart upload replacing an object in [`S3::overwrite_range`].
+enum Part {
+    /// Bytes copied server side from the previous version of the object.
+    Copy(Range<usize>),
+    Upload(Bytes),
+}
+
+/// `len` split into even chunks of at most [`MAX_PART_SIZE`] bytes.
+fn chunks(len: usize) -> impl Iterator<Item = Range<usize>> {
+    let max_part_size = usize::try_from(MAX_PART_SIZE).unwrap_or(usize::MAX);
+    let chunk = len.div_ceil(len.div_ceil(max_part_size).max(1)).max(1);
+    (0..len)
+        .step_by(chunk)
+        .map(move |start| start..(start + chunk).min(len))
+}
+
+impl S3 {
+    /// Writes `data` over the bytes of the object at `location` starting at `offset`, extending
+    /// the object if `data` runs past its end.
+    ///
+    /// The object is replaced by a multipart upload that copies the untouched head and tail with
+    /// `UploadPartCopy` and only uploads the modified range. Heads and tails too small for a part
+    /// of their own are read and uploaded along with `data`. Every read and copy is conditional on
+    /// the inspected version, so concurrent writes fail with
+    /// [`object_store::Error::Precondition`]. The object keeps its attributes.
+    pub async fn overwrite_range(
+        &self,
+        location: &Path,
+        offset: usize,
+        data: Bytes,
+    ) -> object_store::Result<PutResult> {
+        let (meta, _, attributes) = self.head_object(location, None).await?;
+        if offset > meta.size {
+            return Err(Error::OffsetBeyondEnd {
+                offset,
+                size: meta.size,
+            }
+            .into());
+        }
+        if data.is_empty() {
+            return Ok(PutResult {
+                e_tag: meta.e_tag,
+                version: meta.version,
+            });
+        }
+        let end = offset + data.len();
+
+        // Uploaded range of the new object, widened so every part but the last one is large
+        // enough
+        let upload_start = if offset >= MIN_PART_SIZE { offset } else { 0 };
+        let upload_end = if end < meta.size && end - upload_start < MIN_PART_SIZE {
+            (upload_start + MIN_PART_SIZE).min(meta.size)
+        } else {
+            end
+        };
+        let mut upload = BytesMut::with_capacity(upload_end - upload_start);
+        upload.extend_from_slice(&self.read_unchanged(&meta, upload_start..offset).await?);
+        upload.extend_from_slice(&data);
+        upload.extend_from_slice(&self.read_unchanged(&meta, end..upload_end).await?);
+        let upload = upload.freeze();
+
+        let mut parts = Vec::new();
+        parts.extend(chunks(upload_start).map(Part::Copy));
+        parts.extend(chunks(upload.len()).map(|range| Part::Upload(upload.slice(range))));
+        let tail = upload_end.min(meta.size)..meta.size;
+        parts.extend(
+            chunks(tail.len())
+                .map(|range| Part::Copy(tail.start + range.start..tail.start + range.end)),
+        );
+
+        let upload_id = self.create_multipart(location, &attributes).await?;
+        let guard = AbortOnDrop::new(self, location, &upload_id);
+        let upload = async {
+            let mut completed = Vec::with_capacity(parts.len());
+            for (index, part) in parts.into_iter().enumerate() {
+                let part_number = index as i32 + 1;
+                let part = match part {
+                    Part::Copy(range) => {
+                        let request = self
+                            .client
+                            .upload_part_copy()
+                            .bucket(self.bucket.clone())
+                            .key(location.to_string())
+                            .upload_id(&upload_id)
+                            .part_number(part_number)
+                            .copy_source(copy_source(&self.bucket, location.as_ref()))
+                            .copy_source_range(format!("bytes={}-{}", range.start, range.end - 1))
+                            .set_copy_source_if_match(meta.e_tag.clone());
+                        let result = self
+                            .encrypted(request, SseConfig::upload_part_copy)
+                            .send()
+                            .await
+                            .map_err(|err| self.request_error(location, err))?
+                            .copy_part_result
+                            .ok_or(Error::Unknown)?;
+                        self.completed_part(part_number, result.e_tag(), &result)?
+                    }
+                    Part::Upload(bytes) => {
+                        let request = self
+                            .checksummed(self.client.upload_part(), &bytes)
+                            .bucket(self.bucket.clone())
+                            .key(location.to_string())
+                            .upload_id(&upload_id)
+                            .part_number(part_number)
+                            .body(ByteStream::from(bytes));
+                        let response = self
+                            .encrypted(request, SseConfig::upload_part)
+                            .send()
+                            .await
+                            .map_err(|err| self.request_error(location, err))?;
+                        self.completed_part(part_number, response.e_tag(), &response)?
+                    }
+                };
+                completed.push(part);
+            }
+            self.client
+                .complete_multipart_upload()
+                .bucket(self.bucket.clone())
+                .key(location.to_string())
+                .upload_id(&upload_id)
+                .multipart_upload(
+                    CompletedMultipartUpload::builder()
+                        .set_parts(Some(completed))
+                        .build(),
+                )
+                .send()
+                .await
+                .map_err(|err| self.request_error(location, err))
+        }
+        .await;
+
+        let result = match upload {
+            Ok(result) => {
+                guard.disarm();
+                result
+            }
+            Err(err) => {
+                guard.abort().await;
+                return Err(err.into());
+            }
+        };
+        self.record(|| Mutation::Put {
+            location: location.clone(),
+            e_tag: result.e_tag.clone(),
+            version: result.version_id.clone(),
+        });
+        Ok(PutResult {
+            e_tag: result.e_tag,
+            version: result.version_id,
+        })
+    }
+
+    /// Reads `range` of the inspected version of an object.
+    async fn read_unchanged(
+        &self,
+        meta: &ObjectMeta,
+        range: Range<usize>,
+    ) -> object_store::Result<Bytes> {
+        if range.is_empty() {
+            return Ok(Bytes::new());
+        }
+        let options = GetOptions {
+            if_match: meta.e_tag.clone(),
+            range: Some(GetRange::Bounded(range)),
+            ..Default::default()
+        };
+        self.get_opts(&meta.location, options).await?.bytes().await
+    }
+}
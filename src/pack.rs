@@ -0,0 +1,615 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    ops::Range,
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use futures::{
+    channel::oneshot,
+    future::{self, Either, Shared},
+    stream::{self, BoxStream},
+    FutureExt, StreamExt, TryStreamExt,
+};
+use object_store::{
+    path::Path, GetOptions, GetRange, GetResult, GetResultPayload, ListResult, MultipartId,
+    ObjectMeta, ObjectStore, PutMode, PutOptions, PutResult,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWrite;
+
+use crate::{
+    date_time,
+    environment::{default_timer, Clock, Random, SystemClock, SystemRandom, Timer},
+    error::Error,
+    key_encoding::key_to_path,
+    resolve_range,
+};
+
+/// Suffix of container objects holding the packed bodies.
+pub const CONTAINER_SUFFIX: &str = "pack";
+
+/// Suffix of the index objects describing a container.
+pub const INDEX_SUFFIX: &str = "index";
+
+/// Time a put waits for further puts to join its batch.
+pub const DEFAULT_WINDOW: Duration = Duration::from_millis(500);
+
+pub const DEFAULT_MAX_OBJECTS: usize = 1000;
+
+pub const DEFAULT_MAX_BYTES: usize = 1024 * 1024;
+
+/// Location of a packed object in its container.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackEntry {
+    pub location: String,
+    pub offset: usize,
+    pub size: usize,
+}
+
+/// Index object written next to every container.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackIndex {
+    pub container: String,
+    pub created: DateTime<Utc>,
+    /// Entries in the order they were put, a location put twice is only valid at its last entry.
+    pub entries: Vec<PackEntry>,
+}
+
+impl PackIndex {
+    /// Reads the index at `location`.
+    pub async fn read(store: &dyn ObjectStore, location: &Path) -> object_store::Result<Self> {
+        let bytes = store.get(location).await?.bytes().await?;
+        Ok(serde_json::from_slice(&bytes).map_err(Error::from)?)
+    }
+
+    /// Latest entry of `location`.
+    pub fn entry(&self, location: &Path) -> Option<&PackEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.location == location.as_ref())
+    }
+
+    /// Reads the body of `entry` from the container with a ranged get.
+    pub async fn read_entry(
+        &self,
+        store: &dyn ObjectStore,
+        entry: &PackEntry,
+    ) -> object_store::Result<Bytes> {
+        // A ranged get can't address an empty range
+        if entry.size == 0 {
+            return Ok(Bytes::new());
+        }
+        let container = key_to_path(&self.container);
+        read_range(store, &container, entry.offset..entry.offset + entry.size).await
+    }
+}
+
+async fn read_range(
+    store: &dyn ObjectStore,
+    container: &Path,
+    range: Range<usize>,
+) -> object_store::Result<Bytes> {
+    let options = GetOptions {
+        range: Some(GetRange::Bounded(range)),
+        ..Default::default()
+    };
+    store.get_opts(container, options).await?.bytes().await
+}
+
+type BatchResult = Shared<oneshot::Receiver<Result<(), String>>>;
+
+async fn batch_result(result: BatchResult) -> object_store::Result<()> {
+    match result.await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(message)) => Err(Error::PackFailed(message).into()),
+        Err(_) => Err(Error::PackFailed("pack writer was dropped".to_owned()).into()),
+    }
+}
+
+struct Batch {
+    id: u64,
+    objects: Vec<(Path, Bytes)>,
+    bytes: usize,
+    sender: oneshot::Sender<Result<(), String>>,
+    result: BatchResult,
+}
+
+#[derive(Default)]
+struct Pending {
+    next_id: u64,
+    batch: Option<Batch>,
+}
+
+impl std::fmt::Debug for Pending {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pending")
+            .field("next_id", &self.next_id)
+            .field(
+                "objects",
+                &self.batch.as_ref().map_or(0, |batch| batch.objects.len()),
+            )
+            .finish()
+    }
+}
+
+/// Packs many tiny puts into a single container object plus an index.
+///
+/// Each put joins the pending batch and resolves once that batch was written. A batch is written
+/// when it holds `max_objects` objects or `max_bytes` bytes, or `window` after a put joined it,
+/// as `<prefix>/<timestamp>-<random>.pack` followed by its [`PackIndex`] at
+/// `<prefix>/<timestamp>-<random>.index`. The index is only written once the container exists,
+/// so every index found by a reader can be resolved.
+#[derive(Debug)]
+pub struct PackWriter<T: ObjectStore> {
+    inner: T,
+    prefix: Path,
+    window: Duration,
+    max_objects: usize,
+    max_bytes: usize,
+    timer: Arc<dyn Timer>,
+    random: Arc<dyn Random>,
+    clock: Arc<dyn Clock>,
+    pending: Mutex<Pending>,
+}
+
+impl<T: ObjectStore> PackWriter<T> {
+    pub fn new(inner: T, prefix: Path) -> Self {
+        Self {
+            inner,
+            prefix,
+            window: DEFAULT_WINDOW,
+            max_objects: DEFAULT_MAX_OBJECTS,
+            max_bytes: DEFAULT_MAX_BYTES,
+            timer: default_timer(),
+            random: Arc::new(SystemRandom),
+            clock: Arc::new(SystemClock),
+            pending: Mutex::default(),
+        }
+    }
+
+    /// Time a put waits for further puts before its batch is written.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Write a batch as soon as it holds `objects` objects or `bytes` bytes.
+    pub fn with_limits(mut self, objects: usize, bytes: usize) -> Self {
+        self.max_objects = objects.max(1);
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Wait for the window with `timer` instead of the browser clock.
+    pub fn with_timer(mut self, timer: Arc<dyn Timer>) -> Self {
+        self.timer = timer;
+        self
+    }
+
+    /// Name containers with `random` instead of the platform generator.
+    pub fn with_random(mut self, random: Arc<dyn Random>) -> Self {
+        self.random = random;
+        self
+    }
+
+    /// Name containers and date indices with the time of `clock` instead of the platform clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Adds `bytes` under `location` to the pending batch and waits until the batch was written.
+    pub async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+        let (id, result, full) = {
+            let mut pending = self.pending.lock();
+            let mut batch = match pending.batch.take() {
+                Some(batch) => batch,
+                None => {
+                    let (sender, receiver) = oneshot::channel();
+                    pending.next_id += 1;
+                    Batch {
+                        id: pending.next_id,
+                        objects: Vec::new(),
+                        bytes: 0,
+                        sender,
+                        result: receiver.shared(),
+                    }
+                }
+            };
+            batch.bytes += bytes.len();
+            batch.objects.push((location.clone(), bytes));
+            let (id, result) = (batch.id, batch.result.clone());
+            if batch.objects.len() >= self.max_objects || batch.bytes >= self.max_bytes {
+                (id, result, Some(batch))
+            } else {
+                pending.batch = Some(batch);
+                (id, result, None)
+            }
+        };
+        if let Some(batch) = full {
+            self.write(batch).await;
+        } else {
+            let timeout = self.timer.sleep(self.window);
+            if let Either::Left(_) = future::select(timeout, result.clone()).await {
+                // Every put of a batch races to write it, the first one after the window wins
+                let batch = {
+                    let mut pending = self.pending.lock();
+                    match &pending.batch {
+                        Some(batch) if batch.id == id => pending.batch.take(),
+                        _ => None,
+                    }
+                };
+                if let Some(batch) = batch {
+                    self.write(batch).await;
+                }
+            }
+        }
+        batch_result(result).await
+    }
+
+    /// Writes the pending batch right away, e.g. before the page is unloaded.
+    pub async fn flush(&self) -> object_store::Result<()> {
+        let Some(batch) = self.pending.lock().batch.take() else {
+            return Ok(());
+        };
+        let result = batch.result.clone();
+        self.write(batch).await;
+        batch_result(result).await
+    }
+
+    async fn write(&self, batch: Batch) {
+        let result = self
+            .write_objects(batch.objects)
+            .await
+            .map_err(|err| err.to_string());
+        let _ = batch.sender.send(result);
+    }
+
+    async fn write_objects(&self, objects: Vec<(Path, Bytes)>) -> object_store::Result<()> {
+        let created = date_time(self.clock.now());
+        let name = format!(
+            "{:013}-{:016x}",
+            created.timestamp_millis(),
+            self.random.next_u64()
+        );
+        let container = self.prefix.child(format!("{}.{}", name, CONTAINER_SUFFIX));
+        let mut data = BytesMut::new();
+        let entries = objects
+            .into_iter()
+            .map(|(location, bytes)| {
+                let entry = PackEntry {
+                    location: location.to_string(),
+                    offset: data.len(),
+                    size: bytes.len(),
+                };
+                data.extend_from_slice(&bytes);
+                entry
+            })
+            .collect();
+        self.inner.put(&container, data.freeze()).await?;
+        let index = PackIndex {
+            container: container.to_string(),
+            created,
+            entries,
+        };
+        let index_bytes = serde_json::to_vec(&index).map_err(Error::from)?;
+        self.inner
+            .put(
+                &self.prefix.child(format!("{}.{}", name, INDEX_SUFFIX)),
+                index_bytes.into(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Locations of all index objects below the prefix, oldest first.
+    pub async fn indices(&self) -> object_store::Result<Vec<Path>> {
+        let mut indices = self
+            .inner
+            .list(Some(&self.prefix))
+            .try_filter(|meta| future::ready(meta.location.extension() == Some(INDEX_SUFFIX)))
+            .map_ok(|meta| meta.location)
+            .try_collect::<Vec<_>>()
+            .await?;
+        indices.sort();
+        Ok(indices)
+    }
+
+    /// Body of the latest packed object at `location`, `None` if no batch contains it.
+    ///
+    /// Reads the indices newest first and the body with a single ranged read.
+    pub async fn get(&self, location: &Path) -> object_store::Result<Option<Bytes>> {
+        for index in self.indices().await?.iter().rev() {
+            let index = PackIndex::read(&self.inner, index).await?;
+            if let Some(entry) = index.entry(location) {
+                return index.read_entry(&self.inner, entry).await.map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Latest entry of a logical location together with the index it was found in.
+#[derive(Debug, Clone)]
+struct Resolved {
+    index: Path,
+    container: Path,
+    created: DateTime<Utc>,
+    entry: PackEntry,
+}
+
+impl Resolved {
+    fn meta(&self, location: &Path) -> ObjectMeta {
+        ObjectMeta {
+            location: location.clone(),
+            last_modified: self.created,
+            size: self.entry.size,
+            // Every put of a location lands in a new container or at a new offset
+            e_tag: Some(format!("{}:{}", self.container, self.entry.offset)),
+            version: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct IndexCache {
+    /// Index objects already read, indices never change once written.
+    read: BTreeSet<Path>,
+    objects: BTreeMap<Path, Resolved>,
+}
+
+/// Store presenting the objects packed by a [`PackWriter`] as ordinary objects.
+///
+/// Reads resolve logical paths through the indices and read the bodies with ranged gets.
+/// Indices are cached, the prefix is only listed again when a path isn't found or on
+/// [`PackedStore::refresh`], so objects packed by other writers can be served stale until then.
+/// Puts are packed with the writer, other writes fail with
+/// [`object_store::Error::NotImplemented`] as packed objects can't be modified in place.
+#[derive(Debug)]
+pub struct PackedStore<T: ObjectStore> {
+    writer: PackWriter<T>,
+    cache: Mutex<IndexCache>,
+}
+
+impl<T: ObjectStore> PackedStore<T> {
+    pub fn new(writer: PackWriter<T>) -> Self {
+        Self {
+            writer,
+            cache: Mutex::default(),
+        }
+    }
+
+    pub fn writer(&self) -> &PackWriter<T> {
+        &self.writer
+    }
+
+    /// Reads the indices written since the last refresh.
+    pub async fn refresh(&self) -> object_store::Result<()> {
+        let indices = self.writer.indices().await?;
+        let unread = {
+            let cache = self.cache.lock();
+            indices
+                .into_iter()
+                .filter(|index| !cache.read.contains(index))
+                .collect::<Vec<_>>()
+        };
+        for location in unread {
+            let index = PackIndex::read(&self.writer.inner, &location).await?;
+            let container = key_to_path(&index.container);
+            let mut cache = self.cache.lock();
+            for entry in index.entries {
+                let resolved = Resolved {
+                    index: location.clone(),
+                    container: container.clone(),
+                    created: index.created,
+                    entry,
+                };
+                let logical = key_to_path(&resolved.entry.location);
+                match cache.objects.get(&logical) {
+                    // Indices are named by creation time, an older one mustn't win
+                    Some(existing) if existing.index > location => (),
+                    _ => {
+                        cache.objects.insert(logical, resolved);
+                    }
+                }
+            }
+            cache.read.insert(location);
+        }
+        Ok(())
+    }
+
+    async fn resolve(&self, location: &Path) -> object_store::Result<Resolved> {
+        if let Some(resolved) = self.cache.lock().objects.get(location) {
+            return Ok(resolved.clone());
+        }
+        self.refresh().await?;
+        self.cache
+            .lock()
+            .objects
+            .get(location)
+            .cloned()
+            .ok_or_else(|| object_store::Error::NotFound {
+                path: location.to_string(),
+                source: "not found in any pack index".into(),
+            })
+    }
+
+    /// Metadata of all packed objects below `prefix`.
+    async fn objects(&self, prefix: Option<&Path>) -> object_store::Result<Vec<ObjectMeta>> {
+        self.refresh().await?;
+        Ok(self
+            .cache
+            .lock()
+            .objects
+            .iter()
+            .filter(|(location, _)| prefix.is_none_or(|prefix| location.prefix_matches(prefix)))
+            .map(|(location, resolved)| resolved.meta(location))
+            .collect())
+    }
+}
+
+fn check_preconditions(options: &GetOptions, meta: &ObjectMeta) -> object_store::Result<()> {
+    let e_tag = meta.e_tag.as_deref().unwrap_or_default();
+    let matches = |tags: &str| tags == "*" || tags.split(',').map(str::trim).any(|x| x == e_tag);
+    let path = meta.location.to_string();
+    let failed = match (&options.if_match, options.if_unmodified_since) {
+        (Some(tags), _) => !matches(tags),
+        (None, Some(date)) => meta.last_modified > date,
+        (None, None) => false,
+    };
+    if failed {
+        return Err(object_store::Error::Precondition {
+            path,
+            source: "precondition failed".into(),
+        });
+    }
+    let not_modified = match (&options.if_none_match, options.if_modified_since) {
+        (Some(tags), _) => matches(tags),
+        (None, Some(date)) => meta.last_modified <= date,
+        (None, None) => false,
+    };
+    if not_modified {
+        return Err(object_store::Error::NotModified {
+            path,
+            source: "not modified".into(),
+        });
+    }
+    Ok(())
+}
+
+fn not_packable() -> object_store::Error {
+    object_store::Error::NotImplemented
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for PackedStore<T> {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        if opts.mode != PutMode::Overwrite {
+            return Err(not_packable());
+        }
+        self.writer.put(location, bytes).await?;
+        // The next read has to look up the new index
+        self.cache.lock().objects.remove(location);
+        Ok(PutResult {
+            e_tag: None,
+            version: None,
+        })
+    }
+    async fn put_multipart(
+        &self,
+        _location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        Err(not_packable())
+    }
+    async fn abort_multipart(
+        &self,
+        _location: &Path,
+        _multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        Err(not_packable())
+    }
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        if options.version.is_some() {
+            return Err(not_packable());
+        }
+        let resolved = self.resolve(location).await?;
+        let meta = resolved.meta(location);
+        check_preconditions(&options, &meta)?;
+        let range = options
+            .range
+            .as_ref()
+            .map_or(0..meta.size, |range| resolve_range(range, meta.size));
+        let payload = if options.head {
+            stream::empty().boxed()
+        } else if range.is_empty() {
+            // Empty objects and ranges at their end have nothing to read
+            stream::once(future::ready(Ok(Bytes::new()))).boxed()
+        } else {
+            let offset = resolved.entry.offset;
+            let bytes = read_range(
+                &self.writer.inner,
+                &resolved.container,
+                offset + range.start..offset + range.end,
+            )
+            .await?;
+            stream::once(future::ready(Ok(bytes))).boxed()
+        };
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(payload),
+            meta,
+            range,
+        })
+    }
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        Ok(self.resolve(location).await?.meta(location))
+    }
+    async fn delete(&self, _location: &Path) -> object_store::Result<()> {
+        Err(not_packable())
+    }
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        let prefix = prefix.cloned();
+        stream::once(async move { self.objects(prefix.as_ref()).await })
+            .map_ok(|objects| stream::iter(objects.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let mut objects = Vec::new();
+        let mut common_prefixes = BTreeSet::new();
+        for meta in self.objects(prefix).await? {
+            let mut parts = match prefix {
+                Some(prefix) => meta
+                    .location
+                    .prefix_match(prefix)
+                    .map(|parts| parts.collect::<Vec<_>>())
+                    .unwrap_or_default(),
+                None => meta.location.parts().collect(),
+            };
+            if parts.len() > 1 {
+                let first = parts.swap_remove(0);
+                common_prefixes.insert(match prefix {
+                    Some(prefix) => prefix.child(first),
+                    None => Path::from_iter([first]),
+                });
+            } else {
+                objects.push(meta);
+            }
+        }
+        Ok(ListResult {
+            objects,
+            common_prefixes: common_prefixes.into_iter().collect(),
+        })
+    }
+    async fn copy(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(not_packable())
+    }
+    async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(not_packable())
+    }
+}
+
+impl<T: ObjectStore> Display for PackedStore<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Packed({}, {})", self.writer.inner, self.writer.prefix)
+    }
+}
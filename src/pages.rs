@@ -0,0 +1,102 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use bytes::{Bytes, BytesMut};
+use futures::future::try_join_all;
+use object_store::{path::Path, ObjectStore};
+
+use crate::S3;
+
+/// Fixed size pages of an object of which only some are loaded.
+#[derive(Debug, Clone)]
+pub struct SparseBuffer {
+    page_size: usize,
+    pages: BTreeMap<usize, Bytes>,
+}
+
+impl SparseBuffer {
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Content of page `index`, `None` if it wasn't loaded. The last page of an object may be
+    /// shorter than the page size.
+    pub fn page(&self, index: usize) -> Option<&Bytes> {
+        self.pages.get(&index)
+    }
+
+    /// Loaded pages in ascending order.
+    pub fn pages(&self) -> impl Iterator<Item = (usize, &Bytes)> {
+        self.pages.iter().map(|(index, page)| (*index, page))
+    }
+
+    /// Bytes in `range` of the object, `None` unless all pages covering it are loaded.
+    pub fn read(&self, range: Range<usize>) -> Option<Bytes> {
+        if range.is_empty() {
+            return Some(Bytes::new());
+        }
+        let first = range.start / self.page_size;
+        let last = (range.end - 1) / self.page_size;
+        if first == last {
+            let page = self.pages.get(&first)?;
+            let offset = first * self.page_size;
+            return (range.end - offset <= page.len())
+                .then(|| page.slice(range.start - offset..range.end - offset));
+        }
+        let mut buffer = BytesMut::with_capacity(range.len());
+        for index in first..=last {
+            let page = self.pages.get(&index)?;
+            let offset = index * self.page_size;
+            let start = range.start.saturating_sub(offset);
+            let end = (range.end - offset).min(page.len());
+            if start > end {
+                return None;
+            }
+            buffer.extend_from_slice(&page[start..end]);
+        }
+        (buffer.len() == range.len()).then(|| buffer.freeze())
+    }
+}
+
+impl S3 {
+    /// Loads the pages `page_indices` of `page_size` bytes of the object at `location`.
+    ///
+    /// Runs of consecutive pages are fetched with a single ranged request, all runs are
+    /// fetched concurrently. Pages must not start behind the end of the object.
+    pub async fn read_pages(
+        &self,
+        location: &Path,
+        page_size: usize,
+        page_indices: &[usize],
+    ) -> object_store::Result<SparseBuffer> {
+        let page_size = page_size.max(1);
+        let mut indices = page_indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let mut runs: Vec<Range<usize>> = Vec::new();
+        for index in indices {
+            match runs.last_mut() {
+                Some(run) if run.end == index => run.end += 1,
+                _ => runs.push(index..index + 1),
+            }
+        }
+
+        let fetched = try_join_all(runs.into_iter().map(|run| async move {
+            let bytes = self
+                .get_range(location, run.start * page_size..run.end * page_size)
+                .await?;
+            Ok::<_, object_store::Error>((run, bytes))
+        }))
+        .await?;
+
+        let mut pages = BTreeMap::new();
+        for (run, bytes) in fetched {
+            for (offset, index) in run.enumerate() {
+                let start = (offset * page_size).min(bytes.len());
+                let end = (start + page_size).min(bytes.len());
+                pages.insert(index, bytes.slice(start..end));
+            }
+        }
+        Ok(SparseBuffer { page_size, pages })
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use futures::{future::BoxFuture, stream, FutureExt, StreamExt};
+use object_store::{path::Path, GetOptions, GetResult, GetResultPayload, ObjectStore};
+
+use crate::{
+    attributes::PutAttributes, chunks::bounded, error::is_range_not_satisfiable,
+    status::ObjectStatus, S3,
+};
+
+/// Whole-object `get`s above `threshold` bytes are split into ranged `GET`s of `chunk_size`
+/// bytes with up to `parallelism` in flight, see
+/// [`S3Builder::with_parallel_downloads`](crate::builder::S3Builder::with_parallel_downloads).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ParallelDownloads {
+    pub(crate) threshold: usize,
+    pub(crate) chunk_size: usize,
+    pub(crate) parallelism: usize,
+}
+
+impl S3 {
+    /// Reads an object in chunks. The first chunk carries the conditions of `options` and tells
+    /// the object's size, every further chunk is pinned to its ETag, so the parts can't mix
+    /// versions.
+    pub(crate) async fn get_parallel(
+        &self,
+        location: &Path,
+        options: GetOptions,
+        parallel: ParallelDownloads,
+        requested_at: Duration,
+    ) -> object_store::Result<(GetResult, ObjectStatus, PutAttributes)> {
+        let chunk_size = parallel.chunk_size;
+        let version = options.version.clone();
+        let first = GetOptions {
+            if_match: options.if_match.clone(),
+            if_none_match: options.if_none_match.clone(),
+            if_modified_since: options.if_modified_since,
+            if_unmodified_since: options.if_unmodified_since,
+            range: Some((0..chunk_size).into()),
+            version: options.version.clone(),
+            head: false,
+        };
+        let (first, status, attributes) =
+            match self.fetch_object(location, first, requested_at).await {
+                // Empty objects have no first chunk
+                Err(err) if is_range_not_satisfiable(&err) => {
+                    return self.fetch_object(location, options, requested_at).await
+                }
+                result => result?,
+            };
+        let meta = first.meta.clone();
+        let size = meta.size;
+        if size <= chunk_size {
+            return Ok((first, status, attributes));
+        }
+
+        // Objects up to the threshold are completed with a single request
+        let step = if size > parallel.threshold {
+            chunk_size
+        } else {
+            size
+        };
+        let rest = (chunk_size..size).step_by(step).map(|start| {
+            let store = self.clone();
+            let location = location.clone();
+            let options = GetOptions {
+                range: Some((start..(start + step).min(size)).into()),
+                if_match: meta.e_tag.clone(),
+                version: version.clone(),
+                ..Default::default()
+            };
+            async move { store.get_opts(&location, options).await?.bytes().await }.boxed()
+        });
+        let chunks: Vec<BoxFuture<'static, object_store::Result<_>>> =
+            std::iter::once(first.bytes().boxed()).chain(rest).collect();
+        let payload = bounded(
+            stream::iter(chunks).buffered(parallel.parallelism).boxed(),
+            self.max_chunk_size,
+        );
+        Ok((
+            GetResult {
+                payload: GetResultPayload::Stream(payload),
+                meta,
+                range: 0..size,
+            },
+            status,
+            attributes,
+        ))
+    }
+}
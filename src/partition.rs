@@ -0,0 +1,68 @@
+/// AWS partition a region belongs to.
+///
+/// Partitions differ in their DNS suffix and the partition component of ARNs, and the isolated
+/// partitions have no global endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Partition {
+    #[default]
+    Aws,
+    AwsCn,
+    AwsUsGov,
+    AwsIso,
+    AwsIsoB,
+}
+
+impl Partition {
+    /// Partition of `region`, regions of unknown form belong to the standard partition.
+    pub fn from_region(region: &str) -> Self {
+        if region.starts_with("cn-") {
+            Partition::AwsCn
+        } else if region.starts_with("us-gov-") {
+            Partition::AwsUsGov
+        } else if region.starts_with("us-isob-") {
+            Partition::AwsIsoB
+        } else if region.starts_with("us-iso-") {
+            Partition::AwsIso
+        } else {
+            Partition::Aws
+        }
+    }
+
+    /// Partition component of ARNs, e.g. `aws-cn` in `arn:aws-cn:s3:::bucket`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Partition::Aws => "aws",
+            Partition::AwsCn => "aws-cn",
+            Partition::AwsUsGov => "aws-us-gov",
+            Partition::AwsIso => "aws-iso",
+            Partition::AwsIsoB => "aws-iso-b",
+        }
+    }
+
+    pub fn dns_suffix(&self) -> &'static str {
+        match self {
+            Partition::Aws | Partition::AwsUsGov => "amazonaws.com",
+            Partition::AwsCn => "amazonaws.com.cn",
+            Partition::AwsIso => "c2s.ic.gov",
+            Partition::AwsIsoB => "sc2s.sgov.gov",
+        }
+    }
+
+    /// Whether the partition offers global endpoints like `sts.amazonaws.com`.
+    pub fn has_global_endpoints(&self) -> bool {
+        matches!(self, Partition::Aws)
+    }
+
+    /// Endpoint of `service` in `region`, e.g. `https://sts.cn-north-1.amazonaws.com.cn`.
+    pub fn regional_endpoint(&self, service: &str, region: &str) -> String {
+        format!("https://{}.{}.{}", service, region, self.dns_suffix())
+    }
+
+    /// ARN of an S3 bucket or, with `key`, an object.
+    pub fn s3_arn(&self, bucket: &str, key: Option<&str>) -> String {
+        match key {
+            Some(key) => format!("arn:{}:s3:::{}/{}", self.name(), bucket, key),
+            None => format!("arn:{}:s3:::{}", self.name(), bucket),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use aws_sdk_s3::types::Part;
+use chrono::{DateTime, Utc};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+use object_store::{path::Path, MultipartId};
+
+use crate::{error::Error, S3};
+
+/// Part of an unfinished multipart upload as recorded by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadedPart {
+    pub part_number: i32,
+    pub e_tag: Option<String>,
+    pub size: usize,
+    pub last_modified: Option<DateTime<Utc>>,
+    /// Additional checksum of the part, if the upload was started with a checksum algorithm.
+    pub checksum: Option<String>,
+}
+
+impl TryFrom<&Part> for UploadedPart {
+    type Error = Error;
+
+    fn try_from(part: &Part) -> Result<Self, Self::Error> {
+        Ok(UploadedPart {
+            part_number: part.part_number(),
+            e_tag: part.e_tag().map(ToOwned::to_owned),
+            size: part.size() as usize,
+            last_modified: part
+                .last_modified()
+                .map(|date_time| {
+                    DateTime::from_timestamp_millis(date_time.to_millis()?).ok_or(Error::Unknown)
+                })
+                .transpose()?,
+            checksum: part
+                .checksum_crc32_c()
+                .or(part.checksum_sha256())
+                .map(ToOwned::to_owned),
+        })
+    }
+}
+
+impl S3 {
+    /// Parts uploaded so far for the multipart upload `upload_id`, in ascending part order.
+    ///
+    /// Follows the `ListParts` pagination, so uploads with more than 1000 parts are listed
+    /// completely. Together with the upload id this is enough to resume an upload after the
+    /// client lost its local state.
+    pub fn list_parts(
+        &self,
+        location: &Path,
+        upload_id: &MultipartId,
+    ) -> BoxStream<'_, object_store::Result<UploadedPart>> {
+        let key = location.to_string();
+        let upload_id = upload_id.clone();
+        stream::try_unfold(Some(None::<String>), move |marker| {
+            let key = key.clone();
+            let upload_id = upload_id.clone();
+            async move {
+                let Some(marker) = marker else {
+                    return Ok(None);
+                };
+                let response = self
+                    .client
+                    .list_parts()
+                    .bucket(self.bucket.clone())
+                    .key(&key)
+                    .upload_id(upload_id)
+                    .set_part_number_marker(marker)
+                    .send()
+                    .await
+                    .map_err(|err| self.request_error(&key, err))?;
+                let parts = response
+                    .parts()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(UploadedPart::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let next = response
+                    .is_truncated()
+                    .then(|| response.next_part_number_marker().map(ToOwned::to_owned))
+                    .flatten()
+                    .map(Some);
+                Ok::<_, object_store::Error>(Some((stream::iter(parts.into_iter().map(Ok)), next)))
+            }
+        })
+        .try_flatten()
+        .boxed()
+    }
+}
@@ -0,0 +1,279 @@
+use std::{future::Future, ops::Range};
+
+use bytes::Bytes;
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use futures::channel::oneshot;
+use futures::{stream, StreamExt, TryStreamExt};
+use js_sys::Uint8Array;
+use object_store::{path::Path, GetOptions, GetRange, ObjectStore};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+
+#[cfg(feature = "shared_session")]
+use crate::shared_session;
+use crate::{
+    coalesce::coalesce_ranges,
+    error::Error,
+    idb::{prefix_range, wait, Database},
+    S3,
+};
+
+/// IndexedDB object store holding the cached bytes.
+pub(crate) const DATA_STORE: &str = "range_cache";
+
+/// IndexedDB object store holding the size and timestamps of every cached range.
+pub(crate) const ENTRY_STORE: &str = "range_cache_entries";
+
+/// Ranges fetched concurrently on a cache miss.
+const FETCH_CONCURRENCY: usize = 10;
+
+/// Which cached ranges are dropped first once the persistent cache is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// Least recently read ranges first.
+    #[default]
+    Lru,
+    /// Earliest cached ranges first, reads don't write to IndexedDB.
+    Fifo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    size: usize,
+    inserted: i64,
+    used: i64,
+}
+
+/// Byte ranges cached in IndexedDB across page loads, keyed by bucket, key, ETag and range, see
+/// [`S3Builder::with_persistent_cache`](crate::builder::S3Builder::with_persistent_cache).
+///
+/// IndexedDB is only used from the local task, failing cache reads and writes are ignored.
+#[derive(Debug, Clone)]
+pub(crate) struct PersistentCache {
+    pub(crate) max_bytes: usize,
+    pub(crate) policy: EvictionPolicy,
+}
+
+/// Spawns `task` on the local task and returns its output, the returned future is `Send` even
+/// though IndexedDB handles aren't.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+fn local<T: Send + 'static>(
+    task: impl Future<Output = T> + 'static,
+) -> impl Future<Output = Option<T>> + Send {
+    let (tx, rx) = oneshot::channel();
+    wasm_bindgen_futures::spawn_local(async move {
+        let _ = tx.send(task.await);
+    });
+    async move { rx.await.ok() }
+}
+
+/// Native builds have no IndexedDB, the cache misses every read and drops every write.
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+fn local<T: Send + 'static>(
+    _task: impl Future<Output = T> + 'static,
+) -> impl Future<Output = Option<T>> + Send {
+    futures::future::ready(None)
+}
+
+fn cache_key(bucket: &str, location: &Path, e_tag: &str, range: &Range<usize>) -> String {
+    serde_json::json!([bucket, location.as_ref(), e_tag, range.start, range.end]).to_string()
+}
+
+async fn entries(db: &Database) -> Result<Vec<CacheEntry>, Error> {
+    parse_entries(db.get_all(ENTRY_STORE, &prefix_range("")?).await?)
+}
+
+fn parse_entries(values: impl IntoIterator<Item = JsValue>) -> Result<Vec<CacheEntry>, Error> {
+    values
+        .into_iter()
+        .filter_map(|value| value.as_string())
+        .map(|value| Ok(serde_json::from_str(&value)?))
+        .collect()
+}
+
+impl PersistentCache {
+    /// Cached bytes of `key`, marking them as used at `now` (milliseconds since the Unix epoch).
+    async fn get(&self, key: String, now: i64) -> Option<Bytes> {
+        let policy = self.policy;
+        local(async move {
+            let db = Database::open().await.ok()?;
+            let value = db.get(DATA_STORE, &key).await.ok()??;
+            if policy == EvictionPolicy::Lru {
+                if let Some(mut entry) = db
+                    .get(ENTRY_STORE, &key)
+                    .await
+                    .ok()
+                    .flatten()
+                    .and_then(|value| value.as_string())
+                    .and_then(|value| serde_json::from_str::<CacheEntry>(&value).ok())
+                {
+                    entry.used = now;
+                    if let Ok(value) = serde_json::to_string(&entry) {
+                        let _ = db.put(ENTRY_STORE, &key, &value.into()).await;
+                    }
+                }
+            }
+            Some(Bytes::from(Uint8Array::new(&value).to_vec()))
+        })
+        .await
+        .flatten()
+    }
+
+    /// Stores `data` as cached at `now`, first evicting entries as the policy dictates until it
+    /// fits.
+    ///
+    /// The entries are read, evicted and written in one transaction, so concurrent puts of other
+    /// stores or tabs can't push the cache past its capacity.
+    async fn put(&self, key: String, data: Bytes, now: i64) {
+        if data.len() > self.max_bytes {
+            return;
+        }
+        let (max_bytes, policy) = (self.max_bytes, self.policy);
+        local(async move {
+            let db = Database::open().await?;
+            let transaction = db.write_transaction(&[DATA_STORE, ENTRY_STORE])?;
+            let data_store = transaction.object_store(DATA_STORE)?;
+            let entry_store = transaction.object_store(ENTRY_STORE)?;
+            let values: js_sys::Array = wait(&entry_store.get_all_with_key(&prefix_range("")?)?)
+                .await?
+                .dyn_into()?;
+            let mut cached = parse_entries(values.iter())?;
+            match policy {
+                EvictionPolicy::Lru => cached.sort_by_key(|entry| entry.used),
+                EvictionPolicy::Fifo => cached.sort_by_key(|entry| entry.inserted),
+            }
+            let mut size = cached.iter().map(|entry| entry.size).sum::<usize>() + data.len();
+            let mut evicted = Vec::new();
+            for entry in cached {
+                if size <= max_bytes {
+                    break;
+                }
+                size -= entry.size;
+                evicted.push(entry.key);
+            }
+            let entry = CacheEntry {
+                key: key.clone(),
+                size: data.len(),
+                inserted: now,
+                used: now,
+            };
+            let value = JsValue::from(Uint8Array::from(data.as_ref()));
+            let entry = JsValue::from(serde_json::to_string(&entry)?);
+            for evicted in evicted {
+                data_store.delete(&JsValue::from_str(&evicted))?;
+                entry_store.delete(&JsValue::from_str(&evicted))?;
+            }
+            data_store.put_with_key(&value, &JsValue::from_str(&key))?;
+            // Requests of a transaction complete in order, the last one finishing means all did
+            wait(&entry_store.put_with_key(&entry, &JsValue::from_str(&key))?).await?;
+            Ok::<_, Error>(())
+        })
+        .await;
+    }
+}
+
+impl S3 {
+    /// [`ObjectStore::get_ranges`] served from the persistent cache where possible.
+    ///
+    /// The object is inspected with a `HEAD` to find its ETag, missing ranges are fetched from
+    /// that version and cached. Objects without an ETag bypass the cache.
+    pub(crate) async fn get_persisted_ranges(
+        &self,
+        cache: &PersistentCache,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        let Some(e_tag) = self.head(location).await?.e_tag else {
+            return self.get_coalesced_ranges(location, ranges).await;
+        };
+        let keys = ranges
+            .iter()
+            .map(|range| cache_key(&self.bucket, location, &e_tag, range))
+            .collect::<Vec<_>>();
+        let now = self.now().timestamp_millis();
+        let mut cached = Vec::with_capacity(ranges.len());
+        for key in &keys {
+            cached.push(cache.get(key.clone(), now).await);
+        }
+        #[cfg(feature = "shared_session")]
+        let _lock = match self.shared_session && cached.iter().any(Option::is_none) {
+            true => {
+                let name = shared_session::download_lock(&self.bucket, location.as_ref(), &e_tag);
+                let lock = shared_session::lock(&name).await;
+                // Another tab may have downloaded the ranges while this one waited for the lock
+                for (key, cached) in keys.iter().zip(&mut cached) {
+                    if cached.is_none() {
+                        *cached = cache.get(key.clone(), now).await;
+                    }
+                }
+                Some(lock)
+            }
+            false => None,
+        };
+        let misses = ranges
+            .iter()
+            .zip(&cached)
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(range, _)| range.clone())
+            .collect::<Vec<_>>();
+        let fetch = coalesce_ranges(&misses, self.coalesce_gap, self.max_coalesced_size);
+        let fetched = stream::iter(fetch.iter().cloned())
+            .map(|range| {
+                let options = GetOptions {
+                    if_match: Some(e_tag.clone()),
+                    range: Some(GetRange::Bounded(range)),
+                    ..Default::default()
+                };
+                async move { self.get_opts(location, options).await?.bytes().await }
+            })
+            .buffered(FETCH_CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let mut result = Vec::with_capacity(ranges.len());
+        for ((range, key), cached) in ranges.iter().zip(keys).zip(cached) {
+            if let Some(data) = cached {
+                result.push(data);
+                continue;
+            }
+            if range.is_empty() {
+                result.push(Bytes::new());
+                continue;
+            }
+            let idx = fetch.partition_point(|fetched| fetched.start <= range.start) - 1;
+            let offset = fetch[idx].start;
+            let data = &fetched[idx];
+            let data = data.slice(
+                (range.start - offset).min(data.len())..(range.end - offset).min(data.len()),
+            );
+            cache.put(key, data.clone(), now).await;
+            result.push(data);
+        }
+        Ok(result)
+    }
+
+    /// Bytes held by the persistent cache of all stores of this page's origin.
+    pub async fn persistent_cache_size(&self) -> object_store::Result<usize> {
+        let size = local(async {
+            let db = Database::open().await?;
+            Ok::<_, Error>(entries(&db).await?.iter().map(|entry| entry.size).sum())
+        })
+        .await
+        .unwrap_or(Ok(0))?;
+        Ok(size)
+    }
+
+    /// Drops every range of the persistent cache.
+    pub async fn clear_persistent_cache(&self) -> object_store::Result<()> {
+        local(async {
+            let db = Database::open().await?;
+            db.delete_range(DATA_STORE, &prefix_range("")?).await?;
+            db.delete_range(ENTRY_STORE, &prefix_range("")?).await?;
+            Ok::<_, Error>(())
+        })
+        .await
+        .unwrap_or(Ok(()))?;
+        Ok(())
+    }
+}
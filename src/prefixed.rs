@@ -0,0 +1,222 @@
+use std::{fmt::Display, ops::Range};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt};
+use object_store::{
+    path::Path, GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore,
+    PutOptions, PutResult,
+};
+use tokio::io::AsyncWrite;
+
+use crate::{builder::S3Builder, error::Error, S3};
+
+/// Store rooted at a key prefix of a bucket, e.g. for `s3://bucket/warehouse` paths.
+///
+/// The prefix is prepended to every path sent and stripped from every path returned, so callers
+/// only see paths relative to the root. Create one per logical root with [`S3::with_prefix`],
+/// they all share the client of the [`S3`] store.
+#[derive(Debug, Clone)]
+pub struct PrefixedS3 {
+    inner: S3,
+    prefix: Path,
+}
+
+impl PrefixedS3 {
+    pub fn new(inner: S3, prefix: Path) -> Self {
+        Self { inner, prefix }
+    }
+
+    pub fn inner(&self) -> &S3 {
+        &self.inner
+    }
+
+    pub fn prefix(&self) -> &Path {
+        &self.prefix
+    }
+
+    /// Path in the bucket of `location`, relative to the prefix.
+    pub fn full_path(&self, location: &Path) -> Path {
+        Path::from_iter(self.prefix.parts().chain(location.parts()))
+    }
+
+    /// Path relative to the prefix of `location` in the bucket, `None` if it is outside of it.
+    pub fn strip_prefix(&self, location: &Path) -> Option<Path> {
+        location.prefix_match(&self.prefix).map(Path::from_iter)
+    }
+
+    fn full_prefix(&self, prefix: Option<&Path>) -> Path {
+        prefix.map_or_else(|| self.prefix.clone(), |prefix| self.full_path(prefix))
+    }
+
+    fn strip(&self, meta: ObjectMeta) -> object_store::Result<ObjectMeta> {
+        let location =
+            self.strip_prefix(&meta.location)
+                .ok_or_else(|| object_store::Error::Generic {
+                    store: "Prefixed",
+                    source: format!("{} is outside of {}", meta.location, self.prefix).into(),
+                })?;
+        Ok(ObjectMeta { location, ..meta })
+    }
+}
+
+impl S3 {
+    /// Store rooted at `prefix` of the bucket, sharing this store's client.
+    pub fn with_prefix(&self, prefix: impl Into<Path>) -> PrefixedS3 {
+        PrefixedS3::new(self.clone(), prefix.into())
+    }
+}
+
+impl S3Builder {
+    /// Builder for the bucket and key prefix of an `s3://bucket/prefix` URL, build it with
+    /// [`S3Builder::build_prefixed`]. Region, endpoint and credentials are configured as usual.
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidUrl(url.to_owned());
+        let rest = url.strip_prefix("s3://").ok_or_else(invalid)?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(invalid());
+        }
+        let prefix = Path::parse(prefix).map_err(|_| invalid())?;
+        Ok(S3Builder {
+            prefix: Some(prefix),
+            ..S3Builder::default().bucket(bucket)
+        })
+    }
+
+    /// Builds a store rooted at the prefix of [`S3Builder::from_url`], or at the bucket root.
+    pub fn build_prefixed(mut self) -> Result<PrefixedS3, Error> {
+        let prefix = self.prefix.take().unwrap_or_default();
+        Ok(PrefixedS3::new(self.build()?, prefix))
+    }
+}
+
+#[async_trait]
+impl ObjectStore for PrefixedS3 {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        self.inner
+            .put_opts(&self.full_path(location), bytes, opts)
+            .await
+    }
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.inner.put_multipart(&self.full_path(location)).await
+    }
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.inner
+            .abort_multipart(&self.full_path(location), multipart_id)
+            .await
+    }
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let result = self
+            .inner
+            .get_opts(&self.full_path(location), options)
+            .await?;
+        Ok(GetResult {
+            meta: ObjectMeta {
+                location: location.clone(),
+                ..result.meta
+            },
+            ..result
+        })
+    }
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> object_store::Result<Bytes> {
+        self.inner.get_range(&self.full_path(location), range).await
+    }
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        self.inner
+            .get_ranges(&self.full_path(location), ranges)
+            .await
+    }
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        let meta = self.inner.head(&self.full_path(location)).await?;
+        Ok(ObjectMeta {
+            location: location.clone(),
+            ..meta
+        })
+    }
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(&self.full_path(location)).await
+    }
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        let prefix = self.full_prefix(prefix);
+        self.inner
+            .list(Some(&prefix))
+            .map(move |meta| meta.and_then(|meta| self.strip(meta)))
+            .boxed()
+    }
+    fn list_with_offset(
+        &self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        let prefix = self.full_prefix(prefix);
+        self.inner
+            .list_with_offset(Some(&prefix), &self.full_path(offset))
+            .map(move |meta| meta.and_then(|meta| self.strip(meta)))
+            .boxed()
+    }
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let result = self
+            .inner
+            .list_with_delimiter(Some(&self.full_prefix(prefix)))
+            .await?;
+        Ok(ListResult {
+            objects: result
+                .objects
+                .into_iter()
+                .map(|meta| self.strip(meta))
+                .collect::<object_store::Result<_>>()?,
+            common_prefixes: result
+                .common_prefixes
+                .iter()
+                .filter_map(|prefix| self.strip_prefix(prefix))
+                .collect(),
+        })
+    }
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner
+            .copy(&self.full_path(from), &self.full_path(to))
+            .await
+    }
+    async fn rename(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner
+            .rename(&self.full_path(from), &self.full_path(to))
+            .await
+    }
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner
+            .copy_if_not_exists(&self.full_path(from), &self.full_path(to))
+            .await
+    }
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner
+            .rename_if_not_exists(&self.full_path(from), &self.full_path(to))
+            .await
+    }
+}
+
+impl Display for PrefixedS3 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Prefixed({}, {})", self.prefix, self.inner)
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use aws_sdk_s3::presigning::PresigningConfig;
+use http::Method;
+use object_store::path::Path;
+
+use crate::{error::Error, S3};
+
+/// Longest expiry S3 accepts for a presigned URL.
+pub const MAX_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+impl S3 {
+    /// URL granting `method` access to `location` without credentials until `expiry` elapsed.
+    ///
+    /// Supports `GET`, `PUT` and `HEAD`, `expiry` can be at most [`MAX_EXPIRY`]. The signature
+    /// starts at the current time of the store's [`Clock`](crate::environment::Clock), stores
+    /// with a [`RemoteSigner`](crate::remote_signing::RemoteSigner) have it sign the URL.
+    pub async fn signed_url(
+        &self,
+        method: Method,
+        location: &Path,
+        expiry: Duration,
+    ) -> object_store::Result<String> {
+        let config = PresigningConfig::builder()
+            .start_time(self.clock().now())
+            .expires_in(expiry)
+            .build()
+            .map_err(|err| Error::Presigning(err.to_string()))?;
+        let request = match method {
+            Method::GET => self
+                .presign_client
+                .get_object()
+                .bucket(self.bucket.clone())
+                .key(location.to_string())
+                .presigned(config)
+                .await
+                .map_err(Error::from)?,
+            Method::PUT => self
+                .presign_client
+                .put_object()
+                .bucket(self.bucket.clone())
+                .key(location.to_string())
+                .presigned(config)
+                .await
+                .map_err(Error::from)?,
+            Method::HEAD => self
+                .presign_client
+                .head_object()
+                .bucket(self.bucket.clone())
+                .key(location.to_string())
+                .presigned(config)
+                .await
+                .map_err(Error::from)?,
+            method => return Err(Error::UnsupportedMethod(method.to_string()).into()),
+        };
+        match &self.signer {
+            Some(signer) if signer.is_remote() => Ok(signer
+                .resign_url(request.method(), request.uri(), request.headers())
+                .await?),
+            _ => Ok(request.uri().to_string()),
+        }
+    }
+}
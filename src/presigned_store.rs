@@ -0,0 +1,266 @@
+use std::{fmt::Display, sync::Arc};
+
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::SdkBody;
+use bytes::Bytes;
+use chrono::DateTime;
+use futures::{future, stream, stream::BoxStream, StreamExt};
+use http::{header, HeaderMap, Method, StatusCode};
+use object_store::{
+    path::Path, GetOptions, GetResult, GetResultPayload, ListResult, MultipartId, ObjectMeta,
+    ObjectStore, PutOptions, PutResult,
+};
+use tokio::io::AsyncWrite;
+
+use crate::{
+    builder::{Adapter, S3Builder},
+    environment::{default_timer, SystemRandom},
+    error::Error,
+    metrics::MetricsRecorder,
+    parse_content_range, range_header, resolve_range,
+    retry::RetryConfig,
+    throttle::Throttle,
+    timeout::Timeouts,
+};
+
+/// Source of presigned URLs for a [`PresignedStore`], e.g. a backend endpoint that signs the
+/// requests a user is allowed to make.
+#[async_trait]
+pub trait UrlSigner: std::fmt::Debug + Send + Sync {
+    /// URL granting `method`, `GET` or `HEAD`, on `location`.
+    async fn signed_url(&self, method: Method, location: &Path) -> object_store::Result<String>;
+}
+
+/// Read-only store sending every request to a URL of its [`UrlSigner`], it holds no
+/// credentials. Built with [`S3Builder::build_presigned`].
+///
+/// Supports gets, including ranges and conditions, and heads. Everything else fails with
+/// [`object_store::Error::NotImplemented`].
+#[derive(Debug)]
+pub struct PresignedStore {
+    signer: Arc<dyn UrlSigner>,
+    adapter: Adapter,
+    metrics: MetricsRecorder,
+}
+
+impl S3Builder {
+    /// Builds a [`PresignedStore`] reading through the URLs of `signer`.
+    ///
+    /// Only the retry, timeout, concurrency and rate limit settings of the builder apply, the
+    /// bucket, endpoint and credentials are part of the signed URLs.
+    pub fn build_presigned(self, signer: impl UrlSigner + 'static) -> PresignedStore {
+        let timer = self.timer.unwrap_or_else(default_timer);
+        let metrics = MetricsRecorder::default();
+        let adapter = Adapter::new(
+            false,
+            None,
+            metrics.clone(),
+            None,
+            timer.clone(),
+            self.random.unwrap_or_else(|| Arc::new(SystemRandom)),
+            self.retry.unwrap_or_else(RetryConfig::disabled),
+            Timeouts {
+                connect: self.connect_timeout,
+                total: self.request_timeout,
+            },
+            Throttle::new(self.max_concurrent_requests, self.rate_limit, timer),
+        );
+        PresignedStore {
+            signer: Arc::new(signer),
+            adapter,
+            metrics,
+        }
+    }
+}
+
+fn not_implemented() -> object_store::Error {
+    object_store::Error::NotImplemented
+}
+
+/// Metadata of `location` from the headers of a `GET` or `HEAD` response.
+fn object_meta(location: &Path, headers: &HeaderMap) -> Result<ObjectMeta, Error> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let size = match header(header::CONTENT_RANGE.as_str()) {
+        Some(content_range) => parse_content_range(content_range)?.1,
+        None => header(header::CONTENT_LENGTH.as_str())
+            .ok_or(Error::Unknown)?
+            .parse()?,
+    };
+    let last_modified = header(header::LAST_MODIFIED.as_str())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .ok_or(Error::Unknown)?
+        .to_utc();
+    Ok(ObjectMeta {
+        location: location.clone(),
+        last_modified,
+        size,
+        e_tag: header(header::ETAG.as_str()).map(ToOwned::to_owned),
+        version: header("x-amz-version-id").map(ToOwned::to_owned),
+    })
+}
+
+impl PresignedStore {
+    /// Requests and response sizes of the store, only the network fields are collected.
+    pub fn metrics(&self) -> crate::metrics::StoreMetrics {
+        self.metrics.snapshot()
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        location: &Path,
+        options: &GetOptions,
+    ) -> object_store::Result<http::Response<SdkBody>> {
+        let url = self.signer.signed_url(method.clone(), location).await?;
+        let mut request = http::Request::builder().method(method).uri(url);
+        let conditions = [
+            (header::IF_MATCH, options.if_match.clone()),
+            (header::IF_NONE_MATCH, options.if_none_match.clone()),
+            (
+                header::IF_MODIFIED_SINCE,
+                options.if_modified_since.map(|date| date.to_rfc2822()),
+            ),
+            (
+                header::IF_UNMODIFIED_SINCE,
+                options.if_unmodified_since.map(|date| date.to_rfc2822()),
+            ),
+            (
+                header::RANGE,
+                options.range.as_ref().map(range_header).transpose()?,
+            ),
+        ];
+        for (name, value) in conditions {
+            if let Some(value) = value {
+                request = request.header(name, value);
+            }
+        }
+        let (parts, _) = request
+            .body(())
+            .map_err(|err| Error::Presigning(err.to_string()))?
+            .into_parts();
+        let response = self
+            .adapter
+            .send(parts, SdkBody::empty())
+            .await
+            .map_err(|err| Error::Transport(err.to_string()))?;
+        let path = location.to_string();
+        let source = || {
+            Box::new(Error::PresignedRequest {
+                key: path.clone(),
+                status: response.status().as_u16(),
+            })
+        };
+        match response.status() {
+            status if status.is_success() => Ok(response),
+            StatusCode::NOT_FOUND => Err(object_store::Error::NotFound {
+                path: path.clone(),
+                source: source(),
+            }),
+            StatusCode::NOT_MODIFIED => Err(object_store::Error::NotModified {
+                path: path.clone(),
+                source: source(),
+            }),
+            StatusCode::PRECONDITION_FAILED => Err(object_store::Error::Precondition {
+                path: path.clone(),
+                source: source(),
+            }),
+            _ => Err(object_store::Error::Generic {
+                store: "S3",
+                source: source(),
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for PresignedStore {
+    async fn put_opts(
+        &self,
+        _location: &Path,
+        _bytes: Bytes,
+        _opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        Err(not_implemented())
+    }
+    async fn put_multipart(
+        &self,
+        _location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        Err(not_implemented())
+    }
+    async fn abort_multipart(
+        &self,
+        _location: &Path,
+        _multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        Err(not_implemented())
+    }
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let method = match options.head {
+            true => Method::HEAD,
+            false => Method::GET,
+        };
+        let response = self.send(method, location, &options).await?;
+        let meta = object_meta(location, response.headers())?;
+        let range = match response.headers().get(header::CONTENT_RANGE) {
+            Some(content_range) => {
+                parse_content_range(content_range.to_str().map_err(|_| Error::Unknown)?)?.0
+            }
+            None => options
+                .range
+                .as_ref()
+                .filter(|_| options.head)
+                .map_or(0..meta.size, |range| resolve_range(range, meta.size)),
+        };
+        let payload = match options.head {
+            true => stream::empty().boxed(),
+            false => {
+                let bytes = response
+                    .body()
+                    .bytes()
+                    .map(Bytes::copy_from_slice)
+                    .unwrap_or_default();
+                stream::once(future::ready(Ok(bytes))).boxed()
+            }
+        };
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(payload),
+            meta,
+            range,
+        })
+    }
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        let response = self
+            .send(Method::HEAD, location, &GetOptions::default())
+            .await?;
+        Ok(object_meta(location, response.headers())?)
+    }
+    async fn delete(&self, _location: &Path) -> object_store::Result<()> {
+        Err(not_implemented())
+    }
+    fn list(&self, _prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        stream::once(future::ready(Err(not_implemented()))).boxed()
+    }
+    async fn list_with_delimiter(
+        &self,
+        _prefix: Option<&Path>,
+    ) -> object_store::Result<ListResult> {
+        Err(not_implemented())
+    }
+    async fn copy(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(not_implemented())
+    }
+    async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(not_implemented())
+    }
+}
+
+impl Display for PresignedStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Presigned({:?})", self.signer)
+    }
+}
@@ -0,0 +1,232 @@
+use std::fmt::Debug;
+
+use crate::{
+    builder::S3Builder,
+    conditional::{ConditionalDelete, ConditionalPut, CopyIfNotExists},
+    list_encoding::ListEncoding,
+};
+
+/// Quirks of an S3-compatible service, applied by
+/// [`S3Builder::with_profile`](crate::builder::S3Builder::with_profile).
+///
+/// Endpoint, region and addressing style of a profile are only used if the builder doesn't set
+/// them, features the profile marks unsupported are never used.
+pub trait CompatProfile: Debug + Send + Sync {
+    /// Name of the service, e.g. `minio`.
+    fn name(&self) -> &str;
+    /// Endpoint of the service in `region`, `None` to resolve the AWS endpoint.
+    fn endpoint(&self, _region: Option<&str>) -> Option<String> {
+        None
+    }
+    /// Region requests are signed for.
+    fn default_region(&self) -> Option<&str> {
+        None
+    }
+    /// Whether buckets are addressed in the path rather than the host name.
+    fn force_path_style(&self) -> bool {
+        true
+    }
+    /// Headers added to every request.
+    fn headers(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    fn unsupported(&self) -> Unsupported {
+        Unsupported::default()
+    }
+}
+
+/// Features a [`CompatProfile`] lacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Unsupported {
+    /// `If-None-Match` and `If-Match` on `PutObject`, puts fall back to
+    /// [`ConditionalPut::HeadThenPut`].
+    pub conditional_put: bool,
+    /// `If-Match` on `DeleteObject`, deletes fall back to [`ConditionalDelete::HeadThenDelete`].
+    pub conditional_delete: bool,
+    /// `If-None-Match` on `CopyObject`, copies fall back to [`CopyIfNotExists::HeadThenCopy`].
+    pub conditional_copy: bool,
+    /// `x-amz-checksum-*` headers, uploads and downloads go without additional checksums.
+    pub checksums: bool,
+    /// `encoding-type=url` on listings, keys are listed verbatim.
+    pub url_list_encoding: bool,
+    /// `Range: bytes=-<length>`, suffix reads look up the object size with a `HeadObject` first.
+    pub suffix_range: bool,
+    /// Object tags.
+    pub tagging: bool,
+}
+
+/// Amazon S3, addressing buckets in the host name.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Aws;
+
+impl CompatProfile for Aws {
+    fn name(&self) -> &str {
+        "aws"
+    }
+    fn force_path_style(&self) -> bool {
+        false
+    }
+}
+
+/// Cloudflare R2 of the account `account_id`.
+#[derive(Debug, Clone)]
+pub struct R2 {
+    pub account_id: String,
+}
+
+impl R2 {
+    pub fn new(account_id: impl Into<String>) -> Self {
+        Self {
+            account_id: account_id.into(),
+        }
+    }
+}
+
+impl CompatProfile for R2 {
+    fn name(&self) -> &str {
+        "r2"
+    }
+    fn endpoint(&self, _region: Option<&str>) -> Option<String> {
+        Some(format!(
+            "https://{}.r2.cloudflarestorage.com",
+            self.account_id
+        ))
+    }
+    fn default_region(&self) -> Option<&str> {
+        Some("auto")
+    }
+    fn unsupported(&self) -> Unsupported {
+        Unsupported {
+            conditional_copy: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// MinIO, which needs an explicit endpoint.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Minio;
+
+impl CompatProfile for Minio {
+    fn name(&self) -> &str {
+        "minio"
+    }
+    fn default_region(&self) -> Option<&str> {
+        Some("us-east-1")
+    }
+}
+
+/// XML API of Google Cloud Storage with HMAC keys, billing requests to `user_project` for
+/// requester pays buckets.
+#[derive(Debug, Clone, Default)]
+pub struct GcsXml {
+    pub user_project: Option<String>,
+}
+
+impl CompatProfile for GcsXml {
+    fn name(&self) -> &str {
+        "gcs"
+    }
+    fn endpoint(&self, _region: Option<&str>) -> Option<String> {
+        Some("https://storage.googleapis.com".to_owned())
+    }
+    fn default_region(&self) -> Option<&str> {
+        Some("auto")
+    }
+    fn headers(&self) -> Vec<(String, String)> {
+        self.user_project
+            .iter()
+            .map(|project| ("x-goog-user-project".to_owned(), project.clone()))
+            .collect()
+    }
+    fn unsupported(&self) -> Unsupported {
+        Unsupported {
+            conditional_put: true,
+            conditional_delete: true,
+            conditional_copy: true,
+            checksums: true,
+            tagging: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Ceph Object Gateway (RGW), which needs an explicit endpoint.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ceph;
+
+impl CompatProfile for Ceph {
+    fn name(&self) -> &str {
+        "ceph"
+    }
+    fn unsupported(&self) -> Unsupported {
+        Unsupported {
+            conditional_delete: true,
+            conditional_copy: true,
+            checksums: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Wasabi hot cloud storage.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Wasabi;
+
+impl CompatProfile for Wasabi {
+    fn name(&self) -> &str {
+        "wasabi"
+    }
+    fn endpoint(&self, region: Option<&str>) -> Option<String> {
+        Some(format!(
+            "https://s3.{}.wasabisys.com",
+            region.unwrap_or("us-east-1")
+        ))
+    }
+    fn unsupported(&self) -> Unsupported {
+        Unsupported {
+            conditional_put: true,
+            conditional_delete: true,
+            conditional_copy: true,
+            checksums: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl S3Builder {
+    /// Fills in what the builder leaves unset from its profile and turns off unsupported features.
+    pub(crate) fn apply_profile(mut self) -> Self {
+        let Some(profile) = self.profile.take() else {
+            return self;
+        };
+        self.profile_name = Some(profile.name().to_owned());
+        if self.region.is_none() {
+            self.region = profile.default_region().map(ToOwned::to_owned);
+        }
+        if self.endpoint.is_none() {
+            self.endpoint = profile.endpoint(self.region.as_deref());
+        }
+        self.force_path_style = self.force_path_style.or(Some(profile.force_path_style()));
+        self.signed_headers.extend(profile.headers());
+        let unsupported = profile.unsupported();
+        self.unsupported = unsupported;
+        if unsupported.conditional_put {
+            self.conditional_put = ConditionalPut::HeadThenPut;
+        }
+        if unsupported.conditional_delete {
+            self.conditional_delete = ConditionalDelete::HeadThenDelete;
+        }
+        if unsupported.conditional_copy {
+            self.copy_if_not_exists = CopyIfNotExists::HeadThenCopy;
+        }
+        if unsupported.checksums {
+            self.checksum = None;
+            self.verify_checksums = false;
+        }
+        if unsupported.url_list_encoding {
+            self.list_encoding = ListEncoding::Plain;
+        }
+        self
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    fmt::Debug,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::{stream::BoxStream, Stream, StreamExt};
+use object_store::path::Path;
+
+use crate::S3;
+
+/// Transfer a [`Progress`] update belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// `put` or `put_opts`, split into parts above the multipart put threshold.
+    Put,
+    /// `put_multipart` and the uploads built on it, like blob and stream uploads.
+    MultipartUpload,
+    /// The payload stream of a GET.
+    Get,
+}
+
+/// Progress of a transfer, see [`ProgressListener`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress<'a> {
+    pub operation: Operation,
+    pub location: &'a Path,
+    /// Bytes transferred so far.
+    pub bytes: usize,
+    /// Bytes of the whole transfer, `None` for multipart uploads whose size isn't known upfront.
+    pub total: Option<usize>,
+}
+
+/// Receives the progress of uploads and downloads, e.g. to drive a progress bar.
+///
+/// Uploads report every uploaded part and single puts once they're done, downloads report every
+/// chunk of the GET stream as it arrives.
+pub trait ProgressListener: Debug + Send + Sync {
+    fn progress(&self, progress: &Progress<'_>);
+}
+
+/// Reports the progress of a GET payload stream.
+pub(crate) struct ProgressStream {
+    inner: BoxStream<'static, object_store::Result<Bytes>>,
+    location: Path,
+    listener: Arc<dyn ProgressListener>,
+    bytes: usize,
+    total: usize,
+}
+
+impl ProgressStream {
+    pub(crate) fn wrap(
+        inner: BoxStream<'static, object_store::Result<Bytes>>,
+        location: Path,
+        listener: Option<Arc<dyn ProgressListener>>,
+        total: usize,
+    ) -> BoxStream<'static, object_store::Result<Bytes>> {
+        match listener {
+            Some(listener) => Box::pin(ProgressStream {
+                inner,
+                location,
+                listener,
+                bytes: 0,
+                total,
+            }),
+            None => inner,
+        }
+    }
+}
+
+impl Stream for ProgressStream {
+    type Item = object_store::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = match self.inner.poll_next_unpin(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(item) => item,
+        };
+        if let Some(Ok(chunk)) = &item {
+            self.bytes += chunk.len();
+            self.listener.progress(&Progress {
+                operation: Operation::Get,
+                location: &self.location,
+                bytes: self.bytes,
+                total: Some(self.total),
+            });
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl S3 {
+    /// Store reporting the progress of its transfers to `listener` instead of the listener of
+    /// the builder, to follow single operations. The store shares everything else with `self`.
+    pub fn with_progress_listener(&self, listener: impl ProgressListener + 'static) -> S3 {
+        S3 {
+            progress: Some(Arc::new(listener)),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn report_progress(
+        &self,
+        operation: Operation,
+        location: &Path,
+        bytes: usize,
+        total: Option<usize>,
+    ) {
+        if let Some(listener) = &self.progress {
+            listener.progress(&Progress {
+                operation,
+                location,
+                bytes,
+                total,
+            });
+        }
+    }
+}
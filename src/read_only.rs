@@ -0,0 +1,36 @@
+use aws_sdk_s3::{
+    config::{
+        interceptors::BeforeTransmitInterceptorContextRef, ConfigBag, Interceptor,
+        RuntimeComponents,
+    },
+    error::BoxError,
+};
+use http::Method;
+
+use crate::error::Error;
+
+/// Rejects every request that isn't a `GET` or `HEAD` before it would need to be signed.
+///
+/// Used by anonymous stores, which have no credentials to sign writes with.
+#[derive(Debug, Clone)]
+pub(crate) struct ReadOnly;
+
+impl Interceptor for ReadOnly {
+    fn name(&self) -> &'static str {
+        "ReadOnly"
+    }
+
+    fn read_before_signing(
+        &self,
+        context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let method = context.request().method();
+        if method == Method::GET || method == Method::HEAD {
+            Ok(())
+        } else {
+            Err(Box::new(Error::AnonymousWrite(method.to_string())))
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::SdkBody;
+use http::{header, HeaderName, HeaderValue, Method, Uri};
+use parking_lot::Mutex;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{checksum::hex, date_time, environment::Clock, error::Error, upload_ticket::hmac};
+
+/// Characters left unescaped in the query of a canonical request.
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Secret the SDK presigns URLs of a remote signer with, the signature is replaced before the URL
+/// is handed out.
+pub(crate) const PLACEHOLDER_SECRET: &str = "remote-signer";
+
+/// Headers left out of signatures, the same ones the SDK leaves out.
+const UNSIGNED_HEADERS: [&str; 3] = ["authorization", "user-agent", "x-amzn-trace-id"];
+
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// A SigV4 signature to compute, sent to a [`RemoteSigner`].
+///
+/// The backend should check the canonical request, e.g. the method and key, before signing the
+/// string to sign with [`SigningRequest::sign`] or an equivalent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SigningRequest {
+    /// Canonical request the string to sign is derived from.
+    pub canonical_request: String,
+    /// `AWS4-HMAC-SHA256` string to sign.
+    pub string_to_sign: String,
+    /// Timestamp of the request, `YYYYMMDDTHHMMSSZ`.
+    pub date: String,
+    /// Credential scope, `<date>/<region>/<service>/aws4_request`.
+    pub scope: String,
+}
+
+impl SigningRequest {
+    /// Hex encoded signature of the string to sign with `secret_access_key`.
+    pub fn sign(&self, secret_access_key: &str) -> String {
+        hex(&hmac(
+            &signing_key(secret_access_key, &self.scope),
+            &self.string_to_sign,
+        ))
+    }
+}
+
+/// Key of the day, region and service of `scope`.
+fn signing_key(secret_access_key: &str, scope: &str) -> Vec<u8> {
+    scope.split('/').take(4).fold(
+        format!("AWS4{}", secret_access_key).into_bytes(),
+        |key, part| hmac(&key, part),
+    )
+}
+
+/// Signs requests for a store built with
+/// [`S3Builder::with_remote_signer`](crate::builder::S3Builder::with_remote_signer), e.g. by
+/// posting the [`SigningRequest`] to an app backend holding the secret key.
+#[async_trait]
+pub trait RemoteSigner: std::fmt::Debug + Send + Sync {
+    /// Hex encoded signature of `request`.
+    async fn sign(&self, request: &SigningRequest) -> object_store::Result<String>;
+}
+
+/// Computes the signatures of a [`Signer`].
+#[derive(Debug)]
+pub(crate) enum SigningKey {
+    /// The secret access key, the key derived from it for the scope of the day is cached.
+    Secret {
+        secret: String,
+        derived: Mutex<Option<(String, Vec<u8>)>>,
+    },
+    Remote(Arc<dyn RemoteSigner>),
+}
+
+impl SigningKey {
+    pub(crate) fn secret(secret: impl Into<String>) -> Self {
+        SigningKey::Secret {
+            secret: secret.into(),
+            derived: Mutex::default(),
+        }
+    }
+}
+
+/// Signs the requests of the http connector with SigV4 in place of the SDK, which sends them
+/// unsigned.
+///
+/// The credential scope and signing key are derived once per day and the canonical method,
+/// path and query of the last request are reused, so the range requests of a scan over a
+/// single object only hash their headers.
+#[derive(Debug)]
+pub(crate) struct Signer {
+    access_key_id: String,
+    session_token: Option<String>,
+    region: String,
+    service: String,
+    unsigned_payload: bool,
+    key: SigningKey,
+    clock: Arc<dyn Clock>,
+    /// Day and credential scope of the most recent signature.
+    scope: Mutex<Option<(String, String)>>,
+    /// Method, URI and canonical request line of the most recent signature.
+    request_line: Mutex<Option<(Method, Uri, String)>>,
+}
+
+impl Signer {
+    pub(crate) fn new(
+        access_key_id: String,
+        session_token: Option<String>,
+        region: String,
+        service: String,
+        unsigned_payload: bool,
+        key: SigningKey,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            access_key_id,
+            session_token,
+            region,
+            service,
+            unsigned_payload,
+            key,
+            clock,
+            scope: Mutex::default(),
+            request_line: Mutex::default(),
+        }
+    }
+
+    /// Credential scope of signatures made at `date`, `YYYYMMDDTHHMMSSZ`.
+    fn scope(&self, date: &str) -> String {
+        let day = &date[..8];
+        let mut scope = self.scope.lock();
+        match &*scope {
+            Some((cached, scope)) if cached == day => scope.clone(),
+            _ => {
+                let derived = format!("{}/{}/{}/aws4_request", day, self.region, self.service);
+                *scope = Some((day.to_owned(), derived.clone()));
+                derived
+            }
+        }
+    }
+
+    /// Canonical method, path and query of `parts`.
+    fn request_line(&self, parts: &http::request::Parts) -> String {
+        let mut request_line = self.request_line.lock();
+        match &*request_line {
+            Some((method, uri, line)) if *method == parts.method && *uri == parts.uri => {
+                line.clone()
+            }
+            _ => {
+                let line = canonical_request_line(parts);
+                *request_line = Some((parts.method.clone(), parts.uri.clone(), line.clone()));
+                line
+            }
+        }
+    }
+
+    /// Hex encoded signature of `request`.
+    async fn signature(&self, request: &SigningRequest) -> Result<String, Error> {
+        match &self.key {
+            SigningKey::Secret { secret, derived } => {
+                let mut derived = derived.lock();
+                if !matches!(&*derived, Some((scope, _)) if *scope == request.scope) {
+                    *derived = Some((request.scope.clone(), signing_key(secret, &request.scope)));
+                }
+                let (_, key) = derived.as_ref().expect("derived above");
+                Ok(hex(&hmac(key, &request.string_to_sign)))
+            }
+            SigningKey::Remote(remote) => remote
+                .sign(request)
+                .await
+                .map_err(|err| Error::RemoteSigning(err.to_string())),
+        }
+    }
+
+    pub(crate) fn access_key_id(&self) -> &str {
+        &self.access_key_id
+    }
+
+    pub(crate) fn session_token(&self) -> Option<&str> {
+        self.session_token.as_deref()
+    }
+
+    /// Whether the SDK presigns with [`PLACEHOLDER_SECRET`], see [`Signer::resign_url`].
+    pub(crate) fn is_remote(&self) -> bool {
+        matches!(self.key, SigningKey::Remote(_))
+    }
+
+    /// `x-amz-credential` of signatures made at `date`, `YYYYMMDDTHHMMSSZ`.
+    pub(crate) fn credential(&self, date: &str) -> String {
+        format!("{}/{}", self.access_key_id, self.scope(date))
+    }
+
+    /// Signature of a POST policy made at `date`, the remote signer gets the policy `document`
+    /// as canonical request and its base64 encoding as string to sign.
+    pub(crate) async fn sign_policy(
+        &self,
+        date: &str,
+        document: &str,
+        encoded: &str,
+    ) -> Result<String, Error> {
+        self.signature(&SigningRequest {
+            canonical_request: document.to_owned(),
+            string_to_sign: encoded.to_owned(),
+            date: date.to_owned(),
+            scope: self.scope(date),
+        })
+        .await
+    }
+
+    /// Replaces the signature of a URL the SDK presigned with [`PLACEHOLDER_SECRET`].
+    pub(crate) async fn resign_url(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &http::HeaderMap,
+    ) -> Result<String, Error> {
+        let url = uri.to_string();
+        let malformed = || Error::RemoteSigning(format!("malformed presigned url {}", url));
+        let (base, query) = url.split_once('?').ok_or_else(malformed)?;
+        let pairs = query
+            .split('&')
+            .filter(|pair| !pair.starts_with("X-Amz-Signature="))
+            .collect::<Vec<_>>();
+        let param = |name: &str| {
+            pairs
+                .iter()
+                .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+                .map(|value| percent_decode_str(value).decode_utf8_lossy().into_owned())
+                .ok_or_else(malformed)
+        };
+        let date = param("X-Amz-Date")?;
+        let signed_headers = param("X-Amz-SignedHeaders")?;
+        let scope = param("X-Amz-Credential")?
+            .split_once('/')
+            .ok_or_else(malformed)?
+            .1
+            .to_owned();
+
+        let unsigned = format!("{}?{}", base, pairs.join("&"));
+        let mut request = http::Request::builder()
+            .method(method)
+            .uri(unsigned.as_str())
+            .body(())
+            .map_err(|_| malformed())?;
+        *request.headers_mut() = headers.clone();
+        let (parts, _) = request.into_parts();
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}",
+            canonical_request_line(&parts),
+            canonical_headers(&parts, &signed_headers),
+            signed_headers,
+            UNSIGNED_PAYLOAD
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signature = self
+            .signature(&SigningRequest {
+                canonical_request,
+                string_to_sign,
+                date,
+                scope,
+            })
+            .await?;
+        Ok(format!("{}&X-Amz-Signature={}", unsigned, signature))
+    }
+
+    /// Adds the `authorization`, `x-amz-date`, `x-amz-content-sha256` and, for temporary
+    /// credentials, `x-amz-security-token` headers to `parts`.
+    pub(crate) async fn sign(
+        &self,
+        parts: &mut http::request::Parts,
+        body: &SdkBody,
+    ) -> Result<(), Error> {
+        let date = date_time(self.clock.now())
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string();
+        let payload_hash = match body.bytes() {
+            Some(bytes) if !self.unsigned_payload => hex(&Sha256::digest(bytes)),
+            _ => UNSIGNED_PAYLOAD.to_owned(),
+        };
+        let invalid = |name: &str| Error::RemoteSigning(format!("invalid header {}", name));
+        let mut insert = |name: &'static str, value: &str| {
+            let value = HeaderValue::try_from(value).map_err(|_| invalid(name))?;
+            parts.headers.insert(name, value);
+            Ok::<_, Error>(())
+        };
+        insert("x-amz-date", &date)?;
+        insert("x-amz-content-sha256", &payload_hash)?;
+        if let Some(token) = &self.session_token {
+            insert("x-amz-security-token", token)?;
+        }
+        parts.headers.remove(header::AUTHORIZATION);
+
+        let mut signed_headers = parts
+            .headers
+            .keys()
+            .map(HeaderName::as_str)
+            .chain(["host"])
+            .filter(|name| !UNSIGNED_HEADERS.contains(name))
+            .collect::<Vec<_>>();
+        signed_headers.sort_unstable();
+        signed_headers.dedup();
+        let signed_headers = signed_headers.join(";");
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}",
+            self.request_line(parts),
+            canonical_headers(parts, &signed_headers),
+            signed_headers,
+            payload_hash
+        );
+        let scope = self.scope(&date);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signature = self
+            .signature(&SigningRequest {
+                canonical_request,
+                string_to_sign,
+                date,
+                scope: scope.clone(),
+            })
+            .await?;
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, signed_headers, signature
+        );
+        let authorization = HeaderValue::try_from(authorization)
+            .map_err(|_| Error::RemoteSigning(format!("invalid signature {}", signature)))?;
+        parts.headers.insert(header::AUTHORIZATION, authorization);
+        Ok(())
+    }
+}
+
+/// Method, path and query lines of the canonical request of `parts`.
+fn canonical_request_line(parts: &http::request::Parts) -> String {
+    let mut query = parts
+        .uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (reencode(name), reencode(value))
+        })
+        .collect::<Vec<_>>();
+    query.sort();
+    let query = query
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&");
+    let path = match parts.uri.path() {
+        "" => "/",
+        path => path,
+    };
+    format!("{}\n{}\n{}", parts.method, path, query)
+}
+
+/// Canonical header lines of the `signed_headers` of `parts`.
+fn canonical_headers(parts: &http::request::Parts, signed_headers: &str) -> String {
+    signed_headers
+        .split(';')
+        .map(|name| {
+            let values = parts
+                .headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .map(|value| value.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>();
+            match (name, values.is_empty()) {
+                ("host", true) => format!(
+                    "host:{}\n",
+                    parts
+                        .uri
+                        .authority()
+                        .map_or("", |authority| authority.as_str())
+                ),
+                _ => format!("{}:{}\n", name, values.join(",")),
+            }
+        })
+        .collect()
+}
+
+/// Query component in the encoding of canonical requests, whatever it was sent with.
+fn reencode(component: &str) -> String {
+    let decoded = percent_decode_str(component).decode_utf8_lossy();
+    utf8_percent_encode(&decoded, QUERY_VALUE).to_string()
+}
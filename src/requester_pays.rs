@@ -0,0 +1,34 @@
+use aws_sdk_s3::{
+    config::{
+        interceptors::BeforeTransmitInterceptorContextMut, ConfigBag, Interceptor,
+        RuntimeComponents,
+    },
+    error::BoxError,
+};
+use http::{HeaderValue, Method};
+
+/// Adds `x-amz-request-payer: requester` to every `GET` and `HEAD` before it is signed, the
+/// reads of requester-pays buckets are billed to the requester.
+#[derive(Debug, Clone)]
+pub(crate) struct RequesterPays;
+
+impl Interceptor for RequesterPays {
+    fn name(&self) -> &'static str {
+        "RequesterPays"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let request = context.request_mut();
+        if request.method() == Method::GET || request.method() == Method::HEAD {
+            request
+                .headers_mut()
+                .insert("x-amz-request-payer", HeaderValue::from_static("requester"));
+        }
+        Ok(())
+    }
+}
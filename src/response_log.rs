@@ -0,0 +1,49 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use http::{HeaderMap, Method, StatusCode, Uri};
+use parking_lot::Mutex;
+
+/// Raw response headers of a single request issued by the store.
+#[derive(Debug, Clone)]
+pub struct CapturedResponse {
+    pub method: Method,
+    pub uri: Uri,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+}
+
+/// Bounded ring buffer holding the raw responses of the last `capacity` requests.
+#[derive(Debug, Clone)]
+pub(crate) struct ResponseLog {
+    capacity: usize,
+    entries: Arc<Mutex<VecDeque<CapturedResponse>>>,
+}
+
+impl ResponseLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    pub(crate) fn record(&self, response: CapturedResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(response);
+    }
+
+    /// Returns the captured responses, oldest first.
+    pub(crate) fn entries(&self) -> Vec<CapturedResponse> {
+        self.entries.lock().iter().cloned().collect()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.entries.lock().clear();
+    }
+}
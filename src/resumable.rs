@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use aws_sdk_s3::{primitives::ByteStream, types::CompletedMultipartUpload};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use js_sys::Uint8Array;
+use object_store::{path::Path, ObjectStore, PutResult};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, File};
+
+use crate::{
+    attributes::PutAttributes,
+    encryption::SseConfig,
+    error::Error,
+    etag::etag,
+    events::{Direction, TransferEvent},
+    idb::Database,
+    journal::Mutation,
+    key_encoding::key_to_path,
+    now,
+    parts::UploadedPart,
+    S3,
+};
+
+/// IndexedDB object store holding the state of unfinished uploads, keyed by
+/// `<bucket>/<fingerprint>`.
+pub(crate) const UPLOAD_STORE: &str = "resumable_uploads";
+
+/// Bytes at the start of a file that go into its fingerprint.
+const FINGERPRINT_PREFIX: usize = 1024 * 1024;
+
+/// Unfinished multipart upload of a file, persisted across page reloads.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumableUpload {
+    /// Fingerprint of the uploaded file, see [`fingerprint`].
+    pub fingerprint: String,
+    pub location: String,
+    pub upload_id: String,
+    pub part_size: usize,
+    pub size: usize,
+    pub created: DateTime<Utc>,
+    /// Parts the server already holds, reconciled with `ListParts`. Not persisted.
+    #[serde(skip)]
+    pub uploaded_parts: Vec<UploadedPart>,
+}
+
+impl ResumableUpload {
+    fn parts(&self) -> usize {
+        self.size.div_ceil(self.part_size).max(1)
+    }
+
+    fn part_range(&self, part_number: usize) -> std::ops::Range<usize> {
+        let start = (part_number - 1) * self.part_size;
+        start..(start + self.part_size).min(self.size)
+    }
+
+    /// Bytes of the file the server already holds.
+    pub fn uploaded_bytes(&self) -> usize {
+        self.uploaded_parts.iter().map(|part| part.size).sum()
+    }
+}
+
+/// Fingerprint identifying the content of `file` across page reloads.
+///
+/// Hex encoded MD5 digest of the file's name, size, modification time and first MiB, so picking
+/// the same file again yields the same fingerprint without reading all of it.
+pub async fn fingerprint(file: &File) -> Result<String, Error> {
+    let size = file.size() as usize;
+    let head = read_slice(file, 0..size.min(FINGERPRINT_PREFIX)).await?;
+    let mut content = format!("{}\n{}\n{}\n", file.name(), size, file.last_modified()).into_bytes();
+    content.extend_from_slice(&head);
+    Ok(etag(&content))
+}
+
+pub(crate) async fn read_slice(blob: &Blob, range: std::ops::Range<usize>) -> Result<Bytes, Error> {
+    let blob = blob.slice_with_f64_and_f64(range.start as f64, range.end as f64)?;
+    let buffer = JsFuture::from(blob.array_buffer()).await?;
+    Ok(Bytes::from(Uint8Array::new(&buffer).to_vec()))
+}
+
+impl S3 {
+    fn upload_key(&self, fingerprint: &str) -> String {
+        format!("{}/{}", self.bucket, fingerprint)
+    }
+
+    /// Unfinished upload of `file` started by an earlier [`S3::upload_file_resumable`], with the
+    /// parts the server already holds.
+    ///
+    /// Uploads the server no longer knows, e.g. because they were aborted or completed, are
+    /// forgotten and `None` is returned.
+    pub async fn find_resumable_upload(
+        &self,
+        file: &File,
+    ) -> object_store::Result<Option<ResumableUpload>> {
+        let db = Database::open().await?;
+        let key = self.upload_key(&fingerprint(file).await?);
+        self.reconciled_upload(&db, &key).await
+    }
+
+    async fn reconciled_upload(
+        &self,
+        db: &Database,
+        key: &str,
+    ) -> object_store::Result<Option<ResumableUpload>> {
+        let Some(value) = db.get(UPLOAD_STORE, key).await? else {
+            return Ok(None);
+        };
+        let Some(mut upload) = value
+            .as_string()
+            .and_then(|value| serde_json::from_str::<ResumableUpload>(&value).ok())
+        else {
+            db.delete(UPLOAD_STORE, key).await?;
+            return Ok(None);
+        };
+        let parts = self
+            .list_parts(&key_to_path(&upload.location), &upload.upload_id)
+            .try_collect::<Vec<_>>()
+            .await;
+        let parts = match parts {
+            Ok(parts) => parts,
+            Err(object_store::Error::NotFound { .. }) => {
+                db.delete(UPLOAD_STORE, key).await?;
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+        // Parts of a different size were cut short and get uploaded again
+        upload.uploaded_parts = parts
+            .into_iter()
+            .filter(|part| {
+                let part_number = part.part_number as usize;
+                part_number >= 1
+                    && part_number <= upload.parts()
+                    && part.size == upload.part_range(part_number).len()
+                    && part.e_tag.is_some()
+            })
+            .collect();
+        Ok(Some(upload))
+    }
+
+    /// Uploads `file` to `location` in parts, continuing an earlier upload of the same file to
+    /// the same location where it stopped.
+    ///
+    /// The upload id is kept in IndexedDB under the file's [`fingerprint`] until the upload
+    /// completes, so an upload interrupted by a failure or a page reload can be resumed by calling
+    /// this again with the same file. Parts the server holds according to `ListParts` are
+    /// skipped. An unfinished upload of the file to another location is aborted.
+    pub async fn upload_file_resumable(
+        &self,
+        location: &Path,
+        file: &File,
+    ) -> object_store::Result<PutResult> {
+        let db = Database::open().await?;
+        let fingerprint = fingerprint(file).await?;
+        let key = self.upload_key(&fingerprint);
+        let upload = match self.reconciled_upload(&db, &key).await? {
+            Some(upload) if upload.location == location.as_ref() => upload,
+            stale => {
+                // An upload of the file to another location would hold its parts forever
+                if let Some(stale) = stale {
+                    let stale_location = key_to_path(&stale.location);
+                    match self
+                        .abort_multipart(&stale_location, &stale.upload_id)
+                        .await
+                    {
+                        Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+                        Err(err) => return Err(err),
+                    }
+                }
+                let upload = ResumableUpload {
+                    fingerprint,
+                    location: location.to_string(),
+                    upload_id: self
+                        .create_multipart(location, &PutAttributes::default())
+                        .await?,
+                    part_size: self.multipart_part_size,
+                    size: file.size() as usize,
+                    created: now(),
+                    uploaded_parts: Vec::new(),
+                };
+                let value = serde_json::to_string(&upload).map_err(Error::from)?;
+                db.put(UPLOAD_STORE, &key, &value.into()).await?;
+                upload
+            }
+        };
+
+        let mut completed = upload
+            .uploaded_parts
+            .iter()
+            .map(|part| {
+                let completed =
+                    self.completed_part(part.part_number, part.e_tag.as_deref(), part)?;
+                Ok((part.part_number, completed))
+            })
+            .collect::<Result<HashMap<_, _>, Error>>()?;
+        let mut uploaded = upload.uploaded_bytes();
+        for part_number in 1..=upload.parts() {
+            if completed.contains_key(&(part_number as i32)) {
+                continue;
+            }
+            let range = upload.part_range(part_number);
+            let data = read_slice(file, range.clone()).await?;
+            let request = self
+                .checksummed(self.client.upload_part(), &data)
+                .bucket(self.bucket.clone())
+                .key(location.to_string())
+                .upload_id(&upload.upload_id)
+                .part_number(part_number as i32)
+                .body(ByteStream::from(data));
+            let response = self
+                .encrypted(request, SseConfig::upload_part)
+                .send()
+                .await
+                .map_err(|err| self.request_error(location, err))?;
+            let part = self.completed_part(part_number as i32, response.e_tag(), &response)?;
+            completed.insert(part_number as i32, part);
+            uploaded += range.len();
+            self.events.emit(|| TransferEvent::Progress {
+                location: location.to_string(),
+                direction: Direction::Upload,
+                bytes: uploaded,
+            });
+        }
+
+        let mut parts = completed.into_values().collect::<Vec<_>>();
+        parts.sort_by_key(|part| part.part_number());
+        let result = self
+            .client
+            .complete_multipart_upload()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .upload_id(&upload.upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts.clone()))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|err| self.request_error(location, err))?;
+        db.delete(UPLOAD_STORE, &key).await?;
+        self.verify_completion(location.as_ref(), &parts, &result);
+        self.events
+            .emit(|| TransferEvent::finished(location, Direction::Upload, uploaded, true));
+        self.record(|| Mutation::Put {
+            location: location.clone(),
+            e_tag: result.e_tag.clone(),
+            version: result.version_id.clone(),
+        });
+        Ok(PutResult {
+            e_tag: result.e_tag,
+            version: result.version_id,
+        })
+    }
+
+    /// Aborts the unfinished upload of `file`, if any, and forgets it.
+    pub async fn discard_resumable_upload(&self, file: &File) -> object_store::Result<()> {
+        let db = Database::open().await?;
+        let key = self.upload_key(&fingerprint(file).await?);
+        if let Some(upload) = self.reconciled_upload(&db, &key).await? {
+            self.abort_multipart(&key_to_path(&upload.location), &upload.upload_id)
+                .await?;
+            db.delete(UPLOAD_STORE, &key).await?;
+        }
+        Ok(())
+    }
+}
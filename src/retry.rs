@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use http::StatusCode;
+
+use crate::environment::Random;
+
+/// Attempts per request of [`RetryConfig::default`].
+pub const DEFAULT_MAX_ATTEMPTS: usize = 3;
+
+/// Retry policy applied to every request of a store, see
+/// [`S3Builder::with_retry`](crate::builder::S3Builder::with_retry).
+///
+/// Network errors and responses with a retryable status are sent again after a jittered
+/// exponential backoff. Requests with a streaming body can't be replayed and are only sent once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// Attempts per request including the first one.
+    pub max_attempts: usize,
+    /// Upper bound of the backoff before the first retry, doubled for every further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of any backoff.
+    pub max_backoff: Duration,
+    /// Response statuses that are retried.
+    pub retryable_statuses: Vec<u16>,
+}
+
+impl Default for RetryConfig {
+    /// Three attempts with backoffs of up to 100ms and 200ms, retrying throttling and server
+    /// errors.
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(20),
+            retryable_statuses: vec![429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Sends every request exactly once.
+    pub fn disabled() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    /// Attempts per request including the first one, at least one.
+    pub fn with_max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Replaces the retried response statuses.
+    pub fn with_retryable_statuses(mut self, statuses: impl IntoIterator<Item = u16>) -> Self {
+        self.retryable_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    pub fn is_retryable(&self, status: StatusCode) -> bool {
+        self.retryable_statuses.contains(&status.as_u16())
+    }
+
+    /// Jittered backoff before the `retry`th retry, counting from zero.
+    pub(crate) fn backoff(&self, retry: usize, random: &dyn Random) -> Duration {
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry as u32))
+            .min(self.max_backoff);
+        random.jitter(ceiling)
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+use object_store::{
+    path::{Path, PathPart},
+    GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore, PutOptions, PutResult,
+};
+use tokio::io::AsyncWrite;
+
+/// Wrapper store that spreads keys over `shards` hashed top level prefixes.
+///
+/// S3 limits the request rate per key prefix, so heavy write workloads under a single prefix
+/// run into `SlowDown` errors. The logical path `a/b` is stored as `<shard>/a/b` where `<shard>`
+/// is a two digit hex hash of the logical path. All operations translate between the logical and
+/// the physical layout, so callers only ever see logical paths.
+#[derive(Debug)]
+pub struct ShardedStore<T: ObjectStore> {
+    inner: T,
+    shards: usize,
+}
+
+impl<T: ObjectStore> ShardedStore<T> {
+    /// Creates a sharded view of `inner`, `shards` is clamped to `1..=256`.
+    pub fn new(inner: T, shards: usize) -> Self {
+        Self {
+            inner,
+            shards: shards.clamp(1, 256),
+        }
+    }
+
+    /// Shard of a logical path.
+    pub fn shard(&self, location: &Path) -> usize {
+        (fnv1a(location.as_ref().as_bytes()) % self.shards as u64) as usize
+    }
+
+    /// Physical path of a logical path.
+    pub fn physical_path(&self, location: &Path) -> Path {
+        Path::from_iter(
+            std::iter::once(PathPart::from(shard_name(self.shard(location))))
+                .chain(location.parts()),
+        )
+    }
+
+    /// Logical path of a physical path, `None` if the path isn't part of the sharded layout.
+    pub fn logical_path(&self, location: &Path) -> Option<Path> {
+        let mut parts = location.parts();
+        let shard = parts.next()?;
+        self.parse_shard(shard.as_ref())?;
+        Some(Path::from_iter(parts))
+    }
+
+    /// Moves the unsharded objects below `prefix` into the sharded layout.
+    ///
+    /// Keys whose first segment already looks like a shard are skipped. Returns the number of
+    /// migrated objects.
+    pub async fn migrate(&self, prefix: Option<&Path>) -> object_store::Result<usize> {
+        let mut objects = self.inner.list(prefix);
+        let mut migrated = 0;
+        while let Some(object) = objects.try_next().await? {
+            if self.logical_path(&object.location).is_some() {
+                continue;
+            }
+            self.inner
+                .copy(&object.location, &self.physical_path(&object.location))
+                .await?;
+            self.inner.delete(&object.location).await?;
+            migrated += 1;
+        }
+        Ok(migrated)
+    }
+
+    fn parse_shard(&self, segment: &str) -> Option<usize> {
+        if segment.len() != 2 || segment.chars().any(|c| c.is_ascii_uppercase()) {
+            return None;
+        }
+        usize::from_str_radix(segment, 16)
+            .ok()
+            .filter(|shard| *shard < self.shards)
+    }
+
+    fn shard_prefixes(&self, prefix: Option<&Path>) -> Vec<Path> {
+        (0..self.shards)
+            .map(|shard| {
+                let shard = Path::from(shard_name(shard));
+                match prefix {
+                    Some(prefix) => Path::from_iter(shard.parts().chain(prefix.parts())),
+                    None => shard,
+                }
+            })
+            .collect()
+    }
+
+    fn strip(&self, meta: ObjectMeta) -> object_store::Result<ObjectMeta> {
+        let location =
+            self.logical_path(&meta.location)
+                .ok_or_else(|| object_store::Error::Generic {
+                    store: "Sharded",
+                    source: format!("{} is not a sharded path", meta.location).into(),
+                })?;
+        Ok(ObjectMeta { location, ..meta })
+    }
+}
+
+fn shard_name(shard: usize) -> String {
+    format!("{:02x}", shard)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for ShardedStore<T> {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        self.inner
+            .put_opts(&self.physical_path(location), bytes, opts)
+            .await
+    }
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.inner
+            .put_multipart(&self.physical_path(location))
+            .await
+    }
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.inner
+            .abort_multipart(&self.physical_path(location), multipart_id)
+            .await
+    }
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let result = self
+            .inner
+            .get_opts(&self.physical_path(location), options)
+            .await?;
+        Ok(GetResult {
+            meta: ObjectMeta {
+                location: location.clone(),
+                ..result.meta
+            },
+            ..result
+        })
+    }
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        let meta = self.inner.head(&self.physical_path(location)).await?;
+        Ok(ObjectMeta {
+            location: location.clone(),
+            ..meta
+        })
+    }
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(&self.physical_path(location)).await
+    }
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        Box::pin(
+            stream::iter(self.shard_prefixes(prefix))
+                .flat_map(move |prefix| self.inner.list(Some(&prefix)))
+                .map(move |meta| meta.and_then(|meta| self.strip(meta))),
+        )
+    }
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let mut objects = Vec::new();
+        let mut common_prefixes = BTreeSet::new();
+        for prefix in self.shard_prefixes(prefix) {
+            let result = self.inner.list_with_delimiter(Some(&prefix)).await?;
+            for object in result.objects {
+                objects.push(self.strip(object)?);
+            }
+            common_prefixes.extend(
+                result
+                    .common_prefixes
+                    .iter()
+                    .filter_map(|prefix| self.logical_path(prefix)),
+            );
+        }
+        Ok(ListResult {
+            objects,
+            common_prefixes: common_prefixes.into_iter().collect(),
+        })
+    }
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner
+            .copy(&self.physical_path(from), &self.physical_path(to))
+            .await
+    }
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner
+            .copy_if_not_exists(&self.physical_path(from), &self.physical_path(to))
+            .await
+    }
+}
+
+impl<T: ObjectStore> Display for ShardedStore<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Sharded({}, {})", self.shards, self.inner)
+    }
+}
@@ -0,0 +1,209 @@
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use std::time::{Duration, UNIX_EPOCH};
+
+use aws_credential_types::Credentials;
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use js_sys::{Function, Promise, Reflect};
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::oneshot;
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+
+use crate::checksum::hex;
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+use crate::idb::Database;
+
+/// Prefix of the Web Locks and stored credentials of shared sessions.
+const PREFIX: &str = "object_store_s3_wasm";
+
+/// Web Lock held by this tab, released when dropped.
+///
+/// Acquiring the lock waits for every other tab and worker of the origin holding it. Without the
+/// Web Locks API, e.g. outside of secure contexts, locks are granted right away.
+pub(crate) struct SharedLock {
+    _release: oneshot::Sender<()>,
+}
+
+/// Acquires the Web Lock `name`, shared by all tabs and workers of the origin.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+pub(crate) async fn lock(name: &str) -> SharedLock {
+    let name = name.to_owned();
+    let (acquired_tx, acquired_rx) = oneshot::channel();
+    let (release_tx, release_rx) = oneshot::channel::<()>();
+    wasm_bindgen_futures::spawn_local(async move {
+        let Some((locks, request)) = lock_manager() else {
+            let _ = acquired_tx.send(());
+            return;
+        };
+        // The lock is held until the promise returned by the callback settles
+        let held = future_to_promise(async move {
+            let _ = release_rx.await;
+            Ok(JsValue::UNDEFINED)
+        });
+        let callback = Closure::once(move |_: JsValue| -> Promise {
+            let _ = acquired_tx.send(());
+            held
+        });
+        if let Ok(promise) = request.call2(&locks, &name.into(), callback.as_ref()) {
+            let _ = JsFuture::from(Promise::from(promise)).await;
+        }
+    });
+    // A failed lock request drops the sender, the caller goes ahead without the lock
+    let _ = acquired_rx.await;
+    SharedLock {
+        _release: release_tx,
+    }
+}
+
+/// Native builds run a single session, locks are granted right away.
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+pub(crate) async fn lock(_name: &str) -> SharedLock {
+    SharedLock {
+        _release: oneshot::channel().0,
+    }
+}
+
+/// `navigator.locks` and its `request` method, in windows and workers alike.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+fn lock_manager() -> Option<(JsValue, Function)> {
+    let navigator = Reflect::get(&js_sys::global(), &"navigator".into()).ok()?;
+    let locks = Reflect::get(&navigator, &"locks".into()).ok()?;
+    let request = Reflect::get(&locks, &"request".into())
+        .ok()?
+        .dyn_into()
+        .ok()?;
+    Some((locks, request))
+}
+
+/// Credentials as they're stored for other tabs.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SharedCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    expiry: Option<u64>,
+}
+
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+impl From<&Credentials> for SharedCredentials {
+    fn from(credentials: &Credentials) -> Self {
+        Self {
+            access_key_id: credentials.access_key_id().to_owned(),
+            secret_access_key: credentials.secret_access_key().to_owned(),
+            session_token: credentials.session_token().map(ToOwned::to_owned),
+            expiry: credentials.expiry().and_then(|expiry| {
+                let since_epoch = expiry.duration_since(UNIX_EPOCH).ok()?;
+                Some(since_epoch.as_millis() as u64)
+            }),
+        }
+    }
+}
+
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+impl From<SharedCredentials> for Credentials {
+    fn from(shared: SharedCredentials) -> Self {
+        Credentials::new(
+            shared.access_key_id,
+            shared.secret_access_key,
+            shared.session_token,
+            shared
+                .expiry
+                .map(|expiry| UNIX_EPOCH + Duration::from_millis(expiry)),
+            "SharedSession",
+        )
+    }
+}
+
+/// Object store of the credentials shared by the tabs of the origin, by role and identity.
+pub(crate) const CREDENTIALS_STORE: &str = "shared_credentials";
+
+/// Lock and key the credentials of `role_arn` for the identity of `token` are exchanged and
+/// shared under.
+///
+/// Only a digest of the token is part of the key, so a user signing in later on the same origin
+/// never finds the credentials of the previous one.
+pub(crate) fn credentials_key(role_arn: &str, token: &str) -> String {
+    format!(
+        "{}:credentials:{}:{}",
+        PREFIX,
+        role_arn,
+        hex(&Sha256::digest(token.as_bytes()))
+    )
+}
+
+/// Lock of the downloads of a version of an object into the persistent cache.
+pub(crate) fn download_lock(bucket: &str, location: &str, e_tag: &str) -> String {
+    serde_json::json!([PREFIX, "download", bucket, location, e_tag]).to_string()
+}
+
+/// Credentials another tab stored under `key`.
+///
+/// Read while holding the lock of `key`, the tab that held it before stored its credentials
+/// before releasing it.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+pub(crate) async fn stored(key: &str) -> Option<Credentials> {
+    let key = key.to_owned();
+    let (tx, rx) = oneshot::channel();
+    wasm_bindgen_futures::spawn_local(async move {
+        let stored = async {
+            let db = Database::open().await.ok()?;
+            let value = db.get(CREDENTIALS_STORE, &key).await.ok()??.as_string()?;
+            serde_json::from_str::<SharedCredentials>(&value).ok()
+        };
+        let _ = tx.send(stored.await);
+    });
+    rx.await.ok().flatten().map(Into::into)
+}
+
+/// Stores `credentials` under `key` for the other tabs, before the lock of `key` is released.
+///
+/// Sharing is best effort, tabs that can't read them exchange the token themselves.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+pub(crate) async fn store(key: &str, credentials: &Credentials) {
+    let Ok(json) = serde_json::to_string(&SharedCredentials::from(credentials)) else {
+        return;
+    };
+    let key = key.to_owned();
+    let (tx, rx) = oneshot::channel();
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Ok(db) = Database::open().await {
+            let _ = db.put(CREDENTIALS_STORE, &key, &json.into()).await;
+        }
+        let _ = tx.send(());
+    });
+    let _ = rx.await;
+}
+
+/// Deletes the credentials stored under `key`, once they expired or their identity changed.
+#[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+pub(crate) async fn remove(key: &str) {
+    let key = key.to_owned();
+    let (tx, rx) = oneshot::channel();
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Ok(db) = Database::open().await {
+            let _ = db.delete(CREDENTIALS_STORE, &key).await;
+        }
+        let _ = tx.send(());
+    });
+    let _ = rx.await;
+}
+
+/// Native builds have no other sessions to share credentials with.
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+pub(crate) async fn stored(_key: &str) -> Option<Credentials> {
+    None
+}
+
+/// Native builds have no other sessions to share credentials with.
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+pub(crate) async fn store(_key: &str, _credentials: &Credentials) {}
+
+/// Native builds have no other sessions to share credentials with.
+#[cfg(all(feature = "native", not(target_arch = "wasm32")))]
+pub(crate) async fn remove(_key: &str) {}
@@ -0,0 +1,39 @@
+use aws_sdk_s3::{
+    config::{
+        interceptors::BeforeTransmitInterceptorContextMut, ConfigBag, Interceptor,
+        RuntimeComponents,
+    },
+    error::BoxError,
+};
+use http::{HeaderName, HeaderValue};
+
+/// Adds fixed headers to every request before it is signed, so they are covered by the signature.
+#[derive(Debug, Clone)]
+pub(crate) struct SignedHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl SignedHeaders {
+    pub(crate) fn new(headers: Vec<(HeaderName, HeaderValue)>) -> Self {
+        Self { headers }
+    }
+}
+
+impl Interceptor for SignedHeaders {
+    fn name(&self) -> &'static str {
+        "SignedHeaders"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let headers = context.request_mut().headers_mut();
+        for (name, value) in &self.headers {
+            headers.insert(name.clone(), value.clone());
+        }
+        Ok(())
+    }
+}
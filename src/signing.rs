@@ -0,0 +1,82 @@
+use aws_runtime::auth::sigv4::SigV4OperationSigningConfig;
+use aws_sdk_s3::{
+    config::{
+        endpoint::{DefaultResolver, Params, ResolveEndpoint},
+        interceptors::BeforeTransmitInterceptorContextMut,
+        ConfigBag, Interceptor, RuntimeComponents,
+    },
+    error::BoxError,
+};
+use aws_sigv4::http_request::SignableBody;
+use aws_smithy_types::Document;
+
+/// Resolves endpoints with the SDK rules, then overrides the signing region and service name
+/// of every auth scheme, e.g. `auto` for R2 or a custom service name of an on-prem gateway.
+#[derive(Debug)]
+pub(crate) struct SigningOverrides {
+    resolver: DefaultResolver,
+    region: Option<String>,
+    name: Option<String>,
+}
+
+impl SigningOverrides {
+    pub(crate) fn new(region: Option<String>, name: Option<String>) -> Self {
+        SigningOverrides {
+            resolver: DefaultResolver::new(),
+            region,
+            name,
+        }
+    }
+
+    fn rewrite(&self, scheme: &mut Document) {
+        if let Document::Object(scheme) = scheme {
+            if let Some(region) = &self.region {
+                scheme.insert("signingRegion".into(), Document::String(region.clone()));
+            }
+            if let Some(name) = &self.name {
+                scheme.insert("signingName".into(), Document::String(name.clone()));
+            }
+        }
+    }
+}
+
+impl ResolveEndpoint<Params> for SigningOverrides {
+    fn resolve_endpoint(&self, params: &Params) -> aws_smithy_http::endpoint::Result {
+        let endpoint = self.resolver.resolve_endpoint(params)?;
+        let mut schemes = match endpoint.properties().get("authSchemes") {
+            Some(Document::Array(schemes)) => schemes.clone(),
+            _ => return Ok(endpoint),
+        };
+        schemes.iter_mut().for_each(|scheme| self.rewrite(scheme));
+        Ok(endpoint
+            .into_builder()
+            .property("authSchemes", Document::Array(schemes))
+            .build())
+    }
+}
+
+/// Signs requests with `x-amz-content-sha256: UNSIGNED-PAYLOAD` instead of hashing the body,
+/// for endpoints that don't support payload signing or to skip hashing large bodies.
+#[derive(Debug, Clone)]
+pub(crate) struct UnsignedPayload;
+
+impl Interceptor for UnsignedPayload {
+    fn name(&self) -> &'static str {
+        "UnsignedPayload"
+    }
+
+    fn modify_before_signing(
+        &self,
+        _context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(config) = cfg.load::<SigV4OperationSigningConfig>() {
+            let mut config = config.clone();
+            config.signing_options.payload_override = Some(SignableBody::UnsignedPayload);
+            config.signing_options.content_sha256_header = true;
+            cfg.interceptor_state().store_put(config);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use hmac::{Hmac, Mac};
+use object_store::{path::Path, ObjectStore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{error::Error, now, S3};
+
+/// Object recorded in a [`NamespaceManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub location: String,
+    pub size: usize,
+    pub e_tag: Option<String>,
+    pub version: Option<String>,
+}
+
+impl ManifestEntry {
+    /// Whether both entries describe the same content, versions differ between buckets.
+    fn same_content(&self, other: &ManifestEntry) -> bool {
+        self.size == other.size && self.e_tag == other.e_tag
+    }
+}
+
+/// Snapshot of the objects below a prefix, signed with HMAC-SHA256.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceManifest {
+    pub prefix: Option<String>,
+    pub created: DateTime<Utc>,
+    /// Entries sorted by location.
+    pub entries: Vec<ManifestEntry>,
+    /// Base64 encoded signature of the manifest serialized with an empty signature.
+    #[serde(default)]
+    pub signature: String,
+}
+
+/// Difference between a manifest and the current state of a store, or between two manifests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestDiff {
+    /// In the manifest but not in the store.
+    pub missing: Vec<ManifestEntry>,
+    /// In both but with a different size or ETag, the manifest's entry.
+    pub changed: Vec<ManifestEntry>,
+    /// In the store but not in the manifest.
+    pub extra: Vec<ManifestEntry>,
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.changed.is_empty() && self.extra.is_empty()
+    }
+}
+
+impl NamespaceManifest {
+    fn mac(&self, key: &[u8]) -> Result<Hmac<Sha256>, Error> {
+        let unsigned = NamespaceManifest {
+            signature: String::new(),
+            ..self.clone()
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|_| Error::InvalidSignature)?;
+        mac.update(&serde_json::to_vec(&unsigned)?);
+        Ok(mac)
+    }
+
+    fn sign(mut self, key: &[u8]) -> Result<Self, Error> {
+        let signature = self.mac(key)?.finalize().into_bytes();
+        self.signature = aws_smithy_types::base64::encode(signature);
+        Ok(self)
+    }
+
+    /// Checks the signature of the manifest against `key`.
+    pub fn verify(&self, key: &[u8]) -> Result<(), Error> {
+        let signature = aws_smithy_types::base64::decode(&self.signature)
+            .map_err(|_| Error::InvalidSignature)?;
+        self.mac(key)?
+            .verify_slice(&signature)
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    /// Entries to add, update and remove to turn `current` into `self`.
+    pub fn diff(&self, current: &NamespaceManifest) -> ManifestDiff {
+        let mut current = current
+            .entries
+            .iter()
+            .map(|entry| (entry.location.as_str(), entry))
+            .collect::<BTreeMap<_, _>>();
+        let mut diff = ManifestDiff::default();
+        for entry in &self.entries {
+            match current.remove(entry.location.as_str()) {
+                None => diff.missing.push(entry.clone()),
+                Some(existing) if !existing.same_content(entry) => diff.changed.push(entry.clone()),
+                Some(_) => (),
+            }
+        }
+        diff.extra = current.into_values().cloned().collect();
+        diff
+    }
+}
+
+impl S3 {
+    /// Lists the objects below `prefix` into a manifest signed with `key`.
+    pub async fn export_manifest(
+        &self,
+        prefix: Option<&Path>,
+        key: &[u8],
+    ) -> object_store::Result<NamespaceManifest> {
+        let mut entries = self
+            .list(prefix)
+            .map_ok(|meta| ManifestEntry {
+                location: meta.location.to_string(),
+                size: meta.size,
+                e_tag: meta.e_tag,
+                version: meta.version,
+            })
+            .try_collect::<Vec<_>>()
+            .await?;
+        entries.sort_by(|a, b| a.location.cmp(&b.location));
+        let manifest = NamespaceManifest {
+            prefix: prefix.map(ToString::to_string),
+            created: now(),
+            entries,
+            signature: String::new(),
+        };
+        Ok(manifest.sign(key)?)
+    }
+
+    /// Verifies `manifest` with `key` and compares it with the objects below its prefix.
+    ///
+    /// The returned diff lists what has to be copied into and deleted from the store to match the
+    /// manifest, the store itself isn't modified.
+    pub async fn import_manifest(
+        &self,
+        manifest: &NamespaceManifest,
+        key: &[u8],
+    ) -> object_store::Result<ManifestDiff> {
+        manifest.verify(key)?;
+        let prefix = manifest.prefix.clone().map(Path::from);
+        let current = self.export_manifest(prefix.as_ref(), key).await?;
+        Ok(manifest.diff(&current))
+    }
+}
@@ -0,0 +1,87 @@
+use bytes::Bytes;
+use object_store::{path::Path, ObjectStore};
+
+use crate::{error::Error, journal::Mutation, S3};
+
+/// Prefix below which staged uploads are stored until they are promoted or discarded.
+pub const STAGING_PREFIX: &str = ".staging";
+
+/// Object uploaded to a temporary key, see [`S3::staged_put`].
+///
+/// Dropping the handle without promoting or discarding leaves the staged object behind, a
+/// lifecycle rule on [`STAGING_PREFIX`] can clean those up.
+#[derive(Debug)]
+pub struct StagedPut<'a> {
+    store: &'a S3,
+    staging: Path,
+    target: Path,
+    e_tag: Option<String>,
+}
+
+impl StagedPut<'_> {
+    /// Temporary key the data was uploaded to.
+    pub fn staging_path(&self) -> &Path {
+        &self.staging
+    }
+
+    /// Final key the data is promoted to.
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Copies the staged object to the target and deletes it.
+    ///
+    /// The copy only succeeds if the staged object is unchanged since the upload.
+    pub async fn promote(self) -> object_store::Result<()> {
+        self.store
+            .copy_request(&self.staging, &self.target)
+            .set_copy_source_if_match(self.e_tag.clone())
+            .send()
+            .await
+            .map_err(Error::from)?;
+        self.store.record(|| Mutation::Copy {
+            from: self.staging.clone(),
+            to: self.target.clone(),
+        });
+        self.store.delete(&self.staging).await
+    }
+
+    /// Like [`StagedPut::promote`] but fails with [`object_store::Error::AlreadyExists`] instead of
+    /// overwriting an existing target.
+    pub async fn promote_if_not_exists(self) -> object_store::Result<()> {
+        self.store
+            .copy_if_not_exists(&self.staging, &self.target)
+            .await?;
+        self.store.delete(&self.staging).await
+    }
+
+    /// Deletes the staged object, the target is never touched.
+    pub async fn discard(self) -> object_store::Result<()> {
+        self.store.delete(&self.staging).await
+    }
+}
+
+impl S3 {
+    /// Uploads `bytes` to a temporary key below [`STAGING_PREFIX`] instead of `location`.
+    ///
+    /// The returned handle moves the object to `location` once the user confirms, or deletes it.
+    pub async fn staged_put(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+    ) -> object_store::Result<StagedPut<'_>> {
+        let staging = Path::from(format!(
+            "{}/{:016x}/{}",
+            STAGING_PREFIX,
+            self.random.next_u64(),
+            location
+        ));
+        let result = self.put(&staging, bytes).await?;
+        Ok(StagedPut {
+            store: self,
+            staging,
+            target: location.clone(),
+            e_tag: result.e_tag,
+        })
+    }
+}
@@ -0,0 +1,108 @@
+use aws_sdk_s3::{
+    operation::{get_object::GetObjectOutput, head_object::HeadObjectOutput},
+    types,
+};
+use aws_smithy_types::date_time::Format;
+use chrono::{DateTime, Utc};
+
+use crate::storage_class::StorageClass;
+
+/// Governance related status of an object that is not part of [`object_store::ObjectMeta`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectStatus {
+    /// Value of the `x-amz-replication-status` header.
+    pub replication: Option<ReplicationStatus>,
+    /// Parsed `x-amz-restore` header of archived objects.
+    pub restore: Option<RestoreStatus>,
+    /// Value of the `x-amz-archive-status` header, only returned by head requests.
+    pub archive: Option<ArchiveStatus>,
+    /// Value of the `x-amz-storage-class` header, S3 leaves it out for `STANDARD` objects.
+    pub storage_class: Option<StorageClass>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicationStatus {
+    Pending,
+    Completed,
+    Failed,
+    /// The object is itself a replica written by replication.
+    Replica,
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoreStatus {
+    /// Whether a restore request is still in progress.
+    pub ongoing: bool,
+    /// When the restored copy expires, present once the restore finished.
+    pub expiry: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveStatus {
+    ArchiveAccess,
+    DeepArchiveAccess,
+    Other(String),
+}
+
+impl From<&types::ReplicationStatus> for ReplicationStatus {
+    fn from(value: &types::ReplicationStatus) -> Self {
+        match value {
+            types::ReplicationStatus::Pending => ReplicationStatus::Pending,
+            types::ReplicationStatus::Complete => ReplicationStatus::Completed,
+            types::ReplicationStatus::Failed => ReplicationStatus::Failed,
+            types::ReplicationStatus::Replica => ReplicationStatus::Replica,
+            other => ReplicationStatus::Other(other.as_str().to_owned()),
+        }
+    }
+}
+
+impl From<&types::ArchiveStatus> for ArchiveStatus {
+    fn from(value: &types::ArchiveStatus) -> Self {
+        match value {
+            types::ArchiveStatus::ArchiveAccess => ArchiveStatus::ArchiveAccess,
+            types::ArchiveStatus::DeepArchiveAccess => ArchiveStatus::DeepArchiveAccess,
+            other => ArchiveStatus::Other(other.as_str().to_owned()),
+        }
+    }
+}
+
+impl RestoreStatus {
+    /// Parses an `x-amz-restore` header like
+    /// `ongoing-request="false", expiry-date="Fri, 21 Dec 2012 00:00:00 GMT"`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let ongoing = value
+            .split_once("ongoing-request=\"")?
+            .1
+            .starts_with("true");
+        let expiry = value
+            .split_once("expiry-date=\"")
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .and_then(|(date, _)| aws_smithy_types::DateTime::from_str(date, Format::HttpDate).ok())
+            .and_then(|date| date.to_millis().ok())
+            .and_then(DateTime::from_timestamp_millis);
+        Some(RestoreStatus { ongoing, expiry })
+    }
+}
+
+impl From<&HeadObjectOutput> for ObjectStatus {
+    fn from(value: &HeadObjectOutput) -> Self {
+        ObjectStatus {
+            replication: value.replication_status().map(ReplicationStatus::from),
+            restore: value.restore().and_then(RestoreStatus::parse),
+            archive: value.archive_status().map(ArchiveStatus::from),
+            storage_class: value.storage_class().map(StorageClass::from),
+        }
+    }
+}
+
+impl From<&GetObjectOutput> for ObjectStatus {
+    fn from(value: &GetObjectOutput) -> Self {
+        ObjectStatus {
+            replication: value.replication_status().map(ReplicationStatus::from),
+            restore: value.restore().and_then(RestoreStatus::parse),
+            archive: None,
+            storage_class: value.storage_class().map(StorageClass::from),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use aws_sdk_s3::{
+    config::{
+        interceptors::BeforeSerializationInterceptorContextMut, ConfigBag, Interceptor,
+        RuntimeComponents,
+    },
+    error::BoxError,
+    operation::{
+        copy_object::CopyObjectInput, create_multipart_upload::CreateMultipartUploadInput,
+        put_object::PutObjectInput,
+    },
+    types::{self, GlacierJobParameters, RestoreRequest, Tier},
+};
+use object_store::path::Path;
+
+use crate::S3;
+
+/// Storage class of an object, sent as `x-amz-storage-class`.
+///
+/// Set per put with [`PutAttributes::storage_class`](crate::attributes::PutAttributes::storage_class)
+/// or for every put, multipart upload and copy with
+/// [`S3Builder::with_storage_class`](crate::builder::S3Builder::with_storage_class).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageClass {
+    Standard,
+    ReducedRedundancy,
+    StandardIa,
+    OnezoneIa,
+    IntelligentTiering,
+    /// Glacier Flexible Retrieval, objects have to be restored before they can be read.
+    Glacier,
+    /// Glacier Instant Retrieval.
+    GlacierIr,
+    /// Glacier Deep Archive, objects have to be restored before they can be read.
+    DeepArchive,
+    Other(String),
+}
+
+impl StorageClass {
+    pub(crate) fn sdk(&self) -> types::StorageClass {
+        match self {
+            StorageClass::Standard => types::StorageClass::Standard,
+            StorageClass::ReducedRedundancy => types::StorageClass::ReducedRedundancy,
+            StorageClass::StandardIa => types::StorageClass::StandardIa,
+            StorageClass::OnezoneIa => types::StorageClass::OnezoneIa,
+            StorageClass::IntelligentTiering => types::StorageClass::IntelligentTiering,
+            StorageClass::Glacier => types::StorageClass::Glacier,
+            StorageClass::GlacierIr => types::StorageClass::GlacierIr,
+            StorageClass::DeepArchive => types::StorageClass::DeepArchive,
+            StorageClass::Other(class) => types::StorageClass::from(class.as_str()),
+        }
+    }
+
+    /// Whether objects of the class have to be restored with [`S3::restore_object`] before
+    /// they can be read.
+    pub fn is_archived(&self) -> bool {
+        matches!(self, StorageClass::Glacier | StorageClass::DeepArchive)
+    }
+}
+
+impl From<&types::StorageClass> for StorageClass {
+    fn from(value: &types::StorageClass) -> Self {
+        match value {
+            types::StorageClass::Standard => StorageClass::Standard,
+            types::StorageClass::ReducedRedundancy => StorageClass::ReducedRedundancy,
+            types::StorageClass::StandardIa => StorageClass::StandardIa,
+            types::StorageClass::OnezoneIa => StorageClass::OnezoneIa,
+            types::StorageClass::IntelligentTiering => StorageClass::IntelligentTiering,
+            types::StorageClass::Glacier => StorageClass::Glacier,
+            types::StorageClass::GlacierIr => StorageClass::GlacierIr,
+            types::StorageClass::DeepArchive => StorageClass::DeepArchive,
+            other => StorageClass::Other(other.as_str().to_owned()),
+        }
+    }
+}
+
+/// Speed and cost of a restore, see [`S3::restore_object`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestoreTier {
+    /// Minutes, not available for Deep Archive.
+    Expedited,
+    /// Hours.
+    #[default]
+    Standard,
+    /// Cheapest, up to two days.
+    Bulk,
+}
+
+impl RestoreTier {
+    fn sdk(self) -> Tier {
+        match self {
+            RestoreTier::Expedited => Tier::Expedited,
+            RestoreTier::Standard => Tier::Standard,
+            RestoreTier::Bulk => Tier::Bulk,
+        }
+    }
+}
+
+/// Sets the storage class of puts, multipart uploads and copies that don't have one.
+#[derive(Debug, Clone)]
+pub(crate) struct DefaultStorageClass(pub(crate) StorageClass);
+
+impl Interceptor for DefaultStorageClass {
+    fn name(&self) -> &'static str {
+        "DefaultStorageClass"
+    }
+
+    fn modify_before_serialization(
+        &self,
+        context: &mut BeforeSerializationInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let input = context.input_mut();
+        let storage_class = if let Some(input) = input.downcast_mut::<PutObjectInput>() {
+            &mut input.storage_class
+        } else if let Some(input) = input.downcast_mut::<CreateMultipartUploadInput>() {
+            &mut input.storage_class
+        } else if let Some(input) = input.downcast_mut::<CopyObjectInput>() {
+            &mut input.storage_class
+        } else {
+            return Ok(());
+        };
+        storage_class.get_or_insert_with(|| self.0.sdk());
+        Ok(())
+    }
+}
+
+impl S3 {
+    /// Requests a temporary copy of the archived object at `location` that can be read for
+    /// `days` days. Progress shows in [`ObjectStatus::restore`](crate::status::ObjectStatus::restore)
+    /// of [`S3::head_with_status`].
+    ///
+    /// Requesting a restore of an already restored object extends its expiry.
+    pub async fn restore_object(
+        &self,
+        location: &Path,
+        days: u32,
+        tier: RestoreTier,
+    ) -> object_store::Result<()> {
+        let request = RestoreRequest::builder()
+            .days(days as i32)
+            .glacier_job_parameters(GlacierJobParameters::builder().tier(tier.sdk()).build())
+            .build();
+        self.client
+            .restore_object()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .restore_request(request)
+            .send()
+            .await
+            .map_err(|err| self.request_error(location, err))?;
+        Ok(())
+    }
+}
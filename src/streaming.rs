@@ -0,0 +1,261 @@
+use aws_sdk_s3::{
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart},
+};
+use bytes::{Bytes, BytesMut};
+use futures::{
+    future::{self, BoxFuture, Either},
+    stream::{self, FuturesUnordered},
+    FutureExt, Stream, StreamExt, TryStreamExt,
+};
+use object_store::{path::Path, ObjectStore, PutMode, PutOptions, PutResult};
+
+use crate::{
+    attributes::PutAttributes,
+    encryption::SseConfig,
+    error::{status_code, Error},
+    journal::Mutation,
+    multipart::AbortOnDrop,
+    progress::Operation,
+    validate::MAX_PARTS,
+    S3,
+};
+
+/// Parts of a [`S3::put_stream`] uploaded concurrently.
+pub const MAX_CONCURRENT_PARTS: usize = 4;
+
+type PartUpload<'a> = BoxFuture<'a, object_store::Result<CompletedPart>>;
+
+impl S3 {
+    /// Uploads the chunks of `stream` to `location`.
+    ///
+    /// Streams shorter than a part are uploaded with a single put, longer ones with a multipart
+    /// upload of up to [`MAX_CONCURRENT_PARTS`] parts in flight. The stream is only polled while
+    /// there is room for another part, so a slow upload slows down the producer. `size_hint`
+    /// grows the part size for very large streams so they fit into the part limit, the total size
+    /// doesn't need to be known. The multipart upload is aborted if the stream or an upload fails.
+    pub async fn put_stream(
+        &self,
+        location: &Path,
+        stream: impl Stream<Item = object_store::Result<Bytes>>,
+        size_hint: Option<usize>,
+    ) -> object_store::Result<PutResult> {
+        let part_size = size_hint
+            .map(|size| size.div_ceil(MAX_PARTS as usize))
+            .unwrap_or_default()
+            .max(self.multipart_part_size);
+        let mut stream = std::pin::pin!(stream);
+        let mut buffer = BytesMut::new();
+        let mut exhausted = false;
+        while !exhausted && buffer.len() < part_size {
+            match stream.next().await.transpose()? {
+                Some(chunk) => buffer.extend_from_slice(&chunk),
+                None => exhausted = true,
+            }
+        }
+        if exhausted && buffer.len() <= part_size {
+            return self.put(location, buffer.freeze()).await;
+        }
+
+        let upload_id = self
+            .create_multipart(location, &PutAttributes::default())
+            .await?;
+        let guard = AbortOnDrop::new(self, location, &upload_id);
+        let upload = async {
+            let mut in_flight = FuturesUnordered::<PartUpload<'_>>::new();
+            let mut parts = Vec::new();
+            let mut next_part = 1;
+            loop {
+                let part_ready = buffer.len() >= part_size || (exhausted && !buffer.is_empty());
+                if part_ready && in_flight.len() < MAX_CONCURRENT_PARTS {
+                    let data = buffer.split_to(part_size.min(buffer.len())).freeze();
+                    in_flight.push(self.upload_part(location, &upload_id, next_part, data));
+                    next_part += 1;
+                    continue;
+                }
+                let readable = !exhausted && buffer.len() < part_size;
+                if !readable && in_flight.is_empty() {
+                    break;
+                }
+                let event = if readable && !in_flight.is_empty() {
+                    match future::select(stream.next(), in_flight.next()).await {
+                        Either::Left((chunk, _)) => Either::Left(chunk),
+                        Either::Right((part, _)) => Either::Right(part),
+                    }
+                } else if readable {
+                    Either::Left(stream.next().await)
+                } else {
+                    Either::Right(in_flight.next().await)
+                };
+                match event {
+                    Either::Left(Some(chunk)) => buffer.extend_from_slice(&chunk?),
+                    Either::Left(None) => exhausted = true,
+                    Either::Right(Some(part)) => parts.push(part?),
+                    Either::Right(None) => (),
+                }
+            }
+            parts.sort_by_key(|part| part.part_number);
+            let response = self
+                .client
+                .complete_multipart_upload()
+                .bucket(self.bucket.clone())
+                .key(location.to_string())
+                .upload_id(&upload_id)
+                .multipart_upload(
+                    CompletedMultipartUpload::builder()
+                        .set_parts(Some(parts.clone()))
+                        .build(),
+                )
+                .send()
+                .await
+                .map_err(|err| self.request_error(location, err))?;
+            self.verify_completion(location.as_ref(), &parts, &response);
+            Ok::<_, object_store::Error>(PutResult {
+                e_tag: response.e_tag,
+                version: response.version_id,
+            })
+        }
+        .await;
+
+        match upload {
+            Ok(result) => {
+                guard.disarm();
+                self.record(|| Mutation::Put {
+                    location: location.clone(),
+                    e_tag: result.e_tag.clone(),
+                    version: result.version.clone(),
+                });
+                Ok(result)
+            }
+            Err(err) => {
+                guard.abort().await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Uploads `bytes` with a multipart upload of slices of `bytes`, for payloads of
+    /// [`S3::put_opts`](ObjectStore::put_opts) above the multipart put threshold. The condition
+    /// of `opts.mode` is sent with `CompleteMultipartUpload`.
+    pub(crate) async fn put_split(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+        attributes: &PutAttributes,
+    ) -> object_store::Result<PutResult> {
+        let condition = self.put_condition(location, &opts.mode).await?;
+        let part_size = bytes
+            .len()
+            .div_ceil(MAX_PARTS as usize)
+            .max(self.multipart_part_size);
+        let tags = Some(opts.tags.encoded().to_owned()).filter(|tags| !tags.is_empty());
+        let upload_id = self
+            .create_tagged_multipart(location, attributes, tags)
+            .await?;
+        let guard = AbortOnDrop::new(self, location, &upload_id);
+        let upload = async {
+            let mut uploaded = 0;
+            let parts = stream::iter((0..bytes.len()).step_by(part_size).zip(1..))
+                .map(|(start, part_number)| {
+                    let data = bytes.slice(start..(start + part_size).min(bytes.len()));
+                    self.upload_part(location, &upload_id, part_number, data)
+                })
+                .buffered(self.multipart_concurrency)
+                .inspect_ok(|_| {
+                    uploaded = (uploaded + part_size).min(bytes.len());
+                    self.report_progress(Operation::Put, location, uploaded, Some(bytes.len()));
+                })
+                .try_collect::<Vec<_>>()
+                .await?;
+            let request = self
+                .client
+                .complete_multipart_upload()
+                .bucket(self.bucket.clone())
+                .key(location.to_string())
+                .upload_id(&upload_id)
+                .multipart_upload(
+                    CompletedMultipartUpload::builder()
+                        .set_parts(Some(parts.clone()))
+                        .build(),
+                );
+            let response = match condition {
+                Some((name, value)) => {
+                    request
+                        .customize()
+                        .await
+                        .map_err(|_| Error::Unknown)?
+                        .mutate_request(move |request| {
+                            request.headers_mut().insert(name.clone(), value.clone());
+                        })
+                        .send()
+                        .await
+                }
+                None => request.send().await,
+            };
+            let response = match response {
+                Ok(response) => response,
+                // A missing object fails `If-Match` with 404
+                Err(err)
+                    if opts.mode != PutMode::Overwrite
+                        && matches!(status_code(&err), Some(404 | 409 | 412)) =>
+                {
+                    let path = location.to_string();
+                    let source = Box::new(self.request_error(location, err));
+                    return Err(match opts.mode {
+                        PutMode::Create => object_store::Error::AlreadyExists { path, source },
+                        _ => object_store::Error::Precondition { path, source },
+                    });
+                }
+                Err(err) => return Err(self.request_error(location, err).into()),
+            };
+            self.verify_completion(location.as_ref(), &parts, &response);
+            Ok::<_, object_store::Error>(PutResult {
+                e_tag: response.e_tag,
+                version: response.version_id,
+            })
+        }
+        .await;
+
+        match upload {
+            Ok(result) => {
+                guard.disarm();
+                self.record(|| Mutation::Put {
+                    location: location.clone(),
+                    e_tag: result.e_tag.clone(),
+                    version: result.version.clone(),
+                });
+                Ok(result)
+            }
+            Err(err) => {
+                guard.abort().await;
+                Err(err)
+            }
+        }
+    }
+
+    fn upload_part<'a>(
+        &'a self,
+        location: &'a Path,
+        upload_id: &'a str,
+        part_number: i32,
+        data: Bytes,
+    ) -> PartUpload<'a> {
+        async move {
+            let request = self
+                .checksummed(self.client.upload_part(), &data)
+                .bucket(self.bucket.clone())
+                .key(location.to_string())
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(data));
+            let response = self
+                .encrypted(request, SseConfig::upload_part)
+                .send()
+                .await
+                .map_err(|err| self.request_error(location, err))?;
+            Ok(self.completed_part(part_number, response.e_tag(), &response)?)
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,146 @@
+use aws_sdk_s3::types::{Tag, Tagging};
+use object_store::path::Path;
+use percent_encoding::percent_decode_str;
+
+use crate::{error::Error, S3};
+
+/// Tags of an object as key value pairs, in the order S3 returns them.
+///
+/// Converts to and from the URL encoding of [`object_store::TagSet`], the encoding
+/// [`PutOptions::tags`](object_store::PutOptions::tags) writes tags with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagSet(Vec<(String, String)>);
+
+impl TagSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.0.push((key.into(), value.into()));
+    }
+
+    /// Value of the tag `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(tag, _)| tag == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Parses URL encoded `key=value` pairs joined by `&`, like
+    /// [`object_store::TagSet::encoded`].
+    pub fn from_encoded(encoded: &str) -> Result<Self, Error> {
+        let decode = |part: &str| {
+            percent_decode_str(&part.replace('+', " "))
+                .decode_utf8()
+                .map(|decoded| decoded.into_owned())
+                .map_err(|_| Error::InvalidTag(encoded.to_owned()))
+        };
+        encoded
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                Ok((decode(key)?, decode(value)?))
+            })
+            .collect::<Result<_, Error>>()
+            .map(TagSet)
+    }
+
+    fn tagging(&self) -> Tagging {
+        Tagging::builder()
+            .set_tag_set(Some(
+                self.iter()
+                    .map(|(key, value)| Tag::builder().key(key).value(value).build())
+                    .collect(),
+            ))
+            .build()
+    }
+}
+
+impl From<&TagSet> for object_store::TagSet {
+    fn from(tags: &TagSet) -> Self {
+        let mut set = object_store::TagSet::default();
+        for (key, value) in tags.iter() {
+            set.push(key, value);
+        }
+        set
+    }
+}
+
+impl TryFrom<&object_store::TagSet> for TagSet {
+    type Error = Error;
+
+    fn try_from(tags: &object_store::TagSet) -> Result<Self, Error> {
+        TagSet::from_encoded(tags.encoded())
+    }
+}
+
+impl FromIterator<(String, String)> for TagSet {
+    fn from_iter<T: IntoIterator<Item = (String, String)>>(iter: T) -> Self {
+        TagSet(iter.into_iter().collect())
+    }
+}
+
+impl S3 {
+    /// Tags of the object at `location`.
+    pub async fn get_tags(&self, location: &Path) -> object_store::Result<TagSet> {
+        let response = self
+            .client
+            .get_object_tagging()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .send()
+            .await
+            .map_err(|err| self.request_error(location, err))?;
+        Ok(response
+            .tag_set()
+            .unwrap_or_default()
+            .iter()
+            .map(|tag| {
+                let key = tag.key().unwrap_or_default().to_owned();
+                (key, tag.value().unwrap_or_default().to_owned())
+            })
+            .collect())
+    }
+
+    /// Replaces the tags of the object at `location` with `tags`.
+    pub async fn put_tags(&self, location: &Path, tags: &TagSet) -> object_store::Result<()> {
+        self.client
+            .put_object_tagging()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .tagging(tags.tagging())
+            .send()
+            .await
+            .map_err(|err| self.request_error(location, err))?;
+        Ok(())
+    }
+
+    /// Removes all tags of the object at `location`.
+    pub async fn delete_tags(&self, location: &Path) -> object_store::Result<()> {
+        self.client
+            .delete_object_tagging()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .send()
+            .await
+            .map_err(|err| self.request_error(location, err))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use aws_sdk_s3::{config::Region, Client};
+
+use crate::{partition::Partition, S3};
+
+/// Region and endpoint of [`S3::with_target`], unset fields keep the store's configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Target {
+    pub region: Option<String>,
+    pub endpoint: Option<String>,
+}
+
+impl Target {
+    pub fn region(region: impl Into<String>) -> Self {
+        Self::default().with_region(region)
+    }
+
+    pub fn endpoint(url: impl Into<String>) -> Self {
+        Self::default().with_endpoint(url)
+    }
+
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    pub fn with_endpoint(mut self, url: impl Into<String>) -> Self {
+        self.endpoint = Some(url.into());
+        self
+    }
+}
+
+impl S3 {
+    /// Store sending its requests to `target` instead of the configured region and endpoint.
+    ///
+    /// Only the SDK client is rebuilt, credentials, connector, metrics and journal are shared
+    /// with this store, so it is cheap enough to create for a single operation, e.g.
+    /// `store.with_target(Target::region("eu-west-1")).get(&location)`. Capabilities are probed
+    /// again for the target.
+    pub fn with_target(&self, target: Target) -> S3 {
+        let mut config = self.client.config().to_builder();
+        if let Some(region) = &target.region {
+            config.set_region(Some(Region::new(region.clone())));
+        }
+        if let Some(endpoint) = target.endpoint {
+            config.set_endpoint_url(Some(endpoint));
+        }
+        S3 {
+            client: Arc::new(Client::from_conf(config.build())),
+            partition: target
+                .region
+                .as_deref()
+                .map_or(self.partition, Partition::from_region),
+            head_denied: Arc::default(),
+            capabilities: Arc::default(),
+            ..self.clone()
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use object_store::{
+    path::Path, GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore,
+    PutOptions, PutResult,
+};
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+
+use crate::{
+    date_time,
+    environment::{Clock, SystemClock},
+    error::is_not_found,
+    is_unconditional,
+    tiered::DEFAULT_CACHE_TTL,
+};
+
+/// Wrapper store that writes every put through to a local store as well.
+///
+/// Reads of objects put through this wrapper are served from the local store, so data the client
+/// just uploaded (e.g. for a preview) isn't downloaded again. Only unconditional reads are served
+/// locally, conditional and versioned reads always go to `inner`. Local copies are served with the
+/// metadata `inner` returned for the put and revalidated against `inner` with `If-None-Match` once
+/// they're older than the TTL, so objects modified by other clients are picked up after at most
+/// the TTL.
+#[derive(Debug)]
+pub struct TeeStore<T: ObjectStore> {
+    inner: T,
+    local: Arc<dyn ObjectStore>,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+    /// Objects this store wrote through, other objects of the local store aren't served.
+    entries: Mutex<HashMap<Path, LocalEntry>>,
+}
+
+/// Object written through to the local store of a [`TeeStore`].
+#[derive(Debug, Clone)]
+struct LocalEntry {
+    /// Metadata of the object in `inner`.
+    meta: ObjectMeta,
+    /// When the copy was written or last revalidated.
+    validated: SystemTime,
+}
+
+impl<T: ObjectStore> TeeStore<T> {
+    pub fn new(inner: T, local: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            inner,
+            local,
+            ttl: DEFAULT_CACHE_TTL,
+            clock: Arc::new(SystemClock),
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Revalidate local copies against `inner` once they're older than `ttl`,
+    /// [`DEFAULT_CACHE_TTL`] by default. A `ttl` of zero revalidates every read.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Clock the age of local copies and their modification time are taken from.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Store receiving the written through copies.
+    pub fn local(&self) -> &Arc<dyn ObjectStore> {
+        &self.local
+    }
+
+    async fn evict(&self, location: &Path) {
+        self.entries.lock().remove(location);
+        let _ = self.local.delete(location).await;
+    }
+
+    /// Metadata of the local copy of `location` if it's still current, revalidating it with a
+    /// conditional `HEAD` once it's older than the TTL.
+    async fn current_entry(&self, location: &Path) -> Option<ObjectMeta> {
+        let entry = self.entries.lock().get(location).cloned()?;
+        let now = self.clock.now();
+        let age = now.duration_since(entry.validated).unwrap_or_default();
+        if age < self.ttl {
+            return Some(entry.meta);
+        }
+        let options = GetOptions {
+            if_none_match: Some(entry.meta.e_tag.clone()?),
+            head: true,
+            ..Default::default()
+        };
+        match self.inner.get_opts(location, options).await {
+            Err(object_store::Error::NotModified { .. }) => {
+                if let Some(entry) = self.entries.lock().get_mut(location) {
+                    entry.validated = now;
+                }
+                Some(entry.meta)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for TeeStore<T> {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        let size = bytes.len();
+        let result = self.inner.put_opts(location, bytes.clone(), opts).await?;
+        if self.local.put(location, bytes).await.is_err() {
+            self.evict(location).await;
+            return Ok(result);
+        }
+        let now = self.clock.now();
+        let entry = LocalEntry {
+            meta: ObjectMeta {
+                location: location.clone(),
+                // `inner` doesn't return the modification time, it's close to the time of the put
+                last_modified: date_time(now),
+                size,
+                e_tag: result.e_tag.clone(),
+                version: result.version.clone(),
+            },
+            validated: now,
+        };
+        self.entries.lock().insert(location.clone(), entry);
+        Ok(result)
+    }
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.evict(location).await;
+        self.inner.put_multipart(location).await
+    }
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        if is_unconditional(&options) {
+            if let Some(meta) = self.current_entry(location).await {
+                let local_options = GetOptions {
+                    range: options.range.clone(),
+                    head: options.head,
+                    ..Default::default()
+                };
+                match self.local.get_opts(location, local_options).await {
+                    Ok(result) => return Ok(GetResult { meta, ..result }),
+                    Err(err) if is_not_found(&err) => {
+                        self.entries.lock().remove(location);
+                    }
+                    Err(_) => self.evict(location).await,
+                }
+            } else if self.entries.lock().contains_key(location) {
+                self.evict(location).await;
+            }
+        }
+        self.inner.get_opts(location, options).await
+    }
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.inner.head(location).await
+    }
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await?;
+        self.evict(location).await;
+        Ok(())
+    }
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        self.inner.list(prefix)
+    }
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy(from, to).await?;
+        self.evict(to).await;
+        Ok(())
+    }
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await?;
+        self.evict(to).await;
+        Ok(())
+    }
+}
+
+impl<T: ObjectStore> Display for TeeStore<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Tee({}, {})", self.inner, self.local)
+    }
+}
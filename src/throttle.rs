@@ -0,0 +1,79 @@
+use std::{sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::environment::Timer;
+
+/// Token bucket refilled with `rate` tokens per second up to `burst` tokens.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    refilled_at: Duration,
+}
+
+impl TokenBucket {
+    /// Takes a token, or returns how long to wait until the next one is available.
+    fn take(&mut self, now: Duration) -> Option<Duration> {
+        let elapsed = now.saturating_sub(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return None;
+        }
+        Some(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+    }
+}
+
+/// Store-wide limits of the requests in flight and the request rate, applied to every attempt
+/// of every request.
+#[derive(Debug, Clone)]
+pub(crate) struct Throttle {
+    permits: Option<Arc<Semaphore>>,
+    bucket: Option<Arc<Mutex<TokenBucket>>>,
+    timer: Arc<dyn Timer>,
+}
+
+impl Throttle {
+    pub(crate) fn new(
+        max_concurrent: Option<usize>,
+        rate_limit: Option<(u32, u32)>,
+        timer: Arc<dyn Timer>,
+    ) -> Self {
+        Self {
+            permits: max_concurrent.map(|permits| Arc::new(Semaphore::new(permits.max(1)))),
+            bucket: rate_limit.map(|(rate, burst)| {
+                let burst = f64::from(burst.max(1));
+                Arc::new(Mutex::new(TokenBucket {
+                    rate: f64::from(rate.max(1)),
+                    burst,
+                    tokens: burst,
+                    refilled_at: timer.now(),
+                }))
+            }),
+            timer,
+        }
+    }
+
+    /// Waits until a request may be sent. The request counts as in flight until the returned
+    /// permit is dropped.
+    pub(crate) async fn admit(&self) -> Option<OwnedSemaphorePermit> {
+        // Wait for the rate limit first so waiting requests don't hold a permit
+        if let Some(bucket) = &self.bucket {
+            loop {
+                let wait = bucket.lock().take(self.timer.now());
+                match wait {
+                    Some(wait) => self.timer.sleep(wait).await,
+                    None => break,
+                }
+            }
+        }
+        match &self.permits {
+            Some(permits) => permits.clone().acquire_owned().await.ok(),
+            None => None,
+        }
+    }
+}
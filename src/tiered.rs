@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{future, stream, stream::BoxStream, StreamExt};
+use object_store::{
+    path::Path, GetOptions, GetResult, GetResultPayload, ListResult, MultipartId, ObjectMeta,
+    ObjectStore, PutOptions, PutResult,
+};
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+
+use crate::{environment::spawn, is_unconditional, S3};
+
+/// Largest object [`TieredStore`] copies into its cache by default.
+pub const DEFAULT_MAX_CACHED_SIZE: usize = 16 * 1024 * 1024;
+
+/// How long [`TieredStore`] serves a cached object before revalidating it by default.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Tier that served a read of a [`TieredStore`], counted in
+/// [`StoreMetrics::tiers`](crate::metrics::StoreMetrics::tiers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    Cache,
+    Cdn,
+    Origin,
+}
+
+/// Read strategy trying a local cache, then a CDN and finally the signed S3 origin.
+///
+/// The CDN is any store serving the bucket's objects without signing, e.g. an `HttpStore`
+/// pointed at a CDN in front of a public bucket. Whole objects read from the CDN or the origin
+/// are copied into the cache. Only unconditional reads of the latest version are served by the
+/// cache and the CDN. Cached copies are served with the metadata the object was read with and
+/// revalidated against the origin with `If-None-Match` once they're older than the cache TTL.
+/// The CDN may return stale data until its entries expire. Writes go to the origin and evict the
+/// cached copy.
+#[derive(Debug)]
+pub struct TieredStore {
+    origin: S3,
+    cache: Option<Arc<dyn ObjectStore>>,
+    cdn: Option<Arc<dyn ObjectStore>>,
+    max_cached_size: usize,
+    cache_ttl: Duration,
+    /// Objects this store copied into the cache, other entries of the cache aren't served.
+    entries: Arc<Mutex<HashMap<Path, CacheEntry>>>,
+}
+
+/// Object copied into the cache of a [`TieredStore`].
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    /// Metadata of the object as read from the CDN or the origin.
+    meta: ObjectMeta,
+    /// When the copy was read or last revalidated.
+    validated: Duration,
+}
+
+impl TieredStore {
+    pub fn new(origin: S3) -> Self {
+        Self {
+            origin,
+            cache: None,
+            cdn: None,
+            max_cached_size: DEFAULT_MAX_CACHED_SIZE,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Serve reads from `cache` first, e.g. an in-memory or OPFS store.
+    pub fn with_cache(mut self, cache: Arc<dyn ObjectStore>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Try `cdn` before the origin, reads it fails are sent to the origin.
+    pub fn with_cdn(mut self, cdn: Arc<dyn ObjectStore>) -> Self {
+        self.cdn = Some(cdn);
+        self
+    }
+
+    /// Only copy objects of at most `size` bytes into the cache.
+    pub fn with_max_cached_size(mut self, size: usize) -> Self {
+        self.max_cached_size = size;
+        self
+    }
+
+    /// Revalidate cached objects against the origin once they're older than `ttl`,
+    /// [`DEFAULT_CACHE_TTL`] by default. A `ttl` of zero revalidates every read.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    pub fn origin(&self) -> &S3 {
+        &self.origin
+    }
+
+    async fn evict(&self, location: &Path) {
+        self.entries.lock().remove(location);
+        if let Some(cache) = &self.cache {
+            let _ = cache.delete(location).await;
+        }
+    }
+
+    /// Metadata of the cached copy of `location` if it's still current, revalidating it with a
+    /// conditional `HEAD` once it's older than the TTL.
+    async fn current_entry(&self, location: &Path) -> Option<ObjectMeta> {
+        let entry = self.entries.lock().get(location).cloned()?;
+        let now = self.origin.timer.now();
+        if now.saturating_sub(entry.validated) < self.cache_ttl {
+            return Some(entry.meta);
+        }
+        let options = GetOptions {
+            if_none_match: Some(entry.meta.e_tag.clone()?),
+            head: true,
+            ..Default::default()
+        };
+        match self.origin.get_opts(location, options).await {
+            Err(object_store::Error::NotModified { .. }) => {
+                if let Some(entry) = self.entries.lock().get_mut(location) {
+                    entry.validated = now;
+                }
+                Some(entry.meta)
+            }
+            _ => None,
+        }
+    }
+
+    /// Copies a whole object read from a lower tier into the cache.
+    async fn populate(
+        &self,
+        location: &Path,
+        options: &GetOptions,
+        result: GetResult,
+    ) -> object_store::Result<GetResult> {
+        let Some(cache) = &self.cache else {
+            return Ok(result);
+        };
+        if options.range.is_some() || options.head || result.meta.size > self.max_cached_size {
+            return Ok(result);
+        }
+        let (meta, range) = (result.meta.clone(), result.range.clone());
+        let bytes = result.bytes().await?;
+        if cache.put(location, bytes.clone()).await.is_ok() {
+            let entry = CacheEntry {
+                meta: meta.clone(),
+                validated: self.origin.timer.now(),
+            };
+            self.entries.lock().insert(location.clone(), entry);
+        } else {
+            self.evict(location).await;
+        }
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(stream::once(future::ready(Ok(bytes))).boxed()),
+            meta,
+            range,
+        })
+    }
+}
+
+#[async_trait]
+impl ObjectStore for TieredStore {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        bytes: Bytes,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        let result = self.origin.put_opts(location, bytes, opts).await?;
+        self.evict(location).await;
+        Ok(result)
+    }
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        let (upload_id, writer) = self.origin.put_multipart(location).await?;
+        let writer = EvictOnComplete {
+            writer,
+            location: location.clone(),
+            cache: self.cache.clone(),
+            entries: self.entries.clone(),
+        };
+        Ok((upload_id, Box::new(writer)))
+    }
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.origin.abort_multipart(location, multipart_id).await
+    }
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let tier_options = || GetOptions {
+            range: options.range.clone(),
+            head: options.head,
+            ..Default::default()
+        };
+        let unconditional = is_unconditional(&options);
+        if unconditional {
+            if let Some(cache) = &self.cache {
+                if let Some(meta) = self.current_entry(location).await {
+                    if let Ok(result) = cache.get_opts(location, tier_options()).await {
+                        self.origin.metrics.record_tier(Tier::Cache);
+                        return Ok(GetResult { meta, ..result });
+                    }
+                }
+                let cached = self.entries.lock().contains_key(location);
+                if cached {
+                    self.evict(location).await;
+                }
+            }
+            if let Some(cdn) = &self.cdn {
+                if let Ok(result) = cdn.get_opts(location, tier_options()).await {
+                    self.origin.metrics.record_tier(Tier::Cdn);
+                    return self.populate(location, &tier_options(), result).await;
+                }
+            }
+        }
+        let populate = tier_options();
+        let result = self.origin.get_opts(location, options).await?;
+        self.origin.metrics.record_tier(Tier::Origin);
+        if !unconditional {
+            return Ok(result);
+        }
+        self.populate(location, &populate, result).await
+    }
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.origin.head(location).await
+    }
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.origin.delete(location).await?;
+        self.evict(location).await;
+        Ok(())
+    }
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        self.origin.list(prefix)
+    }
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.origin.list_with_delimiter(prefix).await
+    }
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.origin.copy(from, to).await?;
+        self.evict(to).await;
+        Ok(())
+    }
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.origin.copy_if_not_exists(from, to).await?;
+        self.evict(to).await;
+        Ok(())
+    }
+}
+
+/// Writer of a [`TieredStore::put_multipart`] that evicts the cached copy once the upload
+/// completed, so reads during the upload may still be served the previous version.
+struct EvictOnComplete {
+    writer: Box<dyn AsyncWrite + Unpin + Send>,
+    location: Path,
+    cache: Option<Arc<dyn ObjectStore>>,
+    entries: Arc<Mutex<HashMap<Path, CacheEntry>>>,
+}
+
+impl AsyncWrite for EvictOnComplete {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.writer).poll_shutdown(cx);
+        if let Poll::Ready(Ok(())) = result {
+            // The cached copy isn't served anymore once its entry is gone
+            this.entries.lock().remove(&this.location);
+            if let Some(cache) = this.cache.take() {
+                let location = this.location.clone();
+                spawn(async move {
+                    let _ = cache.delete(&location).await;
+                });
+            }
+        }
+        result
+    }
+}
+
+impl Display for TieredStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Tiered({})", self.origin)
+    }
+}
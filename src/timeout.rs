@@ -0,0 +1,177 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::pin,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use aws_smithy_http::result::ConnectorError;
+use futures::future::{self, Either};
+
+use crate::{environment::Timer, error::Error};
+
+/// Phase of a request a timeout of [`Error::RequestTimeout`] bounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    /// Waiting for the response headers, see
+    /// [`S3Builder::with_connect_timeout`](crate::builder::S3Builder::with_connect_timeout).
+    Connect,
+    /// The whole request including its response body, see
+    /// [`S3Builder::with_request_timeout`](crate::builder::S3Builder::with_request_timeout).
+    Total,
+}
+
+impl fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutPhase::Connect => f.write_str("connect"),
+            TimeoutPhase::Total => f.write_str("total"),
+        }
+    }
+}
+
+/// Whether `err` is a request aborted by a connect or request timeout.
+pub fn is_timeout(err: &object_store::Error) -> bool {
+    let object_store::Error::Generic { source, .. } = err else {
+        return false;
+    };
+    let source = match source.downcast_ref::<Error>() {
+        Some(Error::Request { source, .. }) => Some(source.as_ref()),
+        source => source,
+    };
+    matches!(source, Some(Error::RequestTimeout { .. }))
+}
+
+/// Timeouts of every attempt of a request.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Timeouts {
+    pub(crate) connect: Option<Duration>,
+    pub(crate) total: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Awaits `fetch`, aborting it through `control` once a timeout elapses.
+    pub(crate) async fn run<T>(
+        &self,
+        fetch: impl Future<Output = Result<T, Error>>,
+        control: &FetchControl,
+        timer: &dyn Timer,
+    ) -> Result<T, ConnectorError> {
+        let connect = async {
+            if let Some(after) = self.connect {
+                timer.sleep(after).await;
+                if !control.connected.load(Ordering::Relaxed) {
+                    return (TimeoutPhase::Connect, after);
+                }
+            }
+            future::pending().await
+        };
+        let total = async {
+            match self.total {
+                Some(after) => {
+                    timer.sleep(after).await;
+                    (TimeoutPhase::Total, after)
+                }
+                None => future::pending().await,
+            }
+        };
+        let deadline = async {
+            match future::select(pin!(connect), pin!(total)).await {
+                Either::Left((elapsed, _)) | Either::Right((elapsed, _)) => elapsed,
+            }
+        };
+        match future::select(pin!(fetch), pin!(deadline)).await {
+            Either::Left((result, _)) => result.map_err(|err| ConnectorError::io(Box::new(err))),
+            Either::Right(((phase, after), _)) => {
+                control.abort();
+                Err(ConnectorError::timeout(Box::new(Error::RequestTimeout {
+                    phase,
+                    after,
+                })))
+            }
+        }
+    }
+}
+
+/// Abort signal and progress of a single fetch.
+///
+/// Native requests have no abort signal, they're aborted by dropping their future.
+#[derive(Debug, Default)]
+pub(crate) struct FetchControl {
+    #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+    controller: Option<web_sys::AbortController>,
+    /// Timer and total timeout the fetch started with, streamed bodies are bound by it too.
+    #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+    deadline: Option<(std::sync::Arc<dyn Timer>, Duration, Duration)>,
+    /// Resolves once a streamed response body is done.
+    #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+    body: std::cell::RefCell<Option<tokio::sync::oneshot::Receiver<()>>>,
+    connected: AtomicBool,
+}
+
+impl FetchControl {
+    pub(crate) fn new() -> Self {
+        Self {
+            #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+            controller: web_sys::AbortController::new().ok(),
+            ..Default::default()
+        }
+    }
+
+    /// Bounds streamed response bodies by the `total` timeout of `timeouts`, counted from now.
+    #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+    pub(crate) fn with_deadline(
+        mut self,
+        timeouts: &Timeouts,
+        timer: std::sync::Arc<dyn Timer>,
+    ) -> Self {
+        self.deadline = timeouts
+            .total
+            .map(|after| (timer.clone(), timer.now() + after, after));
+        self
+    }
+
+    #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+    pub(crate) fn signal(&self) -> Option<web_sys::AbortSignal> {
+        self.controller
+            .as_ref()
+            .map(web_sys::AbortController::signal)
+    }
+
+    /// Aborts the fetch, if it's still running.
+    pub(crate) fn abort(&self) {
+        #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+        if let Some(controller) = &self.controller {
+            controller.abort();
+        }
+    }
+
+    /// Marks that the response headers arrived.
+    pub(crate) fn connected(&self) {
+        self.connected.store(true, Ordering::Relaxed);
+    }
+
+    /// Streams the response body `stream` within the deadline of the fetch.
+    #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+    pub(crate) fn stream_body(
+        &self,
+        stream: web_sys::ReadableStream,
+    ) -> aws_sdk_s3::primitives::SdkBody {
+        let deadline = self.deadline.as_ref().map(|(timer, expires, after)| {
+            (timer.sleep(expires.saturating_sub(timer.now())), *after)
+        });
+        let (body, done) = crate::fetch_body::stream_body(stream, deadline);
+        *self.body.borrow_mut() = Some(done);
+        body
+    }
+
+    /// Waits until the streamed response body, if any, is done.
+    #[cfg(not(all(feature = "native", not(target_arch = "wasm32"))))]
+    pub(crate) async fn body_done(&self) {
+        let done = self.body.borrow_mut().take();
+        if let Some(done) = done {
+            let _ = done.await;
+        }
+    }
+}
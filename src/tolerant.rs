@@ -0,0 +1,185 @@
+use aws_sdk_s3::{
+    config::{
+        interceptors::BeforeDeserializationInterceptorContextMut, ConfigBag, Interceptor,
+        RuntimeComponents,
+    },
+    error::BoxError,
+    primitives::SdkBody,
+};
+use aws_smithy_http::operation::Metadata;
+
+/// Deviations from well-formed S3 responses the tolerant parser repairs, see
+/// [`S3Builder::with_tolerant_parsing`](crate::builder::S3Builder::with_tolerant_parsing).
+///
+/// The default repairs everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Leniency {
+    /// Drops a byte order mark and whitespace in front of the XML declaration.
+    pub trim_prolog: bool,
+    /// Escapes `&` that don't start a character or entity reference.
+    pub escape_ampersands: bool,
+    /// Removes characters XML 1.0 doesn't allow, e.g. control characters in keys, and replaces
+    /// invalid UTF-8.
+    pub strip_invalid_chars: bool,
+    /// Translates JSON error bodies with a `code` and `message` into S3 XML errors, so they map
+    /// to the same errors as a regular S3 error response.
+    pub json_errors: bool,
+}
+
+impl Default for Leniency {
+    fn default() -> Self {
+        Self {
+            trim_prolog: true,
+            escape_ampersands: true,
+            strip_invalid_chars: true,
+            json_errors: true,
+        }
+    }
+}
+
+impl Leniency {
+    /// Repairs nothing, responses reach the SDK parser unchanged.
+    pub fn strict() -> Self {
+        Self {
+            trim_prolog: false,
+            escape_ampersands: false,
+            strip_invalid_chars: false,
+            json_errors: false,
+        }
+    }
+
+    /// Repaired `body` of a response, `None` if it's fine as is.
+    pub(crate) fn repair(&self, body: &[u8], error: bool) -> Option<Vec<u8>> {
+        let text = match std::str::from_utf8(body) {
+            Ok(text) => text.to_owned(),
+            Err(_) if self.strip_invalid_chars => String::from_utf8_lossy(body).into_owned(),
+            Err(_) => return None,
+        };
+        let mut repaired = text.as_str();
+        if self.trim_prolog {
+            repaired = repaired.trim_start_matches('\u{feff}').trim_start();
+        }
+        let mut repaired = (self.json_errors && error)
+            .then(|| json_error(repaired))
+            .flatten()
+            .unwrap_or_else(|| repaired.to_owned());
+        if self.strip_invalid_chars {
+            repaired.retain(is_xml_char);
+        }
+        if self.escape_ampersands {
+            repaired = escape_ampersands(&repaired);
+        }
+        (repaired.as_bytes() != body).then(|| repaired.into_bytes())
+    }
+}
+
+/// Applies a [`Leniency`] to every response body before the SDK parses it.
+///
+/// Successful `GetObject` bodies are the object's data and are never touched.
+#[derive(Debug, Clone)]
+pub(crate) struct TolerantParser {
+    leniency: Leniency,
+}
+
+impl TolerantParser {
+    pub(crate) fn new(leniency: Leniency) -> Self {
+        Self { leniency }
+    }
+}
+
+impl Interceptor for TolerantParser {
+    fn name(&self) -> &'static str {
+        "TolerantParser"
+    }
+
+    fn modify_before_deserialization(
+        &self,
+        context: &mut BeforeDeserializationInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let response = context.response_mut();
+        let error = !response.status().is_success();
+        let get_object = cfg
+            .load::<Metadata>()
+            .is_some_and(|metadata| metadata.name() == "GetObject");
+        if get_object && !error {
+            return Ok(());
+        }
+        // Streaming bodies aren't in memory and can't be repaired
+        let repaired = response
+            .body()
+            .bytes()
+            .and_then(|body| self.leniency.repair(body, error));
+        if let Some(repaired) = repaired {
+            *response.body_mut() = SdkBody::from(repaired);
+        }
+        Ok(())
+    }
+}
+
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r') || (c >= ' ' && !matches!(c, '\u{fffe}' | '\u{ffff}'))
+}
+
+/// Escapes every `&` of `text` that doesn't start a reference like `&amp;` or `&#38;`.
+fn escape_ampersands(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (idx, part) in text.split('&').enumerate() {
+        if idx > 0 {
+            escaped.push_str(if starts_with_reference(part) {
+                "&"
+            } else {
+                "&amp;"
+            });
+        }
+        escaped.push_str(part);
+    }
+    escaped
+}
+
+fn starts_with_reference(text: &str) -> bool {
+    let Some((name, _)) = text.split_once(';') else {
+        return false;
+    };
+    match name.strip_prefix('#') {
+        Some(hex) if hex.starts_with(['x', 'X']) => {
+            hex.len() > 1 && hex[1..].chars().all(|c| c.is_ascii_hexdigit())
+        }
+        Some(decimal) => !decimal.is_empty() && decimal.chars().all(|c| c.is_ascii_digit()),
+        None => !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric()),
+    }
+}
+
+/// S3 XML error equivalent to a JSON error body like `{"code": "...", "message": "..."}`.
+fn json_error(body: &str) -> Option<String> {
+    if !body.starts_with('{') {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let field = |names: [&str; 2]| {
+        names
+            .iter()
+            .find_map(|name| value.get(*name).and_then(serde_json::Value::as_str))
+    };
+    let code = field(["Code", "code"]);
+    let message = field(["Message", "message"]);
+    if code.is_none() && message.is_none() {
+        return None;
+    }
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?><Error>"#);
+    if let Some(code) = code {
+        xml.push_str(&format!("<Code>{}</Code>", escape_text(code)));
+    }
+    if let Some(message) = message {
+        xml.push_str(&format!("<Message>{}</Message>", escape_text(message)));
+    }
+    xml.push_str("</Error>");
+    Some(xml)
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
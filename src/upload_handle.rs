@@ -0,0 +1,125 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use object_store::multipart::{PartId, PutPart};
+use object_store_next::{
+    path::Path, MultipartUpload, PutMultipartOpts, PutPayload, PutResult, UploadPart,
+};
+use tokio::sync::Semaphore;
+
+use crate::{
+    error::Error,
+    multipart::MultiPartUpload,
+    next_store::{next_error, path, put_attributes},
+    S3,
+};
+
+/// Multipart upload of `object_store` 0.10, returned by [`S3::put_multipart_opts`].
+///
+/// Parts are uploaded when their [`UploadPart`] future is polled, at most as many at once as
+/// [`S3Builder::with_multipart_concurrency`](crate::builder::S3Builder::with_multipart_concurrency)
+/// allows. Further parts wait for a slot, so callers like `WriteMultipart` are slowed down
+/// instead of buffering every part.
+pub struct UploadHandle {
+    upload: Arc<MultiPartUpload>,
+    in_flight: Arc<Semaphore>,
+    next_part: usize,
+}
+
+impl std::fmt::Debug for UploadHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadHandle")
+            .field("location", &self.upload.location)
+            .field("upload_id", &self.upload.upload_id)
+            .field("next_part", &self.next_part)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl MultipartUpload for UploadHandle {
+    fn put_part(&mut self, data: PutPayload) -> UploadPart {
+        let upload = self.upload.clone();
+        let in_flight = self.in_flight.clone();
+        let part_idx = self.next_part;
+        self.next_part += 1;
+        Box::pin(async move {
+            // The semaphore is never closed
+            let _permit = in_flight.acquire_owned().await;
+            upload
+                .put_part(Bytes::from(data).into(), part_idx)
+                .await
+                .map(|_| ())
+                .map_err(next_error)
+        })
+    }
+
+    async fn complete(&mut self) -> object_store_next::Result<PutResult> {
+        let parts = {
+            let uploaded = self.upload.parts.lock();
+            (0..self.next_part)
+                .map(
+                    |idx| match uploaded.get(&idx).and_then(|part| part.e_tag()) {
+                        Some(e_tag) => Ok(PartId {
+                            content_id: e_tag.to_owned(),
+                        }),
+                        None => Err(next_error(Error::MissingPart(idx).into())),
+                    },
+                )
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let (e_tag, version) = self
+            .upload
+            .complete_parts(parts)
+            .await
+            .map_err(next_error)?;
+        Ok(PutResult { e_tag, version })
+    }
+
+    async fn abort(&mut self) -> object_store_next::Result<()> {
+        self.upload
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.upload.bucket)
+            .key(&self.upload.location)
+            .upload_id(&self.upload.upload_id)
+            .send()
+            .await
+            .map_err(|err| {
+                next_error(
+                    Error::from(err)
+                        .request(&self.upload.bucket, &self.upload.location)
+                        .into(),
+                )
+            })?;
+        // Nothing left to abort when the upload is dropped
+        self.upload.completed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl S3 {
+    /// Starts a multipart upload to `location` with the `MultipartUpload` API of
+    /// `object_store` 0.10, for callers on newer `object_store` releases.
+    ///
+    /// The content headers and user metadata of `opts` are set on the object. Tags are ignored
+    /// as the `object_store` documentation allows.
+    pub async fn put_multipart_opts(
+        &self,
+        location: &Path,
+        opts: PutMultipartOpts,
+    ) -> object_store_next::Result<Box<dyn MultipartUpload>> {
+        let attributes = put_attributes(&opts.attributes)?;
+        let location = path(location)?;
+        let upload_id = self
+            .create_multipart(&location, &attributes)
+            .await
+            .map_err(next_error)?;
+        Ok(Box::new(UploadHandle {
+            upload: Arc::new(self.multipart_upload(&location, &upload_id, None)),
+            in_flight: Arc::new(Semaphore::new(self.multipart_concurrency)),
+            next_part: 0,
+        }))
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use object_store::{path::Path, MultipartId};
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+
+use crate::{
+    attributes::PutAttributes,
+    environment::Timer,
+    multipart::{MultiPartUpload, PartWriter},
+    S3,
+};
+
+/// Uploaded part of an [`UploadReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartReport {
+    pub part_number: usize,
+    pub size: usize,
+    /// Hex encoded MD5 digest of the part's bytes, computed before the upload.
+    pub md5: String,
+    /// ETag the server returned for the part.
+    pub e_tag: String,
+    /// Whether the ETag is the MD5 digest, which doesn't hold for parts encrypted with SSE-KMS
+    /// or SSE-C.
+    pub verified: bool,
+}
+
+/// Summary of a completed multipart upload, see [`UploadHandle::report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadReport {
+    /// Parts in ascending part order.
+    pub parts: Vec<PartReport>,
+    pub size: usize,
+    pub e_tag: Option<String>,
+    pub version: Option<String>,
+    /// Time from the start of the upload until it was completed.
+    pub elapsed: Duration,
+    /// Failed requests uploading and completing the parts, each retried attempt counts once.
+    pub failed_requests: u64,
+}
+
+impl UploadReport {
+    /// Whether the ETag of every part matches its MD5 digest.
+    pub fn verified(&self) -> bool {
+        self.parts.iter().all(|part| part.verified)
+    }
+}
+
+/// Collects the [`UploadReport`] of a multipart upload while its parts are uploaded.
+#[derive(Debug, Clone)]
+pub(crate) struct ReportRecorder {
+    timer: Arc<dyn Timer>,
+    started: Duration,
+    state: Arc<Mutex<RecorderState>>,
+}
+
+#[derive(Debug, Default)]
+struct RecorderState {
+    parts: Vec<PartReport>,
+    report: Option<UploadReport>,
+}
+
+impl ReportRecorder {
+    pub(crate) fn new(timer: Arc<dyn Timer>) -> Self {
+        Self {
+            started: timer.now(),
+            timer,
+            state: Arc::default(),
+        }
+    }
+
+    pub(crate) fn part(&self, part_number: usize, size: usize, md5: String, e_tag: &str) {
+        self.state.lock().parts.push(PartReport {
+            part_number,
+            size,
+            verified: e_tag.trim_matches('"') == md5,
+            md5,
+            e_tag: e_tag.to_owned(),
+        });
+    }
+
+    pub(crate) fn complete(
+        &self,
+        e_tag: Option<String>,
+        version: Option<String>,
+        failed_requests: u64,
+    ) {
+        let mut state = self.state.lock();
+        let mut parts = std::mem::take(&mut state.parts);
+        parts.sort_by_key(|part| part.part_number);
+        state.report = Some(UploadReport {
+            size: parts.iter().map(|part| part.size).sum(),
+            parts,
+            e_tag,
+            version,
+            elapsed: self.timer.now().saturating_sub(self.started),
+            failed_requests,
+        });
+    }
+}
+
+/// Writer of a multipart upload that reports on the upload once it's completed.
+///
+/// Like the writer of [`ObjectStore::put_multipart`](object_store::ObjectStore::put_multipart),
+/// the upload is completed by shutting the writer down.
+pub struct UploadHandle {
+    writer: PartWriter<MultiPartUpload>,
+    recorder: ReportRecorder,
+}
+
+impl UploadHandle {
+    /// Report of the upload, `None` until it was completed.
+    pub fn report(&self) -> Option<UploadReport> {
+        self.recorder.state.lock().report.clone()
+    }
+}
+
+impl AsyncWrite for UploadHandle {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().writer).poll_shutdown(cx)
+    }
+}
+
+impl S3 {
+    /// Like [`S3::put_multipart_with_attributes`] but the returned handle also reports part sizes,
+    /// checksums, duration and failed requests of the upload once it's completed.
+    pub async fn put_multipart_with_report(
+        &self,
+        location: &Path,
+        attributes: &PutAttributes,
+    ) -> object_store::Result<(MultipartId, UploadHandle)> {
+        let recorder = ReportRecorder::new(self.timer.clone());
+        let (upload_id, writer) = self
+            .part_writer(location, attributes, Some(recorder.clone()))
+            .await?;
+        Ok((upload_id, UploadHandle { writer, recorder }))
+    }
+}
@@ -0,0 +1,217 @@
+use std::{sync::Arc, time::Duration};
+
+use aws_credential_types::cache::ProvideCachedCredentials;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use http::Method;
+use object_store::{path::Path, ObjectMeta, ObjectStore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{
+    error::Error,
+    remote_signing::{Signer, SigningKey},
+    S3,
+};
+
+/// Prefix of the keys tickets upload to until they're redeemed.
+pub const TICKET_PREFIX: &str = ".upload-tickets";
+
+/// Longest expiry of an [`UploadTicket`].
+pub const MAX_TICKET_EXPIRY: Duration = Duration::from_secs(60 * 60);
+
+/// Metadata key carrying the id of the ticket an object was uploaded with.
+const TICKET_METADATA: &str = "upload-ticket";
+
+/// Restrictions of an upload delegated with [`S3::upload_ticket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadPolicy {
+    /// Largest upload the ticket accepts, in bytes.
+    pub max_size: u64,
+    /// Content type the upload has to declare.
+    pub content_type: String,
+    /// How long the ticket can be used, at most [`MAX_TICKET_EXPIRY`].
+    pub expiry: Duration,
+}
+
+/// Presigned POST form letting a third party upload a single object without credentials.
+///
+/// Hand `url` and `fields` to the uploader, who sends them as a `multipart/form-data` POST with
+/// the payload as the last field named `file`. S3 rejects uploads that violate the policy. The
+/// upload lands below [`TICKET_PREFIX`] and only reaches `target` once it's checked and moved
+/// with [`S3::redeem_upload`], which succeeds once per ticket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadTicket {
+    pub id: String,
+    pub url: String,
+    /// Form fields to send in this order, before the payload.
+    pub fields: Vec<(String, String)>,
+    pub target: String,
+    pub max_size: u64,
+    pub content_type: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl UploadTicket {
+    /// Key the uploader writes to.
+    pub fn staging(&self) -> Path {
+        staging(&self.id)
+    }
+}
+
+fn staging(id: &str) -> Path {
+    Path::from(format!("{}/{}", TICKET_PREFIX, id))
+}
+
+pub(crate) fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl S3 {
+    /// Mints a single-use [`UploadTicket`] for an upload to `target` restricted by `policy`.
+    ///
+    /// The POST policy caps the size with `content-length-range`, pins the content type and the
+    /// ticket id and expires with the ticket, it's signed with the store's credentials or its
+    /// [`RemoteSigner`](crate::remote_signing::RemoteSigner).
+    pub async fn upload_ticket(
+        &self,
+        target: &Path,
+        policy: &UploadPolicy,
+    ) -> object_store::Result<UploadTicket> {
+        if policy.expiry > MAX_TICKET_EXPIRY {
+            return Err(Error::Presigning(format!(
+                "ticket expiry {:?} exceeds {:?}",
+                policy.expiry, MAX_TICKET_EXPIRY
+            ))
+            .into());
+        }
+        let signer = match &self.signer {
+            Some(signer) => signer.clone(),
+            None => Arc::new(self.sdk_signer().await?),
+        };
+        let id = format!(
+            "{:016x}{:016x}",
+            self.random.next_u64(),
+            self.random.next_u64()
+        );
+        let key = staging(&id);
+        let now = self.now();
+        let expires_at = now
+            + chrono::Duration::from_std(policy.expiry)
+                .map_err(|err| Error::Presigning(err.to_string()))?;
+        let date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let credential = signer.credential(&date);
+
+        let mut fields = vec![
+            ("key".to_owned(), key.to_string()),
+            ("Content-Type".to_owned(), policy.content_type.clone()),
+            (format!("x-amz-meta-{}", TICKET_METADATA), id.clone()),
+            ("x-amz-algorithm".to_owned(), "AWS4-HMAC-SHA256".to_owned()),
+            ("x-amz-credential".to_owned(), credential),
+            ("x-amz-date".to_owned(), date.clone()),
+        ];
+        if let Some(token) = signer.session_token() {
+            fields.push(("x-amz-security-token".to_owned(), token.to_owned()));
+        }
+        let mut conditions = vec![
+            serde_json::json!({ "bucket": self.bucket }),
+            serde_json::json!(["content-length-range", 0, policy.max_size]),
+        ];
+        conditions.extend(
+            fields
+                .iter()
+                .map(|(name, value)| serde_json::json!({ name: value })),
+        );
+        let document = serde_json::json!({
+            "expiration": expires_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            "conditions": conditions,
+        });
+        let document = document.to_string();
+        let encoded = aws_smithy_types::base64::encode(&document);
+        let signature = signer.sign_policy(&date, &document, &encoded).await?;
+        fields.push(("policy".to_owned(), encoded));
+        fields.push(("x-amz-signature".to_owned(), signature));
+
+        // The form is posted to the bucket, the URL of the staging key without the key
+        let signed = self.signed_url(Method::HEAD, &key, policy.expiry).await?;
+        let url = signed
+            .split('?')
+            .next()
+            .and_then(|url| url.strip_suffix(key.as_ref()))
+            .ok_or(Error::Unknown)?
+            .to_owned();
+
+        Ok(UploadTicket {
+            id,
+            url,
+            fields,
+            target: target.to_string(),
+            max_size: policy.max_size,
+            content_type: policy.content_type.clone(),
+            expires_at,
+        })
+    }
+
+    /// Signer with the credentials of the SDK, for stores the SDK signs the requests of.
+    async fn sdk_signer(&self) -> Result<Signer, Error> {
+        let config = self.client.config();
+        let region = config
+            .region()
+            .ok_or_else(|| Error::Presigning("a region is required".to_owned()))?;
+        let credentials = config
+            .credentials_cache()
+            .ok_or_else(|| Error::Presigning("credentials are required".to_owned()))?
+            .provide_cached_credentials()
+            .await
+            .map_err(|err| Error::Presigning(err.to_string()))?;
+        Ok(Signer::new(
+            credentials.access_key_id().to_owned(),
+            credentials.session_token().map(ToOwned::to_owned),
+            region.to_string(),
+            "s3".to_owned(),
+            false,
+            SigningKey::secret(credentials.secret_access_key()),
+            self.clock.clone(),
+        ))
+    }
+
+    /// Checks the upload of `ticket` and moves it to the ticket's target.
+    ///
+    /// Uploads larger than the ticket allows, with another content type or of another ticket
+    /// are deleted and fail with [`Error::TicketRejected`]. A ticket is redeemed at most once:
+    /// the upload is copied with [`ObjectStore::copy_if_not_exists`], so a replayed upload is
+    /// deleted and fails with [`object_store::Error::AlreadyExists`] once the target exists.
+    pub async fn redeem_upload(&self, ticket: &UploadTicket) -> object_store::Result<ObjectMeta> {
+        let staging = ticket.staging();
+        let (meta, _, attributes) = self.head_object(&staging, None).await?;
+        let reason = if meta.size as u64 > ticket.max_size {
+            Some("upload exceeds the size limit")
+        } else if attributes.content_type.as_deref() != Some(ticket.content_type.as_str()) {
+            Some("upload has another content type")
+        } else if attributes.metadata.get(TICKET_METADATA) != Some(&ticket.id) {
+            Some("upload belongs to another ticket")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            self.delete(&staging).await?;
+            return Err(Error::TicketRejected {
+                ticket: ticket.id.clone(),
+                reason,
+            }
+            .into());
+        }
+        let target = Path::parse(&ticket.target)?;
+        if let Err(err) = self.copy_if_not_exists(&staging, &target).await {
+            // A replayed upload is never redeemed, don't leave it behind
+            if matches!(err, object_store::Error::AlreadyExists { .. }) {
+                self.delete(&staging).await?;
+            }
+            return Err(err);
+        }
+        self.delete(&staging).await?;
+        self.head(&target).await
+    }
+}
@@ -0,0 +1,82 @@
+use object_store::{path::Path, ObjectMeta, ObjectStore};
+
+use crate::{error::is_not_found, S3};
+
+/// Largest object S3 accepts with a single `PutObject`.
+pub const MAX_PUT_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+/// Largest object S3 accepts at all.
+pub const MAX_OBJECT_SIZE: u64 = 5 * 1024 * 1024 * 1024 * 1024;
+/// Largest number of parts of a multipart upload.
+pub const MAX_PARTS: u64 = 10_000;
+/// Smallest part of a multipart upload except the last one.
+pub const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+/// Largest part of a multipart upload.
+pub const MAX_PART_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Result of [`S3::validate_put`].
+#[derive(Debug, Clone)]
+pub struct PutValidation {
+    /// Metadata of the object that would be overwritten.
+    pub existing: Option<ObjectMeta>,
+    /// Whether the object fits into a single `PutObject`.
+    pub single_put: bool,
+    /// Number of parts a multipart upload of the object needs.
+    pub parts: u64,
+    /// Reasons the upload is going to fail, empty if it should succeed.
+    pub issues: Vec<PutIssue>,
+}
+
+impl PutValidation {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PutIssue {
+    /// The multipart upload would need more than [`MAX_PARTS`] parts.
+    TooManyParts { parts: u64, max: u64 },
+    /// The object is larger than [`MAX_OBJECT_SIZE`].
+    ObjectTooLarge { size: u64, max: u64 },
+    /// The object is larger than the quota configured on the builder.
+    QuotaExceeded { size: u64, quota: u64 },
+}
+
+impl S3 {
+    /// Checks whether an upload of `size` bytes to `location` can succeed before starting it.
+    pub async fn validate_put(
+        &self,
+        location: &Path,
+        size: u64,
+    ) -> object_store::Result<PutValidation> {
+        let existing = match self.head(location).await {
+            Ok(meta) => Some(meta),
+            Err(err) if is_not_found(&err) => None,
+            Err(err) => return Err(err),
+        };
+        let part_size = self.multipart_part_size as u64;
+        let parts = size.div_ceil(part_size).max(1);
+        let mut issues = Vec::new();
+        if parts > MAX_PARTS {
+            issues.push(PutIssue::TooManyParts {
+                parts,
+                max: MAX_PARTS,
+            });
+        }
+        if size > MAX_OBJECT_SIZE {
+            issues.push(PutIssue::ObjectTooLarge {
+                size,
+                max: MAX_OBJECT_SIZE,
+            });
+        }
+        if let Some(quota) = self.upload_quota.filter(|quota| size > *quota) {
+            issues.push(PutIssue::QuotaExceeded { size, quota });
+        }
+        Ok(PutValidation {
+            existing,
+            single_put: size <= MAX_PUT_SIZE,
+            parts,
+            issues,
+        })
+    }
+}
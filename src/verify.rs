@@ -0,0 +1,164 @@
+use bytes::Bytes;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use object_store::{path::Path, ObjectStore};
+use sha2::{Digest, Sha256};
+
+use crate::{checksum::hex, error::Error, S3};
+
+/// Where [`S3::get_verified`] looks up the expected SHA-256 digest of an object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DigestSource {
+    /// `<location>.sha256` next to the object, holding the hex digest optionally followed by
+    /// the file name as written by `sha256sum`.
+    Sidecar,
+    /// `sha256sum` style manifest listing the digests of objects by their location relative to
+    /// the manifest's directory.
+    Manifest(Path),
+}
+
+/// How [`S3::get_verified`] checks a download.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    source: DigestSource,
+    public_key: Option<[u8; 32]>,
+}
+
+impl Verification {
+    /// Checks objects against their `.sha256` sidecar.
+    pub fn sidecar() -> Self {
+        Self {
+            source: DigestSource::Sidecar,
+            public_key: None,
+        }
+    }
+
+    /// Checks objects against the manifest at `manifest`.
+    pub fn manifest(manifest: Path) -> Self {
+        Self {
+            source: DigestSource::Manifest(manifest),
+            public_key: None,
+        }
+    }
+
+    /// Requires the digest file to be signed: `<digest file>.sig` has to hold the base64
+    /// encoded Ed25519 signature of the digest file by the owner of `public_key`.
+    ///
+    /// Only the publisher holds the secret key, readers can verify but not forge signatures.
+    pub fn with_public_key(mut self, public_key: [u8; 32]) -> Self {
+        self.public_key = Some(public_key);
+        self
+    }
+
+    pub fn source(&self) -> &DigestSource {
+        &self.source
+    }
+}
+
+/// Base64 encoded Ed25519 signature of a digest file with `secret_key`, for
+/// [`Verification::with_public_key`].
+pub fn sign_digests(digests: &[u8], secret_key: &[u8; 32]) -> String {
+    aws_smithy_types::base64::encode(SigningKey::from_bytes(secret_key).sign(digests).to_bytes())
+}
+
+/// Ed25519 public key of `secret_key`, which readers pass to [`Verification::with_public_key`].
+pub fn public_key(secret_key: &[u8; 32]) -> [u8; 32] {
+    SigningKey::from_bytes(secret_key)
+        .verifying_key()
+        .to_bytes()
+}
+
+/// Hex encoded SHA-256 digest of `data` as written to sidecars and manifests.
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn signature_path(digests: &Path) -> Result<Path, object_store::path::Error> {
+    Path::parse(format!("{}.sig", digests))
+}
+
+/// Digest listed for `name` in `sha256sum` output, names may be marked binary with `*`.
+fn lookup<'a>(digests: &'a str, name: &str) -> Option<&'a str> {
+    digests.lines().find_map(|line| {
+        let (digest, listed) = line.trim().split_once(char::is_whitespace)?;
+        let listed = listed.trim_start();
+        (listed.strip_prefix('*').unwrap_or(listed) == name).then_some(digest)
+    })
+}
+
+impl S3 {
+    /// Downloads `location` and checks its SHA-256 digest against the detached digest named by
+    /// `verification`.
+    ///
+    /// Fails with [`Error::MissingDigest`] if no digest is listed for the object,
+    /// [`Error::InvalidSignature`] if the digest file's signature doesn't match and
+    /// [`Error::DigestMismatch`] if the downloaded data doesn't match its digest.
+    pub async fn get_verified(
+        &self,
+        location: &Path,
+        verification: &Verification,
+    ) -> object_store::Result<Bytes> {
+        let (digests_path, name) = match &verification.source {
+            DigestSource::Sidecar => (Path::parse(format!("{}.sha256", location))?, None),
+            DigestSource::Manifest(manifest) => {
+                let parts = manifest.parts().collect::<Vec<_>>();
+                let directory = Path::from_iter(parts[..parts.len().saturating_sub(1)].to_vec());
+                let relative = location
+                    .prefix_match(&directory)
+                    .map(|parts| parts.map(|part| part.as_ref().to_owned()))
+                    .map(|parts| parts.collect::<Vec<_>>().join("/"))
+                    .ok_or_else(|| Error::MissingDigest {
+                        key: location.to_string(),
+                        digests: manifest.to_string(),
+                    })?;
+                (manifest.clone(), Some(relative))
+            }
+        };
+        let missing = || Error::MissingDigest {
+            key: location.to_string(),
+            digests: digests_path.to_string(),
+        };
+
+        let digests = match self.get(&digests_path).await {
+            Ok(digests) => digests.bytes().await?,
+            Err(object_store::Error::NotFound { .. }) => return Err(missing().into()),
+            Err(err) => return Err(err),
+        };
+        if let Some(public_key) = &verification.public_key {
+            let signature = match self.get(&signature_path(&digests_path)?).await {
+                Ok(signature) => signature.bytes().await?,
+                Err(object_store::Error::NotFound { .. }) => {
+                    return Err(Error::InvalidSignature.into())
+                }
+                Err(err) => return Err(err),
+            };
+            let signature = std::str::from_utf8(&signature)
+                .ok()
+                .and_then(|signature| aws_smithy_types::base64::decode(signature.trim()).ok())
+                .and_then(|signature| Signature::from_slice(&signature).ok())
+                .ok_or(Error::InvalidSignature)?;
+            VerifyingKey::from_bytes(public_key)
+                .and_then(|public_key| public_key.verify_strict(&digests, &signature))
+                .map_err(|_| Error::InvalidSignature)?;
+        }
+
+        let digests = std::str::from_utf8(&digests).map_err(|_| missing())?;
+        let expected = match &name {
+            None => digests.split_whitespace().next(),
+            Some(name) => lookup(digests, name),
+        }
+        .ok_or_else(missing)?
+        .to_ascii_lowercase();
+
+        let data = self.get(location).await?.bytes().await?;
+        let actual = sha256_hex(&data);
+        if actual != expected {
+            return Err(Error::DigestMismatch {
+                key: location.to_string(),
+                expected,
+                actual,
+            }
+            .into());
+        }
+        Ok(data)
+    }
+}
@@ -0,0 +1,200 @@
+use aws_sdk_s3::types::{DeleteMarkerEntry, EncodingType};
+use chrono::DateTime;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
+};
+use object_store::{path::Path, ObjectMeta};
+
+use crate::{
+    error::Error, journal::Mutation, key_encoding::listed_path, list_encoding::decode_key, S3,
+};
+
+/// Version of an object in a versioned bucket, see [`S3::list_versions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectVersion {
+    /// Metadata of the version, [`ObjectMeta::version`] holds its version id.
+    pub meta: ObjectMeta,
+    /// Whether this is the current version of the object.
+    pub is_latest: bool,
+    /// Whether this version is a delete marker, which has no content. A latest delete marker
+    /// hides the object, permanently deleting the marker restores the previous version.
+    pub is_delete_marker: bool,
+}
+
+/// How [`S3::delete_versioned`] deletes an object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionedDelete {
+    /// Hide the object behind a new delete marker, its versions are kept. Same as
+    /// [`ObjectStore::delete`](object_store::ObjectStore::delete) in a versioned bucket.
+    Marker,
+    /// Permanently delete this version, which may be a delete marker.
+    Version(String),
+}
+
+/// Result of a [`S3::delete_versioned`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletedVersion {
+    /// Id of the created delete marker or of the deleted version, `None` in unversioned
+    /// buckets.
+    pub version: Option<String>,
+    /// Whether a delete marker was created or deleted.
+    pub delete_marker: bool,
+}
+
+fn object_version(
+    version: &aws_sdk_s3::types::ObjectVersion,
+    encoding: Option<&EncodingType>,
+) -> Result<ObjectVersion, Error> {
+    let last_modified = DateTime::from_timestamp_millis(
+        version.last_modified().ok_or(Error::Unknown)?.to_millis()?,
+    )
+    .ok_or(Error::Unknown)?;
+    Ok(ObjectVersion {
+        meta: ObjectMeta {
+            location: listed_path(version.key().ok_or(Error::Unknown)?.to_owned(), encoding)?,
+            last_modified,
+            size: version.size() as usize,
+            e_tag: version.e_tag().map(ToOwned::to_owned),
+            version: version.version_id().map(ToOwned::to_owned),
+        },
+        is_latest: version.is_latest(),
+        is_delete_marker: false,
+    })
+}
+
+fn delete_marker(
+    marker: &DeleteMarkerEntry,
+    encoding: Option<&EncodingType>,
+) -> Result<ObjectVersion, Error> {
+    let last_modified =
+        DateTime::from_timestamp_millis(marker.last_modified().ok_or(Error::Unknown)?.to_millis()?)
+            .ok_or(Error::Unknown)?;
+    Ok(ObjectVersion {
+        meta: ObjectMeta {
+            location: listed_path(marker.key().ok_or(Error::Unknown)?.to_owned(), encoding)?,
+            last_modified,
+            size: 0,
+            e_tag: None,
+            version: marker.version_id().map(ToOwned::to_owned),
+        },
+        is_latest: marker.is_latest(),
+        is_delete_marker: true,
+    })
+}
+
+impl S3 {
+    /// All versions and delete markers of the objects below `prefix`, newest first for every key.
+    pub fn list_versions(
+        &self,
+        prefix: Option<&Path>,
+    ) -> BoxStream<'_, object_store::Result<ObjectVersion>> {
+        let prefix = prefix.map(|prefix| prefix.to_string());
+        // `None` once the last page was fetched, `Some((None, None))` before the first page
+        stream::try_unfold(Some((None::<String>, None::<String>)), move |markers| {
+            let prefix = prefix.clone();
+            async move {
+                let Some((key_marker, version_id_marker)) = markers else {
+                    return Ok(None);
+                };
+                let response = self
+                    .client
+                    .list_object_versions()
+                    .bucket(self.bucket.clone())
+                    .set_prefix(prefix)
+                    .set_key_marker(key_marker)
+                    .set_version_id_marker(version_id_marker)
+                    .set_encoding_type(self.list_encoding.encoding_type())
+                    .send()
+                    .await
+                    .map_err(Error::from)?;
+                let encoding = response.encoding_type();
+                // The key marker is encoded like the keys, but sent back verbatim
+                let next = match response.is_truncated() {
+                    true => Some((
+                        response
+                            .next_key_marker()
+                            .map(|marker| decode_key(marker.to_owned(), encoding))
+                            .transpose()?,
+                        response.next_version_id_marker().map(ToOwned::to_owned),
+                    )),
+                    false => None,
+                };
+                let mut versions = response
+                    .versions()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|version| object_version(version, encoding))
+                    .chain(
+                        response
+                            .delete_markers()
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|marker| delete_marker(marker, encoding)),
+                    )
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Versions and delete markers are listed separately, each by key and newest first
+                versions.sort_by(|a, b| {
+                    a.meta
+                        .location
+                        .cmp(&b.meta.location)
+                        .then(b.is_latest.cmp(&a.is_latest))
+                        .then(b.meta.last_modified.cmp(&a.meta.last_modified))
+                });
+                let versions = versions.into_iter().map(Ok::<_, object_store::Error>);
+                Ok::<_, object_store::Error>(Some((stream::iter(versions), next)))
+            }
+        })
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Permanently deletes one version of the object at `location`.
+    ///
+    /// Unlike [`ObjectStore::delete`](object_store::ObjectStore::delete) this doesn't create a
+    /// delete marker in a versioned bucket.
+    pub async fn delete_version(&self, location: &Path, version: &str) -> object_store::Result<()> {
+        self.delete_versioned(location, VersionedDelete::Version(version.to_owned()))
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the object at `location` by creating a delete marker or permanently deleting a
+    /// version, as `delete` says.
+    pub async fn delete_versioned(
+        &self,
+        location: &Path,
+        delete: VersionedDelete,
+    ) -> object_store::Result<DeletedVersion> {
+        let version = match delete {
+            VersionedDelete::Marker => None,
+            VersionedDelete::Version(version) => Some(version),
+        };
+        let response = self
+            .client
+            .delete_object()
+            .bucket(self.bucket.clone())
+            .key(location.to_string())
+            .set_version_id(version)
+            .send()
+            .await
+            .map_err(|err| self.request_error(location, err))?;
+        self.record(|| Mutation::Delete {
+            location: location.clone(),
+        });
+        Ok(DeletedVersion {
+            version: response.version_id().map(ToOwned::to_owned),
+            delete_marker: response.delete_marker(),
+        })
+    }
+
+    /// Like [`ObjectStore::head`](object_store::ObjectStore::head) for a specific version.
+    pub async fn head_version(
+        &self,
+        location: &Path,
+        version: &str,
+    ) -> object_store::Result<ObjectMeta> {
+        let (meta, _, _) = self.head_object(location, Some(version)).await?;
+        Ok(meta)
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use object_store::{path::Path, GetOptions, ObjectMeta, ObjectStore};
+
+use crate::{
+    error::{is_not_found, Error},
+    S3,
+};
+
+/// The pause between polls grows up to this multiple of the poll interval.
+const MAX_BACKOFF_FACTOR: u32 = 8;
+
+impl S3 {
+    /// Waits until an object exists at `location` and returns its metadata, e.g. the result of a
+    /// server side job.
+    ///
+    /// Fails with [`Error::Timeout`] if the object doesn't appear within `timeout`, see
+    /// [`S3::wait_for_change`].
+    pub async fn wait_for(
+        &self,
+        location: &Path,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> object_store::Result<ObjectMeta> {
+        self.wait_for_change(location, None, timeout, poll_interval)
+            .await
+    }
+
+    /// Waits until the object at `location` has an ETag other than `e_tag`, or until it exists if
+    /// `e_tag` is `None`, and returns its metadata.
+    ///
+    /// Polls with conditional `HeadObject` requests, so an unchanged object is answered with
+    /// `304 Not Modified`. The pause between polls starts at `poll_interval` and doubles up to
+    /// eight times `poll_interval`, jittered by up to half. Fails with [`Error::Timeout`] once
+    /// `timeout` elapsed.
+    pub async fn wait_for_change(
+        &self,
+        location: &Path,
+        e_tag: Option<&str>,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> object_store::Result<ObjectMeta> {
+        let deadline = self.timer.now() + timeout;
+        let max_interval = poll_interval.saturating_mul(MAX_BACKOFF_FACTOR);
+        let mut interval = poll_interval;
+        loop {
+            let options = GetOptions {
+                head: true,
+                if_none_match: e_tag.map(ToOwned::to_owned),
+                ..Default::default()
+            };
+            match self.get_opts(location, options).await {
+                Ok(result) => return Ok(result.meta),
+                Err(object_store::Error::NotModified { .. }) => (),
+                Err(err) if is_not_found(&err) => (),
+                Err(err) => return Err(err),
+            }
+            let now = self.timer.now();
+            if now >= deadline {
+                return Err(Error::Timeout(timeout).into());
+            }
+            let pause = interval / 2 + self.random.jitter(interval / 2);
+            self.timer.sleep(pause.min(deadline - now)).await;
+            interval = interval.saturating_mul(2).min(max_interval);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use aws_credential_types::{
+    provider::{self, error::CredentialsError, ProvideCredentials},
+    Credentials,
+};
+use futures::{
+    future::{self, BoxFuture},
+    lock::Mutex,
+    FutureExt,
+};
+
+#[cfg(feature = "shared_session")]
+use crate::shared_session;
+use crate::{environment::Clock, error::Error};
+
+/// Credentials are exchanged again once they expire within this duration.
+pub const REFRESH_BEFORE_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
+/// Source of the OIDC token exchanged for credentials, asked again for every exchange so it can
+/// refresh the token itself.
+pub trait WebIdentityToken: Debug + Send + Sync {
+    fn token(&self) -> BoxFuture<'_, Result<String, Error>>;
+}
+
+impl WebIdentityToken for String {
+    fn token(&self) -> BoxFuture<'_, Result<String, Error>> {
+        future::ready(Ok(self.clone())).boxed()
+    }
+}
+
+/// Whether `credentials` are still usable [`REFRESH_BEFORE_EXPIRY`] after `now`.
+fn fresh(credentials: &Credentials, now: SystemTime) -> bool {
+    credentials
+        .expiry()
+        .is_none_or(|expiry| expiry > now + REFRESH_BEFORE_EXPIRY)
+}
+
+/// Temporary credentials of STS `AssumeRoleWithWebIdentity`.
+///
+/// The exchange is unsigned and goes through the store's connector, so it works in the browser
+/// without long-lived keys. Credentials are cached and exchanged again
+/// [`REFRESH_BEFORE_EXPIRY`] before they expire, concurrent requests wait for a single exchange.
+/// In a shared session the tabs of the origin take turns exchanging the token, the tab holding
+/// the Web Lock stores its credentials in IndexedDB for the others before releasing it. They're
+/// shared by identity, tabs only find credentials exchanged for the same token, and deleted once
+/// they expire or the token of the tab changes.
+#[derive(Debug)]
+pub(crate) struct WebIdentityProvider {
+    client: aws_sdk_sts::Client,
+    role_arn: String,
+    session_name: String,
+    token: Arc<dyn WebIdentityToken>,
+    clock: Arc<dyn Clock>,
+    cached: Mutex<Option<Credentials>>,
+    /// Whether credentials are shared with other tabs, with the key they were last shared under.
+    #[cfg(feature = "shared_session")]
+    shared: Option<parking_lot::Mutex<Option<String>>>,
+}
+
+impl WebIdentityProvider {
+    pub(crate) fn new(
+        client: aws_sdk_sts::Client,
+        role_arn: String,
+        session_name: String,
+        token: Arc<dyn WebIdentityToken>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            client,
+            role_arn,
+            session_name,
+            token,
+            clock,
+            cached: Mutex::new(None),
+            #[cfg(feature = "shared_session")]
+            shared: None,
+        }
+    }
+
+    /// Shares the credentials with the other tabs of the origin.
+    #[cfg(feature = "shared_session")]
+    pub(crate) fn shared(mut self) -> Self {
+        self.shared = Some(parking_lot::Mutex::new(None));
+        self
+    }
+
+    async fn credentials(&self) -> provider::Result {
+        let mut cached = self.cached.lock().await;
+        let now = self.clock.now();
+        if let Some(credentials) = cached.as_ref().filter(|cached| fresh(cached, now)) {
+            return Ok(credentials.clone());
+        }
+        let token = self
+            .token
+            .token()
+            .await
+            .map_err(CredentialsError::provider_error)?;
+        #[cfg(feature = "shared_session")]
+        let shared = self.shared.as_ref().map(|last| {
+            let key = shared_session::credentials_key(&self.role_arn, &token);
+            let previous = last.lock().replace(key.clone());
+            (previous.filter(|previous| *previous != key), key)
+        });
+        #[cfg(feature = "shared_session")]
+        let _lock = match &shared {
+            Some((previous, key)) => {
+                // Credentials of the previous identity must not outlive its token
+                if let Some(previous) = previous {
+                    shared_session::remove(previous).await;
+                }
+                let lock = shared_session::lock(key).await;
+                // Another tab may have exchanged the token while this one waited for the lock
+                match shared_session::stored(key).await {
+                    Some(credentials) if fresh(&credentials, now) => {
+                        *cached = Some(credentials.clone());
+                        return Ok(credentials);
+                    }
+                    Some(_) => shared_session::remove(key).await,
+                    None => {}
+                }
+                Some(lock)
+            }
+            None => None,
+        };
+        let credentials = self
+            .assume_role(token)
+            .await
+            .map_err(CredentialsError::provider_error)?;
+        #[cfg(feature = "shared_session")]
+        if let Some((_, key)) = &shared {
+            shared_session::store(key, &credentials).await;
+        }
+        *cached = Some(credentials.clone());
+        Ok(credentials)
+    }
+
+    async fn assume_role(&self, token: String) -> Result<Credentials, Error> {
+        let output = self
+            .client
+            .assume_role_with_web_identity()
+            .role_arn(&self.role_arn)
+            .role_session_name(&self.session_name)
+            .web_identity_token(token)
+            .send()
+            .await?;
+        let credentials = output.credentials().ok_or(Error::Unknown)?;
+        let expiry = credentials
+            .expiration()
+            .map(|expiration| SystemTime::try_from(*expiration))
+            .transpose()?;
+        Ok(Credentials::new(
+            credentials.access_key_id().ok_or(Error::Unknown)?,
+            credentials.secret_access_key().ok_or(Error::Unknown)?,
+            credentials.session_token().map(ToOwned::to_owned),
+            expiry,
+            "WebIdentity",
+        ))
+    }
+}
+
+impl ProvideCredentials for WebIdentityProvider {
+    fn provide_credentials<'a>(&'a self) -> provider::future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        provider::future::ProvideCredentials::new(self.credentials())
+    }
+}
@@ -0,0 +1,402 @@
+//! Checks that the store behaves like the other `object_store` backends, against the MinIO
+//! endpoint of `tests/web.rs`.
+//!
+//! Runs headless with `wasm-pack test --headless --chrome` or `--firefox`.
+
+use bytes::Bytes;
+use futures::{stream, StreamExt, TryStreamExt};
+use object_store::{path::Path, GetOptions, GetRange, ObjectStore};
+use object_store_s3_wasm::S3;
+use tokio::io::AsyncWriteExt;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn store() -> S3 {
+    S3::builder()
+        .endpoint("http://localhost:9000")
+        .region("us-east-1")
+        .bucket("test")
+        .access_key_id("UYCQnNlCugeb1BmZtauK")
+        .secret_access_key("wiAL4vRJs7cshy6fCpHDEQrhx8oWefk4UNyMpM6V")
+        .build()
+        .expect("Failed to create s3 client")
+}
+
+/// Store with nothing below `prefix`, left over objects of earlier runs are deleted.
+async fn empty_at(prefix: &str) -> S3 {
+    let s3 = store();
+    let locations = s3
+        .list(Some(&prefix.into()))
+        .map_ok(|meta| meta.location)
+        .boxed();
+    s3.delete_stream(locations)
+        .try_collect::<Vec<_>>()
+        .await
+        .expect("Failed to clean up");
+    s3
+}
+
+/// xorshift, so failures reproduce
+fn random(seed: u64) -> impl FnMut() -> u64 {
+    let mut state = seed;
+    move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    }
+}
+
+fn data(len: usize) -> Bytes {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[wasm_bindgen_test]
+async fn puts_and_gets() {
+    let s3 = empty_at("conformance/put_get").await;
+    let location = Path::from("conformance/put_get/object.bin");
+    let payload = data(1000);
+
+    s3.put(&location, payload.clone()).await.unwrap();
+    let result = s3.get(&location).await.unwrap();
+    assert_eq!(result.meta.location, location);
+    assert_eq!(result.meta.size, payload.len());
+    assert_eq!(result.range, 0..payload.len());
+    assert_eq!(result.bytes().await.unwrap(), payload);
+
+    let head = s3.head(&location).await.unwrap();
+    assert_eq!(head.size, payload.len());
+    assert!(head.e_tag.is_some());
+
+    // Overwrites replace the object
+    s3.put(&location, Bytes::from("short")).await.unwrap();
+    assert_eq!(
+        s3.get(&location).await.unwrap().bytes().await.unwrap(),
+        Bytes::from("short")
+    );
+
+    // Empty objects are objects too
+    let empty = Path::from("conformance/put_get/empty");
+    s3.put(&empty, Bytes::new()).await.unwrap();
+    assert_eq!(s3.head(&empty).await.unwrap().size, 0);
+    assert!(s3
+        .get(&empty)
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap()
+        .is_empty());
+
+    let missing = Path::from("conformance/put_get/missing");
+    assert!(matches!(
+        s3.get(&missing).await,
+        Err(object_store::Error::NotFound { .. })
+    ));
+    assert!(matches!(
+        s3.head(&missing).await,
+        Err(object_store::Error::NotFound { .. })
+    ));
+}
+
+#[wasm_bindgen_test]
+async fn gets_any_range() {
+    let s3 = empty_at("conformance/ranges").await;
+    let location = Path::from("conformance/ranges/object.bin");
+    let size = 4096;
+    let payload = data(size);
+    s3.put(&location, payload.clone()).await.unwrap();
+
+    let mut next = random(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..50 {
+        let (a, b) = (
+            (next() % size as u64) as usize,
+            (next() % size as u64) as usize,
+        );
+        let (start, end) = (a.min(b), a.max(b) + 1);
+        let (range, expected) = match next() % 4 {
+            0 => (GetRange::Bounded(start..end), start..end),
+            1 => (GetRange::Offset(start), start..size),
+            2 => (GetRange::Suffix(end), size - end..size),
+            _ => {
+                // Empty ranges are rejected like any other invalid range
+                let options = GetOptions {
+                    range: Some(GetRange::Bounded(start..start)),
+                    ..Default::default()
+                };
+                assert!(s3.get_opts(&location, options).await.is_err());
+                continue;
+            }
+        };
+        let options = GetOptions {
+            range: Some(range.clone()),
+            ..Default::default()
+        };
+        let result = s3.get_opts(&location, options).await.unwrap();
+        assert_eq!(result.range, expected, "{:?}", range);
+        assert_eq!(result.meta.size, size);
+        assert_eq!(
+            result.bytes().await.unwrap(),
+            payload.slice(expected.clone()),
+            "{:?}",
+            range
+        );
+    }
+
+    let ranges = (0..20)
+        .map(|_| {
+            let start = (next() % size as u64) as usize;
+            start..(start + (next() % 300) as usize + 1).min(size)
+        })
+        .collect::<Vec<_>>();
+    let fetched = s3.get_ranges(&location, &ranges).await.unwrap();
+    for (range, bytes) in ranges.into_iter().zip(fetched) {
+        assert_eq!(bytes, payload.slice(range));
+    }
+
+    // Ranges starting beyond the end are rejected
+    let options = GetOptions {
+        range: Some(GetRange::Offset(size + 1)),
+        ..Default::default()
+    };
+    assert!(s3.get_opts(&location, options).await.is_err());
+}
+
+#[wasm_bindgen_test]
+async fn lists_with_and_without_delimiter() {
+    let s3 = empty_at("conformance/list").await;
+    let locations = [
+        "conformance/list/a.txt",
+        "conformance/list/b/1.txt",
+        "conformance/list/b/2.txt",
+        "conformance/list/b/c/3.txt",
+        "conformance/list/d/4.txt",
+    ];
+    for location in locations {
+        s3.put(&location.into(), Bytes::from(location))
+            .await
+            .unwrap();
+    }
+
+    let mut listed = s3
+        .list(Some(&"conformance/list".into()))
+        .map_ok(|meta| meta.location.to_string())
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    listed.sort();
+    assert_eq!(listed, locations);
+
+    // Prefixes match whole segments only
+    assert_eq!(
+        s3.list(Some(&"conformance/list/b/c".into()))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(s3
+        .list(Some(&"conformance/lis".into()))
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap()
+        .is_empty());
+
+    let result = s3
+        .list_with_delimiter(Some(&"conformance/list".into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        result
+            .objects
+            .iter()
+            .map(|meta| meta.location.to_string())
+            .collect::<Vec<_>>(),
+        vec!["conformance/list/a.txt"]
+    );
+    let mut prefixes = result
+        .common_prefixes
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    prefixes.sort();
+    assert_eq!(prefixes, vec!["conformance/list/b", "conformance/list/d"]);
+
+    let offset = Path::from("conformance/list/b/2.txt");
+    let after = s3
+        .list_with_offset(Some(&"conformance/list".into()), &offset)
+        .map_ok(|meta| meta.location.to_string())
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(
+        after,
+        vec!["conformance/list/b/c/3.txt", "conformance/list/d/4.txt"]
+    );
+}
+
+#[wasm_bindgen_test]
+async fn paginates_listings() {
+    let s3 = empty_at("conformance/pages").await;
+    // More than the 1000 keys of a single ListObjectsV2 response
+    let count = 1010;
+    let mut expected = (0..count)
+        .map(|i| format!("conformance/pages/{}/{:04}", i % 7, i))
+        .collect::<Vec<_>>();
+    stream::iter(expected.clone())
+        .map(|location| {
+            let s3 = s3.clone();
+            async move { s3.put(&location.into(), Bytes::new()).await }
+        })
+        .buffer_unordered(32)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    expected.sort();
+
+    let listed = s3
+        .list(Some(&"conformance/pages".into()))
+        .map_ok(|meta| meta.location.to_string())
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(listed, expected);
+
+    // Pages of any size add up to the whole listing, without duplicates
+    let mut next = random(0x2545_f491_4f6c_dd1d);
+    for _ in 0..5 {
+        let page_size = (next() % 400) as usize + 1;
+        let mut token = None;
+        let mut paged = Vec::new();
+        loop {
+            let page = s3
+                .list_dir_page(Some(&"conformance/pages/3".into()), token, page_size)
+                .await
+                .unwrap();
+            assert!(page.files.len() + page.folders.len() <= page_size);
+            paged.extend(page.files.into_iter().map(|meta| meta.location.to_string()));
+            token = page.next_token;
+            if token.is_none() {
+                break;
+            }
+        }
+        let in_folder = expected
+            .iter()
+            .filter(|location| location.starts_with("conformance/pages/3/"))
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(paged, in_folder, "page size {}", page_size);
+    }
+
+    // Listing after any offset continues the listing in order
+    for _ in 0..5 {
+        let offset = &expected[(next() % count as u64) as usize];
+        let after = s3
+            .list_with_offset(Some(&"conformance/pages".into()), &offset.as_str().into())
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let remaining = expected
+            .iter()
+            .filter(|location| *location > offset)
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(after, remaining, "offset {}", offset);
+    }
+}
+
+#[wasm_bindgen_test]
+async fn copies_and_renames() {
+    let s3 = empty_at("conformance/copy").await;
+    let (source, target) = (
+        Path::from("conformance/copy/source"),
+        Path::from("conformance/copy/target"),
+    );
+    s3.put(&source, Bytes::from("source")).await.unwrap();
+
+    s3.copy(&source, &target).await.unwrap();
+    assert_eq!(
+        s3.get(&target).await.unwrap().bytes().await.unwrap(),
+        Bytes::from("source")
+    );
+    // Copies overwrite
+    s3.put(&source, Bytes::from("updated")).await.unwrap();
+    s3.copy(&source, &target).await.unwrap();
+    assert_eq!(
+        s3.get(&target).await.unwrap().bytes().await.unwrap(),
+        Bytes::from("updated")
+    );
+
+    let renamed = Path::from("conformance/copy/renamed");
+    s3.rename(&target, &renamed).await.unwrap();
+    assert!(s3.head(&target).await.is_err());
+    assert_eq!(s3.head(&renamed).await.unwrap().size, 7);
+
+    let missing = Path::from("conformance/copy/missing");
+    assert!(matches!(
+        s3.copy(&missing, &target).await,
+        Err(object_store::Error::NotFound { .. })
+    ));
+}
+
+#[wasm_bindgen_test]
+async fn uploads_multipart() {
+    let s3 = empty_at("conformance/multipart").await;
+    let location = Path::from("conformance/multipart/object.bin");
+    let payload = data(12 * 1024 * 1024 + 17);
+
+    let (_, mut writer) = s3.put_multipart(&location).await.unwrap();
+    for chunk in payload.chunks(1024 * 1024 + 3) {
+        writer.write_all(chunk).await.unwrap();
+    }
+    writer.shutdown().await.unwrap();
+    assert_eq!(s3.head(&location).await.unwrap().size, payload.len());
+    assert_eq!(
+        s3.get(&location).await.unwrap().bytes().await.unwrap(),
+        payload
+    );
+
+    // Aborted uploads leave nothing behind
+    let aborted = Path::from("conformance/multipart/aborted.bin");
+    let (id, mut writer) = s3.put_multipart(&aborted).await.unwrap();
+    writer.write_all(&payload[..1024]).await.unwrap();
+    s3.abort_multipart(&aborted, &id).await.unwrap();
+    assert!(s3.head(&aborted).await.is_err());
+}
+
+#[wasm_bindgen_test]
+async fn deletes() {
+    let s3 = empty_at("conformance/delete").await;
+    let location = Path::from("conformance/delete/object");
+    s3.put(&location, Bytes::from("data")).await.unwrap();
+
+    s3.delete(&location).await.unwrap();
+    assert!(matches!(
+        s3.head(&location).await,
+        Err(object_store::Error::NotFound { .. })
+    ));
+    // Deleting a missing object succeeds, like on S3
+    s3.delete(&location).await.unwrap();
+
+    let locations = (0..10)
+        .map(|i| Path::from(format!("conformance/delete/{}", i)))
+        .collect::<Vec<_>>();
+    for location in &locations {
+        s3.put(location, Bytes::from("data")).await.unwrap();
+    }
+    let deleted = s3
+        .delete_stream(stream::iter(locations.clone()).map(Ok).boxed())
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(deleted.len(), locations.len());
+    assert!(s3
+        .list(Some(&"conformance/delete".into()))
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,108 @@
+#![cfg(all(feature = "native", not(target_arch = "wasm32")))]
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use object_store::{path::Path, ObjectStore};
+use object_store_s3_wasm::{persistent_cache::EvictionPolicy, S3};
+
+const OBJECT: &[u8] = b"0123456789abcdef";
+
+/// Serves `OBJECT` for every key on a local port, counting the requests.
+fn serve_object() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind listener");
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            counter.fetch_add(1, Ordering::SeqCst);
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut method = String::new();
+            let mut range = None;
+            let mut line = String::new();
+            while reader
+                .read_line(&mut line)
+                .map(|read| read > 0)
+                .unwrap_or(false)
+            {
+                if line == "\r\n" {
+                    break;
+                }
+                if method.is_empty() {
+                    method = line.split(' ').next().unwrap_or_default().to_owned();
+                }
+                if let Some(value) = line.to_lowercase().strip_prefix("range: bytes=") {
+                    let (start, end) = value.trim().split_once('-').unwrap();
+                    range = Some((
+                        start.parse::<usize>().unwrap(),
+                        end.parse::<usize>().unwrap(),
+                    ));
+                }
+                line.clear();
+            }
+            let (status, body, content_range) = match range {
+                Some((start, end)) => (
+                    "206 Partial Content",
+                    &OBJECT[start..=end],
+                    format!(
+                        "Content-Range: bytes {}-{}/{}\r\n",
+                        start,
+                        end,
+                        OBJECT.len()
+                    ),
+                ),
+                None => ("200 OK", OBJECT, String::new()),
+            };
+            let head = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\n{}ETag: \"v1\"\r\nLast-Modified: Wed, 21 Oct 2015 07:28:00 GMT\r\nConnection: close\r\n\r\n",
+                status,
+                body.len(),
+                content_range
+            );
+            let _ = stream.write_all(head.as_bytes());
+            if method != "HEAD" {
+                let _ = stream.write_all(body);
+            }
+        }
+    });
+    (endpoint, requests)
+}
+
+#[tokio::test]
+async fn persistent_cache_passes_through_natively() {
+    let (endpoint, requests) = serve_object();
+    let builder = S3::builder()
+        .endpoint(&endpoint)
+        .region("us-east-1")
+        .bucket("test")
+        .access_key_id("access")
+        .secret_access_key("secret")
+        .with_persistent_cache(1024, EvictionPolicy::Lru);
+    #[cfg(feature = "shared_session")]
+    let builder = builder.with_shared_session();
+    let s3 = builder.build().expect("Failed to create s3 client");
+    let location = Path::from("folder/native.bin");
+
+    for _ in 0..2 {
+        let ranges = s3
+            .get_ranges(&location, &[0..4, 8..12])
+            .await
+            .expect("Failed to get ranges");
+        assert_eq!(ranges, vec![&OBJECT[0..4], &OBJECT[8..12]]);
+    }
+    // Native builds have no IndexedDB, every read goes to the origin
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+    assert_eq!(s3.persistent_cache_size().await.unwrap(), 0);
+    s3.clear_persistent_cache()
+        .await
+        .expect("Failed to clear persistent cache");
+}